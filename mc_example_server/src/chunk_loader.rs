@@ -1,152 +1,166 @@
-use mc_server_lib::{ chunk_manager::ConstChunkProvider, entity::ClientComponent };
-use mc_networking::packets::{
-    client_bound::{ C1AUnloadChunk, ClientBoundPacket },
-    RawPacket
-};
-use mc_utils::ChunkData;
-
-use std::sync::{Arc, RwLock};
-
-use dashmap::DashMap;
-use rayon::{ ThreadPool, ThreadPoolBuilder};
-use minecraft_data_rs::{ Api as McApi, models::version::Version as McVer };
-use bevy_ecs::entity::Entity;
-use bevy_ecs::system::{ Res, Commands };
-use bevy_ecs::world::World;
+use mc_server_lib::async_chunk_provider::AsyncChunkProvider;
+use mc_server_lib::digging::{ BlockHardness, ToolKind };
+use mc_utils::{ AABB, BlockState, ChunkData, ChunkGenerator };
+
+use std::collections::{ HashMap, HashSet };
+use std::ops::RangeInclusive;
+use std::sync::{ Arc, RwLock };
+
+use minecraft_data_rs::{ Api as McApi, models::block::Block, models::version::Version as McVer };
+
+fn load_api() -> McApi {
+    McApi::new(McVer {
+        version: mc_networking::PROTOCOL_VERSION,
+        minecraft_version: mc_networking::MINECRAFT_VERSION.into(),
+        major_version: mc_networking::MINECRAFT_VERSION.into(),
+    })
+}
 
 lazy_static::lazy_static! {
-    static ref MC_API: McApi = McApi::new(McVer {
-        version: 759,
-        minecraft_version: "1.19".into(),
-        major_version: "1.19".into(),
-    });
-}
+    pub(crate) static ref MC_API: McApi = load_api();
 
-#[derive(Default)]
-struct ChunkLoadingData {
-    data: Option<(ChunkData, RawPacket)>,
-    waiters: Vec<Entity>,
+    /// Current block data, replaced by [reload_block_data]
+    static ref BLOCK_DATA: RwLock<Arc<BlockData>> = RwLock::new(Arc::new(BlockData::load(&MC_API)));
 }
 
-pub struct StoneChunkProvider {
-    loading_chunks: DashMap<(i32, i32), Arc<RwLock<ChunkLoadingData>>>,
-    unloading_chunks: DashMap<(i32, i32), Vec<Entity>>,
-    thread_pool: ThreadPool,
+fn block_states(block: &Block) -> Option<RangeInclusive<BlockState>> {
+    Some(block.min_state_id? as BlockState..=block.max_state_id? as BlockState)
+}
 
-    ground_block_state: u32,
+/// Block properties read from the minecraft data
+pub struct BlockData {
+    /// Block states stopping the fall of the players in them
+    fall_cancelling_states: HashSet<BlockState>,
+    /// Block states with a full block bounding box, looked up for every block around the players
+    solid_states: HashSet<BlockState>,
+    /// Mining properties of every block state
+    hardnesses: HashMap<BlockState, BlockHardness>,
 }
-impl StoneChunkProvider {
-    pub fn new() -> Self {
-        Self {
-            loading_chunks: DashMap::default(),
-            unloading_chunks: DashMap::default(),
-            thread_pool: ThreadPoolBuilder::new().build().unwrap(),
 
-            ground_block_state: MC_API.blocks.blocks_by_name().unwrap()["polished_andesite"].id,
+impl BlockData {
+    pub fn load(api: &McApi) -> Self {
+        let blocks = api.blocks.blocks_array().unwrap();
+        let fall_cancelling_states = {
+            let blocks = api.blocks.blocks_by_name().unwrap();
+            ["water", "ladder", "vine", "cobweb", "scaffolding", "twisting_vines", "weeping_vines"].iter()
+                .filter_map(|name| blocks.get(*name))
+                .filter_map(block_states)
+                .flatten()
+                .collect()
+        };
+        let solid_states = blocks.iter()
+            .filter(|block| block.bounding_box == "block")
+            .filter_map(block_states)
+            .flatten()
+            .collect();
+        let hardnesses = blocks.iter()
+            .filter_map(|block| {
+                let hardness = BlockHardness {
+                    hardness: block.hardness.unwrap_or(-1.),
+                    effective_tool: match block.material.as_deref() {
+                        Some("mineable/pickaxe") => Some(ToolKind::Pickaxe),
+                        Some("mineable/axe") => Some(ToolKind::Axe),
+                        Some("mineable/shovel") => Some(ToolKind::Shovel),
+                        Some("mineable/hoe") => Some(ToolKind::Hoe),
+                        _ => None,
+                    },
+                    requires_tool: block.harvest_tools.is_some(),
+                };
+                Some(block_states(block)?.map(move |state| (state, hardness)))
+            })
+            .flatten()
+            .collect();
+
+        Self {
+            fall_cancelling_states,
+            solid_states,
+            hardnesses,
         }
     }
 }
 
-impl ConstChunkProvider for StoneChunkProvider {
-    fn const_load_chunk(
-        &self, player: Entity, _commands: &mut Commands,
-        chunk_x: i32, chunk_z: i32
-    ) {
-        if let Some(entry) = self.loading_chunks.get(&(chunk_x, chunk_z)) {
-            let loading_data = &*entry;
-            loading_data.write().unwrap().waiters.push(player.clone());
-            return;
-        }
-        if self.loading_chunks.contains_key(&(chunk_x, chunk_z)) {
-            return;
-        }
+/// The block data in use, lookups keep the instance they started with if it is reloaded meanwhile
+pub fn block_data() -> Arc<BlockData> {
+    Arc::clone(&BLOCK_DATA.read().unwrap())
+}
 
-        let final_chunk_data = Arc::new(RwLock::new(ChunkLoadingData {
-            data: None,
-            waiters: vec![player],
-        }));
-        self.loading_chunks
-            .insert((chunk_x, chunk_z), Arc::clone(&final_chunk_data));
-
-        let ground_block_state = self.ground_block_state as u16;
-        self.thread_pool.spawn(move || {
-            let mut chunk_data = ChunkData::new(crate::WORLD_HEIGHT / 16);
-
-            if (chunk_z == 0 || chunk_z == 2) && chunk_x >= 0 {
-                for x in 0..16 {
-                    chunk_data.set_block(x, 21, 7, ground_block_state);
-                    chunk_data.set_block(x, 21, 8, ground_block_state);
-                    chunk_data.set_block(x, 21, 9, ground_block_state);
-                }
-            }
-            //chunk_data.get_section_mut(1).fill_with(ground_block_state);
+/// Reads the minecraft data again and replaces the block data used by the lookups
+/// Parsing the data takes a while, it shouldn't be called from the tick thread
+pub fn reload_block_data() -> Arc<BlockData> {
+    let block_data = Arc::new(BlockData::load(&load_api()));
+    *BLOCK_DATA.write().unwrap() = Arc::clone(&block_data);
+    block_data
+}
+
+/// Mining properties of a block state, None for unknown states
+pub fn block_hardness(block: BlockState) -> Option<BlockHardness> {
+    block_data().hardnesses.get(&block).copied()
+}
 
-            let packet = chunk_data.encode_full(chunk_x, chunk_z);
-            let packet = packet.to_rawpacket();
+/// Whether players in this block don't take fall damage
+pub fn cancels_fall(block: BlockState) -> bool {
+    block_data().fall_cancelling_states.contains(&block)
+}
 
-            let mut loading_data = final_chunk_data.write().unwrap();
-            loading_data.data = Some((chunk_data, packet));
-        });
-    }
+/// Full block for solid blocks, slabs and stairs are approximated as full blocks
+pub fn collision_shape(block: BlockState) -> Option<AABB> {
+    block_data().solid_states.contains(&block).then(AABB::full_block)
+}
+
+pub type StoneChunkProvider = AsyncChunkProvider<StoneGenerator>;
 
-    fn const_unload_chunk(
-        &self, player: Entity, _commands: &mut Commands,
-        x: i32, z: i32
-    ) {
-        if let Some(entry) = self.loading_chunks.get(&(x, z)) {
-            let mut loading_data = entry.write().unwrap();
-            loading_data.waiters.retain(|s| *s != player);
+/// Generates the stone walkways of the example world
+pub struct StoneGenerator {
+    ground_block_state: u16,
+}
+impl StoneGenerator {
+    pub fn new() -> Self {
+        Self {
+            ground_block_state: MC_API.blocks.blocks_by_name().unwrap()["polished_andesite"].id as u16,
         }
+    }
+}
 
-        match self.unloading_chunks.get_mut(&(x, z)) {
-            Some(mut players) => {
-                players.push(player.clone());
-            }
-            None => {
-                self.unloading_chunks.insert((x, z), vec![player.clone()]);
+impl ChunkGenerator for StoneGenerator {
+    fn generate(&self, chunk_x: i32, chunk_z: i32, sections: usize) -> ChunkData {
+        let mut chunk_data = ChunkData::new(sections);
+
+        if (chunk_z == 0 || chunk_z == 2) && chunk_x >= 0 {
+            for x in 0..16 {
+                chunk_data.set_block(x, 21, 7, self.ground_block_state);
+                chunk_data.set_block(x, 21, 8, self.ground_block_state);
+                chunk_data.set_block(x, 21, 9, self.ground_block_state);
             }
         }
+        //chunk_data.get_section_mut(1).fill_with(ground_block_state);
+
+        chunk_data
     }
 }
 
-pub fn stone_chunk_provider(
-    world: &World,
-    chunk_provider: Res<Arc<StoneChunkProvider>>,
-) {
-    chunk_provider.unloading_chunks
-        .iter()
-        .for_each(|unloading_chunk| {
-            let unload_packet = C1AUnloadChunk {
-                chunk_x: unloading_chunk.key().0,
-                chunk_z: unloading_chunk.key().1,
-            }.to_rawpacket();
-
-            (&*unloading_chunk).iter().copied().for_each(|player| {
-                if let Some(entry) = world.get_entity(player) {
-                    entry.get::<ClientComponent>().unwrap()
-                        .0.send_raw_packet_sync(unload_packet.clone());
-                }
-            });
-        });
-    chunk_provider.unloading_chunks.clear();
-
-    chunk_provider.loading_chunks
-        .retain(|_, v| {
-            // We keep chunks that aren't yet loaded
-            if v.read().unwrap().data.is_none()
-            { return true }
-
-            let mut final_data = v.write().unwrap();
-            let data = final_data.data.take().unwrap();
-            let (_, raw_packet) = data;
-
-            for waiter in final_data.waiters.iter().copied() {
-                if let Some(entry) = world.get_entity(waiter) {
-                    entry.get::<ClientComponent>().unwrap()
-                        .0.send_raw_packet_sync(raw_packet.clone());
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
 
-            false
+    #[test]
+    fn reload_replaces_block_data() {
+        let before = block_data();
+        let stone = MC_API.blocks.blocks_by_name().unwrap()["stone"].min_state_id.unwrap() as BlockState;
+
+        let lookups = thread::spawn(move || {
+            for _ in 0..1000 {
+                assert!(collision_shape(stone).is_some());
+                assert!(collision_shape(0).is_none());
+                assert!(!cancels_fall(stone));
+            }
         });
+        let reloaded = reload_block_data();
+        lookups.join().unwrap();
+
+        assert!(!Arc::ptr_eq(&before, &reloaded));
+        assert!(Arc::ptr_eq(&block_data(), &reloaded));
+        assert_eq!(block_hardness(stone).map(|hardness| hardness.requires_tool), Some(true));
+    }
 }
@@ -1,20 +1,31 @@
-use crate::chunk_loader::StoneChunkProvider;
+use crate::error::{ ServerError, ServerResult };
 use crate::game_systems::SpawnPositionComponent;
-use mc_networking::client::client_event::{ ClientEvent, LoginStartResult };
+use crate::chunk_loader::{ block_hardness, cancels_fall };
+use mc_networking::client::{ ClientState, StatusResponse };
+use mc_networking::client::client_event::ClientEvent;
 use mc_networking::packets::{ client_bound::*, server_bound::* };
 use mc_server_lib::entity::{
     NetworkIdComponent, LocationComponent, ObjectUuidComponent, UsernameComponent,
-    ClientComponent,
-    chunk::{ ChunkObserverComponent, ChunkLocationComponent }
+    ClientComponent, PermissionLevelComponent, LivingEntityComponent,
+    equipment::EquipmentComponent,
+    collision::BlockCollisionComponent,
+    living::{ DamageSource, FallComponent },
+    ping::PingComponent,
+    movement_validation::MovementValidatorComponent,
+    teleport::TeleportComponent,
 };
-use mc_utils::Location;
+use mc_server_lib::chunk_holder::ChunkHolder;
+use mc_server_lib::digging::DiggingComponent;
+use mc_server_lib::mc_app::LoginPipeline;
+use mc_server_lib::chat_manager::{ ChatManager, OP_PERMISSION_LEVEL };
 
-use std::sync::Arc;
+use mc_networking::data_types::Gamemode;
+use mc_utils::{ Location, PositionExt };
 
 use uuid::Uuid;
-use log::{ debug, info };
+use log::{ debug, info, warn };
 use bevy_ecs::entity::Entity;
-use bevy_ecs::system::{ Query, Res, Commands };
+use bevy_ecs::system::{ Query, Res, ResMut, Commands };
 use bevy_ecs::component::Component;
 
 #[derive(Component)]
@@ -28,194 +39,580 @@ pub fn handle_clients(
         Option<&mut LocationComponent>,
         Option<&ObjectUuidComponent>,
         Option<&UsernameComponent>,
+        Option<&PermissionLevelComponent>,
+        Option<&mut FallComponent>,
+        Option<&mut LivingEntityComponent>,
+        Option<&mut DiggingComponent>,
+        Option<&mut PingComponent>,
+        Option<&mut TeleportComponent>,
+        Option<&MovementValidatorComponent>,
+        Option<&BlockCollisionComponent>,
+        Option<&mut EquipmentComponent>,
     )>,
     mut commands: Commands,
-    stone_chunk_provider: Res<Arc<StoneChunkProvider>>,
+    pipeline: Res<LoginPipeline>,
+    chat_manager: Res<ChatManager>,
+    mut chunk_holder: ResMut<ChunkHolder>,
 ) {
     query.for_each_mut(|(
         entity, client_component, client_events_component, 
-        mut location_component, object_uuid, username_component
+        mut location_component, object_uuid, username_component, permission_level,
+        mut fall_component, mut living_component, mut digging_component, mut ping_component,
+        mut teleport_component, movement_validator, block_collisions, mut equipment_component,
     )| {
         if let Ok(event) = client_events_component.0.try_recv() {
-            handle_client_event(
+            let result = handle_client_event(
                 entity, client_component,
                 location_component.as_mut().map(|a| &mut **a),
+                fall_component.as_mut().zip(living_component.as_mut()).map(|(a, b)| (&mut **a, &mut **b)),
+                digging_component.as_mut().map(|a| &mut **a),
+                ping_component.as_mut().map(|a| &mut **a),
+                teleport_component.as_mut().map(|a| &mut **a),
+                movement_validator, block_collisions,
+                equipment_component.as_mut().map(|a| &mut **a),
                 object_uuid, username_component,
-                &mut commands, event, &*stone_chunk_provider
+                permission_level.map(|a| a.0).unwrap_or(0),
+                &mut commands, event, &*pipeline, &*chat_manager, &mut *chunk_holder
             );
+            // The entity is then despawned by the client_cleanup_system
+            if let Err(error) = result {
+                warn!("Disconnecting {}: {}", client_component.0.peer_addr(), error);
+                client_component.0.disconnect_sync("Internal server error");
+            }
         }
     });
 }
 
+/// Despawns the entities of disconnected clients and removes them for the other players
+pub fn client_cleanup_system(
+    query: Query<(Entity, &ClientComponent, Option<&NetworkIdComponent>)>,
+    mut commands: Commands,
+) {
+    let mut removed_ids = vec![];
+    query.for_each(|(entity, client, network_id)| {
+        if client.0.try_get_state() == Some(ClientState::Disconnected) {
+            commands.entity(entity).despawn();
+            removed_ids.extend(network_id.map(|id| id.0));
+        }
+    });
+    if removed_ids.is_empty() {
+        return;
+    }
+
+    let packet = C38RemoveEntities { entities: removed_ids }.to_rawpacket();
+    query.for_each(|(_, client, _)| {
+        if client.0.try_get_state() == Some(ClientState::Play) {
+            client.0.send_raw_packet_sync(packet.clone());
+        }
+    });
+}
+
+fn status_response() -> StatusResponse {
+    let description: serde_json::Value =
+        serde_json::from_str(include_str!("slp_description.json")).unwrap();
+    StatusResponse::default().with_description(description)
+}
+
 fn handle_client_event(
     entity: Entity, client_component: &ClientComponent,
     location_component: Option<&mut LocationComponent>,
+    fall: Option<(&mut FallComponent, &mut LivingEntityComponent)>,
+    digging: Option<&mut DiggingComponent>,
+    ping: Option<&mut PingComponent>,
+    teleport: Option<&mut TeleportComponent>,
+    movement_validator: Option<&MovementValidatorComponent>,
+    block_collisions: Option<&BlockCollisionComponent>,
+    equipment: Option<&mut EquipmentComponent>,
     object_uuid: Option<&ObjectUuidComponent>, username_component: Option<&UsernameComponent>,
+    permission_level: u8,
     commands: &mut Commands,
     event: ClientEvent,
-    chunk_provider: &Arc<StoneChunkProvider>,
-) {
+    pipeline: &LoginPipeline,
+    chat_manager: &ChatManager,
+    chunk_holder: &mut ChunkHolder,
+) -> ServerResult<()> {
     match event {
         ClientEvent::ServerListPing { response } => {
-            response
-                .send(serde_json::from_str(include_str!("slp_response.json")).unwrap())
-                .unwrap();
+            response.send(status_response().into())
+                .map_err(|_| ServerError::ResponseDropped("server list ping"))?;
         }
 
-        ClientEvent::LoginStart { username, response } => {
-            let uuid = Uuid::new_v3(
-                &Uuid::new_v4(),
-                format!("OfflinePlayer:{}", username).as_bytes(),
-            );
-            commands.entity(entity)
-                .insert(ObjectUuidComponent(uuid))
-                .insert(UsernameComponent(username.clone()));
+        ClientEvent::LegacyServerListPing { response } => {
+            response.send((&status_response()).into())
+                .map_err(|_| ServerError::ResponseDropped("legacy server list ping"))?;
+        }
 
-            response
-                .send(LoginStartResult::Accept {
-                    compress: false,
-                    encrypt: false,
-                    username, uuid,
-                }).unwrap();
+        ClientEvent::LoginStart { username, response } => {
+            response.send(pipeline.accept_login(entity, username, commands))
+                .map_err(|_| ServerError::ResponseDropped("login start"))?;
         }
 
         ClientEvent::LoggedIn => {
             let player_username = username_component.map(|a| a.0.clone()).unwrap_or("You".to_string());
             info!("Player {player_username} just logged in");
 
-            let network_id = NetworkIdComponent::new();
-            let spawn_location = Location {
-                x: 1.5, y: 22., z: 8.5, yaw: -90., pitch: 0.,
-            };
-
+            let network_id = pipeline.join(
+                entity, client_component, &player_username,
+                object_uuid.map(|a| a.0).unwrap_or_else(Uuid::new_v4),
+                commands,
+            );
+            // Everyone is an operator on the example server
             commands.entity(entity)
-                .insert(network_id)
-                .insert(ChunkObserverComponent {
-                    radius: 12,
-                    loaded_chunks: Default::default(),
-                    chunk_provider: Box::new(Arc::clone(chunk_provider)) as _
-                })
-                .insert(ChunkLocationComponent::new(0, 0))
-                .insert(LocationComponent(spawn_location))
-                .insert(SpawnPositionComponent(spawn_location));
-
-            client_component.0.send_packet_sync(&C23Login {
-                entity_id: network_id.0,
-                is_hardcore: false,
-                gamemode: 2,
-                previous_gamemode: -1,
-                dimension_type: "heav:voidy".into(),
-                dimension_name: "heav:voidy".into(),
-                dimension_names: vec!["heav:voidy".into()],
-                registry_codec: crate::registry_codec::REGISTRY_CODEC.clone(),
-                hashed_seed: 0,
-                max_players: 2,
-                view_distance: 12,
-                simulation_distance: 12,
-                reduced_debug_info: false,
-                enable_respawn_screen: true,
-                is_debug: false,
-                is_flat: true,
-                death_location: None,
-            });
-
-            client_component.0.send_packet_sync(&{
-                let mut bldr = C16PluginMessageBuilder::new("minecraft:brand".into());
-                bldr.encoder.write_string(&username_component.map(|a| a.0.clone()).unwrap());
-                bldr.build()
-            });
-
-            client_component.0.send_packet_sync(&C2FPlayerAbilities::new(
-                true, false, false, false, 1., 0.1
-            ));
-            client_component.0.send_packet_sync(&C47SetHeldItem {
-                slot: 3,
-            });
-
-            let default_player = C34AddPlayer {
-                uuid: Uuid::new_v4(),
-                name: "".to_string(),
-                properties: vec![],
-                gamemode: 0,
-                ping: 0,
-                display_name: None,
-                sig_data: (),
-            };
-            client_component.0.send_packet_sync(&C34PlayerInfo::AddPlayers {
+                .insert(SpawnPositionComponent(pipeline.spawn_location))
+                .insert(PermissionLevelComponent(OP_PERMISSION_LEVEL))
+                .insert(LivingEntityComponent::new(20.))
+                .insert(FallComponent::default())
+                .insert(DiggingComponent::default());
+
+            send_packet(client_component, &C34PlayerInfo::AddPlayers {
                 players: vec![
-                    C34AddPlayer {
-                        uuid: object_uuid.map(|a| a.0.clone()).unwrap_or(Uuid::new_v4()),
-                        name: player_username.clone(),
-                        ..default_player.clone()
-                    },
                     C34AddPlayer {
                         uuid: Uuid::new_v4(),
-                        name: username_component.map(|a| a.0.clone()).unwrap_or("You".to_string()) + "2",
+                        name: player_username.clone() + "2",
+                        properties: vec![],
+                        gamemode: Gamemode::Survival,
+                        ping: 0,
                         display_name: Some(format!(r#"{{"text": "{}", "strikethrough": true}}"#, player_username)),
-                        ..default_player.clone()
+                        sig_data: (),
                     },
                 ],
-            });
-
-            client_component.0.send_packet_sync(&C4ASetDefaultSpawnPosition {
-                location: spawn_location.block_position(),
-                angle: spawn_location.pitch,
-            });
-            client_component.0.send_packet_sync(&C63TeleportEntity {
+            })?;
+            let spawn_location = pipeline.spawn_location;
+            send_packet(client_component, &C63TeleportEntity {
                 entity_id: network_id.0,
                 x: spawn_location.x, y: spawn_location.y, z: spawn_location.z,
                 yaw: spawn_location.yaw_angle(), pitch: spawn_location.pitch_angle(),
                 on_ground: false,
-            });
-            client_component.0.send_packet_sync(&C36SynchronizePlayerPosition {
-                x: spawn_location.x, y: spawn_location.y, z: spawn_location.z,
-                yaw: spawn_location.yaw, pitch: spawn_location.pitch,
-                flags: 0, teleport_id: 0, dismount_vehicle: false,
-            });
-            client_component.0.send_packet_sync(&C59UpdateTime {
-                world_age: 0,
-                time_of_day: -18000, // Not moving midnight
-            });
+            })?;
+            // Not moving midnight
+            send_packet(client_component, &C59UpdateTime::frozen(0, 18000))?;
+            send_packet(client_component, &chat_manager.commands_packet(OP_PERMISSION_LEVEL))?;
         }
 
-        ClientEvent::Logout => {
-            commands.entity(entity).despawn();
+        // The entity is despawned by client_cleanup_system
+        ClientEvent::Logout => (),
+
+        ClientEvent::Ping { delay } => {
+            if let Some(ping) = ping {
+                ping.update(delay);
+            }
+        }
+
+        ClientEvent::Brand { brand } => {
+            let player_username = username_component.map(|a| a.0.as_str()).unwrap_or("A player");
+            debug!("{player_username} is using the {brand:?} client");
         }
 
         ClientEvent::PluginMessage(S0CPluginMessage { channel, data }) => {
             debug!("Received {channel:?}: {}", String::from_utf8_lossy(&data));
         }
 
+        ClientEvent::ChatCommand(S03ChatCommand { command, .. }) => {
+            if let Err(error) = chat_manager.handle_command(entity, permission_level, &command, commands) {
+                send_packet(client_component, &C5FSystemChatMessage {
+                    content: error.to_chat(),
+                    overlay: false,
+                })?;
+            }
+        }
+
+        ClientEvent::CommandSuggestionsRequest(S08CommandSuggestionsRequest { transaction_id, text }) => {
+            send_packet(client_component, &chat_manager.suggestions(transaction_id, permission_level, &text))?;
+        }
+
+        ClientEvent::ConfirmTeleportation(S00ConfirmTeleportation { teleport_id }) => {
+            if let Some(teleport) = teleport {
+                teleport.confirm(teleport_id.0);
+            }
+        }
+
+        // Positions sent before the last teleportation is confirmed are from before it
+        ClientEvent::SetPlayerPosition(..) | ClientEvent::SetPlayerPositionAndRotation(..)
+            if teleport.as_ref().map_or(false, |teleport| !teleport.accepts_positions()) => (),
         ClientEvent::SetPlayerPosition(p) => {
             let location_cp = if let Some(a) = location_component {
                 a
-            } else { return };
+            } else { return Ok(()) };
 
-            location_cp.0.x = p.x;
-            location_cp.0.y = p.feet_y;
-            location_cp.0.z = p.z;
+            let to = Location { x: p.x, y: p.feet_y, z: p.z, ..location_cp.0 };
+            if move_player(
+                location_cp, to, movement_validator, block_collisions, teleport, client_component, chunk_holder,
+            ) {
+                update_fall(fall, &location_cp.0, p.on_ground, chunk_holder);
+            }
         },
         ClientEvent::SetPlayerPositionAndRotation(p) => {
             let location_cp = if let Some(a) = location_component {
                 a
-            } else { return };
+            } else { return Ok(()) };
 
-            location_cp.0.x = p.x;
-            location_cp.0.y = p.feet_y;
-            location_cp.0.z = p.z;
-
-            location_cp.0.yaw = p.yaw;
-            location_cp.0.pitch = p.pitch;
+            let to = Location { x: p.x, y: p.feet_y, z: p.z, yaw: p.yaw, pitch: p.pitch };
+            if move_player(
+                location_cp, to, movement_validator, block_collisions, teleport, client_component, chunk_holder,
+            ) {
+                update_fall(fall, &location_cp.0, p.on_ground, chunk_holder);
+            }
         },
         ClientEvent::SetPlayerRotation(p) => {
             let location_cp = if let Some(a) = location_component {
                 a
-            } else { return };
+            } else { return Ok(()) };
 
             location_cp.0.yaw = p.yaw;
             location_cp.0.pitch = p.pitch;
         },
 
+        ClientEvent::SetHeldItem(S27SetHeldItem { slot }) => {
+            if let Some(equipment) = equipment {
+                equipment.set_held_slot(slot);
+            }
+        }
+
+        ClientEvent::PlayerAction(S1CPlayerAction { status, position, sequence, .. }) => {
+            let digging = if let Some(a) = digging {
+                a
+            } else { return Ok(()) };
+
+            let broken = match status {
+                S1CStatus::StartedDigging => {
+                    // The example players always mine bare handed
+                    let ticks = chunk_holder.get_block(position)
+                        .and_then(block_hardness)
+                        .and_then(|hardness| hardness.break_ticks(None));
+                    match ticks {
+                        Some(0) => true,
+                        Some(ticks) => {
+                            digging.start(position, ticks);
+                            false
+                        }
+                        None => false,
+                    }
+                }
+                S1CStatus::CancelledDigging => {
+                    digging.cancel(position);
+                    false
+                }
+                S1CStatus::FinishedDigging => digging.finish(position),
+                _ => return Ok(()),
+            };
+
+            if broken {
+                chunk_holder.set_block(position, 0);
+            } else if let Some(block) = chunk_holder.get_block(position) {
+                // Reverts the break predicted by the client
+                send_packet(client_component, &C09BlockChange {
+                    position,
+                    block_id: block as i32,
+                })?;
+            }
+            send_packet(client_component, &C05AcknowledgeBlockChange { seq_id: sequence })?;
+        }
+
         _ => (),
     }
+    Ok(())
+}
+
+/// Queues a packet, failing instead of blocking if the client is too far behind
+fn send_packet(client: &ClientComponent, packet: &impl ClientBoundPacket) -> ServerResult<()> {
+    client.0.try_send_raw_packet(packet.to_rawpacket())?;
+    Ok(())
+}
+
+/// Moves the player to the location sent by its client, unless the validator rejects the move
+/// Players clipping in blocks are then pushed out of them
+/// Returns whether the player moved
+fn move_player(
+    location_cp: &mut LocationComponent, to: Location,
+    movement_validator: Option<&MovementValidatorComponent>,
+    block_collisions: Option<&BlockCollisionComponent>,
+    mut teleport: Option<&mut TeleportComponent>,
+    client_component: &ClientComponent,
+    chunk_holder: &ChunkHolder,
+) -> bool {
+    let moved = match (movement_validator, teleport.as_deref_mut()) {
+        (Some(validator), Some(teleport)) => validator.apply_move(to, location_cp, teleport, client_component),
+        _ => {
+            location_cp.0 = to;
+            true
+        }
+    };
+    if let (true, Some(block_collisions), Some(teleport)) = (moved, block_collisions, teleport) {
+        block_collisions.apply(location_cp, teleport, client_component, chunk_holder);
+    }
+    moved
+}
+
+/// Accumulates the fall of the player, damaging it when it lands
+fn update_fall(
+    fall: Option<(&mut FallComponent, &mut LivingEntityComponent)>,
+    location: &Location, on_ground: bool, chunk_holder: &ChunkHolder,
+) {
+    let (fall, living) = match fall {
+        Some(fall) => fall,
+        None => return,
+    };
+    // Water and ladders stop the fall of the players in them or right above them
+    let feet = location.block_position();
+    let cancelled = [feet, feet.sub_y(1)].iter()
+        .filter_map(|position| chunk_holder.get_block(*position))
+        .any(cancels_fall);
+
+    if let Some(damage) = fall.update(location.y, on_ground, cancelled) {
+        living.damage(damage, DamageSource::Fall);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_loader::{ MC_API, StoneChunkProvider, StoneGenerator, collision_shape };
+    use mc_networking::client::Client;
+    use mc_networking::client::client_event::LoginStartResult;
+    use mc_networking::data_types::{ Position, encoder::PacketEncoder };
+    use mc_networking::packets::{ PacketCompression, RawPacket };
+    use mc_server_lib::async_chunk_provider::async_chunk_provider_system;
+    use mc_server_lib::chunk_holder::chunk_holder_block_changes;
+    use mc_server_lib::chunk_manager::ConstChunkProvider;
+    use mc_server_lib::digging::digging_progress_system;
+    use mc_server_lib::test_utils::{ connected_client, read_packets_until };
+
+    use std::sync::Arc;
+    use std::time::{ Duration, Instant };
+
+    use bevy_ecs::schedule::{ Stage, SystemStage };
+    use bevy_ecs::system::CommandQueue;
+    use bevy_ecs::world::World;
+    use bytes::BytesMut;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpStream;
+
+    /// Spawn location of the tests, above the walkway generated in the chunk 0, 0
+    const SPAWN: Location = Location { x: 3.5, y: 22., z: 8.5, yaw: 0., pitch: 0. };
+
+    /// Client that logged in and reached the play state
+    async fn playing_client() -> (Client, flume::Receiver<ClientEvent>, TcpStream) {
+        let (ClientComponent(client), event_receiver, mut peer) = connected_client().await;
+
+        let mut handshake = PacketEncoder::default();
+        handshake.write_varint(mc_networking::PROTOCOL_VERSION);
+        handshake.write_string("localhost");
+        handshake.write_u16(25565);
+        handshake.write_varint(2);
+        let mut login_start = PacketEncoder::default();
+        login_start.write_string("Steve");
+        login_start.write_bool(false);
+        for packet in [handshake, login_start] {
+            let mut bytes = BytesMut::new();
+            RawPacket::new(0x00, packet.into_inner().freeze()).encode(PacketCompression::default(), &mut bytes);
+            peer.write_all(&bytes).await.unwrap();
+        }
+
+        match event_receiver.recv_async().await.unwrap() {
+            ClientEvent::LoginStart { username, response } => response.send(LoginStartResult::Accept {
+                uuid: Uuid::new_v4(),
+                username,
+                encrypt: false,
+                compress: false,
+            }).unwrap(),
+            event => panic!("expected a login start, got {event:?}"),
+        }
+        client.wait_for_state(ClientState::Play).await;
+        (client, event_receiver, peer)
+    }
+
+    /// Player at the [SPAWN] of the example world, the chunk it stands in is generated and sent to
+    /// it by the chunk provider of the server
+    /// The events sent to the returned sender are handled by [handle_clients]
+    async fn player_in_stone_world() -> (World, Entity, flume::Sender<ClientEvent>, TcpStream) {
+        let (ClientComponent(client), _events, peer) = connected_client().await;
+        let (event_sender, event_receiver) = flume::unbounded();
+
+        let mut world = World::default();
+        world.insert_resource(LoginPipeline::new(
+            mc_server_lib::mc_app::DimensionConfig::new("test:world"),
+            |_| crate::registry_codec::REGISTRY_CODEC.clone(),
+        ));
+        world.insert_resource(ChatManager::new());
+        world.insert_resource(ChunkHolder::default());
+        let chunk_provider = Arc::new(StoneChunkProvider::new(StoneGenerator::new(), crate::WORLD_HEIGHT / 16));
+        world.insert_resource(Arc::clone(&chunk_provider));
+        let player = world.spawn()
+            .insert(ClientComponent(client))
+            .insert(ClientEventsComponent(event_receiver))
+            .insert(NetworkIdComponent::new())
+            .insert(LocationComponent(SPAWN))
+            .insert(TeleportComponent::default())
+            .id();
+
+        let mut queue = CommandQueue::default();
+        chunk_provider.const_load_chunk(player, &mut Commands::new(&mut queue, &world), 0, 0);
+        let mut stage = SystemStage::single(async_chunk_provider_system::<StoneGenerator>);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !world.resource::<ChunkHolder>().contains(0, 0) {
+            assert!(Instant::now() < deadline, "the chunk was never generated");
+            stage.run(&mut world);
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        (world, player, event_sender, peer)
+    }
+
+    #[tokio::test]
+    async fn disconnected_client_is_despawned() {
+        let (ClientComponent(client), _events, _peer) = connected_client().await;
+        let (other_client, _other_events, mut other_peer) = playing_client().await;
+
+        let mut world = World::default();
+        let network_id = NetworkIdComponent::new();
+        let entity = world.spawn()
+            .insert(ClientComponent(client.clone()))
+            .insert(network_id)
+            .id();
+        let other_entity = world.spawn().insert(ClientComponent(other_client)).id();
+        let mut stage = SystemStage::single(client_cleanup_system);
+
+        stage.run(&mut world);
+        assert!(world.get_entity(entity).is_some());
+
+        client.disconnect("Bye").await;
+        stage.run(&mut world);
+        assert!(world.get_entity(entity).is_none());
+        assert!(world.get_entity(other_entity).is_some());
+
+        // The remaining players no longer see the disconnected one
+        let removal = read_packets_until(&mut other_peer, C38RemoveEntities::PACKET_ID).await
+            .pop()
+            .unwrap();
+        assert_eq!(removal.data, C38RemoveEntities { entities: vec![network_id.0] }.to_rawpacket().data);
+    }
+
+    #[tokio::test]
+    async fn dropped_response_disconnects_the_client() {
+        let (ClientComponent(client), _events, _peer) = connected_client().await;
+        let (event_sender, event_receiver) = flume::unbounded();
+
+        let mut world = World::default();
+        world.insert_resource(LoginPipeline::new(
+            mc_server_lib::mc_app::DimensionConfig::new("test:world"),
+            |_| crate::registry_codec::REGISTRY_CODEC.clone(),
+        ));
+        world.insert_resource(ChatManager::new());
+        world.insert_resource(ChunkHolder::default());
+        world.spawn()
+            .insert(ClientComponent(client.clone()))
+            .insert(ClientEventsComponent(event_receiver));
+
+        // The client task stopped waiting for the response
+        let (response, response_receiver) = tokio::sync::oneshot::channel();
+        drop(response_receiver);
+        event_sender.send(ClientEvent::ServerListPing { response }).unwrap();
+
+        SystemStage::single(handle_clients).run(&mut world);
+        assert_eq!(client.get_state().await, ClientState::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn survival_digging_breaks_generated_block() {
+        let (mut world, player, events, mut peer) = player_in_stone_world().await;
+        world.entity_mut(player).insert(DiggingComponent::default());
+        let mut handler = SystemStage::single(handle_clients);
+
+        // Part of the walkway under the spawn
+        let position = Position { x: 3, y: 21, z: 8 };
+        let ground = world.resource::<ChunkHolder>().get_block(position).unwrap();
+        let hardness = block_hardness(ground).expect("the walkway isn't a real block");
+        let action = |status, sequence| ClientEvent::PlayerAction(S1CPlayerAction {
+            status,
+            position,
+            face: S1CDiggingFace::Top,
+            sequence,
+        });
+
+        events.send(action(S1CStatus::StartedDigging, 1)).unwrap();
+        handler.run(&mut world);
+        assert!(world.get::<DiggingComponent>(player).unwrap().is_digging(position));
+        // The block isn't broken yet, the client is told it is still there
+        let ids = read_packets_until(&mut peer, C05AcknowledgeBlockChange::PACKET_ID).await
+            .iter()
+            .map(|packet| packet.packet_id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [
+            C1FChunkDataAndUpdateLight::PACKET_ID,
+            C09BlockChange::PACKET_ID,
+            C05AcknowledgeBlockChange::PACKET_ID,
+        ]);
+
+        // The example players mine bare handed
+        let mut digging_progress = SystemStage::single(digging_progress_system);
+        for _ in 0..hardness.break_ticks(None).unwrap() {
+            digging_progress.run(&mut world);
+        }
+        events.send(action(S1CStatus::FinishedDigging, 2)).unwrap();
+        handler.run(&mut world);
+        assert_eq!(world.resource::<ChunkHolder>().get_block(position), Some(0));
+
+        // The player was sent the chunk so it receives its changes
+        SystemStage::single(chunk_holder_block_changes).run(&mut world);
+        let packets = read_packets_until(&mut peer, C09BlockChange::PACKET_ID).await;
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].packet_id, C05AcknowledgeBlockChange::PACKET_ID);
+        assert_eq!(packets[1].data, C09BlockChange { position, block_id: 0 }.to_rawpacket().data);
+    }
+
+    #[tokio::test]
+    async fn player_clipping_in_generated_block_is_pushed_out() {
+        let (mut world, player, events, mut peer) = player_in_stone_world().await;
+        world.entity_mut(player).insert(BlockCollisionComponent(collision_shape));
+
+        // Inside of the walkway under the spawn
+        events.send(ClientEvent::SetPlayerPosition(S13SetPlayerPosition {
+            x: SPAWN.x,
+            feet_y: 21.4,
+            z: SPAWN.z,
+            on_ground: false,
+        })).unwrap();
+        SystemStage::single(handle_clients).run(&mut world);
+
+        let standing = Location { y: 22., ..SPAWN };
+        assert_eq!(world.get::<LocationComponent>(player).unwrap().0, standing);
+        assert!(world.get::<TeleportComponent>(player).unwrap().is_pending());
+        let correction = read_packets_until(&mut peer, C36SynchronizePlayerPosition::PACKET_ID).await
+            .pop()
+            .unwrap();
+        assert_eq!(correction.data, C36SynchronizePlayerPosition {
+            x: standing.x, y: standing.y, z: standing.z, yaw: 0., pitch: 0.,
+            flags: 0, teleport_id: 0, dismount_vehicle: false,
+        }.to_rawpacket().data);
+    }
+
+    #[tokio::test]
+    async fn water_cancels_fall_damage() {
+        let (mut world, player, events, _peer) = player_in_stone_world().await;
+        world.entity_mut(player)
+            .insert(FallComponent::default())
+            .insert(LivingEntityComponent::new(20.));
+        let mut handler = SystemStage::single(handle_clients);
+        let mut fall = |world: &mut World| {
+            for (feet_y, on_ground) in [(40., false), (SPAWN.y, true)] {
+                events.send(ClientEvent::SetPlayerPosition(S13SetPlayerPosition {
+                    x: SPAWN.x,
+                    feet_y,
+                    z: SPAWN.z,
+                    on_ground,
+                })).unwrap();
+                handler.run(world);
+            }
+        };
+
+        // Over the walkway, where the player lands
+        let water = MC_API.blocks.blocks_by_name().unwrap()["water"].min_state_id.unwrap() as u16;
+        let surface = SPAWN.block_position();
+        assert!(world.resource_mut::<ChunkHolder>().set_block(surface, water));
+        fall(&mut world);
+        assert_eq!(world.get::<LivingEntityComponent>(player).unwrap().health, 20.);
+
+        assert!(world.resource_mut::<ChunkHolder>().set_block(surface, 0));
+        fall(&mut world);
+        assert!(world.get::<LivingEntityComponent>(player).unwrap().health < 20.);
+    }
 }
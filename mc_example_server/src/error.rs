@@ -0,0 +1,14 @@
+use mc_networking::client::TrySendError;
+
+use thiserror::Error;
+
+/// Errors of the handling of a client, the client is disconnected when one happens
+#[derive(Error, Debug)]
+pub enum ServerError {
+    #[error("the client stopped waiting for the {0} response")]
+    ResponseDropped(&'static str),
+    #[error("could not send a packet: {0}")]
+    Send(#[from] TrySendError),
+}
+
+pub type ServerResult<T> = Result<T, ServerError>;
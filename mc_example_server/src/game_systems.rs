@@ -1,12 +1,20 @@
+use crate::chunk_loader::reload_block_data;
 use mc_server_lib::entity::{ ClientComponent, LocationComponent };
-use mc_networking::packets::client_bound::*;
+use mc_server_lib::entity::physics::gravity_system;
+use mc_server_lib::entity::movement::entity_movement_system;
+use mc_server_lib::entity_pool::teleport_entity;
+use mc_server_lib::chat_manager::{ ChatManager, ArgumentKind, ArgumentValue, OP_PERMISSION_LEVEL };
 use mc_utils::Location;
 
+use std::thread;
+
 use bevy_ecs::entity::Entity;
-use bevy_ecs::schedule::SystemSet;
+use bevy_ecs::schedule::{ SystemSet, ParallelSystemDescriptorCoercion };
 use bevy_ecs::system::{ Query, Commands };
 use bevy_ecs::component::Component;
 use bevy_ecs::query::{ With, Added };
+use bevy_ecs::world::World;
+use log::info;
 
 #[derive(Component)]
 pub struct SpawnPositionComponent(pub Location);
@@ -21,6 +29,33 @@ pub fn game_systems() -> SystemSet {
         .with_system(teleport_if_dead)
         .with_system(add_update_timer)
         .with_system(update_status)
+        .with_system(gravity_system.label("gravity"))
+        .with_system(entity_movement_system.after("gravity"))
+}
+
+pub fn chat_manager() -> ChatManager {
+    let mut chat_manager = ChatManager::new();
+    chat_manager.register_command("tp", &[("location", ArgumentKind::Vec3)], |sender, arguments, commands| {
+        let target = match arguments {
+            [ArgumentValue::Vec3(target)] => *target,
+            _ => return,
+        };
+        commands.add(move |world: &mut World| {
+            let location = match world.get::<LocationComponent>(sender) {
+                Some(location) => target.resolve(&location.0),
+                None => return,
+            };
+            teleport_entity(world, sender, location);
+        });
+    }).with_permission_level(OP_PERMISSION_LEVEL);
+    chat_manager.register_command("reload", &[], |_, _, _| {
+        // Parsing the minecraft data would stall the tick
+        thread::spawn(|| {
+            reload_block_data();
+            info!("Reloaded the block data");
+        });
+    }).with_permission_level(OP_PERMISSION_LEVEL);
+    chat_manager
 }
 
 fn add_update_timer(
@@ -41,20 +76,16 @@ fn update_status(
             return;
         }
         timer.last_update = 6;
-        client.0.send_packet_sync(&C40SetActionBarText {
-            text: format!(r#"{{"text": "{:.01}%"}}"#, 100. + (-1. / ((location.0.x - 1.5) / 25. + 1.).max(1.)) * 100.),
-        });
+        let progress = 100. + (-1. / ((location.0.x - 1.5) / 25. + 1.).max(1.)) * 100.;
+        client.0.send_action_bar(serde_json::json!({ "text": format!("{:.01}%", progress) }));
     });
 }
 
 fn teleport_if_dead(
-    mut query: Query<(
-        &ClientComponent,
-        Option<&SpawnPositionComponent>,
-        &mut LocationComponent,
-    )>,
+    query: Query<(Entity, Option<&SpawnPositionComponent>, &LocationComponent), With<ClientComponent>>,
+    mut commands: Commands,
 ) {
-    query.for_each_mut(|(client_cp, spawn_pos, mut location_cp)| {
+    query.for_each(|(entity, spawn_pos, location_cp)| {
         if location_cp.0.z > 6.5 && location_cp.0.z < 10.5 && 
             location_cp.0.x > -0.3 && location_cp.0.y > 21. {
             return;
@@ -64,11 +95,6 @@ fn teleport_if_dead(
             x: 0., y: 50., z: 0.,
             yaw: 0., pitch: 0.
         });
-        location_cp.0 = spawn_pos;
-
-        client_cp.0.send_packet_sync(&C36SynchronizePlayerPosition {
-            x: spawn_pos.x, y: spawn_pos.y, z: spawn_pos.z, yaw: 0., pitch: 0.,
-            flags: 0b11000, teleport_id: 0, dismount_vehicle: false,
-        });
+        commands.add(move |world: &mut World| teleport_entity(world, entity, spawn_pos));
     });
 }
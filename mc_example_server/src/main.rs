@@ -1,14 +1,19 @@
 mod chunk_loader;
 mod client_handler;
+mod error;
 mod registry_codec;
 mod game_systems;
 
-use crate::chunk_loader::*;
-use chunk_loader::StoneChunkProvider;
-use client_handler::{ ClientEventsComponent, handle_clients };
-use mc_server_lib::mc_app::{ McApp, McAppStage };
+use chunk_loader::{ StoneChunkProvider, StoneGenerator, collision_shape };
+use client_handler::{ ClientEventsComponent, handle_clients, client_cleanup_system };
+use mc_server_lib::async_chunk_provider::async_chunk_provider_system;
+use mc_server_lib::mc_app::{ McApp, McAppStage, LoginPipeline, DimensionConfig };
 use mc_server_lib::entity::ClientComponent;
+use mc_server_lib::entity::movement_validation::MaxSpeedValidator;
 use mc_networking::client::Client;
+use mc_networking::data_types::Gamemode;
+use mc_utils::Location;
+use mc_utils::abort_contract::AbortContract;
 use mc_utils::tick_scheduler::{TickProfiler, TickScheduler};
 
 use std::{ sync::{ Arc, RwLock }, time::Duration };
@@ -65,7 +70,13 @@ async fn start_network_server(addr: impl ToSocketAddrs, clients: Arc<RwLock<Vec<
     let listener = TcpListener::bind(addr).await.unwrap();
 
     loop {
-        let (socket, ..) = listener.accept().await.unwrap();
+        let socket = match listener.accept().await {
+            Ok((socket, _)) => socket,
+            Err(error) => {
+                warn!("Could not accept a connection: {}", error);
+                continue;
+            }
+        };
         let (client, event_receiver) = Client::new(socket, 100, 500);
         clients.write().unwrap().push((
             ClientComponent(client), ClientEventsComponent(event_receiver)
@@ -78,27 +89,46 @@ fn main() {
 
     setup_logger(if cfg!(debug_assertions) { LevelFilter::Debug } else { LevelFilter::Info });
 
+    let abort_contract = Arc::new(AbortContract::new());
+
     // Starts legion in a nes thread
-    std::thread::spawn({
+    let ecs_thread = std::thread::spawn({
         let pending_clients = Arc::clone(&pending_clients);
+        let abort_contract = Arc::clone(&abort_contract);
         || {
-            let chunk_provider = Arc::new(StoneChunkProvider::new());
+            let chunk_provider = Arc::new(StoneChunkProvider::new(StoneGenerator::new(), WORLD_HEIGHT / 16));
 
             let mut app = McApp::new();
             app.world.insert_resource(Arc::clone(&chunk_provider));
+            let mut login_pipeline = LoginPipeline::new(
+                DimensionConfig::new("heav:voidy"),
+                |_| registry_codec::REGISTRY_CODEC.clone(),
+            )
+                .with_chunk_provider(move || Box::new(Arc::clone(&chunk_provider)) as _)
+                .with_movement_validator(|| Box::new(MaxSpeedValidator::default()) as _)
+                .with_block_collisions(collision_shape)
+                .with_idle_timeout(Duration::from_secs(60))
+                .with_spawn_location(Location { x: 1.5, y: 22., z: 8.5, yaw: -90., pitch: 0. })
+                .with_gamemode(Gamemode::Adventure);
+            login_pipeline.max_players = 2;
+            login_pipeline.brand = "heav".to_string();
+            app.world.insert_resource(login_pipeline);
+            app.world.insert_resource(game_systems::chat_manager());
 
             app.add_system(McAppStage::BeforeTick, client_pusher_system(pending_clients));
 
-            app.add_system(McAppStage::Tick, stone_chunk_provider);
+            app.add_system(McAppStage::Tick, async_chunk_provider_system::<StoneGenerator>);
             app.add_system(McAppStage::Tick, handle_clients);
+            app.add_system(McAppStage::AfterTick, client_cleanup_system);
             app.add_system_set(McAppStage::Tick, game_systems::game_systems());
 
             TickScheduler::builder()
                 .minimum_duration_per_ticks(Duration::from_secs(1) / 120)
                 .profiling_interval(Duration::from_secs(3))
+                .abort_contract(abort_contract)
                 .build()
                 .start(
-                    move || {
+                    || {
                         app.tick();
                     },
                     Some(|profiler: &TickProfiler| {
@@ -108,11 +138,22 @@ fn main() {
                         }
                     }),
                 );
+            info!("Shutting down");
+            app.shutdown();
         }
     });
 
     let tokio_runtime = runtime::Builder::new_multi_thread()
         .enable_all().build().unwrap();
     let _ = tokio_runtime.enter();
-    tokio_runtime.block_on(start_network_server("0.0.0.0:25565", pending_clients));
+    tokio_runtime.block_on(async {
+        tokio::select! {
+            _ = start_network_server("0.0.0.0:25565", pending_clients) => (),
+            _ = tokio::signal::ctrl_c() => (),
+        }
+    });
+
+    // Lets the current tick end and runs the shutdown systems
+    abort_contract.abort();
+    ecs_thread.join().unwrap();
 }
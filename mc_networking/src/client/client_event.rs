@@ -1,3 +1,4 @@
+use super::LegacyStatusResponse;
 use crate::packets::server_bound::*;
 
 use tokio::sync::oneshot;
@@ -21,6 +22,11 @@ pub enum ClientEvent {
     ServerListPing {
         response: oneshot::Sender<serde_json::Value>,
     },
+    /// Sent for the server list pings of clients older than 1.7 and some server scanners
+    /// The client is disconnected after the response is sent
+    LegacyServerListPing {
+        response: oneshot::Sender<LegacyStatusResponse>,
+    },
     /// Sent after a client has been entered the "login" phase
     /// A respone in the respone channel will be awaited, after which
     /// the client will either be disconnected
@@ -41,8 +47,16 @@ pub enum ClientEvent {
         delay: u128,
     },
 
+    ConfirmTeleportation(S00ConfirmTeleportation),
+    ChatCommand(S03ChatCommand),
     ChatMessage(S04ChatMessage),
+    CommandSuggestionsRequest(S08CommandSuggestionsRequest),
     ClickContainer(S0AClickContainer),
+    /// The client's brand, sent on the `minecraft:brand` channel
+    Brand {
+        brand: String,
+    },
+    /// Plugin messages on any channel other than `minecraft:brand`
     PluginMessage(S0CPluginMessage),
     Interact(S0FInteract),
     SetPlayerPosition(S13SetPlayerPosition),
@@ -53,6 +67,7 @@ pub enum ClientEvent {
     PlayerAction(S1CPlayerAction),
     SetHeldItem(S27SetHeldItem),
     SetCreativeModeSlot(S2ASetCreativeModeSlot),
+    UpdateSign(S2DUpdateSign),
     SwingArm(S2ESwingArm),
     UseItemOn(S30UseItemOn),
 }
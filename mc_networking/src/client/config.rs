@@ -0,0 +1,36 @@
+use super::RateLimit;
+use crate::packets::DEFAULT_MAX_PACKET_SIZE;
+
+/// Threshold used by the servers that don't set one, in bytes
+pub const DEFAULT_COMPRESSION_THRESHOLD: i32 = 50;
+
+/// Settings of a connection, see [Client::with_config](super::Client::with_config)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClientConfig {
+    /// Size of the [ClientEvent](super::client_event::ClientEvent) channel
+    pub event_buffer: usize,
+    /// Size of the outgoing packets queue
+    pub packet_buffer: usize,
+    /// Packets of at least this many bytes are compressed once a player accepted with
+    /// `compress` logs in, None to never compress
+    pub compression_threshold: Option<i32>,
+    /// Encrypts the connection of every player, even when the
+    /// [LoginStartResult](super::client_event::LoginStartResult) doesn't ask for it
+    /// The players aren't authenticated with the session servers
+    pub encryption: bool,
+    pub rate_limit: RateLimit,
+    /// Packets announcing a bigger length disconnect the client
+    pub max_packet_size: usize,
+}
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            event_buffer: 100,
+            packet_buffer: 100,
+            compression_threshold: Some(DEFAULT_COMPRESSION_THRESHOLD),
+            encryption: false,
+            rate_limit: RateLimit::default(),
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+        }
+    }
+}
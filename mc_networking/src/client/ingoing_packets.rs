@@ -1,4 +1,4 @@
-use super::{ keep_alive::*, * };
+use super::{ keep_alive::*, rate_limit::*, * };
 use crate::{
     packets::{ client_bound::*, server_bound::*, PacketCompression, RawPacket },
     DecodingError,
@@ -13,11 +13,10 @@ use openssl::{
 };
 use rand::RngCore;
 use serde_json::json;
-use std::{ convert::TryInto, sync::Arc };
+use std::{ convert::TryInto, sync::{ Arc, Mutex } };
 use thiserror::Error;
 use tokio::{
-    io::AsyncReadExt,
-    net::tcp::OwnedReadHalf,
+    io::{ AsyncRead, AsyncReadExt },
     sync::{ oneshot, Notify, RwLock },
     time::Instant,
 };
@@ -32,6 +31,8 @@ pub(super) enum ClientListenError {
     PacketSenderSendError(#[from] flume::SendError<OutgoingPacketEvent>),
     #[error("could not receive an event response")]
     ResponseRecvError(#[from] oneshot::error::RecvError),
+    #[error("the client exceeded its rate limit")]
+    TooManyPackets,
     #[error(
         "received an invalid packet (id {packet_id:x}, name {packet_name:?}) on state {state:?}: \
          {message}"
@@ -47,10 +48,13 @@ pub(super) type ClientListenResult<T> = Result<T, ClientListenError>;
 
 pub(super) async fn listen_ingoing_packets(
     compression: Arc<RwLock<PacketCompression>>,
-    mut read: OwnedReadHalf,
+    mut read: impl AsyncRead + Unpin,
     packet_sender: flume::Sender<OutgoingPacketEvent>,
     event_sender: flume::Sender<ClientEvent>,
-    state: Arc<RwLock<ClientState>>,
+    state: Arc<SharedClientState>,
+    protocol_version: Arc<RwLock<Option<i32>>>,
+    last_packet_received: Arc<Mutex<std::time::Instant>>,
+    config: ClientConfig,
 ) -> ClientListenResult<()> {
     let keep_alive_data = Arc::new(RwLock::new(KeepAliveData {
         has_responded: false,
@@ -72,8 +76,13 @@ pub(super) async fn listen_ingoing_packets(
         bytes
     };
 
+    let mut packet_bucket = config.rate_limit.max_packets_per_second.map(TokenBucket::new);
+    let mut byte_bucket = config.rate_limit.max_bytes_per_second.map(TokenBucket::new);
+
     let mut read_bytes = BytesMut::with_capacity(10);
     let mut encryption: Option<Crypter> = None;
+    // Only the first packet of a connection can be a legacy server list ping
+    let mut first_packet = true;
 
     loop {
         if let ClientState::Disconnected = state.read().await.clone() {
@@ -86,7 +95,25 @@ pub(super) async fn listen_ingoing_packets(
             let mut new_bytes = [0; 1024];
             let mut decrypted_new_bytes = [0; 1024];
             loop {
-                match RawPacket::decode(&mut read_bytes, packet_compression) {
+                // A modern handshake never starts with 0xFE as it is shorter than 254 bytes
+                if first_packet && read_bytes.first() == Some(&0xFE) {
+                    debug!("Received a legacy server list ping");
+                    let (response_sender, response_receiver) = oneshot::channel();
+                    event_sender
+                        .send_async(ClientEvent::LegacyServerListPing {
+                            response: response_sender,
+                        })
+                        .await
+                        .unwrap();
+                    let response: LegacyStatusResponse = response_receiver.await.unwrap();
+                    packet_sender
+                        .send_async(OutgoingPacketEvent::Raw(response.encode().into()))
+                        .await?;
+                    *(state.write().await) = ClientState::Disconnected;
+                    event_sender.send_async(ClientEvent::Logout).await.unwrap();
+                    return Ok(());
+                }
+                match RawPacket::decode_with_max_size(&mut read_bytes, packet_compression, config.max_packet_size) {
                     Ok(raw_packet) => break raw_packet,
                     Err(DecodingError::NotEnoughBytes) => (),
                     Err(DecodingError::ZeroSize { packet_id }) => {
@@ -96,6 +123,18 @@ pub(super) async fn listen_ingoing_packets(
                     Err(e) => return Err(e.into()),
                 }
                 let received = read.read(&mut new_bytes).await?;
+                if received == 0 {
+                    // The connection was closed after being disconnected by the server
+                    if *state.read().await == ClientState::Disconnected {
+                        return Ok(());
+                    }
+                    return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+                }
+                if let Some(bucket) = &mut byte_bucket {
+                    if !bucket.take(received) {
+                        return Err(ClientListenError::TooManyPackets);
+                    }
+                }
                 let decrypted_output = if let Some(encryption) = &mut encryption {
                     let encrypted = encryption
                         .update(&new_bytes[0..received], &mut decrypted_new_bytes)
@@ -108,6 +147,13 @@ pub(super) async fn listen_ingoing_packets(
                 read_bytes.extend_from_slice(&decrypted_output[0..received]);
             }
         };
+        first_packet = false;
+        *last_packet_received.lock().unwrap() = std::time::Instant::now();
+        if let Some(bucket) = &mut packet_bucket {
+            if !bucket.take(1) {
+                return Err(ClientListenError::TooManyPackets);
+            }
+        }
         trace!(
             "Received packet 0x{:x} with data of length {}",
             raw_packet.packet_id,
@@ -117,8 +163,33 @@ pub(super) async fn listen_ingoing_packets(
         let current_state = state.read().await.clone();
         match current_state {
             ClientState::Handshaking => {
-                let handshake = S00Handshake::decode(raw_packet)?;
+                let handshake = S00Handshake::decode(raw_packet, &current_state)?;
                 trace!("Received Handshake: {:?}", handshake);
+                *protocol_version.write().await = Some(handshake.protocol_version);
+                // Status requests of other versions are expected, they just show the server as incompatible
+                if handshake.next_state == 2 && handshake.protocol_version != crate::PROTOCOL_VERSION {
+                    warn!(
+                        "Client logging in with protocol version {} while {} ({}) is implemented",
+                        handshake.protocol_version,
+                        crate::PROTOCOL_VERSION,
+                        crate::MINECRAFT_VERSION,
+                    );
+                    let reason = if handshake.protocol_version < crate::PROTOCOL_VERSION {
+                        format!("Outdated client! Please use {}", crate::MINECRAFT_VERSION)
+                    } else {
+                        format!("Outdated server! I'm still on {}", crate::MINECRAFT_VERSION)
+                    };
+                    packet_sender
+                        .send_async(OutgoingPacketEvent::Packet(
+                            C00LoginDisconnect {
+                                reason: json!({ "text": reason }),
+                            }.to_rawpacket(),
+                        ))
+                        .await?;
+                    *(state.write().await) = ClientState::Disconnected;
+                    event_sender.send_async(ClientEvent::Logout).await.unwrap();
+                    break;
+                }
                 *(state.write().await) = match handshake.next_state {
                     1 => ClientState::Status,
                     2 => ClientState::Login,
@@ -136,7 +207,7 @@ pub(super) async fn listen_ingoing_packets(
 
             ClientState::Status => {
                 if raw_packet.packet_id == S00Request::PACKET_ID {
-                    S00Request::decode(raw_packet)?;
+                    S00Request::decode(raw_packet, &current_state)?;
                     let event_response = {
                         let (response_sender, response_receiver) = oneshot::channel();
                         event_sender
@@ -157,7 +228,7 @@ pub(super) async fn listen_ingoing_packets(
                         .await?;
                 }
                 else if raw_packet.packet_id == S01Ping::PACKET_ID {
-                    let packet: S01Ping = S01Ping::decode(raw_packet)?;
+                    let packet: S01Ping = S01Ping::decode(raw_packet, &current_state)?;
                     packet_sender
                         .send_async(OutgoingPacketEvent::Packet(
                             C01Pong {
@@ -169,20 +240,15 @@ pub(super) async fn listen_ingoing_packets(
                     event_sender.send_async(ClientEvent::Logout).await.unwrap();
                     break;
                 } else {
-                    return Err(ClientListenError::InvalidPacket {
-                        packet_id: raw_packet.packet_id,
-                        state: current_state,
-                        packet_name: None,
-                        message: "unknown packet id".to_string(),
-                    });
+                    // The frame was already consumed, an unknown id doesn't desync the stream
+                    debug!("Unknown packet id received (status state) 0x{:02x}", raw_packet.packet_id);
                 }
             }
 
             ClientState::Login => {
                 macro_rules! enable_compression {
                     () => {
-                        if login_compress {
-                            let new_compression = 50;
+                        if let (true, Some(new_compression)) = (login_compress, config.compression_threshold) {
                             let compression_notify = Arc::new(Notify::new());
                             packet_sender
                                 .send_async(OutgoingPacketEvent::PacketNow(
@@ -206,7 +272,7 @@ pub(super) async fn listen_ingoing_packets(
                 match raw_packet.packet_id {
                     S00LoginStart::PACKET_ID => {
                         debug!("login start");
-                        let login_state = S00LoginStart::decode(raw_packet)?;
+                        let login_state = S00LoginStart::decode(raw_packet, &current_state)?;
 
                         let event_response = {
                             let (response_sender, response_receiver) = oneshot::channel();
@@ -229,7 +295,7 @@ pub(super) async fn listen_ingoing_packets(
                                 login_compress = compress;
                                 login_uuid = Some(uuid);
                                 login_username = Some(username.clone());
-                                if encrypt {
+                                if encrypt || config.encryption {
                                     packet_sender
                                         .send_async(OutgoingPacketEvent::Packet(
                                                 C01EncryptionRequest {
@@ -295,7 +361,7 @@ pub(super) async fn listen_ingoing_packets(
                                 message: "this packet must follow a login start packet".to_string(),
                             }),
                         };
-                        let encryption_response = S01EncryptionResponse::decode(raw_packet)?;
+                        let encryption_response = S01EncryptionResponse::decode(raw_packet, &current_state)?;
 
                         let shared_key: [u8; 16] = {
                             let mut shared_key = [0; 128];
@@ -384,7 +450,7 @@ pub(super) async fn listen_ingoing_packets(
                 macro_rules! match_packets {
                     ( $struct_name: ident body $variant: ident ) => {
                         event_sender
-                            .send_async(ClientEvent::$variant($struct_name::decode(raw_packet)?))
+                            .send_async(ClientEvent::$variant($struct_name::decode(raw_packet, &current_state)?))
                             .await
                             .unwrap()
                     };
@@ -408,15 +474,23 @@ pub(super) async fn listen_ingoing_packets(
                 }
 
                 match_packets! {
+                    S00ConfirmTeleportation => ConfirmTeleportation,
+                    S03ChatCommand => ChatCommand,
                     S04ChatMessage => ChatMessage,
-                    S06ClientCommand => { unimplemented!("S06ClientCommand") },
+                    S06ClientCommand => {
+                        debug!("Unsupported packet received (play state) S06ClientCommand");
+                    },
+                    S08CommandSuggestionsRequest => CommandSuggestionsRequest,
                     S0AClickContainer => ClickContainer,
-                    S0CPluginMessage => PluginMessage,
+                    S0CPluginMessage => {
+                        let event = plugin_message_event(S0CPluginMessage::decode(raw_packet, &current_state)?)?;
+                        event_sender.send_async(event).await.unwrap();
+                    },
                     S0FInteract => Interact,
                     S11KeepAlive => {
                         debug!("Received keep alive");
                         let mut data = keep_alive_data.write().await;
-                        let keep_alive = S11KeepAlive::decode(raw_packet)?;
+                        let keep_alive = S11KeepAlive::decode(raw_packet, &current_state)?;
 
                         if keep_alive.id == data.last_id {
                             data.has_responded = true;
@@ -428,14 +502,27 @@ pub(super) async fn listen_ingoing_packets(
                             .await
                             .unwrap();
                     },
-                    S13SetPlayerPosition => SetPlayerPosition,
-                    S14SetPlayerPositionAndRotation => SetPlayerPositionAndRotation,
-                    S15SetPlayerRotation => SetPlayerRotation,
+                    S13SetPlayerPosition => {
+                        let packet = S13SetPlayerPosition::decode(raw_packet, &current_state)?;
+                        check_finite(S13SetPlayerPosition::PACKET_ID, "SetPlayerPosition", packet.is_finite())?;
+                        event_sender.send_async(ClientEvent::SetPlayerPosition(packet)).await.unwrap();
+                    },
+                    S14SetPlayerPositionAndRotation => {
+                        let packet = S14SetPlayerPositionAndRotation::decode(raw_packet, &current_state)?;
+                        check_finite(S14SetPlayerPositionAndRotation::PACKET_ID, "SetPlayerPositionAndRotation", packet.is_finite())?;
+                        event_sender.send_async(ClientEvent::SetPlayerPositionAndRotation(packet)).await.unwrap();
+                    },
+                    S15SetPlayerRotation => {
+                        let packet = S15SetPlayerRotation::decode(raw_packet, &current_state)?;
+                        check_finite(S15SetPlayerRotation::PACKET_ID, "SetPlayerRotation", packet.is_finite())?;
+                        event_sender.send_async(ClientEvent::SetPlayerRotation(packet)).await.unwrap();
+                    },
                     S1DPlayerCommand => PlayerCommand,
                     S1BPlayerAbilities => PlayerAbilities,
                     S1CPlayerAction => PlayerAction,
                     S27SetHeldItem => SetHeldItem,
                     S2ASetCreativeModeSlot => SetCreativeModeSlot,
+                    S2DUpdateSign => UpdateSign,
                     S2ESwingArm => SwingArm,
                     S30UseItemOn => UseItemOn,
                     _ {
@@ -455,3 +542,53 @@ pub(super) async fn listen_ingoing_packets(
     }
     Ok(())
 }
+
+/// Rejects movement packets with NaN or infinite values, which would break every distance
+/// computed from the player's position
+fn check_finite(packet_id: i32, packet_name: &str, is_finite: bool) -> ClientListenResult<()> {
+    if is_finite {
+        return Ok(());
+    }
+    Err(ClientListenError::InvalidPacket {
+        packet_id,
+        state: ClientState::Play,
+        packet_name: Some(packet_name.to_string()),
+        message: "non-finite position".to_string(),
+    })
+}
+
+/// Turns a plugin message into a [ClientEvent], decoding the channels known by the server
+fn plugin_message_event(message: S0CPluginMessage) -> Result<ClientEvent, DecodingError> {
+    Ok(match message.brand() {
+        Some(brand) => ClientEvent::Brand { brand: brand? },
+        None => ClientEvent::PluginMessage(message),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brand_plugin_message_event() {
+        let mut data = crate::data_types::encoder::PacketEncoder::default();
+        data.write_string("fabric");
+        let message = S0CPluginMessage {
+            channel: "minecraft:brand".into(),
+            data: data.into_inner().to_vec(),
+        };
+        assert!(matches!(
+            plugin_message_event(message),
+            Ok(ClientEvent::Brand { brand }) if brand == "fabric"
+        ));
+
+        let message = S0CPluginMessage {
+            channel: "mymod:config".into(),
+            data: vec![1, 2, 3],
+        };
+        assert!(matches!(
+            plugin_message_event(message),
+            Ok(ClientEvent::PluginMessage(S0CPluginMessage { data, .. })) if data == [1, 2, 3]
+        ));
+    }
+}
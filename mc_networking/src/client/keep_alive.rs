@@ -19,7 +19,7 @@ pub(super) struct KeepAliveData {
 
 pub(super) async fn handle_keep_alive(
     packet_sender: flume::Sender<OutgoingPacketEvent>,
-    state: Arc<RwLock<ClientState>>,
+    state: Arc<SharedClientState>,
     data: Arc<RwLock<KeepAliveData>>,
 ) {
     let start = Instant::now();
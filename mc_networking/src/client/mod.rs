@@ -1,7 +1,11 @@
 pub mod client_event;
+mod config;
 mod ingoing_packets;
 mod keep_alive;
 mod outgoing_packets;
+mod rate_limit;
+mod state;
+mod status_response;
 
 use crate::{
     packets::{client_bound::*, PacketCompression, RawPacket},
@@ -10,13 +14,28 @@ use crate::{
 use client_event::*;
 use ingoing_packets::*;
 use outgoing_packets::*;
+pub use config::{ClientConfig, DEFAULT_COMPRESSION_THRESHOLD};
+pub use rate_limit::RateLimit;
+pub use status_response::{FaviconError, LegacyStatusResponse, StatusResponse};
+use state::*;
 
 use lazy_static::lazy_static;
 use log::*;
 use openssl::{self, pkey, rsa::Rsa};
 use serde_json::json;
-use std::sync::Arc;
-use tokio::{self, net::TcpStream, sync::RwLock, task::spawn};
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use thiserror::Error;
+use tokio::{
+    self,
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    sync::RwLock,
+    task::spawn,
+};
 
 const KEEP_ALIVE_TIMEOUT: u64 = 30_000;
 const KEEP_ALIVE_INTERVAL: u64 = 15_000;
@@ -33,6 +52,26 @@ pub enum ClientState {
     Play,
     Disconnected,
 }
+impl ClientState {
+    /// How far in the connection the state is, Status and Login are both after Handshaking
+    fn progress(&self) -> u8 {
+        match self {
+            ClientState::Handshaking => 0,
+            ClientState::Status | ClientState::Login => 1,
+            ClientState::Play => 2,
+            ClientState::Disconnected => 3,
+        }
+    }
+}
+
+/// Error returned by [Client::try_send_raw_packet], gives back the packet that couldn't be sent
+#[derive(Error, Debug)]
+pub enum TrySendError {
+    #[error("the outgoing packet buffer is full")]
+    Full(RawPacket),
+    #[error("the client is disconnected")]
+    Disconnected(RawPacket),
+}
 
 /// Handles TCPStreams as minecraft clients into a stream of events
 #[derive(Clone)]
@@ -40,24 +79,58 @@ pub enum ClientState {
                     // remove them
 pub struct Client {
     compression: Arc<RwLock<PacketCompression>>,
-    state: Arc<RwLock<ClientState>>,
+    state: Arc<SharedClientState>,
+    protocol_version: Arc<RwLock<Option<i32>>>,
     event_sender: flume::Sender<ClientEvent>,
     packet_sender: flume::Sender<OutgoingPacketEvent>,
-    peer_addr: std::net::SocketAddr,
+    peer_addr: SocketAddr,
+    connected_since: Instant,
+    last_packet_received: Arc<Mutex<Instant>>,
 }
 impl Client {
-    /// Creates a new [Client] from a tokio socket
+    /// Creates a new [Client] from a tokio socket with the default [ClientConfig]
+    /// and the given buffer sizes
     pub fn new(
         socket: TcpStream,
         event_buffer: usize,
         packet_buffer: usize,
+    ) -> (Self, flume::Receiver<ClientEvent>) {
+        Self::with_config(socket, ClientConfig {
+            event_buffer,
+            packet_buffer,
+            ..ClientConfig::default()
+        })
+    }
+
+    /// Creates a new [Client] from a tokio socket with the given settings
+    pub fn with_config(
+        socket: TcpStream,
+        config: ClientConfig,
     ) -> (Self, flume::Receiver<ClientEvent>) {
         let peer_addr = socket.peer_addr().unwrap();
+        // Packets are already batched by the sending task, waiting for more only adds latency
+        if let Err(e) = socket.set_nodelay(true) {
+            warn!("Could not disable Nagle's algorithm for {}: '{}'", peer_addr, e);
+        }
         let (read, write) = socket.into_split();
-        let state = Arc::new(RwLock::new(ClientState::Handshaking));
-        let (event_sender, event_receiver) = flume::bounded(event_buffer);
-        let (packet_sender, packet_receiver) = flume::bounded(packet_buffer);
+        Self::from_parts(read, write, peer_addr, config)
+    }
+
+    /// Creates a new [Client] from the two halves of any connection,
+    /// e.g. a [tokio::io::duplex] in tests
+    pub fn from_parts(
+        read: impl AsyncRead + Unpin + Send + 'static,
+        write: impl AsyncWrite + Unpin + Send + 'static,
+        peer_addr: SocketAddr,
+        config: ClientConfig,
+    ) -> (Self, flume::Receiver<ClientEvent>) {
+        let connected_since = Instant::now();
+        let last_packet_received = Arc::new(Mutex::new(connected_since));
+        let state = Arc::new(SharedClientState::new(ClientState::Handshaking));
+        let (event_sender, event_receiver) = flume::bounded(config.event_buffer);
+        let (packet_sender, packet_receiver) = flume::bounded(config.packet_buffer);
         let compression = Arc::default();
+        let protocol_version = Arc::default();
 
         // Packet sending task
         spawn({
@@ -65,6 +138,8 @@ impl Client {
             let state = Arc::clone(&state);
             let listener_sender = event_sender.clone();
             let compression = Arc::clone(&compression);
+            let protocol_version = Arc::clone(&protocol_version);
+            let last_packet_received = Arc::clone(&last_packet_received);
             let peer_addr = peer_addr;
 
             async move {
@@ -74,6 +149,9 @@ impl Client {
                     packet_sender.clone(),
                     listener_sender.clone(),
                     Arc::clone(&state),
+                    protocol_version,
+                    last_packet_received,
+                    config,
                 )
                 .await
                 {
@@ -89,6 +167,31 @@ impl Client {
                             ()
                         }
 
+                        ClientListenError::TooManyPackets => {
+                            let reason = json!({ "text": "Too many packets" });
+                            let packet = match *state.read().await {
+                                ClientState::Login => C00LoginDisconnect { reason }.to_rawpacket(),
+                                _ => C17Disconnect { reason }.to_rawpacket(),
+                            };
+                            let _ = packet_sender
+                                .send_async(OutgoingPacketEvent::Packet(packet))
+                                .await;
+                            warn!("{:?} exceeded its rate limit, disconnecting", peer_addr);
+                        }
+
+                        ClientListenError::InvalidPacket { packet_name, message, .. }
+                            if *state.read().await == ClientState::Play =>
+                        {
+                            let reason = json!({ "text": format!("Invalid packet: {}", message) });
+                            let _ = packet_sender
+                                .send_async(OutgoingPacketEvent::Packet(C17Disconnect { reason }.to_rawpacket()))
+                                .await;
+                            warn!(
+                                "{:?} sent an invalid {:?} packet ({}), disconnecting",
+                                peer_addr, packet_name, message
+                            );
+                        }
+
                         e => {
                             *state.write().await = ClientState::Disconnected;
                             packet_sender
@@ -123,9 +226,12 @@ impl Client {
             Client {
                 compression,
                 state,
+                protocol_version,
                 event_sender,
                 packet_sender,
                 peer_addr,
+                connected_since,
+                last_packet_received,
             },
             event_receiver,
         )
@@ -136,6 +242,102 @@ impl Client {
         self.state.read().await.clone()
     }
 
+    /// Return the current connection state without blocking,
+    /// None if it is being modified
+    pub fn try_get_state(&self) -> Option<ClientState> {
+        self.state.try_read().map(|state| state.clone())
+    }
+
+    /// Waits until the client reaches the given state or a later one, and returns the state it is in
+    /// e.g. waiting for [ClientState::Play] returns [ClientState::Disconnected] if the login failed
+    pub async fn wait_for_state(&self, state: ClientState) -> ClientState {
+        // Subscribing before reading the current state so no change can be missed
+        let changes = self.state.subscribe();
+        let mut current = self.get_state().await;
+        while current != state && current.progress() <= state.progress() {
+            current = match changes.recv_async().await {
+                Ok(new_state) => new_state,
+                Err(..) => break,
+            };
+        }
+        current
+    }
+
+    /// Returns a receiver of every state the client will go through
+    pub fn on_state_change(&self) -> flume::Receiver<ClientState> {
+        self.state.subscribe()
+    }
+
+    /// Address of the remote end of the connection
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// When the connection was accepted
+    pub fn connected_since(&self) -> Instant {
+        self.connected_since
+    }
+
+    /// When the last packet of any kind was received, the connection time if none was received yet
+    pub fn last_packet_received(&self) -> Instant {
+        *self.last_packet_received.lock().unwrap()
+    }
+
+    /// The protocol version given by the client in its handshake, None if it hasn't been received yet
+    pub async fn protocol_version(&self) -> Option<i32> {
+        *self.protocol_version.read().await
+    }
+
+    /// Sends the disconnect packet matching the current state with the given reason,
+    /// then mark the client as disconnected and emit a [ClientEvent::Logout]
+    /// Does nothing if the client is already disconnected
+    pub async fn disconnect(&self, reason: impl Into<serde_json::Value>) {
+        let mut state = self.state.write().await;
+        if *state == ClientState::Disconnected {
+            return;
+        }
+        if let Some(packet) = Self::disconnect_packet(&state, reason.into()) {
+            // The packet sending task may already be stopped if the connection was lost
+            let _ = self
+                .packet_sender
+                .send_async(OutgoingPacketEvent::Packet(packet))
+                .await;
+        }
+        *state = ClientState::Disconnected;
+        drop(state);
+
+        let _ = self.event_sender.send_async(ClientEvent::Logout).await;
+    }
+
+    /// Same as [Client::disconnect] but blocks the current thread, for the threads outside of
+    /// the tokio runtime
+    /// The [ClientEvent::Logout] is dropped if the event buffer is full, as the thread
+    /// receiving the events may be the current one
+    pub fn disconnect_sync(&self, reason: impl Into<serde_json::Value>) {
+        let mut state = self.state.write_blocking();
+        if *state == ClientState::Disconnected {
+            return;
+        }
+        if let Some(packet) = Self::disconnect_packet(&state, reason.into()) {
+            // The packet sending task may already be stopped if the connection was lost
+            let _ = self.packet_sender.send(OutgoingPacketEvent::Packet(packet));
+        }
+        *state = ClientState::Disconnected;
+        drop(state);
+
+        let _ = self.event_sender.try_send(ClientEvent::Logout);
+    }
+
+    /// Disconnect packet of the given state, None for the states without one
+    fn disconnect_packet(state: &ClientState, reason: serde_json::Value) -> Option<RawPacket> {
+        match state {
+            ClientState::Login => Some(C00LoginDisconnect { reason }.to_rawpacket()),
+            ClientState::Play => Some(C17Disconnect { reason }.to_rawpacket()),
+            // There is no disconnect packet in these states, the connection is just closed
+            ClientState::Handshaking | ClientState::Status | ClientState::Disconnected => None,
+        }
+    }
+
     /// Add a raw packet to the send buffer
     /// Block asynchronously if the buffer is full
     pub async fn send_raw_packet_async(&self, packet: RawPacket) {
@@ -151,6 +353,25 @@ impl Client {
             .send(OutgoingPacketEvent::Packet(packet))
             .unwrap();
     }
+    /// Add a raw packet to the send buffer
+    /// Returns an error instead of blocking if the buffer is full
+    pub fn try_send_raw_packet(&self, packet: RawPacket) -> Result<(), TrySendError> {
+        self.packet_sender
+            .try_send(OutgoingPacketEvent::Packet(packet))
+            .map_err(|e| match e {
+                flume::TrySendError::Full(OutgoingPacketEvent::Packet(packet)) => {
+                    TrySendError::Full(packet)
+                }
+                flume::TrySendError::Disconnected(OutgoingPacketEvent::Packet(packet)) => {
+                    TrySendError::Disconnected(packet)
+                }
+                _ => unreachable!(),
+            })
+    }
+    /// Number of packets waiting in the send buffer
+    pub fn outgoing_queue_len(&self) -> usize {
+        self.packet_sender.len()
+    }
     /// Add a packet to the send buffer
     /// Block asynchronously if the buffer is full
     pub async fn send_packet_async<U: ClientBoundPacket>(&self, packet: &U) {
@@ -163,4 +384,422 @@ impl Client {
         let raw_packet = packet.to_rawpacket();
         self.send_raw_packet_sync(raw_packet);
     }
+
+    /// Sends a message that isn't from a player, above the hotbar if overlay is true
+    /// Block the current thread if the buffer is full
+    pub fn send_system_message(&self, message: impl Into<serde_json::Value>, overlay: bool) {
+        self.send_packet_sync(&C5FSystemChatMessage {
+            content: message.into(),
+            overlay,
+        });
+    }
+
+    /// Sends a message shown above the hotbar
+    /// Block the current thread if the buffer is full
+    pub fn send_action_bar(&self, text: impl Into<serde_json::Value>) {
+        self.send_packet_sync(&C40SetActionBarText {
+            text: text.into().to_string(),
+        });
+    }
+
+    /// Sends the brand of the server, shown in the debug screen of the client
+    /// Block the current thread if the buffer is full
+    pub fn send_brand(&self, brand: &str) {
+        let mut builder = C15PluginMessageBuilder::new("minecraft:brand".into());
+        builder.encoder.write_string(brand);
+        self.send_packet_sync(&builder.build());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::server_bound::{S11KeepAlive, S13SetPlayerPosition, ServerBoundPacket};
+    use bytes::BytesMut;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    async fn connected_client() -> (Client, flume::Receiver<ClientEvent>, TcpStream) {
+        connected_client_with_rate_limit(RateLimit::default()).await
+    }
+
+    async fn connected_client_with_rate_limit(
+        rate_limit: RateLimit,
+    ) -> (Client, flume::Receiver<ClientEvent>, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let peer = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+        let (client, event_receiver) = Client::with_config(socket, ClientConfig {
+            event_buffer: 10,
+            packet_buffer: 10,
+            rate_limit,
+            ..ClientConfig::default()
+        });
+        (client, event_receiver, peer)
+    }
+
+    /// Sends an uncompressed packet from the peer to the client
+    async fn write_packet(peer: &mut (impl AsyncWrite + Unpin), packet: RawPacket) {
+        let mut bytes = BytesMut::new();
+        packet.encode(PacketCompression::default(), &mut bytes);
+        peer.write_all(&bytes).await.unwrap();
+    }
+
+    /// Reads the next uncompressed packet sent to the peer
+    async fn read_packet(peer: &mut (impl AsyncRead + Unpin)) -> RawPacket {
+        let mut bytes = BytesMut::new();
+        loop {
+            match RawPacket::decode(&mut bytes.clone(), PacketCompression::default()) {
+                Ok(packet) => break packet,
+                Err(DecodingError::NotEnoughBytes) => (),
+                Err(e) => panic!("could not decode packet: {}", e),
+            }
+            let mut buffer = [0; 256];
+            let read = peer.read(&mut buffer).await.unwrap();
+            assert!(read > 0, "connection closed before receiving a packet");
+            bytes.extend_from_slice(&buffer[0..read]);
+        }
+    }
+
+    #[tokio::test]
+    async fn disconnect_in_login() {
+        let (client, event_receiver, mut peer) = connected_client().await;
+        *client.state.write().await = ClientState::Login;
+
+        client.disconnect("Bye").await;
+        assert_eq!(read_packet(&mut peer).await.packet_id, C00LoginDisconnect::PACKET_ID);
+        assert_eq!(client.get_state().await, ClientState::Disconnected);
+        assert!(matches!(event_receiver.recv_async().await, Ok(ClientEvent::Logout)));
+    }
+
+    #[tokio::test]
+    async fn disconnect_in_play() {
+        let (client, event_receiver, mut peer) = connected_client().await;
+        *client.state.write().await = ClientState::Play;
+
+        client.disconnect(json!({ "text": "Bye", "color": "red" })).await;
+        assert_eq!(read_packet(&mut peer).await.packet_id, C17Disconnect::PACKET_ID);
+        assert_eq!(client.get_state().await, ClientState::Disconnected);
+        assert!(matches!(event_receiver.recv_async().await, Ok(ClientEvent::Logout)));
+
+        // Disconnecting twice does nothing
+        client.disconnect("Bye again").await;
+        assert!(event_receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn unknown_packet_is_skipped() {
+        let (client, event_receiver, mut peer) = connected_client().await;
+        *client.state.write().await = ClientState::Play;
+
+        write_packet(
+            &mut peer,
+            RawPacket::new(0x7F, bytes::Bytes::from_static(&[1, 2, 3])),
+        )
+        .await;
+        // A packet following the unknown one is still handled
+        write_packet(
+            &mut peer,
+            RawPacket::new(S11KeepAlive::PACKET_ID, bytes::Bytes::copy_from_slice(&42i64.to_be_bytes())),
+        )
+        .await;
+
+        assert!(matches!(event_receiver.recv_async().await, Ok(ClientEvent::Ping { .. })));
+        assert_eq!(client.get_state().await, ClientState::Play);
+    }
+
+    #[tokio::test]
+    async fn try_send_on_full_buffer() {
+        // The outgoing task can't run before the test yields, so nothing is drained
+        let (client, _event_receiver, _peer) = connected_client().await;
+        let packet = C1EKeepAlive { id: 0 }.to_rawpacket();
+
+        for _ in 0..10 {
+            client.try_send_raw_packet(packet.clone()).unwrap();
+        }
+        assert_eq!(client.outgoing_queue_len(), 10);
+        assert!(matches!(
+            client.try_send_raw_packet(packet),
+            Err(TrySendError::Full(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn connection_metadata() {
+        let before = Instant::now();
+        let (client, _event_receiver, mut peer) = connected_client().await;
+        assert_eq!(client.peer_addr(), peer.local_addr().unwrap());
+        assert!(client.connected_since() >= before);
+        assert_eq!(client.last_packet_received(), client.connected_since());
+        assert_eq!(client.protocol_version().await, None);
+
+        let mut handshake = crate::data_types::encoder::PacketEncoder::default();
+        handshake.write_varint(759);
+        handshake.write_string("localhost");
+        handshake.write_u16(25565);
+        handshake.write_varint(1);
+        write_packet(&mut peer, RawPacket::new(0x00, handshake.into_inner().freeze())).await;
+
+        while client.get_state().await != ClientState::Status {
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+        }
+        assert_eq!(client.protocol_version().await, Some(759));
+        assert!(client.last_packet_received() > client.connected_since());
+    }
+
+    #[tokio::test]
+    async fn status_over_duplex() {
+        let (mut peer, connection) = tokio::io::duplex(4096);
+        let (read, write) = tokio::io::split(connection);
+        let (client, event_receiver) = Client::from_parts(
+            read,
+            write,
+            ([127, 0, 0, 1], 25565).into(),
+            ClientConfig::default(),
+        );
+
+        let mut handshake = crate::data_types::encoder::PacketEncoder::default();
+        handshake.write_varint(759);
+        handshake.write_string("localhost");
+        handshake.write_u16(25565);
+        handshake.write_varint(1);
+        write_packet(&mut peer, RawPacket::new(0x00, handshake.into_inner().freeze())).await;
+        write_packet(&mut peer, RawPacket::new(0x00, bytes::Bytes::new())).await;
+
+        match event_receiver.recv_async().await {
+            Ok(ClientEvent::ServerListPing { response }) => {
+                response.send(json!({ "description": "Duplex" })).unwrap();
+            }
+            _ => panic!("expected a server list ping"),
+        }
+        let status = read_packet(&mut peer).await;
+        assert_eq!(status.packet_id, C00StatusResponse::PACKET_ID);
+        assert!(String::from_utf8_lossy(&status.data).contains("Duplex"));
+        assert_eq!(client.get_state().await, ClientState::Status);
+        assert_eq!(client.protocol_version().await, Some(759));
+    }
+
+    #[tokio::test]
+    async fn disconnect_sync_in_play() {
+        let (client, event_receiver, mut peer) = connected_client().await;
+        *client.state.write().await = ClientState::Play;
+
+        client.disconnect_sync("Bye");
+        assert_eq!(read_packet(&mut peer).await.packet_id, C17Disconnect::PACKET_ID);
+        assert_eq!(client.get_state().await, ClientState::Disconnected);
+        assert!(matches!(event_receiver.recv_async().await, Ok(ClientEvent::Logout)));
+    }
+
+    #[tokio::test]
+    async fn action_bar_message() {
+        let (client, _event_receiver, mut peer) = connected_client().await;
+        *client.state.write().await = ClientState::Play;
+
+        client.send_action_bar(json!({ "text": "50%" }));
+        let packet = read_packet(&mut peer).await;
+        assert_eq!(packet.packet_id, C40SetActionBarText::PACKET_ID);
+        let mut expected = crate::data_types::encoder::PacketEncoder::default();
+        expected.write_string(r#"{"text":"50%"}"#);
+        assert_eq!(&*packet.data, &*expected.into_inner());
+    }
+
+    #[tokio::test]
+    async fn legacy_server_list_ping() {
+        let (client, event_receiver, mut peer) = connected_client().await;
+        peer.write_all(&[0xFE, 0x01]).await.unwrap();
+
+        match event_receiver.recv_async().await {
+            Ok(ClientEvent::LegacyServerListPing { response }) => {
+                response
+                    .send(LegacyStatusResponse {
+                        protocol: 127,
+                        version_name: "1.19".to_string(),
+                        motd: "Hi".to_string(),
+                        online_players: 3,
+                        max_players: 20,
+                    })
+                    .unwrap();
+            }
+            _ => panic!("expected a legacy server list ping"),
+        }
+        assert!(matches!(event_receiver.recv_async().await, Ok(ClientEvent::Logout)));
+
+        let mut header = [0; 3];
+        peer.read_exact(&mut header).await.unwrap();
+        assert_eq!(header[0], 0xFF);
+        let length = u16::from_be_bytes([header[1], header[2]]) as usize;
+        let mut text = vec![0; length * 2];
+        peer.read_exact(&mut text).await.unwrap();
+        let text = text
+            .chunks(2)
+            .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+            .collect::<Vec<_>>();
+        assert_eq!(String::from_utf16(&text).unwrap(), "\u{a7}1\0127\01.19\0Hi\03\020");
+        assert_eq!(client.get_state().await, ClientState::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn outdated_client_is_disconnected() {
+        let (client, event_receiver, mut peer) = connected_client().await;
+
+        let mut handshake = crate::data_types::encoder::PacketEncoder::default();
+        handshake.write_varint(crate::PROTOCOL_VERSION - 1);
+        handshake.write_string("localhost");
+        handshake.write_u16(25565);
+        handshake.write_varint(2);
+        write_packet(&mut peer, RawPacket::new(0x00, handshake.into_inner().freeze())).await;
+
+        assert!(matches!(event_receiver.recv_async().await, Ok(ClientEvent::Logout)));
+        let disconnect = read_packet(&mut peer).await;
+        assert_eq!(disconnect.packet_id, C00LoginDisconnect::PACKET_ID);
+        assert!(String::from_utf8_lossy(&disconnect.data).contains("Outdated client"));
+        assert_eq!(client.get_state().await, ClientState::Disconnected);
+        assert_eq!(client.protocol_version().await, Some(crate::PROTOCOL_VERSION - 1));
+    }
+
+    #[tokio::test]
+    async fn newer_client_is_disconnected_but_can_ping() {
+        let handshake = |next_state| {
+            let mut handshake = crate::data_types::encoder::PacketEncoder::default();
+            handshake.write_varint(crate::PROTOCOL_VERSION + 1);
+            handshake.write_string("localhost");
+            handshake.write_u16(25565);
+            handshake.write_varint(next_state);
+            RawPacket::new(0x00, handshake.into_inner().freeze())
+        };
+
+        // The server list shows the server as incompatible
+        let (client, _event_receiver, mut peer) = connected_client().await;
+        write_packet(&mut peer, handshake(1)).await;
+        assert_eq!(client.wait_for_state(ClientState::Status).await, ClientState::Status);
+
+        let (client, event_receiver, mut peer) = connected_client().await;
+        write_packet(&mut peer, handshake(2)).await;
+        assert!(matches!(event_receiver.recv_async().await, Ok(ClientEvent::Logout)));
+        let disconnect = read_packet(&mut peer).await;
+        assert_eq!(disconnect.packet_id, C00LoginDisconnect::PACKET_ID);
+        assert!(String::from_utf8_lossy(&disconnect.data).contains("Outdated server"));
+        assert_eq!(client.get_state().await, ClientState::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn packet_flood_disconnects() {
+        let (client, event_receiver, mut peer) = connected_client_with_rate_limit(RateLimit {
+            max_packets_per_second: Some(5),
+            max_bytes_per_second: None,
+        })
+        .await;
+        *client.state.write().await = ClientState::Play;
+
+        for _ in 0..20 {
+            write_packet(&mut peer, RawPacket::new(0x7F, bytes::Bytes::new())).await;
+        }
+
+        assert!(matches!(event_receiver.recv_async().await, Ok(ClientEvent::Logout)));
+        assert_eq!(client.get_state().await, ClientState::Disconnected);
+        assert_eq!(read_packet(&mut peer).await.packet_id, C17Disconnect::PACKET_ID);
+    }
+
+    #[tokio::test]
+    async fn non_finite_position_is_rejected() {
+        let (client, event_receiver, mut peer) = connected_client().await;
+        *client.state.write().await = ClientState::Play;
+
+        let mut data = BytesMut::new();
+        for coordinate in [f64::NAN, 64., 0.] {
+            data.extend_from_slice(&coordinate.to_be_bytes());
+        }
+        data.extend_from_slice(&[1]);
+        write_packet(&mut peer, RawPacket::new(S13SetPlayerPosition::PACKET_ID, data.freeze())).await;
+
+        // The position is never given to the server
+        assert!(matches!(event_receiver.recv_async().await, Ok(ClientEvent::Logout)));
+        assert_eq!(client.get_state().await, ClientState::Disconnected);
+        let disconnect = read_packet(&mut peer).await;
+        assert_eq!(disconnect.packet_id, C17Disconnect::PACKET_ID);
+        assert!(String::from_utf8_lossy(&disconnect.data).contains("non-finite position"));
+    }
+
+    #[tokio::test]
+    async fn configured_compression_threshold() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut peer = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+        let (client, event_receiver) = Client::with_config(socket, ClientConfig {
+            compression_threshold: Some(128),
+            ..ClientConfig::default()
+        });
+
+        let mut handshake = crate::data_types::encoder::PacketEncoder::default();
+        handshake.write_varint(crate::PROTOCOL_VERSION);
+        handshake.write_string("localhost");
+        handshake.write_u16(25565);
+        handshake.write_varint(2);
+        write_packet(&mut peer, RawPacket::new(0x00, handshake.into_inner().freeze())).await;
+        let mut login_start = crate::data_types::encoder::PacketEncoder::default();
+        login_start.write_string("Steve");
+        login_start.write_bool(false);
+        write_packet(&mut peer, RawPacket::new(0x00, login_start.into_inner().freeze())).await;
+
+        match event_receiver.recv_async().await {
+            Ok(ClientEvent::LoginStart { username, response }) => {
+                response
+                    .send(LoginStartResult::Accept {
+                        uuid: uuid::Uuid::nil(),
+                        username,
+                        encrypt: false,
+                        compress: true,
+                    })
+                    .unwrap();
+            }
+            _ => panic!("expected a login start"),
+        }
+        assert!(matches!(event_receiver.recv_async().await, Ok(ClientEvent::LoggedIn)));
+
+        // Both packets can arrive in the same read, so the bytes are kept between them
+        let mut bytes = BytesMut::new();
+        let mut packets = vec![];
+        for compression in [PacketCompression::default(), PacketCompression::new(128)] {
+            loop {
+                let mut remaining = bytes.clone();
+                match RawPacket::decode(&mut remaining, compression) {
+                    Ok(packet) => {
+                        packets.push(packet);
+                        bytes = remaining;
+                        break;
+                    }
+                    Err(DecodingError::NotEnoughBytes) => (),
+                    Err(e) => panic!("could not decode packet: {}", e),
+                }
+                assert!(peer.read_buf(&mut bytes).await.unwrap() > 0, "connection closed");
+            }
+        }
+        assert_eq!(packets[0].packet_id, C03SetCompression::PACKET_ID);
+        assert_eq!(packets[0].data, C03SetCompression { threshold: 128 }.to_rawpacket().data);
+        assert_eq!(packets[1].packet_id, C02LoginSuccess::PACKET_ID);
+        assert_eq!(client.get_state().await, ClientState::Play);
+    }
+
+    #[tokio::test]
+    async fn wait_for_state_transitions() {
+        let (client, _event_receiver, _peer) = connected_client().await;
+        let changes = client.on_state_change();
+        let waiter = tokio::spawn({
+            let client = client.clone();
+            async move { client.wait_for_state(ClientState::Play).await }
+        });
+        tokio::task::yield_now().await;
+
+        *client.state.write().await = ClientState::Login;
+        assert!(!waiter.is_finished());
+        *client.state.write().await = ClientState::Play;
+
+        assert_eq!(waiter.await.unwrap(), ClientState::Play);
+        assert_eq!(changes.recv_async().await.unwrap(), ClientState::Login);
+        assert_eq!(changes.recv_async().await.unwrap(), ClientState::Play);
+        // Already reached states resolve immediately
+        assert_eq!(client.wait_for_state(ClientState::Login).await, ClientState::Play);
+    }
 }
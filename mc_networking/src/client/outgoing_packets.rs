@@ -1,7 +1,7 @@
 use super::*;
 use crate::packets::{PacketCompression, RawPacket};
 
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 
 use log::*;
 use openssl::symm::{Cipher, Crypter, Mode};
@@ -9,12 +9,14 @@ use openssl::symm::{Cipher, Crypter, Mode};
 use std::sync::Arc;
 
 use tokio::{
-    io::AsyncWriteExt,
-    net::tcp::OwnedWriteHalf,
-    sync::{Notify, RwLock},
+    io::{AsyncWrite, AsyncWriteExt},
+    sync::Notify,
     task::block_in_place,
 };
 
+/// Size in bytes after which no more queued packets are added to a write
+const MAX_BATCH_SIZE: usize = 64 * 1024;
+
 #[derive(Debug)]
 pub(super) enum OutgoingPacketEvent {
     /// Send a packet
@@ -25,57 +27,172 @@ pub(super) enum OutgoingPacketEvent {
     SetCompression(PacketCompression),
     /// Sets the shared_key to enable encryption
     SetEncryption(Option<[u8; 16]>),
+    /// Sends bytes as is, only used to answer legacy server list pings
+    Raw(Bytes),
 }
 
 pub(super) async fn listen_outgoing_packets(
-    mut write: OwnedWriteHalf,
+    mut write: impl AsyncWrite + Unpin,
     packet_receiver: flume::Receiver<OutgoingPacketEvent>,
-    _state: Arc<RwLock<ClientState>>,
+    _state: Arc<SharedClientState>,
 ) {
     let mut packet_buffer = BytesMut::with_capacity(200);
+    // Every packet ready to be sent, written at once
+    let mut batch = BytesMut::with_capacity(200);
+    let mut sent_notifies = Vec::new();
     let mut compression = PacketCompression::default();
     let mut encryption: Option<(Cipher, Crypter)> = None;
 
-    let dummy_notify = Arc::new(Notify::new());
-
-    while let Ok(event) = packet_receiver.recv_async().await {
-        match (event, dummy_notify.clone()) {
-            (OutgoingPacketEvent::Packet(packet), notify)
-            | (OutgoingPacketEvent::PacketNow(packet, notify), ..) => {
-                let packet_id = packet.packet_id;
-                if packet.will_compress(compression) {
-                    block_in_place(|| packet.encode(compression, &mut packet_buffer))
-                } else {
-                    packet.encode(compression, &mut packet_buffer)
-                };
-                if let Some((cipher, crypter)) = &mut encryption {
-                    let unencrypted = packet_buffer.split();
-                    packet_buffer.resize(unencrypted.len() + cipher.block_size(), 0);
-                    let encrypted_length =
-                        crypter.update(&unencrypted, &mut packet_buffer).unwrap();
-                    packet_buffer.truncate(encrypted_length);
+    while let Ok(first_event) = packet_receiver.recv_async().await {
+        let mut next_event = Some(first_event);
+        while let Some(event) = next_event {
+            match event {
+                OutgoingPacketEvent::Packet(packet) => {
+                    encode_packet(&packet, compression, &mut encryption, &mut packet_buffer);
+                    batch.extend_from_slice(&packet_buffer);
+                    packet_buffer.clear();
                 }
-                match write.write_all(&packet_buffer).await {
-                    Ok(..) => (),
-                    Err(e) => warn!("Error when sending packet 0x{:02x}: '{}'", packet_id, e),
+
+                OutgoingPacketEvent::PacketNow(packet, notify) => {
+                    encode_packet(&packet, compression, &mut encryption, &mut packet_buffer);
+                    batch.extend_from_slice(&packet_buffer);
+                    packet_buffer.clear();
+                    sent_notifies.push(notify);
                 }
-                write.flush().await.unwrap();
-                notify.notify_one();
-                packet_buffer.clear();
+
+                OutgoingPacketEvent::Raw(bytes) =>
+                    batch.extend_from_slice(&bytes),
+
+                OutgoingPacketEvent::SetCompression(nc) => 
+                    compression = nc,
+
+                OutgoingPacketEvent::SetEncryption(e) =>
+                    encryption = e.map(|shared_key| {
+                        let cipher = Cipher::aes_128_cfb8();
+                        (
+                            cipher,
+                            Crypter::new(cipher, Mode::Encrypt, &shared_key, Some(&shared_key))
+                            .unwrap(),
+                        )
+                    }),
+            }
+            // Packets already queued are sent with the same write, up to MAX_BATCH_SIZE so
+            // a busy queue doesn't buffer without bound
+            next_event = if batch.len() < MAX_BATCH_SIZE {
+                packet_receiver.try_recv().ok()
+            } else {
+                None
+            };
+        }
+
+        if !batch.is_empty() {
+            match write.write_all(&batch).await {
+                Ok(..) => (),
+                Err(e) => warn!("Error when sending {} bytes of packets: '{}'", batch.len(), e),
             }
+            write.flush().await.unwrap();
+            batch.clear();
+        }
+        for notify in sent_notifies.drain(..) {
+            notify.notify_one();
+        }
+    }
+}
+
+/// Encodes the packet into the empty buffer, compressed and encrypted
+fn encode_packet(
+    packet: &RawPacket,
+    compression: PacketCompression,
+    encryption: &mut Option<(Cipher, Crypter)>,
+    packet_buffer: &mut BytesMut,
+) {
+    if packet.will_compress(compression) {
+        block_in_place(|| packet.encode(compression, packet_buffer))
+    } else {
+        packet.encode(compression, packet_buffer)
+    };
+    if let Some((cipher, crypter)) = encryption {
+        let unencrypted = packet_buffer.split();
+        packet_buffer.resize(unencrypted.len() + cipher.block_size(), 0);
+        let encrypted_length =
+            crypter.update(&unencrypted, packet_buffer).unwrap();
+        packet_buffer.truncate(encrypted_length);
+    }
+}
 
-            (OutgoingPacketEvent::SetCompression(nc), ..) => 
-                compression = nc,
-
-            (OutgoingPacketEvent::SetEncryption(e), ..) =>
-                encryption = e.map(|shared_key| {
-                    let cipher = Cipher::aes_128_cfb8();
-                    (
-                        cipher,
-                        Crypter::new(cipher, Mode::Encrypt, &shared_key, Some(&shared_key))
-                        .unwrap(),
-                    )
-                }),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io,
+        pin::Pin,
+        sync::Mutex,
+        task::{Context, Poll},
+    };
+
+    /// Records every write call
+    #[derive(Clone, Default)]
+    struct MockWriter(Arc<Mutex<Vec<Vec<u8>>>>);
+    impl AsyncWrite for MockWriter {
+        fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+            self.0.lock().unwrap().push(buf.to_vec());
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn queued_packets_are_written_at_once() {
+        let writer = MockWriter::default();
+        let (packet_sender, packet_receiver) = flume::unbounded();
+        let packets = (1..=3)
+            .map(|i| RawPacket::new(i, Bytes::from(vec![i as u8; 4])))
+            .collect::<Vec<_>>();
+        for packet in &packets {
+            packet_sender.send(OutgoingPacketEvent::Packet(packet.clone())).unwrap();
         }
+        drop(packet_sender);
+
+        let state = Arc::new(SharedClientState::new(ClientState::Play));
+        listen_outgoing_packets(writer.clone(), packet_receiver, state).await;
+
+        let mut expected = BytesMut::new();
+        for packet in &packets {
+            let mut encoded = BytesMut::new();
+            packet.encode(PacketCompression::default(), &mut encoded);
+            expected.extend_from_slice(&encoded);
+        }
+        let writes = writer.0.lock().unwrap();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0], expected.to_vec());
+    }
+
+    #[tokio::test]
+    async fn batches_are_capped() {
+        let writer = MockWriter::default();
+        let (packet_sender, packet_receiver) = flume::unbounded();
+        let packet = RawPacket::new(1, Bytes::from(vec![0; 1000]));
+        let mut encoded = BytesMut::new();
+        packet.encode(PacketCompression::default(), &mut encoded);
+        let count = MAX_BATCH_SIZE / encoded.len() * 3;
+        for _ in 0..count {
+            packet_sender.send(OutgoingPacketEvent::Packet(packet.clone())).unwrap();
+        }
+        drop(packet_sender);
+
+        let state = Arc::new(SharedClientState::new(ClientState::Play));
+        listen_outgoing_packets(writer.clone(), packet_receiver, state).await;
+
+        let writes = writer.0.lock().unwrap();
+        assert!(writes.len() >= 3);
+        assert!(writes.iter().all(|write| write.len() < MAX_BATCH_SIZE + encoded.len()));
+        assert_eq!(writes.iter().map(Vec::len).sum::<usize>(), count * encoded.len());
     }
 }
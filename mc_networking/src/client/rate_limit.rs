@@ -0,0 +1,57 @@
+use tokio::time::Instant;
+
+/// Limits of the packets a client can send, each limit can be exceeded for at most one second
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    /// None for no limit
+    pub max_packets_per_second: Option<u32>,
+    /// None for no limit
+    pub max_bytes_per_second: Option<u32>,
+}
+impl RateLimit {
+    pub fn unlimited() -> Self {
+        Self {
+            max_packets_per_second: None,
+            max_bytes_per_second: None,
+        }
+    }
+}
+impl Default for RateLimit {
+    /// Limits that a vanilla client never reaches
+    fn default() -> Self {
+        Self {
+            max_packets_per_second: Some(500),
+            max_bytes_per_second: Some(1 << 20),
+        }
+    }
+}
+
+/// Token bucket holding at most one second worth of tokens
+pub(super) struct TokenBucket {
+    per_second: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+impl TokenBucket {
+    pub fn new(per_second: u32) -> Self {
+        Self {
+            per_second: per_second as f64,
+            tokens: per_second as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes the given amount of tokens, returns false if there isn't enough of them
+    pub fn take(&mut self, amount: usize) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.per_second);
+        self.last_refill = now;
+
+        if self.tokens < amount as f64 {
+            return false;
+        }
+        self.tokens -= amount as f64;
+        true
+    }
+}
@@ -0,0 +1,93 @@
+use super::ClientState;
+
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Mutex,
+};
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// [ClientState] shared between the [Client](super::Client) and its tasks,
+/// every change is sent to the subscribed receivers
+#[derive(Debug)]
+pub(super) struct SharedClientState {
+    state: RwLock<ClientState>,
+    listeners: Mutex<Vec<flume::Sender<ClientState>>>,
+}
+impl SharedClientState {
+    pub fn new(state: ClientState) -> Self {
+        Self {
+            state: RwLock::new(state),
+            listeners: Mutex::default(),
+        }
+    }
+
+    pub async fn read(&self) -> RwLockReadGuard<'_, ClientState> {
+        self.state.read().await
+    }
+
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, ClientState>> {
+        self.state.try_read().ok()
+    }
+
+    /// The new state is sent to the listeners when the guard is dropped, if it changed
+    pub async fn write(&self) -> StateWriteGuard<'_> {
+        let guard = self.state.write().await;
+        StateWriteGuard {
+            previous: guard.clone(),
+            guard,
+            listeners: &self.listeners,
+        }
+    }
+
+    /// Blocks the current thread until the state can be written,
+    /// for the threads outside of the tokio runtime
+    pub fn write_blocking(&self) -> StateWriteGuard<'_> {
+        loop {
+            if let Ok(guard) = self.state.try_write() {
+                return StateWriteGuard {
+                    previous: guard.clone(),
+                    guard,
+                    listeners: &self.listeners,
+                };
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    /// Returns a receiver of all future state changes
+    pub fn subscribe(&self) -> flume::Receiver<ClientState> {
+        let (sender, receiver) = flume::unbounded();
+        self.listeners.lock().unwrap().push(sender);
+        receiver
+    }
+}
+
+pub(super) struct StateWriteGuard<'a> {
+    guard: RwLockWriteGuard<'a, ClientState>,
+    previous: ClientState,
+    listeners: &'a Mutex<Vec<flume::Sender<ClientState>>>,
+}
+impl<'a> Deref for StateWriteGuard<'a> {
+    type Target = ClientState;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+impl<'a> DerefMut for StateWriteGuard<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+impl<'a> Drop for StateWriteGuard<'a> {
+    fn drop(&mut self) {
+        if *self.guard != self.previous {
+            let new_state = self.guard.clone();
+            // Dropped receivers are removed
+            self.listeners
+                .lock()
+                .unwrap()
+                .retain(|listener| listener.send(new_state.clone()).is_ok());
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use serde_json::json;
+use thiserror::Error;
+use uuid::Uuid;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum FaviconError {
+    #[error("the favicon is not a png image")]
+    NotPng,
+    #[error("the favicon must be 64x64, not {width}x{height}")]
+    InvalidSize { width: u32, height: u32 },
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(n >> (18 - i * 6)) as usize & 63] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Builds the json response to a [ClientEvent::ServerListPing](super::client_event::ClientEvent::ServerListPing)
+#[derive(Clone, Debug)]
+pub struct StatusResponse {
+    pub version_name: String,
+    pub protocol: i32,
+    pub max_players: u32,
+    pub online_players: u32,
+    /// Players shown when hovering the player count
+    pub sample: Vec<(String, Uuid)>,
+    /// Chat component shown under the server name
+    pub description: serde_json::Value,
+    /// Data url of the png favicon
+    pub favicon: Option<String>,
+}
+impl StatusResponse {
+    pub fn with_version(self, version_name: impl ToString, protocol: i32) -> Self {
+        Self {
+            version_name: version_name.to_string(),
+            protocol,
+            ..self
+        }
+    }
+
+    pub fn with_players(self, online_players: u32, max_players: u32) -> Self {
+        Self {
+            online_players,
+            max_players,
+            ..self
+        }
+    }
+
+    pub fn with_sample_player(mut self, name: impl ToString, uuid: Uuid) -> Self {
+        self.sample.push((name.to_string(), uuid));
+        self
+    }
+
+    pub fn with_description(self, description: impl Into<serde_json::Value>) -> Self {
+        Self {
+            description: description.into(),
+            ..self
+        }
+    }
+
+    /// Sets the favicon from the bytes of a 64x64 png image
+    pub fn with_favicon(self, png: &[u8]) -> Result<Self, FaviconError> {
+        // The IHDR chunk, holding the size of the image, always comes right after the signature
+        if png.len() < 24 || png[0..8] != PNG_SIGNATURE || &png[12..16] != b"IHDR" {
+            return Err(FaviconError::NotPng);
+        }
+        let width = u32::from_be_bytes([png[16], png[17], png[18], png[19]]);
+        let height = u32::from_be_bytes([png[20], png[21], png[22], png[23]]);
+        if (width, height) != (64, 64) {
+            return Err(FaviconError::InvalidSize { width, height });
+        }
+        Ok(Self {
+            favicon: Some(format!("data:image/png;base64,{}", base64_encode(png))),
+            ..self
+        })
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let sample = self.sample.iter()
+            .map(|(name, uuid)| json!({ "name": name, "id": uuid.to_string() }))
+            .collect::<Vec<_>>();
+        let mut response = json!({
+            "version": {
+                "name": self.version_name,
+                "protocol": self.protocol,
+            },
+            "players": {
+                "max": self.max_players,
+                "online": self.online_players,
+                "sample": sample,
+            },
+            "description": self.description,
+        });
+        if let Some(favicon) = &self.favicon {
+            response["favicon"] = json!(favicon);
+        }
+        response
+    }
+}
+impl Default for StatusResponse {
+    /// The version implemented by this crate and no players
+    fn default() -> Self {
+        Self {
+            version_name: crate::MINECRAFT_VERSION.to_string(),
+            protocol: crate::PROTOCOL_VERSION,
+            max_players: 0,
+            online_players: 0,
+            sample: vec![],
+            description: json!({ "text": "A Minecraft Server" }),
+            favicon: None,
+        }
+    }
+}
+impl From<StatusResponse> for serde_json::Value {
+    fn from(response: StatusResponse) -> Self {
+        response.to_json()
+    }
+}
+
+/// Response to a [ClientEvent::LegacyServerListPing](super::client_event::ClientEvent::LegacyServerListPing)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LegacyStatusResponse {
+    pub protocol: i32,
+    pub version_name: String,
+    /// Plain text, legacy clients can't display chat components
+    pub motd: String,
+    pub online_players: u32,
+    pub max_players: u32,
+}
+impl LegacyStatusResponse {
+    /// Encodes the response in the kick packet (0xFF) expected by legacy clients
+    pub fn encode(&self) -> Vec<u8> {
+        let text = format!(
+            "\u{a7}1\0{}\0{}\0{}\0{}\0{}",
+            self.protocol, self.version_name, self.motd, self.online_players, self.max_players,
+        );
+        let utf16 = text.encode_utf16().collect::<Vec<_>>();
+        let mut bytes = Vec::with_capacity(3 + utf16.len() * 2);
+        bytes.push(0xFF);
+        bytes.extend_from_slice(&(utf16.len() as u16).to_be_bytes());
+        for unit in utf16 {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        }
+        bytes
+    }
+}
+impl From<&StatusResponse> for LegacyStatusResponse {
+    /// Only keeps the text of the root component of the description as the motd
+    fn from(response: &StatusResponse) -> Self {
+        let motd = response.description.as_str()
+            .or_else(|| response.description["text"].as_str())
+            .unwrap_or_default();
+        Self {
+            protocol: response.protocol,
+            version_name: response.version_name.clone(),
+            motd: motd.to_string(),
+            online_players: response.online_players,
+            max_players: response.max_players,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend_from_slice(&13u32.to_be_bytes());
+        png.extend_from_slice(b"IHDR");
+        png.extend_from_slice(&width.to_be_bytes());
+        png.extend_from_slice(&height.to_be_bytes());
+        png.extend_from_slice(&[8, 6, 0, 0, 0]);
+        png
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"M"), "TQ==");
+        assert_eq!(base64_encode(b"Ma"), "TWE=");
+        assert_eq!(base64_encode(b"Man"), "TWFu");
+        assert_eq!(base64_encode(b"Many hands"), "TWFueSBoYW5kcw==");
+    }
+
+    #[test]
+    fn favicon_must_be_64x64_png() {
+        assert_eq!(
+            StatusResponse::default().with_favicon(&png_header(32, 64)).unwrap_err(),
+            FaviconError::InvalidSize { width: 32, height: 64 },
+        );
+        assert_eq!(
+            StatusResponse::default().with_favicon(b"GIF89a not a png at all").unwrap_err(),
+            FaviconError::NotPng,
+        );
+
+        let png = png_header(64, 64);
+        let response = StatusResponse::default().with_favicon(&png).unwrap().to_json();
+        let favicon = response["favicon"].as_str().unwrap();
+        assert_eq!(favicon, format!("data:image/png;base64,{}", base64_encode(&png)));
+        assert!(favicon.starts_with("data:image/png;base64,iVBORw0KGgo"));
+    }
+
+    #[test]
+    fn status_json() {
+        let uuid = Uuid::from_u128(1);
+        let response = StatusResponse::default()
+            .with_version("Custom 1.19", 759)
+            .with_players(1, 20)
+            .with_sample_player("Notch", uuid)
+            .with_description(json!({ "text": "Hello", "color": "red" }))
+            .to_json();
+        assert_eq!(response, json!({
+            "version": { "name": "Custom 1.19", "protocol": 759 },
+            "players": {
+                "max": 20,
+                "online": 1,
+                "sample": [{ "name": "Notch", "id": "00000000-0000-0000-0000-000000000001" }],
+            },
+            "description": { "text": "Hello", "color": "red" },
+        }));
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    data_types::encoder::varint,
+    packets::{PacketCompression, RawPacket, DEFAULT_MAX_PACKET_SIZE},
+    DecodingError,
+};
+
+use bytes::BytesMut;
+use openssl::symm::{Cipher, Crypter, Mode};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Frames [RawPacket]s with the current compression and encryption of the connection,
+/// e.g. with a `Framed<TcpStream, MinecraftCodec>`
+pub struct MinecraftCodec {
+    compression: PacketCompression,
+    max_packet_size: usize,
+    encrypter: Option<Crypter>,
+    decrypter: Option<Crypter>,
+    /// Number of bytes at the start of the read buffer that have already been decrypted
+    decrypted: usize,
+}
+impl MinecraftCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Packets announcing a bigger length fail to decode
+    pub fn with_max_packet_size(self, max_packet_size: usize) -> Self {
+        Self {
+            max_packet_size,
+            ..self
+        }
+    }
+
+    pub fn compression(&self) -> PacketCompression {
+        self.compression
+    }
+
+    pub fn set_compression(&mut self, compression: PacketCompression) {
+        self.compression = compression;
+    }
+
+    /// Enables encryption in both directions with the given shared key, or disables it
+    pub fn set_encryption(&mut self, shared_key: Option<[u8; 16]>) {
+        let crypter = |mode| {
+            shared_key.map(|shared_key| {
+                Crypter::new(Cipher::aes_128_cfb8(), mode, &shared_key, Some(&shared_key)).unwrap()
+            })
+        };
+        self.encrypter = crypter(Mode::Encrypt);
+        self.decrypter = crypter(Mode::Decrypt);
+    }
+}
+
+impl Default for MinecraftCodec {
+    fn default() -> Self {
+        Self {
+            compression: PacketCompression::default(),
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+            encrypter: None,
+            decrypter: None,
+            decrypted: 0,
+        }
+    }
+}
+
+/// Encrypts or decrypts the data in place, the cipher keeps the length of the data
+fn apply_cipher(crypter: &mut Crypter, data: &mut [u8]) {
+    let mut output = vec![0; data.len() + Cipher::aes_128_cfb8().block_size()];
+    let length = crypter.update(data, &mut output).unwrap();
+    data.copy_from_slice(&output[0..length]);
+}
+
+impl Decoder for MinecraftCodec {
+    type Item = RawPacket;
+    type Error = DecodingError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<RawPacket>, DecodingError> {
+        if let Some(decrypter) = &mut self.decrypter {
+            apply_cipher(decrypter, &mut src[self.decrypted..]);
+        }
+        self.decrypted = src.len();
+
+        // The length is read on a copy of the slice so nothing is consumed if the packet
+        // isn't complete yet
+        let mut length_bytes = &src[..];
+        let packet_length = match varint::decode_buf(&mut length_bytes) {
+            Ok(length) => length as usize,
+            Err(DecodingError::NotEnoughBytes) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if packet_length > self.max_packet_size {
+            return Err(DecodingError::parse_error(
+                "raw packet",
+                format!("packet length {} is above {}", packet_length, self.max_packet_size),
+            ));
+        }
+        let frame_length = src.len() - length_bytes.len() + packet_length;
+        if src.len() < frame_length {
+            src.reserve(frame_length - src.len());
+            return Ok(None);
+        }
+
+        let mut frame = src.split_to(frame_length);
+        self.decrypted -= frame_length;
+        RawPacket::decode_with_max_size(&mut frame, self.compression, self.max_packet_size).map(Some)
+    }
+}
+
+impl Encoder<RawPacket> for MinecraftCodec {
+    type Error = DecodingError;
+
+    fn encode(&mut self, packet: RawPacket, dst: &mut BytesMut) -> Result<(), DecodingError> {
+        let mut encoded = BytesMut::new();
+        packet.encode(self.compression, &mut encoded);
+        if let Some(encrypter) = &mut self.encrypter {
+            apply_cipher(encrypter, &mut encoded);
+        }
+        dst.extend_from_slice(&encoded);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    fn round_trip(mut codec: MinecraftCodec, packets: &[RawPacket]) {
+        let mut buffer = BytesMut::new();
+        for packet in packets {
+            codec.encode(packet.clone(), &mut buffer).unwrap();
+        }
+
+        // Feeds the bytes one by one to check that incomplete packets are kept
+        let mut received = BytesMut::new();
+        let mut decoded = vec![];
+        for byte in buffer {
+            received.extend_from_slice(&[byte]);
+            if let Some(packet) = codec.decode(&mut received).unwrap() {
+                decoded.push(packet);
+            }
+        }
+        assert!(received.is_empty());
+        assert_eq!(decoded.len(), packets.len());
+        for (decoded, packet) in decoded.iter().zip(packets) {
+            assert_eq!(decoded.packet_id, packet.packet_id);
+            assert_eq!(decoded.data, packet.data);
+        }
+    }
+
+    fn packets() -> Vec<RawPacket> {
+        vec![
+            RawPacket::new(0x00, Bytes::from_static(&[1, 2, 3])),
+            RawPacket::new(0x22, Bytes::from(vec![7; 300])),
+            RawPacket::new(0x7F, Bytes::new()),
+        ]
+    }
+
+    #[test]
+    fn uncompressed_round_trip() {
+        round_trip(MinecraftCodec::new(), &packets());
+    }
+
+    #[test]
+    fn compressed_round_trip() {
+        let mut codec = MinecraftCodec::new();
+        codec.set_compression(PacketCompression::new(64));
+        round_trip(codec, &packets());
+    }
+
+    #[test]
+    fn encrypted_round_trip() {
+        let mut codec = MinecraftCodec::new();
+        codec.set_compression(PacketCompression::new(64));
+        codec.set_encryption(Some([42; 16]));
+        round_trip(codec, &packets());
+    }
+}
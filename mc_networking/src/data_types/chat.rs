@@ -0,0 +1,290 @@
+use serde::{Serialize, Serializer};
+
+/// Color of a [Chat] component, named colors or any RGB color
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChatColor {
+    Black,
+    DarkBlue,
+    DarkGreen,
+    DarkAqua,
+    DarkRed,
+    DarkPurple,
+    Gold,
+    Gray,
+    DarkGray,
+    Blue,
+    Green,
+    Aqua,
+    Red,
+    LightPurple,
+    Yellow,
+    White,
+    /// Reset the color to the default one
+    Reset,
+    /// Any 0xRRGGBB color, only the 24 least significant bits are used
+    Hex(u32),
+}
+impl ChatColor {
+    /// Name of the color as used in the json chat format
+    pub fn name(&self) -> String {
+        match self {
+            Self::Black => "black",
+            Self::DarkBlue => "dark_blue",
+            Self::DarkGreen => "dark_green",
+            Self::DarkAqua => "dark_aqua",
+            Self::DarkRed => "dark_red",
+            Self::DarkPurple => "dark_purple",
+            Self::Gold => "gold",
+            Self::Gray => "gray",
+            Self::DarkGray => "dark_gray",
+            Self::Blue => "blue",
+            Self::Green => "green",
+            Self::Aqua => "aqua",
+            Self::Red => "red",
+            Self::LightPurple => "light_purple",
+            Self::Yellow => "yellow",
+            Self::White => "white",
+            Self::Reset => "reset",
+            Self::Hex(rgb) => return format!("#{:06X}", rgb & 0xFFFFFF),
+        }
+        .to_string()
+    }
+}
+impl Serialize for ChatColor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name())
+    }
+}
+
+/// Action executed when the player clicks on a [Chat] component
+///
+/// <https://wiki.vg/Chat#Click_events>
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "action", content = "value", rename_all = "snake_case")]
+pub enum ClickEvent {
+    OpenUrl(String),
+    /// The command must start with a /
+    RunCommand(String),
+    SuggestCommand(String),
+    /// Only used in books
+    ChangePage(u32),
+    CopyToClipboard(String),
+}
+
+/// Tooltip shown when the player hovers a [Chat] component
+///
+/// <https://wiki.vg/Chat#Hover_events>
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "action", content = "contents", rename_all = "snake_case")]
+pub enum HoverEvent {
+    ShowText(Box<Chat>),
+    ShowItem {
+        /// Identifier of the item
+        id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        count: Option<i32>,
+        /// The item's NBT as a SNBT string
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tag: Option<String>,
+    },
+    ShowEntity {
+        /// Identifier of the entity type
+        #[serde(rename = "type")]
+        kind: String,
+        /// The entity's uuid as an hyphenated string
+        id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<Box<Chat>>,
+    },
+}
+
+/// Builder for json chat components, converts into a [serde_json::Value]
+/// that can be used in any packet taking a chat field
+///
+/// <https://wiki.vg/Chat>
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Chat {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    translate: Option<String>,
+    #[serde(rename = "with", skip_serializing_if = "Vec::is_empty")]
+    translate_args: Vec<Chat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<ChatColor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bold: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    italic: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    underlined: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strikethrough: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    obfuscated: Option<bool>,
+    #[serde(rename = "clickEvent", skip_serializing_if = "Option::is_none")]
+    click_event: Option<ClickEvent>,
+    #[serde(rename = "hoverEvent", skip_serializing_if = "Option::is_none")]
+    hover_event: Option<HoverEvent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    extra: Vec<Chat>,
+}
+impl Chat {
+    fn empty() -> Self {
+        Self {
+            text: None,
+            translate: None,
+            translate_args: vec![],
+            color: None,
+            bold: None,
+            italic: None,
+            underlined: None,
+            strikethrough: None,
+            obfuscated: None,
+            click_event: None,
+            hover_event: None,
+            extra: vec![],
+        }
+    }
+
+    /// Creates a component displaying the given text
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: Some(text.into()),
+            ..Self::empty()
+        }
+    }
+
+    /// Creates a component translated client side from the given key (e.g. chat.type.text)
+    /// Use [Chat::with_arg] to fill in the translation parameters
+    pub fn translate(key: impl Into<String>) -> Self {
+        Self {
+            translate: Some(key.into()),
+            ..Self::empty()
+        }
+    }
+
+    /// Adds a parameter to a translated component
+    pub fn with_arg(mut self, arg: impl Into<Chat>) -> Self {
+        self.translate_args.push(arg.into());
+        self
+    }
+
+    pub fn color(mut self, color: ChatColor) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn bold(mut self, bold: bool) -> Self {
+        self.bold = Some(bold);
+        self
+    }
+
+    pub fn italic(mut self, italic: bool) -> Self {
+        self.italic = Some(italic);
+        self
+    }
+
+    pub fn underlined(mut self, underlined: bool) -> Self {
+        self.underlined = Some(underlined);
+        self
+    }
+
+    pub fn strikethrough(mut self, strikethrough: bool) -> Self {
+        self.strikethrough = Some(strikethrough);
+        self
+    }
+
+    pub fn obfuscated(mut self, obfuscated: bool) -> Self {
+        self.obfuscated = Some(obfuscated);
+        self
+    }
+
+    pub fn click_event(mut self, click_event: ClickEvent) -> Self {
+        self.click_event = Some(click_event);
+        self
+    }
+
+    pub fn hover_event(mut self, hover_event: HoverEvent) -> Self {
+        self.hover_event = Some(hover_event);
+        self
+    }
+
+    /// Adds a child component, which inherits the style of this one
+    pub fn extra(mut self, child: impl Into<Chat>) -> Self {
+        self.extra.push(child.into());
+        self
+    }
+}
+
+impl From<&str> for Chat {
+    fn from(text: &str) -> Self {
+        Self::text(text)
+    }
+}
+
+impl From<String> for Chat {
+    fn from(text: String) -> Self {
+        Self::text(text)
+    }
+}
+
+impl From<Chat> for serde_json::Value {
+    fn from(chat: Chat) -> Self {
+        serde_json::to_value(chat).expect("Chat components are always valid json")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn simple_component() {
+        let chat = Chat::text("Hello")
+            .color(ChatColor::Gold)
+            .bold(true)
+            .extra(Chat::text(" world").italic(true).color(ChatColor::Hex(0x7FA1FF)));
+
+        assert_eq!(
+            Value::from(chat),
+            json!({
+                "text": "Hello",
+                "color": "gold",
+                "bold": true,
+                "extra": [
+                    { "text": " world", "italic": true, "color": "#7FA1FF" }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn translated_component_with_events() {
+        let chat = Chat::translate("chat.type.text")
+            .with_arg(
+                Chat::text("Heav")
+                    .click_event(ClickEvent::SuggestCommand("/msg Heav ".into()))
+                    .hover_event(HoverEvent::ShowText(Box::new(Chat::text("Send a message")))),
+            )
+            .with_arg("hi")
+            .underlined(false);
+
+        assert_eq!(
+            Value::from(chat),
+            json!({
+                "translate": "chat.type.text",
+                "with": [
+                    {
+                        "text": "Heav",
+                        "clickEvent": { "action": "suggest_command", "value": "/msg Heav " },
+                        "hoverEvent": { "action": "show_text", "contents": { "text": "Send a message" } }
+                    },
+                    { "text": "hi" }
+                ],
+                "underlined": false
+            })
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use super::{Identifier, Slot};
+
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Enchantment {
+    pub id: Identifier,
+    pub level: i16,
+}
+impl Enchantment {
+    fn to_nbt(&self) -> nbt::Value {
+        let mut compound = HashMap::new();
+        compound.insert("id".to_string(), nbt::Value::String(self.id.to_string()));
+        compound.insert("lvl".to_string(), nbt::Value::Short(self.level));
+        nbt::Value::Compound(compound)
+    }
+}
+
+/// Builder for the nbt of a [Slot::Present]
+///
+/// <https://minecraft.fandom.com/wiki/Player.dat_format#Item_structure>
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ItemNbt {
+    display_name: Option<serde_json::Value>,
+    lore: Vec<serde_json::Value>,
+    enchantments: Vec<Enchantment>,
+    unbreakable: bool,
+    custom_model_data: Option<i32>,
+}
+impl ItemNbt {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Chat component replacing the name of the item
+    pub fn display_name(mut self, name: impl Into<serde_json::Value>) -> Self {
+        self.display_name = Some(name.into());
+        self
+    }
+
+    /// Adds a line of lore, as a chat component
+    pub fn lore(mut self, line: impl Into<serde_json::Value>) -> Self {
+        self.lore.push(line.into());
+        self
+    }
+
+    /// Adds an enchantment, ids without namespace are in the minecraft namespace
+    pub fn enchantment(mut self, id: &str, level: i16) -> Self {
+        self.enchantments.push(Enchantment {
+            id: id.into(),
+            level,
+        });
+        self
+    }
+
+    pub fn unbreakable(mut self, unbreakable: bool) -> Self {
+        self.unbreakable = unbreakable;
+        self
+    }
+
+    pub fn custom_model_data(mut self, custom_model_data: i32) -> Self {
+        self.custom_model_data = Some(custom_model_data);
+        self
+    }
+
+    pub fn build(&self) -> nbt::Blob {
+        let mut blob = nbt::Blob::new();
+
+        let mut display = HashMap::new();
+        if let Some(name) = &self.display_name {
+            display.insert("Name".to_string(), nbt::Value::String(name.to_string()));
+        }
+        if !self.lore.is_empty() {
+            let lore = self.lore.iter()
+                .map(|line| nbt::Value::String(line.to_string()))
+                .collect();
+            display.insert("Lore".to_string(), nbt::Value::List(lore));
+        }
+        if !display.is_empty() {
+            blob.insert("display", nbt::Value::Compound(display)).unwrap();
+        }
+
+        if !self.enchantments.is_empty() {
+            let enchantments = self.enchantments.iter().map(Enchantment::to_nbt).collect();
+            blob.insert("Enchantments", nbt::Value::List(enchantments)).unwrap();
+        }
+        if self.unbreakable {
+            blob.insert("Unbreakable", nbt::Value::Byte(1)).unwrap();
+        }
+        if let Some(custom_model_data) = self.custom_model_data {
+            blob.insert("CustomModelData", nbt::Value::Int(custom_model_data)).unwrap();
+        }
+        blob
+    }
+
+    /// Creates a slot of the given item with this nbt
+    pub fn slot(&self, item_id: i32, item_count: u8) -> Slot {
+        Slot::Present {
+            item_id,
+            item_count,
+            nbt: self.build(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_types::chat::Chat;
+
+    #[test]
+    fn sharpness_sword() {
+        let nbt = ItemNbt::new()
+            .display_name(Chat::text("Excalibur"))
+            .lore("Pulled from a stone")
+            .enchantment("sharpness", 5)
+            .unbreakable(true)
+            .custom_model_data(7)
+            .build();
+
+        let enchantment = match nbt.get("Enchantments") {
+            Some(nbt::Value::List(enchantments)) if enchantments.len() == 1 => &enchantments[0],
+            other => panic!("invalid enchantments {:?}", other),
+        };
+        match enchantment {
+            nbt::Value::Compound(enchantment) => {
+                assert_eq!(enchantment.get("id"), Some(&nbt::Value::String("minecraft:sharpness".into())));
+                assert_eq!(enchantment.get("lvl"), Some(&nbt::Value::Short(5)));
+            }
+            other => panic!("invalid enchantment {:?}", other),
+        }
+
+        let display = match nbt.get("display") {
+            Some(nbt::Value::Compound(display)) => display,
+            other => panic!("invalid display {:?}", other),
+        };
+        assert_eq!(display.get("Name"), Some(&nbt::Value::String(r#"{"text":"Excalibur"}"#.into())));
+        assert_eq!(
+            display.get("Lore"),
+            Some(&nbt::Value::List(vec![nbt::Value::String(r#""Pulled from a stone""#.into())]))
+        );
+        assert_eq!(nbt.get("Unbreakable"), Some(&nbt::Value::Byte(1)));
+        assert_eq!(nbt.get("CustomModelData"), Some(&nbt::Value::Int(7)));
+
+        assert!(ItemNbt::new().build().get("display").is_none());
+    }
+}
@@ -0,0 +1,126 @@
+use super::MetadataValue;
+
+use std::collections::HashMap;
+
+/// Index of the bit mask holding the flags of every entity
+pub const ENTITY_FLAGS_METADATA_INDEX: u8 = 0;
+pub const ON_FIRE_FLAG: u8 = 0x01;
+pub const CROUCHING_FLAG: u8 = 0x02;
+pub const SPRINTING_FLAG: u8 = 0x08;
+pub const SWIMMING_FLAG: u8 = 0x10;
+pub const INVISIBLE_FLAG: u8 = 0x20;
+pub const GLOWING_FLAG: u8 = 0x40;
+pub const FLYING_WITH_ELYTRA_FLAG: u8 = 0x80;
+pub const CUSTOM_NAME_METADATA_INDEX: u8 = 2;
+pub const CUSTOM_NAME_VISIBLE_METADATA_INDEX: u8 = 3;
+pub const SILENT_METADATA_INDEX: u8 = 4;
+pub const NO_GRAVITY_METADATA_INDEX: u8 = 5;
+
+/// Builds the metadata fields shared by every entity, for a
+/// [C4DSetEntityMetadata](crate::packets::client_bound::C4DSetEntityMetadata)
+///
+/// <https://wiki.vg/Entity_metadata#Entity>
+#[derive(Clone, Debug, Default)]
+pub struct MetadataBuilder {
+    /// None if no flag has been set
+    flags: Option<u8>,
+    metadata: HashMap<u8, MetadataValue>,
+}
+impl MetadataBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn flag(mut self, mask: u8, value: bool) -> Self {
+        let flags = self.flags.get_or_insert(0);
+        if value {
+            *flags |= mask;
+        } else {
+            *flags &= !mask;
+        }
+        self
+    }
+
+    pub fn on_fire(self, on_fire: bool) -> Self {
+        self.flag(ON_FIRE_FLAG, on_fire)
+    }
+
+    pub fn crouching(self, crouching: bool) -> Self {
+        self.flag(CROUCHING_FLAG, crouching)
+    }
+
+    pub fn sprinting(self, sprinting: bool) -> Self {
+        self.flag(SPRINTING_FLAG, sprinting)
+    }
+
+    pub fn swimming(self, swimming: bool) -> Self {
+        self.flag(SWIMMING_FLAG, swimming)
+    }
+
+    pub fn invisible(self, invisible: bool) -> Self {
+        self.flag(INVISIBLE_FLAG, invisible)
+    }
+
+    pub fn glowing(self, glowing: bool) -> Self {
+        self.flag(GLOWING_FLAG, glowing)
+    }
+
+    pub fn flying_with_elytra(self, flying_with_elytra: bool) -> Self {
+        self.flag(FLYING_WITH_ELYTRA_FLAG, flying_with_elytra)
+    }
+
+    /// Chat component shown above the entity, None to remove it
+    pub fn custom_name(mut self, custom_name: Option<serde_json::Value>) -> Self {
+        self.metadata.insert(CUSTOM_NAME_METADATA_INDEX, MetadataValue::OptChat(custom_name));
+        self
+    }
+
+    pub fn custom_name_visible(mut self, visible: bool) -> Self {
+        self.metadata.insert(CUSTOM_NAME_VISIBLE_METADATA_INDEX, MetadataValue::Boolean(visible));
+        self
+    }
+
+    pub fn silent(mut self, silent: bool) -> Self {
+        self.metadata.insert(SILENT_METADATA_INDEX, MetadataValue::Boolean(silent));
+        self
+    }
+
+    pub fn no_gravity(mut self, no_gravity: bool) -> Self {
+        self.metadata.insert(NO_GRAVITY_METADATA_INDEX, MetadataValue::Boolean(no_gravity));
+        self
+    }
+
+    /// Returns the fields that have been set, indexed by their metadata index
+    pub fn build(self) -> HashMap<u8, MetadataValue> {
+        let mut metadata = self.metadata;
+        if let Some(flags) = self.flags {
+            metadata.insert(ENTITY_FLAGS_METADATA_INDEX, MetadataValue::Byte(flags));
+        }
+        metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_are_packed() {
+        let metadata = MetadataBuilder::new().crouching(true).sprinting(true).build();
+        assert_eq!(metadata.len(), 1);
+        assert!(matches!(metadata.get(&ENTITY_FLAGS_METADATA_INDEX), Some(MetadataValue::Byte(0x0A))));
+
+        let metadata = MetadataBuilder::new()
+            .crouching(true)
+            .glowing(true)
+            .crouching(false)
+            .silent(true)
+            .build();
+        assert!(matches!(metadata.get(&ENTITY_FLAGS_METADATA_INDEX), Some(MetadataValue::Byte(0x40))));
+        assert!(matches!(metadata.get(&SILENT_METADATA_INDEX), Some(MetadataValue::Boolean(true))));
+
+        // Fields that haven't been set are left out
+        let metadata = MetadataBuilder::new().no_gravity(true).build();
+        assert!(!metadata.contains_key(&ENTITY_FLAGS_METADATA_INDEX));
+    }
+}
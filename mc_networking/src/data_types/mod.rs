@@ -2,18 +2,25 @@ use crate::{data_types::encoder::PacketEncoder, DecodingError, DecodingResult};
 
 use byteorder::ReadBytesExt;
 use bytes::Bytes;
+use num_derive::{FromPrimitive, ToPrimitive};
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 use std::io::{Cursor, Read};
 use tokio::io::{AsyncRead, AsyncReadExt};
 use uuid::Uuid;
 
 pub mod bitbuffer;
 pub mod bitset;
+pub mod chat;
 pub mod command_data;
 pub mod encoder;
 mod identifier;
+mod item_nbt;
+mod metadata_builder;
 
 pub use identifier::*;
+pub use item_nbt::*;
+pub use metadata_builder::*;
 
 pub type VarInt = i32;
 pub type VarLong = i64;
@@ -81,6 +88,65 @@ impl Slot {
             Slot::Present { .. } => true,
         }
     }
+
+    /// Number of items in the slot, 0 if it is empty
+    pub fn item_count(&self) -> u8 {
+        match self {
+            Slot::NotPresent => 0,
+            Slot::Present { item_count, .. } => *item_count,
+        }
+    }
+
+    /// If both slots contain the same item with the same nbt
+    pub fn is_stackable_with(&self, other: &Slot) -> bool {
+        match (self, other) {
+            (
+                Slot::Present { item_id, nbt, .. },
+                Slot::Present { item_id: other_item_id, nbt: other_nbt, .. },
+            ) => item_id == other_item_id && nbt == other_nbt,
+            _ => false,
+        }
+    }
+
+    /// Moves as many items from `other` to this slot as fit in a stack of `max_stack` items
+    /// Nothing is moved if the slots contain different items
+    /// Returns the number of moved items
+    pub fn try_merge(&mut self, other: &mut Slot, max_stack: u8) -> u8 {
+        if !self.is_present() {
+            *self = other.split(max_stack);
+            return self.item_count();
+        }
+        if !self.is_stackable_with(other) {
+            return 0;
+        }
+        let moved = other.split(max_stack.saturating_sub(self.item_count())).item_count();
+        if let Slot::Present { item_count, .. } = self {
+            *item_count += moved;
+        }
+        moved
+    }
+
+    /// Takes at most `amount` items out of the slot, which is emptied if no item is left
+    pub fn split(&mut self, amount: u8) -> Slot {
+        let count = self.item_count();
+        if amount >= count {
+            return std::mem::take(self);
+        }
+        match self {
+            Slot::NotPresent => Slot::NotPresent,
+            Slot::Present { item_id, item_count, nbt } => {
+                *item_count -= amount;
+                if amount == 0 {
+                    return Slot::NotPresent;
+                }
+                Slot::Present {
+                    item_id: *item_id,
+                    item_count: amount,
+                    nbt: nbt.clone(),
+                }
+            }
+        }
+    }
 }
 impl Default for Slot {
     fn default() -> Self {
@@ -119,6 +185,12 @@ pub struct Particle {
     pub data: i32,
 }
 impl Particle {
+    /// Whether the data of this particle is a block state (block, block_marker and falling_dust),
+    /// other particles are sent without data
+    pub fn has_block_state_data(&self) -> bool {
+        matches!(self.id, 2 | 3 | 25)
+    }
+
     pub fn encode(&self) -> Bytes {
         let mut data = PacketEncoder::default();
         data.write_varint(self.id);
@@ -169,6 +241,151 @@ impl Pose {
     }
 }
 
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+pub enum Gamemode {
+    Survival = 0,
+    Creative = 1,
+    Adventure = 2,
+    Spectator = 3,
+}
+impl Gamemode {
+    pub fn to_i8(self) -> i8 {
+        self as i8
+    }
+
+    pub fn from_i8(value: i8) -> Option<Self> {
+        <Self as num_traits::FromPrimitive>::from_i8(value)
+    }
+
+    /// Encodes an optional gamemode like the previous gamemode of [crate::packets::client_bound::C23Login],
+    /// -1 meaning no gamemode
+    pub fn encode_optional(gamemode: Option<Self>) -> i8 {
+        gamemode.map_or(-1, Self::to_i8)
+    }
+
+    /// Decodes an optional gamemode encoded with [Gamemode::encode_optional],
+    /// returns None if the value is neither a gamemode nor -1
+    pub fn decode_optional(value: i8) -> Option<Option<Self>> {
+        match value {
+            -1 => Some(None),
+            value => Self::from_i8(value).map(Some),
+        }
+    }
+}
+impl From<Gamemode> for i8 {
+    fn from(gamemode: Gamemode) -> Self {
+        gamemode.to_i8()
+    }
+}
+impl From<Gamemode> for i32 {
+    fn from(gamemode: Gamemode) -> Self {
+        gamemode.to_i8() as i32
+    }
+}
+impl TryFrom<i8> for Gamemode {
+    type Error = DecodingError;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        Self::from_i8(value)
+            .ok_or_else(|| DecodingError::parse_error("gamemode", format!("invalid gamemode {value}")))
+    }
+}
+
+/// Hand used by a player, sent as a VarInt by the serverbound packets
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+pub enum Hand {
+    MainHand = 0,
+    OffHand = 1,
+}
+impl Hand {
+    pub fn to_varint(self) -> VarInt {
+        self as VarInt
+    }
+
+    pub fn from_varint(value: VarInt) -> Option<Self> {
+        <Self as num_traits::FromPrimitive>::from_i32(value)
+    }
+
+    /// Equipment slot holding the item of this hand
+    pub fn equipment_slot(self) -> EquipmentSlot {
+        match self {
+            Hand::MainHand => EquipmentSlot::MainHand,
+            Hand::OffHand => EquipmentSlot::OffHand,
+        }
+    }
+}
+impl From<Hand> for VarInt {
+    fn from(hand: Hand) -> Self {
+        hand.to_varint()
+    }
+}
+impl TryFrom<VarInt> for Hand {
+    type Error = DecodingError;
+
+    fn try_from(value: VarInt) -> Result<Self, Self::Error> {
+        Self::from_varint(value)
+            .ok_or_else(|| DecodingError::parse_error("hand", format!("invalid hand {value}")))
+    }
+}
+
+/// Equipment slot of an entity, in the order of the protocol
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+pub enum EquipmentSlot {
+    MainHand = 0,
+    OffHand = 1,
+    Feet = 2,
+    Legs = 3,
+    Chest = 4,
+    Head = 5,
+}
+impl EquipmentSlot {
+    pub const ALL: [Self; 6] = [
+        Self::MainHand,
+        Self::OffHand,
+        Self::Feet,
+        Self::Legs,
+        Self::Chest,
+        Self::Head,
+    ];
+
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        <Self as num_traits::FromPrimitive>::from_u8(id)
+    }
+
+    /// The hand holding this slot, None for the armor slots
+    pub fn hand(self) -> Option<Hand> {
+        match self {
+            Self::MainHand => Some(Hand::MainHand),
+            Self::OffHand => Some(Hand::OffHand),
+            _ => None,
+        }
+    }
+
+    /// Index of the slot in the player inventory window, None for the main hand which
+    /// is the held hotbar slot
+    /// See <https://wiki.vg/Inventory#Player_Inventory>
+    pub fn player_inventory_index(self) -> Option<usize> {
+        match self {
+            Self::MainHand => None,
+            Self::OffHand => Some(45),
+            Self::Feet => Some(8),
+            Self::Legs => Some(7),
+            Self::Chest => Some(6),
+            Self::Head => Some(5),
+        }
+    }
+}
+impl From<Hand> for EquipmentSlot {
+    fn from(hand: Hand) -> Self {
+        hand.equipment_slot()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum MetadataValue {
     Byte(u8),
@@ -356,3 +573,93 @@ impl MetadataValue {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(item_id: i32, item_count: u8) -> Slot {
+        Slot::Present { item_id, item_count, nbt: nbt::Blob::new() }
+    }
+
+    #[test]
+    fn merge_partial_stacks() {
+        let mut target = item(1, 40);
+        let mut source = item(1, 30);
+        assert_eq!(target.try_merge(&mut source, 64), 24);
+        assert_eq!(target, item(1, 64));
+        assert_eq!(source, item(1, 6));
+
+        let mut empty = Slot::NotPresent;
+        assert_eq!(empty.try_merge(&mut source, 64), 6);
+        assert_eq!(empty, item(1, 6));
+        assert_eq!(source, Slot::NotPresent);
+    }
+
+    #[test]
+    fn split_emptying_source() {
+        let mut source = item(2, 5);
+        assert_eq!(source.split(2), item(2, 2));
+        assert_eq!(source, item(2, 3));
+        assert_eq!(source.split(10), item(2, 3));
+        assert_eq!(source, Slot::NotPresent);
+        assert_eq!(source.split(1), Slot::NotPresent);
+    }
+
+    #[test]
+    fn merge_refuses_different_nbt() {
+        let mut named = nbt::Blob::new();
+        named.insert("Damage", nbt::Value::Int(3)).unwrap();
+        let mut target = item(3, 1);
+        let mut source = Slot::Present { item_id: 3, item_count: 1, nbt: named };
+
+        assert!(!target.is_stackable_with(&source));
+        assert_eq!(target.try_merge(&mut source, 64), 0);
+        assert_eq!(target, item(3, 1));
+        assert_eq!(source.item_count(), 1);
+        assert!(!item(3, 1).is_stackable_with(&item(4, 1)));
+    }
+
+    #[test]
+    fn hand_conversions() {
+        assert_eq!(Hand::try_from(0).ok(), Some(Hand::MainHand));
+        assert_eq!(Hand::try_from(1).ok(), Some(Hand::OffHand));
+        assert!(Hand::try_from(2).is_err());
+        assert_eq!(VarInt::from(Hand::OffHand), 1);
+        assert_eq!(EquipmentSlot::from(Hand::MainHand), EquipmentSlot::MainHand);
+        assert_eq!(Hand::OffHand.equipment_slot().hand(), Some(Hand::OffHand));
+    }
+
+    #[test]
+    fn equipment_slot_conversions() {
+        for (id, slot) in EquipmentSlot::ALL.iter().enumerate() {
+            assert_eq!(slot.id(), id as u8);
+            assert_eq!(EquipmentSlot::from_id(id as u8), Some(*slot));
+        }
+        assert_eq!(EquipmentSlot::from_id(6), None);
+        assert_eq!(EquipmentSlot::Head.hand(), None);
+        assert_eq!(EquipmentSlot::Head.player_inventory_index(), Some(5));
+        assert_eq!(EquipmentSlot::OffHand.player_inventory_index(), Some(45));
+        assert_eq!(EquipmentSlot::MainHand.player_inventory_index(), None);
+    }
+
+    #[test]
+    fn gamemode_round_trip() {
+        for gamemode in [Gamemode::Survival, Gamemode::Creative, Gamemode::Adventure, Gamemode::Spectator] {
+            assert_eq!(Gamemode::from_i8(gamemode.to_i8()), Some(gamemode));
+            assert_eq!(Gamemode::try_from(i8::from(gamemode)).ok(), Some(gamemode));
+        }
+        assert_eq!(Gamemode::Adventure.to_i8(), 2);
+        assert_eq!(Gamemode::from_i8(4), None);
+        assert!(Gamemode::try_from(-1).is_err());
+    }
+
+    #[test]
+    fn optional_gamemode() {
+        assert_eq!(Gamemode::encode_optional(None), -1);
+        assert_eq!(Gamemode::encode_optional(Some(Gamemode::Creative)), 1);
+        assert_eq!(Gamemode::decode_optional(-1), Some(None));
+        assert_eq!(Gamemode::decode_optional(3), Some(Some(Gamemode::Spectator)));
+        assert_eq!(Gamemode::decode_optional(-2), None);
+    }
+}
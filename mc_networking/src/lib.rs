@@ -1,10 +1,16 @@
 pub mod client;
+pub mod codec;
 pub mod data_types;
 pub mod nbt_map;
 pub mod packets;
 
 use crate::data_types::VarInt;
 
+/// Protocol version implemented by the packets of this crate
+pub const PROTOCOL_VERSION: i32 = 759;
+/// Minecraft version matching [PROTOCOL_VERSION]
+pub const MINECRAFT_VERSION: &str = "1.19";
+
 use thiserror::Error;
 use tokio::io;
 
@@ -172,8 +172,8 @@ mod play {
     use super::ClientBoundPacket;
     use crate::{
         data_types::{
-            command_data, encoder::PacketEncoder, Angle, Identifier, MetadataValue, Position, Slot,
-            VarInt, bitset::BitSet
+            command_data, encoder::PacketEncoder, Angle, EquipmentSlot, Gamemode, Identifier,
+            MetadataValue, Particle, Position, Slot, VarInt, VarLong, bitset::BitSet
         },
         nbt_map::NBTMap,
         DecodingResult as Result,
@@ -209,6 +209,7 @@ mod play {
         fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
             encoder.write_varint(self.entity_id);
             encoder.write_uuid(&self.object_uuid);
+            encoder.write_varint(self.kind);
             encoder.write_f64(self.x);
             encoder.write_f64(self.y);
             encoder.write_f64(self.z);
@@ -272,6 +273,22 @@ mod play {
         }
     }
 
+    /// Animations played by a [C03EntityAnimation]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum EntityAnimation {
+        SwingMainArm = 0,
+        TakeDamage = 1,
+        LeaveBed = 2,
+        SwingOffHand = 3,
+        Critical = 4,
+        MagicCritical = 5,
+    }
+    impl EntityAnimation {
+        pub fn id(self) -> u8 {
+            self as u8
+        }
+    }
+
     /// Sent whenever an entity should change animation.
     ///
     /// <https://wiki.vg/Protocol#Entity_Animation_.28clientbound.29>
@@ -280,6 +297,14 @@ mod play {
         pub entity_id: VarInt,
         pub animation: u8,
     }
+    impl C03EntityAnimation {
+        pub fn new(entity_id: VarInt, animation: EntityAnimation) -> Self {
+            Self {
+                entity_id,
+                animation: animation.id(),
+            }
+        }
+    }
     impl ClientBoundPacket for C03EntityAnimation {
         const PACKET_ID: i32 = 0x03;
 
@@ -317,7 +342,7 @@ mod play {
         pub destroy_stage: i8,
     }
     impl ClientBoundPacket for C06SetBlockDestroyStage {
-        const PACKET_ID: i32 = 0x07;
+        const PACKET_ID: i32 = 0x06;
 
         fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
             encoder.write_varint(self.entity_id);
@@ -343,16 +368,112 @@ mod play {
         }
     }
 
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum C0ABossBarColor {
+        Pink = 0,
+        Blue = 1,
+        Red = 2,
+        Green = 3,
+        Yellow = 4,
+        Purple = 5,
+        White = 6,
+    }
+
+    /// Number of notches shown on the bar
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum C0ABossBarDivision {
+        NoDivision = 0,
+        SixNotches = 1,
+        TenNotches = 2,
+        TwelveNotches = 3,
+        TwentyNotches = 4,
+    }
+
+    /// Bit mask of the boss bar flags, 0x1 darkens the sky, 0x2 plays the end music
+    /// and 0x4 creates fog
+    pub type C0ABossBarFlags = u8;
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum C0ABossBarAction {
+        Add {
+            title: serde_json::Value,
+            /// From 0 to 1
+            health: f32,
+            color: C0ABossBarColor,
+            division: C0ABossBarDivision,
+            flags: C0ABossBarFlags,
+        },
+        Remove,
+        UpdateHealth {
+            health: f32,
+        },
+        UpdateTitle {
+            title: serde_json::Value,
+        },
+        UpdateStyle {
+            color: C0ABossBarColor,
+            division: C0ABossBarDivision,
+        },
+        UpdateFlags {
+            flags: C0ABossBarFlags,
+        },
+    }
+
+    /// Adds, updates or removes a boss bar shown at the top of the screen.
+    ///
+    /// <https://wiki.vg/Protocol#Boss_Bar>
+    #[derive(Clone, Debug)]
+    pub struct C0ABossBar {
+        /// Unique id of the bar
+        pub uuid: Uuid,
+        pub action: C0ABossBarAction,
+    }
+    impl ClientBoundPacket for C0ABossBar {
+        const PACKET_ID: i32 = 0x0A;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_uuid(&self.uuid);
+            match &self.action {
+                C0ABossBarAction::Add { title, health, color, division, flags } => {
+                    encoder.write_varint(0);
+                    encoder.write_string(&title.to_string());
+                    encoder.write_f32(*health);
+                    encoder.write_varint(*color as i32);
+                    encoder.write_varint(*division as i32);
+                    encoder.write_u8(*flags);
+                }
+                C0ABossBarAction::Remove => encoder.write_varint(1),
+                C0ABossBarAction::UpdateHealth { health } => {
+                    encoder.write_varint(2);
+                    encoder.write_f32(*health);
+                }
+                C0ABossBarAction::UpdateTitle { title } => {
+                    encoder.write_varint(3);
+                    encoder.write_string(&title.to_string());
+                }
+                C0ABossBarAction::UpdateStyle { color, division } => {
+                    encoder.write_varint(4);
+                    encoder.write_varint(*color as i32);
+                    encoder.write_varint(*division as i32);
+                }
+                C0ABossBarAction::UpdateFlags { flags } => {
+                    encoder.write_varint(5);
+                    encoder.write_u8(*flags);
+                }
+            }
+        }
+    }
+
     /// Sets the message to preview on the client.
     ///
     /// <https://wiki.vg/Protocol#Chat_Preview_.28clientbound.29>
     #[derive(Clone, Debug)]
-    pub struct COCChatPreview {
+    pub struct C0CChatPreview {
         query_id: i32,
         component_is_present: bool,
         message_to_preview: serde_json::Value,
     }
-    impl ClientBoundPacket for COCChatPreview {
+    impl ClientBoundPacket for C0CChatPreview {
         const PACKET_ID: i32 = 0x0C;
 
         fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
@@ -362,6 +483,62 @@ mod play {
         }
     }
 
+    /// Hides the title and subtitle shown to the player.
+    ///
+    /// <https://wiki.vg/Protocol#Clear_Titles>
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct C0DClearTitles {
+        /// Also resets the texts and the animation times to their defaults
+        pub reset: bool,
+    }
+    impl ClientBoundPacket for C0DClearTitles {
+        const PACKET_ID: i32 = 0x0D;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_bool(self.reset);
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct C0ECommandSuggestionMatch {
+        /// One eligible value to insert
+        pub value: String,
+        pub tooltip: Option<serde_json::Value>,
+    }
+
+    /// The server responds with a list of auto-completions of the last word sent to it.
+    /// The start and length give the part of the text replaced by the matches,
+    /// which the client also uses to highlight parse errors.
+    ///
+    /// <https://wiki.vg/Protocol#Command_Suggestions_Response>
+    #[derive(Clone, Debug)]
+    pub struct C0ECommandSuggestionsResponse {
+        /// Transaction ID of the request
+        pub id: VarInt,
+        /// Start of the text to replace
+        pub start: VarInt,
+        /// Length of the text to replace
+        pub length: VarInt,
+        pub matches: Vec<C0ECommandSuggestionMatch>,
+    }
+    impl ClientBoundPacket for C0ECommandSuggestionsResponse {
+        const PACKET_ID: i32 = 0x0E;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_varint(self.id);
+            encoder.write_varint(self.start);
+            encoder.write_varint(self.length);
+            encoder.write_varint(self.matches.len() as VarInt);
+            for suggestion in self.matches.iter() {
+                encoder.write_string(&suggestion.value);
+                encoder.write_bool(suggestion.tooltip.is_some());
+                if let Some(tooltip) = &suggestion.tooltip {
+                    encoder.write_string(&tooltip.to_string());
+                }
+            }
+        }
+    }
+
     /// Lists all of the commands on the server, and how they are parsed.
     /// This is a directed graph, with one root node.
     /// Each redirect or child node must refer only to nodes that have already been declared.
@@ -531,6 +708,46 @@ mod play {
         pub event: u8,
         pub value: f32,
     }
+    impl C1BGameEvent {
+        pub fn end_raining() -> Self {
+            Self { event: 1, value: 0. }
+        }
+
+        pub fn begin_raining() -> Self {
+            Self { event: 2, value: 0. }
+        }
+
+        pub fn change_gamemode(gamemode: Gamemode) -> Self {
+            Self {
+                event: 3,
+                value: gamemode.to_i8() as f32,
+            }
+        }
+
+        /// If show_credits is false the player is just respawned
+        pub fn win_game(show_credits: bool) -> Self {
+            Self {
+                event: 4,
+                value: if show_credits { 1. } else { 0. },
+            }
+        }
+
+        /// From 0 to 1
+        pub fn rain_level(level: f32) -> Self {
+            Self {
+                event: 7,
+                value: level,
+            }
+        }
+
+        /// From 0 to 1
+        pub fn thunder_level(level: f32) -> Self {
+            Self {
+                event: 8,
+                value: level,
+            }
+        }
+    }
     impl ClientBoundPacket for C1BGameEvent {
         const PACKET_ID: i32 = 0x1B;
 
@@ -540,6 +757,41 @@ mod play {
         }
     }
 
+    /// Sets up the world border, usually sent once after the login
+    ///
+    /// <https://wiki.vg/Protocol#Initialize_World_Border>
+    #[derive(Clone, Debug)]
+    pub struct C1DInitializeWorldBorder {
+        pub x: f64,
+        pub z: f64,
+        /// Current length of a single side of the world border, in meters
+        pub old_diameter: f64,
+        /// Target length of a single side of the world border, in meters
+        pub new_diameter: f64,
+        /// Number of real-time milliseconds until the new diameter is reached
+        pub speed: VarLong,
+        /// Resulting coordinates from a portal teleport are limited to ±value, usually 29999984
+        pub portal_teleport_boundary: VarInt,
+        /// In meters
+        pub warning_blocks: VarInt,
+        /// In seconds as set by /worldborder warning time
+        pub warning_time: VarInt,
+    }
+    impl ClientBoundPacket for C1DInitializeWorldBorder {
+        const PACKET_ID: i32 = 0x1D;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_f64(self.x);
+            encoder.write_f64(self.z);
+            encoder.write_f64(self.old_diameter);
+            encoder.write_f64(self.new_diameter);
+            encoder.write_varlong(self.speed);
+            encoder.write_varint(self.portal_teleport_boundary);
+            encoder.write_varint(self.warning_blocks);
+            encoder.write_varint(self.warning_time);
+        }
+    }
+
     /// The server will frequently send out a keep-alive, each containing a random ID.
     /// The client must respond with the same packet.
     /// If the client does not respond to them for over 30 seconds, the server kicks the client.
@@ -725,6 +977,44 @@ mod play {
         }
     }
 
+    /// Displays the given particle count times, each one randomly offset by
+    /// a gaussian of the given offset and moving at a random speed up to max_speed.
+    ///
+    /// <https://wiki.vg/Protocol#Particle_2>
+    #[derive(Clone, Debug)]
+    pub struct C21Particle {
+        pub particle: Particle,
+        /// If true, particle distance increases from 256 to 65536
+        pub long_distance: bool,
+        pub x: f64,
+        pub y: f64,
+        pub z: f64,
+        pub offset_x: f32,
+        pub offset_y: f32,
+        pub offset_z: f32,
+        pub max_speed: f32,
+        pub count: i32,
+    }
+    impl ClientBoundPacket for C21Particle {
+        const PACKET_ID: i32 = 0x21;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_varint(self.particle.id);
+            encoder.write_bool(self.long_distance);
+            encoder.write_f64(self.x);
+            encoder.write_f64(self.y);
+            encoder.write_f64(self.z);
+            encoder.write_f32(self.offset_x);
+            encoder.write_f32(self.offset_y);
+            encoder.write_f32(self.offset_z);
+            encoder.write_f32(self.max_speed);
+            encoder.write_i32(self.count);
+            if self.particle.has_block_state_data() {
+                encoder.write_varint(self.particle.data);
+            }
+        }
+    }
+
     #[derive(Clone, Debug, Serialize)]
     pub struct C23DimensionElement {
         /// Whether piglins shake and transform to zombified piglins.
@@ -966,13 +1256,10 @@ mod play {
         pub entity_id: i32,
         /// Probably changes the player's hearts
         pub is_hardcore: bool,
-        /// 0: Survival, 1: Creative,
-        /// 2: Adventure, 3: Spectator.
-        pub gamemode: i8,
-        /// 0: survival, 1: creative, 2: adventure, 3: spectator.
+        pub gamemode: Gamemode,
         /// The hardcore flag is not included. The previous gamemode.
-        /// Defaults to -1 if there is no previous gamemode. (More information needed)
-        pub previous_gamemode: i8,
+        /// None if there is no previous gamemode, encoded as -1. (More information needed)
+        pub previous_gamemode: Option<Gamemode>,
         /// Identifiers for all dimensions on the server.
         pub dimension_names: Vec<Identifier>,
         /// Represents certain registries that are sent from the server and are applied on the client.
@@ -1010,8 +1297,8 @@ mod play {
         fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
             encoder.write_i32(self.entity_id);
             encoder.write_bool(self.is_hardcore);
-            encoder.write_i8(self.gamemode);
-            encoder.write_i8(self.previous_gamemode);
+            encoder.write_i8(self.gamemode.to_i8());
+            encoder.write_i8(Gamemode::encode_optional(self.previous_gamemode));
             encoder.write_varint(self.dimension_names.len() as _);
             for name in &self.dimension_names
             { encoder.write_string(&name); }
@@ -1118,6 +1405,23 @@ mod play {
         }
     }
 
+    /// Sent when the client has placed a sign and is allowed to send S2DUpdateSign.
+    /// There must already be a sign at the given location (which the client does not do automatically)
+    /// - send a Block Change first.
+    ///
+    /// <https://wiki.vg/Protocol#Open_Sign_Editor>
+    #[derive(Clone, Debug)]
+    pub struct C2COpenSignEditor {
+        pub location: Position,
+    }
+    impl ClientBoundPacket for C2COpenSignEditor {
+        const PACKET_ID: i32 = 0x2C;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_u64(self.location.encode());
+        }
+    }
+
     /// Use the new method for easier use
     ///
     /// <https://wiki.vg/Protocol#Player_Abilities_.28clientbound.29>
@@ -1244,7 +1548,7 @@ mod play {
         pub uuid: Uuid,
         pub name: String,
         pub properties: Vec<C34PlayerInfoAddPlayerProperty>,
-        pub gamemode: VarInt,
+        pub gamemode: Gamemode,
         /// Measured in milliseconds.
         pub ping: VarInt,
         pub display_name: Option<String>,
@@ -1264,7 +1568,7 @@ mod play {
                     encoder.write_string(sig);
                 }
             }
-            encoder.write_varint(self.gamemode);
+            encoder.write_varint(self.gamemode.into());
             encoder.write_varint(self.ping);
             encoder.write_bool(self.display_name.is_some());
             if let Some(dm) = &self.display_name {
@@ -1276,12 +1580,12 @@ mod play {
     #[derive(Clone, Debug)]
     pub struct C34UpdateGamemode {
         pub uuid: Uuid,
-        pub gamemode: VarInt,
+        pub gamemode: Gamemode,
     }
     impl C34UpdateGamemode {
         pub fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
             encoder.write_uuid(&self.uuid);
-            encoder.write_varint(self.gamemode);
+            encoder.write_varint(self.gamemode.into());
         }
     }
     #[derive(Clone, Debug)]
@@ -1373,6 +1677,46 @@ mod play {
         }
     }
 
+    /// Builder for the [C36SynchronizePlayerPosition::flags], every field set
+    /// in the flags is relative to the current position of the player
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct RelativeFlags(u8);
+    impl RelativeFlags {
+        /// Every field is absolute
+        pub fn new() -> Self {
+            Self(0)
+        }
+
+        pub fn x(self) -> Self {
+            Self(self.0 | 0x01)
+        }
+
+        pub fn y(self) -> Self {
+            Self(self.0 | 0x02)
+        }
+
+        pub fn z(self) -> Self {
+            Self(self.0 | 0x04)
+        }
+
+        pub fn yaw(self) -> Self {
+            Self(self.0 | 0x08)
+        }
+
+        pub fn pitch(self) -> Self {
+            Self(self.0 | 0x10)
+        }
+
+        pub fn bits(self) -> u8 {
+            self.0
+        }
+    }
+    impl From<RelativeFlags> for u8 {
+        fn from(flags: RelativeFlags) -> Self {
+            flags.bits()
+        }
+    }
+
     /// Updates the player's position on the server.
     ///
     /// <https://wiki.vg/Protocol#Synchronize_Player_Position>
@@ -1391,6 +1735,7 @@ mod play {
         /// Bit field for relativity of coordinates:
         /// <Dinnerbone> It's a bitfield, X/Y/Z/Y_ROT/X_ROT.
         /// <Dinnerbone> If X is set, the x value is relative and not absolute.
+        /// See [RelativeFlags]
         pub flags: u8,
         /// Client should confirm this packet with Accept Teleportation containing the same Teleport ID.
         pub teleport_id: i32,
@@ -1470,6 +1815,34 @@ mod play {
         pub inverted_trust_edges: bool,
         pub blocks: Vec<C3DBlockChange>,
     }
+    impl C3DUpdateSectionBlocks {
+        /// Creates a packet without any block change for the section containing the given block
+        pub fn for_block(world_x: i32, world_y: i32, world_z: i32) -> Self {
+            Self {
+                section_x: world_x.div_euclid(16),
+                section_y: world_y.div_euclid(16),
+                section_z: world_z.div_euclid(16),
+                inverted_trust_edges: false,
+                blocks: vec![],
+            }
+        }
+
+        /// Adds a block change from its world coordinates
+        /// Panics if the block isn't in the section of the packet
+        pub fn add_block(&mut self, world_x: i32, world_y: i32, world_z: i32, block_id: i32) {
+            let section = (world_x.div_euclid(16), world_y.div_euclid(16), world_z.div_euclid(16));
+            assert_eq!(
+                section, (self.section_x, self.section_y, self.section_z),
+                "block {} {} {} is not in the section of the packet", world_x, world_y, world_z,
+            );
+            self.blocks.push(C3DBlockChange {
+                x: world_x.rem_euclid(16) as u8,
+                y: world_y.rem_euclid(16) as u8,
+                z: world_z.rem_euclid(16) as u8,
+                block_id,
+            });
+        }
+    }
     impl ClientBoundPacket for C3DUpdateSectionBlocks {
         const PACKET_ID: i32 = 0x3D;
 
@@ -1482,6 +1855,10 @@ mod play {
             encoder.write_bool(self.inverted_trust_edges);
             encoder.write_varint(self.blocks.len() as VarInt);
             for block_change in self.blocks.iter() {
+                debug_assert!(
+                    block_change.x < 16 && block_change.y < 16 && block_change.z < 16,
+                    "block change coordinates must be local to the section",
+                );
                 encoder.write_varlong(
                     (block_change.block_id as i64      ) << 12
                   | (block_change.x        as i64 & 0xF) << 8
@@ -1505,6 +1882,89 @@ mod play {
         }
     }
 
+    /// Moves the center of the world border
+    ///
+    /// <https://wiki.vg/Protocol#Set_Border_Center>
+    #[derive(Clone, Debug)]
+    pub struct C41SetBorderCenter {
+        pub x: f64,
+        pub z: f64,
+    }
+    impl ClientBoundPacket for C41SetBorderCenter {
+        const PACKET_ID: i32 = 0x41;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_f64(self.x);
+            encoder.write_f64(self.z);
+        }
+    }
+
+    /// Shrinks or grows the world border over time
+    ///
+    /// <https://wiki.vg/Protocol#Set_Border_Lerp_Size>
+    #[derive(Clone, Debug)]
+    pub struct C42SetBorderLerpSize {
+        /// Current length of a single side of the world border, in meters
+        pub old_diameter: f64,
+        /// Target length of a single side of the world border, in meters
+        pub new_diameter: f64,
+        /// Number of real-time milliseconds until the new diameter is reached
+        pub speed: VarLong,
+    }
+    impl ClientBoundPacket for C42SetBorderLerpSize {
+        const PACKET_ID: i32 = 0x42;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_f64(self.old_diameter);
+            encoder.write_f64(self.new_diameter);
+            encoder.write_varlong(self.speed);
+        }
+    }
+
+    /// Instantly resizes the world border
+    ///
+    /// <https://wiki.vg/Protocol#Set_Border_Size>
+    #[derive(Clone, Debug)]
+    pub struct C43SetBorderSize {
+        /// Length of a single side of the world border, in meters
+        pub diameter: f64,
+    }
+    impl ClientBoundPacket for C43SetBorderSize {
+        const PACKET_ID: i32 = 0x43;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_f64(self.diameter);
+        }
+    }
+
+    /// <https://wiki.vg/Protocol#Set_Border_Warning_Delay>
+    #[derive(Clone, Debug)]
+    pub struct C44SetBorderWarningDelay {
+        /// In seconds as set by /worldborder warning time
+        pub warning_time: VarInt,
+    }
+    impl ClientBoundPacket for C44SetBorderWarningDelay {
+        const PACKET_ID: i32 = 0x44;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_varint(self.warning_time);
+        }
+    }
+
+    /// <https://wiki.vg/Protocol#Set_Border_Warning_Distance>
+    #[derive(Clone, Debug)]
+    pub struct C45SetBorderWarningDistance {
+        /// In meters
+        pub warning_blocks: VarInt,
+    }
+    impl ClientBoundPacket for C45SetBorderWarningDistance {
+        const PACKET_ID: i32 = 0x45;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_varint(self.warning_blocks);
+        }
+    }
+
 
     /// Sent to change the player's slot selection.
     ///
@@ -1544,6 +2004,22 @@ mod play {
         }
     }
 
+    /// Sent by the server when the view distance changes
+    ///
+    /// <https://wiki.vg/Protocol#Set_Render_Distance>
+    #[derive(Clone, Debug)]
+    pub struct C49SetRenderDistance {
+        /// In chunks, from 2 to 32
+        pub view_distance: VarInt,
+    }
+    impl ClientBoundPacket for C49SetRenderDistance {
+        const PACKET_ID: i32 = 0x49;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_varint(self.view_distance);
+        }
+    }
+
     /// Sent by the server after login to specify the coordinates of the spawn point
     /// (the point at which players spawn at, and which the compass points to).
     /// It can be sent at any time to update the point compasses point at.
@@ -1565,6 +2041,32 @@ mod play {
         }
     }
 
+    /// Slot of the screen where an objective can be displayed
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum C4CDisplaySlot {
+        List = 0,
+        Sidebar = 1,
+        BelowName = 2,
+    }
+
+    /// Displays an objective in a slot of the screen.
+    ///
+    /// <https://wiki.vg/Protocol#Display_Objective>
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct C4CDisplayObjective {
+        pub position: C4CDisplaySlot,
+        /// An empty name clears the slot
+        pub objective_name: String,
+    }
+    impl ClientBoundPacket for C4CDisplayObjective {
+        const PACKET_ID: i32 = 0x4C;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_i8(self.position as i8);
+            encoder.write_string(&self.objective_name);
+        }
+    }
+
     /// Updates one or more metadata properties for an existing entity.
     /// Any properties not included in the Metadata field are left unchanged.
     ///
@@ -1587,6 +2089,24 @@ mod play {
         }
     }
 
+    /// Sent when an entity has been leashed to another entity.
+    ///
+    /// <https://wiki.vg/Protocol#Link_Entities>
+    #[derive(Clone, Debug)]
+    pub struct C4EAttachEntity {
+        pub attached_entity_id: i32,
+        /// ID of the entity holding the lead. Set to -1 to detach.
+        pub holding_entity_id: i32,
+    }
+    impl ClientBoundPacket for C4EAttachEntity {
+        const PACKET_ID: i32 = 0x4E;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_i32(self.attached_entity_id);
+            encoder.write_i32(self.holding_entity_id);
+        }
+    }
+
     /// Velocity is believed to be in units of 1/8000 of a block per server tick (50ms);
     /// for example, -1343 would move (-1343 / 8000) = −0.167875 blocks per tick (or −3,3575 blocks per second).
     /// 
@@ -1612,24 +2132,13 @@ mod play {
         }
     }
 
-    #[derive(Clone, Copy, Debug)]
-    #[repr(u8)]
-    pub enum C47EntityEquipmentSlot {
-        MainHand = 0,
-        OffHand = 1,
-        Feet = 2,
-        Legs = 3,
-        Chest = 4,
-        Head = 5,
-    }
-
     /// Change one or more slots of an entity's equipment
     ///
     /// <https://wiki.vg/Protocol#Set_Equipment>
     #[derive(Clone, Debug)]
     pub struct C50EntityEquipment {
         pub entity_id: VarInt,
-        pub equipment: Vec<(C47EntityEquipmentSlot, Slot)>,
+        pub equipment: Vec<(EquipmentSlot, Slot)>,
     }
     impl ClientBoundPacket for C50EntityEquipment {
         const PACKET_ID: i32 = 0x50;
@@ -1638,7 +2147,7 @@ mod play {
             encoder.write_varint(self.entity_id);
             for (i, (slot_pos, slot)) in self.equipment.iter().enumerate() {
                 encoder.write_u8(
-                    *slot_pos as u8
+                    slot_pos.id()
                         | if i == self.equipment.len() - 1 {
                             0
                         } else {
@@ -1650,10 +2159,181 @@ mod play {
         }
     }
 
+    /// Sent by the server when the client should change experience levels.
+    ///
+    /// <https://wiki.vg/Protocol#Set_Experience>
+    #[derive(Clone, Debug)]
+    pub struct C51SetExperience {
+        /// Between 0 and 1
+        pub experience_bar: f32,
+        pub level: VarInt,
+        /// See <https://minecraft.fandom.com/wiki/Experience#Leveling_up> for the levels
+        pub total_experience: VarInt,
+    }
+    impl ClientBoundPacket for C51SetExperience {
+        const PACKET_ID: i32 = 0x51;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_f32(self.experience_bar);
+            encoder.write_varint(self.level);
+            encoder.write_varint(self.total_experience);
+        }
+    }
+
+    /// Sent by the server to update/set the health of the player it is sent to.
+    /// A health of 0 or less displays the death screen.
+    ///
+    /// <https://wiki.vg/Protocol#Set_Health>
+    #[derive(Clone, Debug)]
+    pub struct C52SetHealth {
+        /// 0 or less = dead, 20 = full HP.
+        pub health: f32,
+        /// 0-20
+        pub food: VarInt,
+        /// Seems to vary from 0.0 to 5.0 in integer increments.
+        pub food_saturation: f32,
+    }
+    impl ClientBoundPacket for C52SetHealth {
+        const PACKET_ID: i32 = 0x52;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_f32(self.health);
+            encoder.write_varint(self.food);
+            encoder.write_f32(self.food_saturation);
+        }
+    }
+
+    /// How the scores of an objective are shown
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum C53ObjectiveType {
+        Integer = 0,
+        Hearts = 1,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum C53UpdateObjectivesMode {
+        Create {
+            display_name: serde_json::Value,
+            kind: C53ObjectiveType,
+        },
+        Remove,
+        Update {
+            display_name: serde_json::Value,
+            kind: C53ObjectiveType,
+        },
+    }
+
+    /// Creates, removes or updates a scoreboard objective.
+    ///
+    /// <https://wiki.vg/Protocol#Update_Objectives>
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct C53UpdateObjectives {
+        /// Unique name of the objective, at most 16 characters
+        pub objective_name: String,
+        pub mode: C53UpdateObjectivesMode,
+    }
+    impl ClientBoundPacket for C53UpdateObjectives {
+        const PACKET_ID: i32 = 0x53;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_string(&self.objective_name);
+            match &self.mode {
+                C53UpdateObjectivesMode::Create { display_name, kind } => {
+                    encoder.write_i8(0);
+                    encoder.write_string(&display_name.to_string());
+                    encoder.write_varint(*kind as i32);
+                }
+                C53UpdateObjectivesMode::Remove => encoder.write_i8(1),
+                C53UpdateObjectivesMode::Update { display_name, kind } => {
+                    encoder.write_i8(2);
+                    encoder.write_string(&display_name.to_string());
+                    encoder.write_varint(*kind as i32);
+                }
+            }
+        }
+    }
+
+    /// Sets the entities riding the given vehicle, replacing the previous passengers.
+    /// An empty list dismounts every passenger.
+    ///
+    /// <https://wiki.vg/Protocol#Set_Passengers>
+    #[derive(Clone, Debug)]
+    pub struct C54SetPassengers {
+        /// Vehicle's EID
+        pub entity_id: VarInt,
+        /// EIDs of entity's passengers
+        pub passengers: Vec<VarInt>,
+    }
+    impl ClientBoundPacket for C54SetPassengers {
+        const PACKET_ID: i32 = 0x54;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_varint(self.entity_id);
+            encoder.write_varint(self.passengers.len() as VarInt);
+            for passenger in &self.passengers {
+                encoder.write_varint(*passenger);
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum C56UpdateScoreAction {
+        CreateOrUpdate {
+            value: VarInt,
+        },
+        Remove,
+    }
+
+    /// Sets or removes the score of an entity in an objective.
+    ///
+    /// <https://wiki.vg/Protocol#Update_Score>
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct C56UpdateScore {
+        /// Username of the player or uuid of the entity owning the score, at most 40 characters
+        pub entity_name: String,
+        pub objective_name: String,
+        pub action: C56UpdateScoreAction,
+    }
+    impl ClientBoundPacket for C56UpdateScore {
+        const PACKET_ID: i32 = 0x56;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_string(&self.entity_name);
+            match &self.action {
+                C56UpdateScoreAction::CreateOrUpdate { value } => {
+                    encoder.write_varint(0);
+                    encoder.write_string(&self.objective_name);
+                    encoder.write_varint(*value);
+                }
+                C56UpdateScoreAction::Remove => {
+                    encoder.write_varint(1);
+                    encoder.write_string(&self.objective_name);
+                }
+            }
+        }
+    }
+
+    /// Sets the subtitle shown along with the next title.
+    ///
+    /// <https://wiki.vg/Protocol#Set_Subtitle_Text>
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct C58SetSubtitleText {
+        pub subtitle: serde_json::Value,
+    }
+    impl ClientBoundPacket for C58SetSubtitleText {
+        const PACKET_ID: i32 = 0x58;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_string(&self.subtitle.to_string());
+        }
+    }
+
     /// Time is based on ticks, where 20 ticks happen every second.
     /// There are 24000 ticks in a day, making Minecraft days exactly 20 minutes long.
     /// The time of day is based on the timestamp modulo 24000. 0 is sunrise, 6000 is noon, 12000 is sunset, and 18000 is midnight.
     /// The default SMP server increments the time by 20 every second.
+    ///
+    /// <https://wiki.vg/Protocol#Update_Time>
     #[derive(Clone, Debug)]
     pub struct C59UpdateTime {
         /// In ticks; not changed by server commands.
@@ -1661,6 +2341,16 @@ mod play {
         /// The world (or region) time, in ticks. If negative the sun will stop moving at the Math.abs of the time.
         pub time_of_day: i64,
     }
+    impl C59UpdateTime {
+        /// Creates the packet with the sun stopped at the given time of day
+        pub fn frozen(world_age: i64, time_of_day: i64) -> Self {
+            Self {
+                world_age,
+                // -0 would let the sun move again, so -1 is used like the notchian server
+                time_of_day: if time_of_day == 0 { -1 } else { -time_of_day.abs() },
+            }
+        }
+    }
     impl ClientBoundPacket for C59UpdateTime {
         const PACKET_ID: i32 = 0x59;
 
@@ -1670,6 +2360,60 @@ mod play {
         }
     }
 
+    /// Shows a title in the middle of the screen, along with the last subtitle sent.
+    ///
+    /// <https://wiki.vg/Protocol#Set_Title_Text>
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct C5ASetTitleText {
+        pub title: serde_json::Value,
+    }
+    impl ClientBoundPacket for C5ASetTitleText {
+        const PACKET_ID: i32 = 0x5A;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_string(&self.title.to_string());
+        }
+    }
+
+    /// Sets the durations of the title animations, in ticks.
+    ///
+    /// <https://wiki.vg/Protocol#Set_Title_Animation_Times>
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct C5BSetTitleAnimationTimes {
+        pub fade_in: i32,
+        pub stay: i32,
+        pub fade_out: i32,
+    }
+    impl ClientBoundPacket for C5BSetTitleAnimationTimes {
+        const PACKET_ID: i32 = 0x5B;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_i32(self.fade_in);
+            encoder.write_i32(self.stay);
+            encoder.write_i32(self.fade_out);
+        }
+    }
+
+    /// Used for messages not sent by a player (server announcements, command feedbacks...),
+    /// unlike C30PlayerChatMessage it isn't signed.
+    ///
+    /// <https://wiki.vg/Protocol#System_Chat_Message>
+    #[derive(Clone, Debug)]
+    pub struct C5FSystemChatMessage {
+        pub content: serde_json::Value,
+        /// Whether the message is displayed above the hotbar instead of in the chat box
+        pub overlay: bool,
+    }
+    impl ClientBoundPacket for C5FSystemChatMessage {
+        const PACKET_ID: i32 = 0x5F;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_string(&self.content.to_string());
+            // 1: system message (chat box), 2: game info (above hotbar)
+            encoder.write_varint(if self.overlay { 2 } else { 1 });
+        }
+    }
+
     /// This packet may be used by custom servers to display additional information above/below the player list.
     /// It is never sent by the Notchian server.
     ///
@@ -1690,6 +2434,28 @@ mod play {
         }
     }
 
+    /// Sent by the server when someone picks up an item lying on the ground, its sole purpose
+    /// appears to be the animation of the item flying towards you.
+    /// It doesn't destroy the entity in the client memory, and it doesn't add it to your inventory.
+    ///
+    /// <https://wiki.vg/Protocol#Pickup_Item>
+    #[derive(Clone, Debug)]
+    pub struct C62PickupItem {
+        pub collected_entity_id: VarInt,
+        pub collector_entity_id: VarInt,
+        /// Seems to be 1 for experience orbs, otherwise the number of items in the stack
+        pub pickup_item_count: VarInt,
+    }
+    impl ClientBoundPacket for C62PickupItem {
+        const PACKET_ID: i32 = 0x62;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_varint(self.collected_entity_id);
+            encoder.write_varint(self.collector_entity_id);
+            encoder.write_varint(self.pickup_item_count);
+        }
+    }
+
     /// This packet is sent by the server when an entity moves more than 8 blocks.
     ///
     /// <https://wiki.vg/Protocol#Teleport_Entity>
@@ -1722,5 +2488,460 @@ mod play {
             encoder.write_bool(self.on_ground);
         }
     }
+
+    /// <https://wiki.vg/Protocol#Update_Attributes>
+    #[derive(Clone, Debug)]
+    pub struct C65AttributeModifier {
+        pub uuid: Uuid,
+        pub amount: f64,
+        /// 0: add amount, 1: add amount * base, 2: multiply by (1 + amount)
+        pub operation: u8,
+    }
+
+    /// <https://wiki.vg/Protocol#Update_Attributes>
+    #[derive(Clone, Debug)]
+    pub struct C65AttributeProperty {
+        /// e.g. minecraft:generic.movement_speed
+        pub key: Identifier,
+        pub value: f64,
+        pub modifiers: Vec<C65AttributeModifier>,
+    }
+
+    /// Sets attributes on the given entity, unknown attributes will cause a game crash.
+    ///
+    /// <https://wiki.vg/Protocol#Update_Attributes>
+    #[derive(Clone, Debug)]
+    pub struct C65UpdateAttributes {
+        pub entity_id: VarInt,
+        pub properties: Vec<C65AttributeProperty>,
+    }
+    impl ClientBoundPacket for C65UpdateAttributes {
+        const PACKET_ID: i32 = 0x65;
+
+        fn encode<D: BufMut>(&self, encoder: &mut PacketEncoder<D>) {
+            encoder.write_varint(self.entity_id);
+            encoder.write_varint(self.properties.len() as VarInt);
+            for property in &self.properties {
+                encoder.write_string(&property.key);
+                encoder.write_f64(property.value);
+                encoder.write_varint(property.modifiers.len() as VarInt);
+                for modifier in &property.modifiers {
+                    encoder.write_uuid(&modifier.uuid);
+                    encoder.write_f64(modifier.amount);
+                    encoder.write_u8(modifier.operation);
+                }
+            }
+        }
+    }
 }
 pub use play::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_types::{Gamemode, Position};
+    use std::collections::HashMap;
+
+    /// Lists the name and id of each given packet
+    macro_rules! packet_ids {
+        ($($packet:ty),* $(,)?) => {
+            vec![$((
+                std::any::type_name::<$packet>().rsplit("::").next().unwrap(),
+                <$packet as ClientBoundPacket>::PACKET_ID,
+            )),*]
+        };
+    }
+
+    /// Checks that the hex prefix of each packet matches its id and that no id is used twice
+    fn check_packet_ids(state: &str, packets: Vec<(&str, i32)>) {
+        let mut seen = HashMap::new();
+        for (name, id) in packets {
+            let prefix = name.get(1..3).and_then(|hex| i32::from_str_radix(hex, 16).ok());
+            assert_eq!(prefix, Some(id), "{} packet {} has id {:#04X}", state, name, id);
+            if let Some(other) = seen.insert(id, name) {
+                panic!("{} packets {} and {} share the id {:#04X}", state, other, name, id);
+            }
+        }
+    }
+
+    #[test]
+    fn packet_ids_are_consistent() {
+        check_packet_ids("status", packet_ids![C00StatusResponse, C01Pong]);
+        check_packet_ids(
+            "login",
+            packet_ids![
+                C00LoginDisconnect,
+                C01EncryptionRequest,
+                C02LoginSuccess,
+                C03SetCompression,
+                C04LoginPluginRequest,
+            ],
+        );
+        check_packet_ids(
+            "play",
+            packet_ids![
+                C00SpawnEntity,
+                C01SpawnExperienceOrb,
+                C02SpawnPlayer,
+                C03EntityAnimation,
+                C05AcknowledgeBlockChange,
+                C06SetBlockDestroyStage,
+                C09BlockChange,
+                C0ABossBar,
+                C0CChatPreview,
+                C0DClearTitles,
+                C0ECommandSuggestionsResponse,
+                C0FCommands,
+                C11SetContainerContent,
+                C13SetContainerSlot,
+                C15PluginMessage,
+                C17Disconnect,
+                C1AUnloadChunk,
+                C1BGameEvent,
+                C1DInitializeWorldBorder,
+                C1EKeepAlive,
+                C1FChunkDataAndUpdateLight,
+                C21Particle,
+                C23Login,
+                C26UpdateEntityPosition,
+                C27UpdateEntityPositionAndRotation,
+                C28UpdateEntityRotation,
+                C2COpenSignEditor,
+                C2FPlayerAbilities,
+                C30PlayerChatMessage,
+                C34PlayerInfo,
+                C36SynchronizePlayerPosition,
+                C38RemoveEntities,
+                C3CSetHeadRotation,
+                C3DUpdateSectionBlocks,
+                C40SetActionBarText,
+                C41SetBorderCenter,
+                C42SetBorderLerpSize,
+                C43SetBorderSize,
+                C44SetBorderWarningDelay,
+                C45SetBorderWarningDistance,
+                C47SetHeldItem,
+                C48SetCenterChunk,
+                C49SetRenderDistance,
+                C4ASetDefaultSpawnPosition,
+                C4CDisplayObjective,
+                C4DSetEntityMetadata,
+                C4EAttachEntity,
+                C4FSetEntityVelocity,
+                C50EntityEquipment,
+                C51SetExperience,
+                C52SetHealth,
+                C53UpdateObjectives,
+                C54SetPassengers,
+                C56UpdateScore,
+                C58SetSubtitleText,
+                C59UpdateTime,
+                C5ASetTitleText,
+                C5BSetTitleAnimationTimes,
+                C5FSystemChatMessage,
+                C60SetTabListHeaderAndFooter,
+                C62PickupItem,
+                C63TeleportEntity,
+                C65UpdateAttributes,
+            ],
+        );
+    }
+
+    #[test]
+    fn entity_animation_ids() {
+        let animations = [
+            (EntityAnimation::SwingMainArm, 0),
+            (EntityAnimation::TakeDamage, 1),
+            (EntityAnimation::LeaveBed, 2),
+            (EntityAnimation::SwingOffHand, 3),
+            (EntityAnimation::Critical, 4),
+            (EntityAnimation::MagicCritical, 5),
+        ];
+        for (animation, id) in animations {
+            assert_eq!(animation.id(), id);
+        }
+        let raw = C03EntityAnimation::new(42, EntityAnimation::SwingOffHand).to_rawpacket();
+        assert_eq!(&*raw.data, &[42, 3]);
+    }
+
+    #[test]
+    fn update_time_frozen() {
+        let packet = C59UpdateTime::frozen(42, 18000);
+        assert_eq!(packet.world_age, 42);
+        assert_eq!(packet.time_of_day, -18000);
+
+        assert_eq!(C59UpdateTime::frozen(0, -6000).time_of_day, -6000);
+        assert_eq!(C59UpdateTime::frozen(0, 0).time_of_day, -1);
+
+        let raw = packet.to_rawpacket();
+        assert_eq!(raw.packet_id, 0x59);
+        assert_eq!(&raw.data[8..16], &(-18000i64).to_be_bytes());
+    }
+
+    #[test]
+    fn initialize_world_border_encoding() {
+        let raw = C1DInitializeWorldBorder {
+            x: 10.5,
+            z: -3.,
+            old_diameter: 100.,
+            new_diameter: 50.,
+            speed: 300,
+            portal_teleport_boundary: 29999984,
+            warning_blocks: 5,
+            warning_time: 15,
+        }
+        .to_rawpacket();
+
+        let mut expected = PacketEncoder::default();
+        for value in [10.5, -3., 100., 50.] {
+            expected.write_f64(value);
+        }
+        expected.write_varlong(300);
+        expected.write_varint(29999984);
+        expected.write_varint(5);
+        expected.write_varint(15);
+        assert_eq!(raw.packet_id, 0x1D);
+        assert_eq!(&*raw.data, &*expected.into_inner());
+    }
+
+    #[test]
+    fn border_size_encoding() {
+        let raw = C43SetBorderSize { diameter: 64. }.to_rawpacket();
+        assert_eq!(raw.packet_id, 0x43);
+        assert_eq!(&*raw.data, &64f64.to_be_bytes());
+
+        let raw = C42SetBorderLerpSize { old_diameter: 64., new_diameter: 32., speed: 1000 }.to_rawpacket();
+        assert_eq!(&raw.data[0..16], &[64f64.to_be_bytes(), 32f64.to_be_bytes()].concat()[..]);
+        // 1000 is encoded as a two bytes VarLong
+        assert_eq!(&raw.data[16..], &[0xE8, 0x07]);
+    }
+
+    #[test]
+    fn boss_bar_encoding() {
+        let uuid = uuid::Uuid::from_u128(7);
+        let title = serde_json::json!({ "text": "Dragon" });
+        let raw = C0ABossBar {
+            uuid,
+            action: C0ABossBarAction::Add {
+                title: title.clone(),
+                health: 0.5,
+                color: C0ABossBarColor::Purple,
+                division: C0ABossBarDivision::TenNotches,
+                flags: 0x2,
+            },
+        }
+        .to_rawpacket();
+
+        let mut expected = PacketEncoder::default();
+        expected.write_uuid(&uuid);
+        expected.write_varint(0);
+        expected.write_string(&title.to_string());
+        expected.write_f32(0.5);
+        expected.write_varint(5);
+        expected.write_varint(2);
+        expected.write_u8(0x2);
+        assert_eq!(raw.packet_id, 0x0A);
+        assert_eq!(&*raw.data, &*expected.into_inner());
+
+        let raw = C0ABossBar { uuid, action: C0ABossBarAction::Remove }.to_rawpacket();
+        assert_eq!(&raw.data[16..], &[1]);
+    }
+
+    #[test]
+    fn update_score_encoding() {
+        let raw = C56UpdateScore {
+            entity_name: "Line".to_string(),
+            objective_name: "sidebar".to_string(),
+            action: C56UpdateScoreAction::CreateOrUpdate { value: 3 },
+        }
+        .to_rawpacket();
+        let mut expected = PacketEncoder::default();
+        expected.write_string("Line");
+        expected.write_varint(0);
+        expected.write_string("sidebar");
+        expected.write_varint(3);
+        assert_eq!(raw.packet_id, 0x56);
+        assert_eq!(&*raw.data, &*expected.into_inner());
+
+        let raw = C56UpdateScore {
+            entity_name: "Line".to_string(),
+            objective_name: "sidebar".to_string(),
+            action: C56UpdateScoreAction::Remove,
+        }
+        .to_rawpacket();
+        let mut expected = PacketEncoder::default();
+        expected.write_string("Line");
+        expected.write_varint(1);
+        expected.write_string("sidebar");
+        assert_eq!(&*raw.data, &*expected.into_inner());
+    }
+
+    #[test]
+    fn open_sign_editor_encoding() {
+        let location = Position { x: -12, y: 70, z: 5 };
+        let raw = C2COpenSignEditor { location }.to_rawpacket();
+        assert_eq!(raw.packet_id, 0x2C);
+        assert_eq!(&*raw.data, &location.encode().to_be_bytes());
+    }
+
+    #[test]
+    fn system_chat_message_encoding() {
+        let content = serde_json::json!({ "text": "Server restarting", "color": "red" });
+        let raw = C5FSystemChatMessage {
+            content: content.clone(),
+            overlay: true,
+        }
+        .to_rawpacket();
+
+        let mut expected = PacketEncoder::default();
+        expected.write_string(&content.to_string());
+        expected.write_varint(2);
+        assert_eq!(raw.packet_id, 0x5F);
+        assert_eq!(&*raw.data, &*expected.into_inner());
+    }
+
+    #[test]
+    fn game_event_constructors() {
+        let packet = C1BGameEvent::change_gamemode(Gamemode::Creative);
+        assert_eq!(packet.event, 3);
+        assert_eq!(packet.value, 1.);
+
+        let packet = C1BGameEvent::win_game(true);
+        assert_eq!((packet.event, packet.value), (4, 1.));
+        let packet = C1BGameEvent::begin_raining();
+        assert_eq!((packet.event, packet.value), (2, 0.));
+        let packet = C1BGameEvent::end_raining();
+        assert_eq!((packet.event, packet.value), (1, 0.));
+        let packet = C1BGameEvent::thunder_level(0.5);
+        assert_eq!((packet.event, packet.value), (8, 0.5));
+    }
+
+    #[test]
+    fn update_attributes_encoding() {
+        let uuid = uuid::Uuid::from_u128(0x662A6B8D_DA3E_4C1C_8813_96EA6097278D);
+        let raw = C65UpdateAttributes {
+            entity_id: 12,
+            properties: vec![C65AttributeProperty {
+                key: "generic.movement_speed".into(),
+                value: 0.1,
+                modifiers: vec![C65AttributeModifier {
+                    uuid,
+                    amount: 0.3,
+                    operation: 2,
+                }],
+            }],
+        }
+        .to_rawpacket();
+
+        let mut expected = PacketEncoder::default();
+        expected.write_varint(12);
+        expected.write_varint(1);
+        expected.write_string("minecraft:generic.movement_speed");
+        expected.write_f64(0.1);
+        expected.write_varint(1);
+        expected.write_bytes(&0x662A6B8D_DA3E_4C1C_8813_96EA6097278Du128.to_be_bytes());
+        expected.write_f64(0.3);
+        expected.write_u8(2);
+        assert_eq!(raw.packet_id, 0x65);
+        assert_eq!(&*raw.data, &*expected.into_inner());
+    }
+
+    #[test]
+    fn set_passengers_encoding() {
+        let raw = C54SetPassengers {
+            entity_id: 3,
+            passengers: vec![7, 300],
+        }
+        .to_rawpacket();
+        assert_eq!(raw.packet_id, 0x54);
+        assert_eq!(&*raw.data, &[3, 2, 7, 0xAC, 0x02]);
+
+        // Dismount
+        let raw = C54SetPassengers {
+            entity_id: 3,
+            passengers: vec![],
+        }
+        .to_rawpacket();
+        assert_eq!(&*raw.data, &[3, 0]);
+    }
+
+    #[test]
+    fn attach_entity_encoding() {
+        let raw = C4EAttachEntity {
+            attached_entity_id: 5,
+            holding_entity_id: -1,
+        }
+        .to_rawpacket();
+        assert_eq!(raw.packet_id, 0x4E);
+        assert_eq!(&*raw.data, &[0, 0, 0, 5, 0xFF, 0xFF, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn particle_encoding() {
+        // 28 is minecraft:flame
+        let flame = C21Particle {
+            particle: Particle { id: 28, data: 0 },
+            long_distance: false,
+            x: 0.5,
+            y: 64.,
+            z: -0.5,
+            offset_x: 0.2,
+            offset_y: 0.5,
+            offset_z: 0.2,
+            max_speed: 0.01,
+            count: 20,
+        };
+        let raw = flame.to_rawpacket();
+
+        let mut expected = PacketEncoder::default();
+        expected.write_varint(28);
+        expected.write_bool(false);
+        expected.write_f64(0.5);
+        expected.write_f64(64.);
+        expected.write_f64(-0.5);
+        expected.write_f32(0.2);
+        expected.write_f32(0.5);
+        expected.write_f32(0.2);
+        expected.write_f32(0.01);
+        expected.write_i32(20);
+        assert_eq!(raw.packet_id, 0x21);
+        assert_eq!(&*raw.data, &*expected.into_inner());
+
+        // Block particles are followed by their block state
+        let block = C21Particle {
+            particle: Particle { id: 2, data: 1 },
+            ..flame
+        };
+        let raw = block.to_rawpacket();
+        assert_eq!(raw.data.len(), 1 + 1 + 3 * 8 + 4 * 4 + 4 + 1);
+        assert_eq!(raw.data.last(), Some(&1));
+    }
+
+    #[test]
+    fn section_blocks_from_world_coordinates() {
+        let mut packet = C3DUpdateSectionBlocks::for_block(-1, 70, 33);
+        assert_eq!((packet.section_x, packet.section_y, packet.section_z), (-1, 4, 2));
+        packet.add_block(-1, 70, 33, 1);
+        packet.add_block(-16, 64, 47, 2);
+        assert_eq!(packet.blocks, vec![
+            C3DBlockChange { x: 15, y: 6, z: 1, block_id: 1 },
+            C3DBlockChange { x: 0, y: 0, z: 15, block_id: 2 },
+        ]);
+
+        let raw = packet.to_rawpacket();
+        let mut expected = PacketEncoder::default();
+        expected.write_u64((0x3FFFFF << 42) | (2 << 20) | 4);
+        expected.write_bool(false);
+        expected.write_varint(2);
+        expected.write_varlong(1 << 12 | 15 << 8 | 1 << 4 | 6);
+        expected.write_varlong(2 << 12 | 15 << 4);
+        assert_eq!(&*raw.data, &*expected.into_inner());
+    }
+
+    #[test]
+    #[should_panic]
+    fn section_blocks_reject_other_sections() {
+        let mut packet = C3DUpdateSectionBlocks::for_block(0, 0, 0);
+        packet.add_block(16, 0, 0, 1);
+    }
+}
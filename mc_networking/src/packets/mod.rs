@@ -6,7 +6,15 @@ use crate::{data_types::encoder::varint, DecodingError, DecodingResult};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use flate2::{write::ZlibEncoder, Compression};
 
-use std::{fmt::Debug, io::Write, ops::Deref};
+use std::{
+    fmt::Debug,
+    io::{Read, Write},
+    ops::Deref,
+};
+
+/// Maximum size of the packets accepted by [RawPacket::decode], above the 2 MiB
+/// of the biggest packets sent by vanilla clients
+pub const DEFAULT_MAX_PACKET_SIZE: usize = 2 * 1024 * 1024 + 1024;
 
 #[derive(Debug, Clone, Copy)]
 pub struct PacketCompression(i32);
@@ -99,12 +107,30 @@ impl RawPacket<Bytes> {
         })
     }
 
+    /// Decodes a packet of at most [DEFAULT_MAX_PACKET_SIZE] bytes,
+    /// see [RawPacket::decode_with_max_size]
     pub fn decode(
         bytes: &mut BytesMut,
         compression: PacketCompression,
+    ) -> DecodingResult<RawPacket<Bytes>> {
+        Self::decode_with_max_size(bytes, compression, DEFAULT_MAX_PACKET_SIZE)
+    }
+
+    /// Decodes a packet, failing without allocating anything if its announced length
+    /// is above `max_size`
+    pub fn decode_with_max_size(
+        bytes: &mut BytesMut,
+        compression: PacketCompression,
+        max_size: usize,
     ) -> DecodingResult<RawPacket<Bytes>> {
         let mut taker = bytes.take(varint::MAX_BYTE_SIZE);
         let packet_length = varint::decode_buf(&mut taker)?;
+        if packet_length < 0 || packet_length as usize > max_size {
+            return Err(DecodingError::parse_error(
+                "raw packet",
+                format!("packet length {} is not between 0 and {}", packet_length, max_size),
+            ));
+        }
         taker.get_mut().reserve(packet_length as usize);
         taker.set_limit(packet_length as usize);
         if taker.remaining() < packet_length as usize {
@@ -117,10 +143,22 @@ impl RawPacket<Bytes> {
                 let content_length = taker.remaining();
                 Self::decode_content(taker.into_inner(), content_length)
             } else {
+                if content_length < 0 || content_length as usize > max_size {
+                    return Err(DecodingError::parse_error(
+                        "raw packet",
+                        format!("content length {} is not between 0 and {}", content_length, max_size),
+                    ));
+                }
                 let mut uncompressed = BytesMut::with_capacity(content_length as usize);
-                let mut decoder = flate2::write::ZlibDecoder::new((&mut uncompressed).writer());
-                std::io::copy(&mut (&mut taker).reader(), &mut decoder)?;
-                decoder.finish()?;
+                {
+                    // Decompression stops one byte after the declared length to detect bigger contents
+                    let mut decoder = flate2::read::ZlibDecoder::new((&mut taker).reader())
+                        .take(content_length as u64 + 1);
+                    std::io::copy(&mut decoder, &mut (&mut uncompressed).writer())?;
+                }
+                // Skips what the decoder didn't need to read
+                let remaining = taker.remaining();
+                taker.advance(remaining);
                 if uncompressed.len() != content_length as usize {
                     return Err(DecodingError::ParseError {
                         data_type: "raw packet".to_string(),
@@ -145,3 +183,74 @@ impl Debug for RawPacket {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frames a compressed packet declaring the given content length
+    fn compressed_packet(content_length: i32, content: &[u8]) -> BytesMut {
+        let mut compressor = ZlibEncoder::new(vec![], Compression::fast());
+        compressor.write_all(content).unwrap();
+        let compressed = compressor.finish().unwrap();
+
+        let mut packet = BytesMut::new();
+        varint::encode_into(content_length, &mut packet);
+        packet.extend_from_slice(&compressed);
+        let mut bytes = BytesMut::new();
+        varint::encode_into(packet.len() as i32, &mut bytes);
+        bytes.extend_from_slice(&packet);
+        bytes
+    }
+
+    #[test]
+    fn decompression_bomb_is_rejected() {
+        let compression = PacketCompression::new(64);
+        // Barely anything to decompress but a huge declared length
+        let mut bytes = compressed_packet(i32::MAX, &[1; 100]);
+        assert!(matches!(
+            RawPacket::decode(&mut bytes, compression),
+            Err(DecodingError::ParseError { .. })
+        ));
+
+        // Contents decompressing to more than declared
+        let mut bytes = compressed_packet(100, &[1; 1_000_000]);
+        assert!(matches!(
+            RawPacket::decode(&mut bytes, compression),
+            Err(DecodingError::ParseError { .. })
+        ));
+
+        let mut content = vec![0x05];
+        content.extend_from_slice(&[1; 100]);
+        let mut bytes = compressed_packet(101, &content);
+        let packet = RawPacket::decode(&mut bytes, compression).unwrap();
+        assert_eq!(packet.packet_id, 0x05);
+        assert_eq!(packet.data.len(), 100);
+    }
+
+    #[test]
+    fn oversized_packet_is_rejected() {
+        let mut bytes = BytesMut::new();
+        varint::encode_into(i32::MAX, &mut bytes);
+        bytes.extend_from_slice(&[0; 16]);
+        assert!(matches!(
+            RawPacket::decode(&mut bytes, PacketCompression::default()),
+            Err(DecodingError::ParseError { .. })
+        ));
+        // Nothing was reserved for the announced length
+        assert!(bytes.capacity() < 1024);
+
+        let mut bytes = BytesMut::new();
+        varint::encode_into(300, &mut bytes);
+        bytes.extend_from_slice(&[0; 300]);
+        assert!(matches!(
+            RawPacket::decode_with_max_size(&mut bytes, PacketCompression::default(), 256),
+            Err(DecodingError::ParseError { .. })
+        ));
+
+        let mut bytes = BytesMut::new();
+        RawPacket::new(0x01, Bytes::from_static(&[1, 2])).encode(PacketCompression::default(), &mut bytes);
+        let packet = RawPacket::decode_with_max_size(&mut bytes, PacketCompression::default(), 3).unwrap();
+        assert_eq!(packet.data, Bytes::from_static(&[1, 2]));
+    }
+}
@@ -1,12 +1,22 @@
 use crate::{
-    data_types::encoder::PacketDecoder, packets::RawPacket, DecodingError, DecodingResult,
+    client::ClientState, data_types::encoder::PacketDecoder, packets::RawPacket, DecodingError,
+    DecodingResult,
 };
 
 pub trait ServerBoundPacket: Sized {
     const PACKET_ID: i32;
+    /// State in which the packet is sent, ids are only unique within a state
+    const STATE: ClientState;
     fn run_decoder(decoder: &mut PacketDecoder) -> DecodingResult<Self>;
 
-    fn decode(raw_packet: RawPacket) -> DecodingResult<Self> {
+    /// Decodes the packet received in the given state, failing if it can't be sent in it
+    fn decode(raw_packet: RawPacket, state: &ClientState) -> DecodingResult<Self> {
+        if *state != Self::STATE {
+            return Err(DecodingError::parse_error(
+                &format!("packet 0x{:x}", Self::PACKET_ID),
+                &format!("can only be sent in the {:?} state, not {:?}", Self::STATE, state),
+            ));
+        }
         if raw_packet.packet_id != Self::PACKET_ID {
             return Err(DecodingError::parse_error(
                 &format!("packet 0x{:x}", Self::PACKET_ID),
@@ -31,6 +41,7 @@ pub trait ServerBoundPacket: Sized {
 mod handshake {
     use super::ServerBoundPacket;
     use crate::{
+        client::ClientState,
         data_types::{encoder::PacketDecoder, VarInt},
         DecodingResult,
     };
@@ -55,6 +66,7 @@ mod handshake {
     }
     impl ServerBoundPacket for S00Handshake {
         const PACKET_ID: i32 = 0x00;
+        const STATE: ClientState = ClientState::Handshaking;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> DecodingResult<Self> {
             Ok(Self {
@@ -70,7 +82,9 @@ pub use handshake::*;
 
 mod status {
     use super::ServerBoundPacket;
-    use crate::{data_types::encoder::PacketDecoder, DecodingResult as Result};
+    use crate::{
+        client::ClientState, data_types::encoder::PacketDecoder, DecodingResult as Result,
+    };
 
     /// Initiate SLP and should be responded with C00Response
     ///
@@ -79,6 +93,7 @@ mod status {
     pub struct S00Request;
     impl ServerBoundPacket for S00Request {
         const PACKET_ID: i32 = 0x00;
+        const STATE: ClientState = ClientState::Status;
 
         fn run_decoder(_decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(S00Request)
@@ -96,6 +111,7 @@ mod status {
     }
     impl ServerBoundPacket for S01Ping {
         const PACKET_ID: i32 = 0x01;
+        const STATE: ClientState = ClientState::Status;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(Self {
@@ -109,6 +125,7 @@ pub use status::*;
 mod login {
     use super::ServerBoundPacket;
     use crate::{
+        client::ClientState,
         data_types::{encoder::PacketDecoder, VarInt},
         DecodingResult as Result
     };
@@ -135,6 +152,7 @@ mod login {
     }
     impl ServerBoundPacket for S00LoginStart {
         const PACKET_ID: i32 = 0x00;
+        const STATE: ClientState = ClientState::Login;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             let name = decoder.read_string()?;
@@ -174,6 +192,7 @@ mod login {
     }
     impl ServerBoundPacket for S01EncryptionResponse {
         const PACKET_ID: i32 = 0x01;
+        const STATE: ClientState = ClientState::Login;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             let shared_secret = decoder.read_varint().and_then(|l|
@@ -219,6 +238,7 @@ mod login {
     }
     impl ServerBoundPacket for S02LoginPluginResponse {
         const PACKET_ID: i32 = 0x02;
+        const STATE: ClientState = ClientState::Login;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             let message_id = decoder.read_varint()?;
@@ -240,12 +260,15 @@ pub use login::*;
 mod play {
     use super::ServerBoundPacket;
     use crate::{
-        data_types::{encoder::PacketDecoder, Position, Slot, VarInt, Identifier},
+        client::ClientState,
+        data_types::{encoder::PacketDecoder, Hand, Position, Slot, VarInt, Identifier},
+        packets::RawPacket,
         DecodingError as Error, DecodingResult as Result,
     };
 
     use num_traits::FromPrimitive;
     use num_derive::{ ToPrimitive, FromPrimitive };
+    use std::convert::TryFrom;
 
     /// Sent by client as confirmation of C37SynchronizePlayerPosition
     ///
@@ -257,6 +280,7 @@ mod play {
     }
     impl ServerBoundPacket for S00ConfirmTeleportation {
         const PACKET_ID: i32 = 0x00;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(Self {
@@ -265,6 +289,40 @@ mod play {
         }
     }
 
+    /// Sent when the player runs a command, without the leading '/'
+    ///
+    /// <https://wiki.vg/Protocol#Chat_Command>
+    #[derive(Clone, Debug)]
+    pub struct S03ChatCommand {
+        pub command: String,
+        pub timestamp: i64,
+        /// The salt used to verify the signature hash.
+        pub salt: i64,
+        /// Signatures of the message arguments, as argument name and signature
+        pub argument_signatures: Vec<(String, Vec<u8>)>,
+        pub signed_preview: bool,
+    }
+    impl ServerBoundPacket for S03ChatCommand {
+        const PACKET_ID: i32 = 0x03;
+        const STATE: ClientState = ClientState::Play;
+
+        fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
+            Ok(Self {
+                command: decoder.read_string()?,
+                timestamp: decoder.read_i64()?,
+                salt: decoder.read_i64()?,
+                argument_signatures: {
+                    let count = decoder.read_varint()?;
+                    (0..count).map(|_| Ok((
+                        decoder.read_string()?,
+                        decoder.read_varint().and_then(|l| decoder.read_bytes(l as _))?,
+                    ))).collect::<Result<_>>()?
+                },
+                signed_preview: decoder.read_bool()?,
+            })
+        }
+    }
+
     /// Used to send a chat message to the server.
     /// The message may not be longer than 256 characters or else the server will kick the client.
     ///
@@ -288,6 +346,7 @@ mod play {
     }
     impl ServerBoundPacket for S04ChatMessage {
         const PACKET_ID: i32 = 0x04;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(Self {
@@ -322,6 +381,7 @@ mod play {
     }
     impl ServerBoundPacket for S06ClientCommand {
         const PACKET_ID: i32 = 0x06;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             let ai = decoder.read_varint()?;
@@ -360,6 +420,7 @@ mod play {
     }
     impl ServerBoundPacket for S07ClientInformation {
         const PACKET_ID: i32 = 0x07;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(Self {
@@ -375,6 +436,29 @@ mod play {
         }
     }
 
+    /// Sent when the client needs to tab-complete a command argument,
+    /// answered with a C0ECommandSuggestionsResponse
+    ///
+    /// <https://wiki.vg/Protocol#Command_Suggestions_Request>
+    #[derive(Clone, Debug)]
+    pub struct S08CommandSuggestionsRequest {
+        /// The id of the transaction, sent back by the server in the response
+        pub transaction_id: VarInt,
+        /// All the text behind the cursor including the '/'
+        pub text: String,
+    }
+    impl ServerBoundPacket for S08CommandSuggestionsRequest {
+        const PACKET_ID: i32 = 0x08;
+        const STATE: ClientState = ClientState::Play;
+
+        fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
+            Ok(Self {
+                transaction_id: decoder.read_varint()?,
+                text: decoder.read_string()?,
+            })
+        }
+    }
+
     /// This packet is sent by the player when it clicks on a slot in a window.
     ///
     /// <https://wiki.vg/Protocol#Click_Container>
@@ -398,6 +482,7 @@ mod play {
     }
     impl ServerBoundPacket for S0AClickContainer {
         const PACKET_ID: i32 = 0x0A;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             let window_id = decoder.read_u8()?;
@@ -435,6 +520,7 @@ mod play {
     }
     impl ServerBoundPacket for S0CPluginMessage {
         const PACKET_ID: i32 = 0x0C;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(Self {
@@ -443,6 +529,19 @@ mod play {
             })
         }
     }
+    impl S0CPluginMessage {
+        /// Decodes the client's brand if this message is on the `minecraft:brand` channel
+        pub fn brand(&self) -> Option<Result<String>> {
+            if self.channel.namespace() != "minecraft" || self.channel.name() != "brand" {
+                return None;
+            }
+            let mut decoder = PacketDecoder::new(RawPacket::new(
+                Self::PACKET_ID,
+                self.data.clone().into(),
+            ));
+            Some(decoder.read_string())
+        }
+    }
 
     #[derive(Clone, Copy, Debug)]
     pub enum S0FInteractKind {
@@ -455,8 +554,7 @@ mod play {
             target_x: f32,
             target_y: f32,
             target_z: f32,
-            /// 0: main hand, 1: off hand.
-            hand: VarInt,
+            hand: Hand,
         },
     }
     impl S0FInteractKind {
@@ -497,6 +595,7 @@ mod play {
     }
     impl ServerBoundPacket for S0FInteract {
         const PACKET_ID: i32 = 0x0F;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             let entity_id = decoder.read_varint()?;
@@ -507,7 +606,7 @@ mod play {
                     target_x: decoder.read_f32()?,
                     target_y: decoder.read_f32()?,
                     target_z: decoder.read_f32()?,
-                    hand: decoder.read_varint()?,
+                    hand: Hand::try_from(decoder.read_varint()?)?,
                 },
 
                 v => return Err(Error::parse_error(
@@ -535,6 +634,7 @@ mod play {
     }
     impl ServerBoundPacket for S11KeepAlive {
         const PACKET_ID: i32 = 0x11;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(Self {
@@ -559,6 +659,7 @@ mod play {
     }
     impl ServerBoundPacket for S13SetPlayerPosition {
         const PACKET_ID: i32 = 0x13;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(Self {
@@ -569,6 +670,12 @@ mod play {
             })
         }
     }
+    impl S13SetPlayerPosition {
+        /// False if any coordinate is NaN or infinite
+        pub fn is_finite(&self) -> bool {
+            self.x.is_finite() && self.feet_y.is_finite() && self.z.is_finite()
+        }
+    }
 
     /// A combination of S13PlayerRotation and S13PlayerPosition.
     ///
@@ -590,6 +697,7 @@ mod play {
     }
     impl ServerBoundPacket for S14SetPlayerPositionAndRotation {
         const PACKET_ID: i32 = 0x14;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(Self {
@@ -602,6 +710,13 @@ mod play {
             })
         }
     }
+    impl S14SetPlayerPositionAndRotation {
+        /// False if any coordinate or angle is NaN or infinite
+        pub fn is_finite(&self) -> bool {
+            self.x.is_finite() && self.feet_y.is_finite() && self.z.is_finite()
+                && self.yaw.is_finite() && self.pitch.is_finite()
+        }
+    }
 
     /// Updates the direction the player is looking in.
     ///
@@ -617,6 +732,7 @@ mod play {
     }
     impl ServerBoundPacket for S15SetPlayerRotation {
         const PACKET_ID: i32 = 0x15;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(Self {
@@ -626,6 +742,12 @@ mod play {
             })
         }
     }
+    impl S15SetPlayerRotation {
+        /// False if any angle is NaN or infinite
+        pub fn is_finite(&self) -> bool {
+            self.yaw.is_finite() && self.pitch.is_finite()
+        }
+    }
 
     /// This packet is used to indicate whether the player is on ground (walking/swimming), or airborne (jumping/falling).
     ///
@@ -637,6 +759,7 @@ mod play {
     }
     impl ServerBoundPacket for S16SetPlayerOnGround {
         const PACKET_ID: i32 = 0x15;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(Self {
@@ -656,6 +779,7 @@ mod play {
     }
     impl ServerBoundPacket for S1BPlayerAbilities {
         const PACKET_ID: i32 = 0x1B;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(Self {
@@ -720,6 +844,7 @@ mod play {
     }
     impl ServerBoundPacket for S1CPlayerAction {
         const PACKET_ID: i32 = 0x1C;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             let sid = decoder.read_varint()?;
@@ -782,6 +907,7 @@ mod play {
     }
     impl ServerBoundPacket for S1DPlayerCommand {
         const PACKET_ID: i32 = 0x1D;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             let entity_id = decoder.read_varint()?;
@@ -810,6 +936,7 @@ mod play {
     }
     impl ServerBoundPacket for S27SetHeldItem {
         const PACKET_ID: i32 = 0x27;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(Self {
@@ -831,6 +958,7 @@ mod play {
     }
     impl ServerBoundPacket for S2ASetCreativeModeSlot {
         const PACKET_ID: i32 = 0x2A;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(Self {
@@ -840,20 +968,63 @@ mod play {
         }
     }
 
+    /// This message is sent from the client to the server when the “Done” button is pushed after placing a sign.
+    ///
+    /// <https://wiki.vg/Protocol#Update_Sign>
+    #[derive(Clone, Debug)]
+    pub struct S2DUpdateSign {
+        /// Block Coordinates
+        pub location: Position,
+        /// The 4 lines of the sign, from top to bottom
+        pub lines: [String; 4],
+    }
+    impl S2DUpdateSign {
+        /// Maximum length of a single line, in characters
+        pub const MAX_LINE_LENGTH: usize = 384;
+    }
+    impl ServerBoundPacket for S2DUpdateSign {
+        const PACKET_ID: i32 = 0x2D;
+        const STATE: ClientState = ClientState::Play;
+
+        fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
+            let location = Position::decode(decoder.read_i64()?);
+            let mut lines: [String; 4] = Default::default();
+            for line in lines.iter_mut() {
+                *line = decoder.read_string()?;
+                let length = line.chars().count();
+                if length > Self::MAX_LINE_LENGTH {
+                    return Err(Error::parse_error(
+                        "packet 0x2D",
+                        format!(
+                            "sign line too long (expected at most {} characters, received {length})",
+                            Self::MAX_LINE_LENGTH
+                        ),
+                    ));
+                }
+            }
+
+            Ok(Self {
+                location,
+                lines,
+            })
+        }
+    }
+
     /// Sent when the player's arm swings.
     ///
     /// <https://wiki.vg/Protocol#Animation_.28serverbound.29>
     #[derive(Clone, Debug)]
     pub struct S2ESwingArm {
-        /// Hand used for the animation. 0: main hand, 1: off hand.
-        pub hand: VarInt,
+        /// Hand used for the animation
+        pub hand: Hand,
     }
     impl ServerBoundPacket for S2ESwingArm {
         const PACKET_ID: i32 = 0x2E;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(Self {
-                hand: decoder.read_varint()?,
+                hand: Hand::try_from(decoder.read_varint()?)?,
             })
         }
     }
@@ -863,8 +1034,8 @@ mod play {
     /// <https://wiki.vg/Protocol#Player_Block_Placement>
     #[derive(Clone, Debug)]
     pub struct S30UseItemOn {
-        /// The hand from which the block is placed; 0: main hand, 1: off hand
-        pub hand: VarInt,
+        /// The hand from which the block is placed
+        pub hand: Hand,
         /// Block position
         pub position: Position,
         /// The face on which the block is placed
@@ -881,10 +1052,11 @@ mod play {
     }
     impl ServerBoundPacket for S30UseItemOn {
         const PACKET_ID: i32 = 0x30;
+        const STATE: ClientState = ClientState::Play;
 
         fn run_decoder(decoder: &mut PacketDecoder) -> Result<Self> {
             Ok(Self {
-                hand: decoder.read_varint()?,
+                hand: Hand::try_from(decoder.read_varint()?)?,
                 position: Position::decode(decoder.read_i64()?),
                 face: {
                     let fid = decoder.read_i8()?;
@@ -904,3 +1076,122 @@ mod play {
     }
 }
 pub use play::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_types::{encoder::PacketEncoder, Position};
+
+    fn raw_packet<P: ServerBoundPacket>(encode: impl FnOnce(&mut PacketEncoder)) -> RawPacket {
+        let mut encoder = PacketEncoder::default();
+        encode(&mut encoder);
+        RawPacket::new(P::PACKET_ID, encoder.into_inner().freeze())
+    }
+
+    #[test]
+    fn packet_ids_are_per_state() {
+        let raw = raw_packet::<S00LoginStart>(|encoder| {
+            encoder.write_string("Notch");
+            encoder.write_bool(false);
+        });
+        let login_start = S00LoginStart::decode(raw.clone(), &ClientState::Login).unwrap();
+        assert_eq!(login_start.name, "Notch");
+        assert!(S00Request::decode(raw.clone(), &ClientState::Login).is_err());
+        assert!(S00LoginStart::decode(raw, &ClientState::Status).is_err());
+
+        let raw = RawPacket::new(0x00, bytes::Bytes::new());
+        assert!(S00Request::decode(raw.clone(), &ClientState::Status).is_ok());
+        assert!(S00LoginStart::decode(raw.clone(), &ClientState::Status).is_err());
+        assert!(S00ConfirmTeleportation::decode(raw, &ClientState::Status).is_err());
+    }
+
+    #[test]
+    fn brand_plugin_message_decoding() {
+        let raw = raw_packet::<S0CPluginMessage>(|encoder| {
+            encoder.write_string("minecraft:brand");
+            encoder.write_string("vanilla");
+        });
+        let packet = S0CPluginMessage::decode(raw, &ClientState::Play).unwrap();
+        assert_eq!(packet.brand().unwrap().unwrap(), "vanilla");
+
+        let raw = raw_packet::<S0CPluginMessage>(|encoder| {
+            encoder.write_string("mymod:brand");
+            encoder.write_string("vanilla");
+        });
+        assert!(S0CPluginMessage::decode(raw, &ClientState::Play).unwrap().brand().is_none());
+    }
+
+    #[test]
+    fn update_sign_decoding() {
+        let location = Position { x: 10, y: 64, z: -3 };
+        let raw = raw_packet::<S2DUpdateSign>(|encoder| {
+            encoder.write_u64(location.encode());
+            for line in ["Hello", "", "world", "!"] {
+                encoder.write_string(line);
+            }
+        });
+        let packet = S2DUpdateSign::decode(raw, &ClientState::Play).unwrap();
+        assert_eq!(packet.location, location);
+        assert_eq!(packet.lines, ["Hello", "", "world", "!"].map(String::from));
+    }
+
+    #[test]
+    fn chat_command_decoding() {
+        let raw = raw_packet::<S03ChatCommand>(|encoder| {
+            encoder.write_string("tp 10 64 ~5");
+            encoder.write_i64(1000);
+            encoder.write_i64(-2);
+            encoder.write_varint(1);
+            encoder.write_string("target");
+            encoder.write_varint(2);
+            encoder.write_bytes(&[1, 2]);
+            encoder.write_bool(false);
+        });
+        let packet = S03ChatCommand::decode(raw, &ClientState::Play).unwrap();
+        assert_eq!(packet.command, "tp 10 64 ~5");
+        assert_eq!(packet.salt, -2);
+        assert_eq!(packet.argument_signatures, vec![("target".to_string(), vec![1, 2])]);
+        assert!(!packet.signed_preview);
+    }
+
+    #[test]
+    fn update_sign_limits() {
+        let too_long = "a".repeat(S2DUpdateSign::MAX_LINE_LENGTH + 1);
+        let raw = raw_packet::<S2DUpdateSign>(|encoder| {
+            encoder.write_u64(0);
+            for line in ["", too_long.as_str(), "", ""] {
+                encoder.write_string(line);
+            }
+        });
+        assert!(S2DUpdateSign::decode(raw, &ClientState::Play).is_err());
+
+        // A fifth line is left unread and makes the decoding fail
+        let raw = raw_packet::<S2DUpdateSign>(|encoder| {
+            encoder.write_u64(0);
+            for _ in 0..5 {
+                encoder.write_string("line");
+            }
+        });
+        assert!(S2DUpdateSign::decode(raw, &ClientState::Play).is_err());
+
+        let raw = raw_packet::<S2DUpdateSign>(|encoder| {
+            encoder.write_u64(0);
+            for _ in 0..3 {
+                encoder.write_string("line");
+            }
+        });
+        assert!(S2DUpdateSign::decode(raw, &ClientState::Play).is_err());
+    }
+
+    #[test]
+    fn command_suggestions_request_decoding() {
+        // Id of the packet in the 1.19 protocol, 0x09 is Click Container Button
+        let mut encoder = PacketEncoder::default();
+        encoder.write_varint(7);
+        encoder.write_string("/tp ");
+        let raw = RawPacket::new(0x08, encoder.into_inner().freeze());
+        let packet = S08CommandSuggestionsRequest::decode(raw, &ClientState::Play).unwrap();
+        assert_eq!(packet.transaction_id, 7);
+        assert_eq!(packet.text, "/tp ");
+    }
+}
@@ -0,0 +1,267 @@
+use crate::{ chunk_holder::ChunkHolder, chunk_manager::ConstChunkProvider, entity::ClientComponent };
+use mc_networking::packets::{
+    client_bound::{ C1AUnloadChunk, ClientBoundPacket },
+    RawPacket
+};
+use mc_utils::{ ChunkData, ChunkGenerator };
+
+use std::sync::{ Arc, RwLock };
+
+use dashmap::DashMap;
+use rayon::{ ThreadPool, ThreadPoolBuilder };
+use bevy_ecs::entity::Entity;
+use bevy_ecs::system::{ Res, ResMut, Commands, Query };
+
+#[derive(Default)]
+struct ChunkLoadingData {
+    data: Option<(ChunkData, RawPacket)>,
+    waiters: Vec<Entity>,
+}
+
+/// Chunk provider generating chunks on a thread pool so slow generators never stall the tick
+/// Generated chunks are added to the [ChunkHolder] and sent to the players waiting for them
+/// by the [async_chunk_provider_system]
+pub struct AsyncChunkProvider<G: ChunkGenerator> {
+    generator: Arc<G>,
+    sections: usize,
+    loading_chunks: DashMap<(i32, i32), Arc<RwLock<ChunkLoadingData>>>,
+    unloading_chunks: DashMap<(i32, i32), Vec<Entity>>,
+    thread_pool: ThreadPool,
+}
+
+impl<G: ChunkGenerator + 'static> AsyncChunkProvider<G> {
+    /// Creates a provider generating chunks of `sections` sections of height
+    pub fn new(generator: G, sections: usize) -> Self {
+        Self::with_thread_pool(generator, sections, ThreadPoolBuilder::new().build().unwrap())
+    }
+
+    pub fn with_thread_pool(generator: G, sections: usize, thread_pool: ThreadPool) -> Self {
+        Self {
+            generator: Arc::new(generator),
+            sections,
+            loading_chunks: DashMap::default(),
+            unloading_chunks: DashMap::default(),
+            thread_pool,
+        }
+    }
+
+    /// Number of chunks requested by players that are still being generated
+    pub fn pending_chunk_count(&self) -> usize {
+        self.loading_chunks.iter()
+            .filter(|entry| entry.read().map(|data| data.data.is_none()).unwrap_or(true))
+            .count()
+    }
+
+    /// Sends the generated chunks and the chunk unloads to the players
+    /// Generated chunks are added to the holder with the players they are sent to as viewers,
+    /// chunks the holder already has are sent as held instead as they may have been modified
+    /// Never waits for a chunk generation, chunks that aren't ready are sent by a later call
+    pub fn flush(&self, chunk_holder: &mut ChunkHolder, clients: &Query<&ClientComponent>) {
+        let send_to = |player: Entity, packet: &RawPacket| {
+            if let Ok(client) = clients.get(player) {
+                client.0.send_raw_packet_sync(packet.clone());
+            }
+        };
+
+        self.unloading_chunks.retain(|&(chunk_x, chunk_z), players| {
+            let unload_packet = C1AUnloadChunk {
+                chunk_x,
+                chunk_z,
+            }.to_rawpacket();
+            for player in players.iter().copied() {
+                chunk_holder.remove_viewer(chunk_x, chunk_z, player);
+                send_to(player, &unload_packet);
+            }
+            false
+        });
+
+        self.loading_chunks.retain(|&(chunk_x, chunk_z), loading_data| {
+            // The generating thread only holds the lock while storing the chunk
+            let mut loading_data = match loading_data.try_write() {
+                Ok(loading_data) => loading_data,
+                Err(_) => return true,
+            };
+            // We keep chunks that aren't yet generated
+            let (chunk_data, raw_packet) = match loading_data.data.take() {
+                Some(data) => data,
+                None => return true,
+            };
+            let raw_packet = match chunk_holder.get(chunk_x, chunk_z) {
+                Some(held_chunk) => held_chunk.encode_full(chunk_x, chunk_z).to_rawpacket(),
+                None => {
+                    chunk_holder.insert(chunk_x, chunk_z, chunk_data);
+                    raw_packet
+                }
+            };
+            for player in loading_data.waiters.iter().copied() {
+                if clients.contains(player) {
+                    chunk_holder.add_viewer(chunk_x, chunk_z, player);
+                }
+                send_to(player, &raw_packet);
+            }
+            false
+        });
+    }
+}
+
+impl<G: ChunkGenerator + 'static> ConstChunkProvider for AsyncChunkProvider<G> {
+    fn const_load_chunk(
+        &self, player: Entity, _commands: &mut Commands,
+        chunk_x: i32, chunk_z: i32
+    ) {
+        if let Some(entry) = self.loading_chunks.get(&(chunk_x, chunk_z)) {
+            entry.write().unwrap().waiters.push(player);
+            return;
+        }
+
+        let final_chunk_data = Arc::new(RwLock::new(ChunkLoadingData {
+            data: None,
+            waiters: vec![player],
+        }));
+        self.loading_chunks
+            .insert((chunk_x, chunk_z), Arc::clone(&final_chunk_data));
+
+        let generator = Arc::clone(&self.generator);
+        let sections = self.sections;
+        self.thread_pool.spawn(move || {
+            let chunk_data = generator.generate(chunk_x, chunk_z, sections);
+            let packet = chunk_data.encode_full(chunk_x, chunk_z).to_rawpacket();
+
+            final_chunk_data.write().unwrap().data = Some((chunk_data, packet));
+        });
+    }
+
+    fn const_unload_chunk(
+        &self, player: Entity, _commands: &mut Commands,
+        x: i32, z: i32
+    ) {
+        if let Some(entry) = self.loading_chunks.get(&(x, z)) {
+            entry.write().unwrap().waiters.retain(|s| *s != player);
+        }
+        self.unloading_chunks.entry((x, z)).or_default().push(player);
+    }
+}
+
+/// Sends the chunks generated by the [AsyncChunkProvider] resource
+pub fn async_chunk_provider_system<G: ChunkGenerator + 'static>(
+    chunk_provider: Res<Arc<AsyncChunkProvider<G>>>,
+    mut chunk_holder: ResMut<ChunkHolder>,
+    clients: Query<&ClientComponent>,
+) {
+    chunk_provider.flush(&mut chunk_holder, &clients);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{ connected_client, read_packets };
+    use mc_networking::data_types::Position;
+    use mc_networking::packets::client_bound::C1FChunkDataAndUpdateLight;
+
+    use bevy_ecs::schedule::{ Stage, SystemStage };
+    use bevy_ecs::system::CommandQueue;
+    use bevy_ecs::world::World;
+    use std::time::{ Duration, Instant };
+
+    struct SlowGenerator;
+    impl ChunkGenerator for SlowGenerator {
+        fn generate(&self, _chunk_x: i32, _chunk_z: i32, sections: usize) -> ChunkData {
+            std::thread::sleep(Duration::from_millis(200));
+            ChunkData::new(sections)
+        }
+    }
+
+    /// Puts a block at the bottom corner of every chunk
+    struct CornerGenerator;
+    impl ChunkGenerator for CornerGenerator {
+        fn generate(&self, _chunk_x: i32, _chunk_z: i32, sections: usize) -> ChunkData {
+            let mut chunk_data = ChunkData::new(sections);
+            chunk_data.set_block(0, 0, 0, 1);
+            chunk_data
+        }
+    }
+
+    fn load_chunk<G: ChunkGenerator + 'static>(world: &mut World, player: Entity, chunk_x: i32, chunk_z: i32) {
+        let mut queue = CommandQueue::default();
+        let provider = Arc::clone(world.resource::<Arc<AsyncChunkProvider<G>>>());
+        provider.const_load_chunk(player, &mut Commands::new(&mut queue, world), chunk_x, chunk_z);
+    }
+
+    #[test]
+    fn slow_generation_does_not_block() {
+        let mut world = World::default();
+        world.init_resource::<ChunkHolder>();
+        world.insert_resource(Arc::new(AsyncChunkProvider::new(SlowGenerator, 4)));
+        let player = world.spawn().id();
+        let mut stage = SystemStage::single(async_chunk_provider_system::<SlowGenerator>);
+        let pending_chunk_count = |world: &World| {
+            world.resource::<Arc<AsyncChunkProvider<SlowGenerator>>>().pending_chunk_count()
+        };
+
+        let start = Instant::now();
+        for chunk_x in -1..=1 {
+            load_chunk::<SlowGenerator>(&mut world, player, chunk_x, 0);
+        }
+        stage.run(&mut world);
+        assert!(start.elapsed() < Duration::from_millis(50));
+        assert_eq!(pending_chunk_count(&world), 3);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while pending_chunk_count(&world) > 0 {
+            assert!(Instant::now() < deadline, "chunks were never generated");
+            let tick_start = Instant::now();
+            stage.run(&mut world);
+            assert!(tick_start.elapsed() < Duration::from_millis(50));
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        stage.run(&mut world);
+        assert!(world.resource::<Arc<AsyncChunkProvider<SlowGenerator>>>().loading_chunks.is_empty());
+        assert_eq!(world.resource::<ChunkHolder>().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn generated_chunks_are_held() {
+        let (client_a, _events_a, mut peer_a) = connected_client().await;
+        let (client_b, _events_b, mut peer_b) = connected_client().await;
+        let mut world = World::default();
+        world.init_resource::<ChunkHolder>();
+        world.insert_resource(Arc::new(AsyncChunkProvider::new(CornerGenerator, 4)));
+        let a = world.spawn().insert(client_a).id();
+        let b = world.spawn().insert(client_b).id();
+        let mut stage = SystemStage::single(async_chunk_provider_system::<CornerGenerator>);
+        let flush_all = |world: &mut World, stage: &mut SystemStage| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            loop {
+                stage.run(world);
+                if world.resource::<Arc<AsyncChunkProvider<CornerGenerator>>>().loading_chunks.is_empty() {
+                    break;
+                }
+                assert!(Instant::now() < deadline, "chunks were never generated");
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        };
+
+        let corner = Position { x: 32, y: 0, z: -48 };
+        load_chunk::<CornerGenerator>(&mut world, a, 2, -3);
+        flush_all(&mut world, &mut stage);
+        let holder = world.resource::<ChunkHolder>();
+        assert_eq!(holder.get_block(corner), Some(1));
+        assert_eq!(holder.viewers(2, -3).collect::<Vec<_>>(), [a]);
+
+        // The chunk is modified once held, later players receive the modified chunk
+        world.resource_mut::<ChunkHolder>().set_block(corner, 2);
+        load_chunk::<CornerGenerator>(&mut world, b, 2, -3);
+        flush_all(&mut world, &mut stage);
+        let holder = world.resource::<ChunkHolder>();
+        assert_eq!(holder.get_block(corner), Some(2));
+        let mut viewers = holder.viewers(2, -3).collect::<Vec<_>>();
+        viewers.sort();
+        assert_eq!(viewers, [a, b]);
+
+        let packet_a = read_packets(&mut peer_a, 1).await.remove(0);
+        let packet_b = read_packets(&mut peer_b, 1).await.remove(0);
+        assert_eq!(packet_a.packet_id, C1FChunkDataAndUpdateLight::PACKET_ID);
+        assert_eq!(packet_b.data, holder.get(2, -3).unwrap().encode_full(2, -3).to_rawpacket().data);
+        assert_ne!(packet_a.data, packet_b.data);
+    }
+}
@@ -0,0 +1,217 @@
+use crate::entity::ClientComponent;
+use mc_networking::packets::client_bound::{
+    C0ABossBar, C0ABossBarAction, C0ABossBarColor, C0ABossBarDivision, C0ABossBarFlags,
+};
+
+use ahash::{ AHashMap, AHashSet };
+use bevy_ecs::entity::Entity;
+use bevy_ecs::system::{ Query, ResMut };
+use uuid::Uuid;
+
+/// A boss bar and the players seeing it
+#[derive(Debug, Clone)]
+pub struct BossBar {
+    title: serde_json::Value,
+    health: f32,
+    color: C0ABossBarColor,
+    division: C0ABossBarDivision,
+    flags: C0ABossBarFlags,
+    viewers: AHashSet<Entity>,
+}
+
+impl BossBar {
+    pub fn title(&self) -> &serde_json::Value {
+        &self.title
+    }
+
+    /// From 0 to 1
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+
+    pub fn viewers(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.viewers.iter().copied()
+    }
+
+    fn add_action(&self) -> C0ABossBarAction {
+        C0ABossBarAction::Add {
+            title: self.title.clone(),
+            health: self.health,
+            color: self.color,
+            division: self.division,
+            flags: self.flags,
+        }
+    }
+}
+
+/// Resource holding the boss bars, every change is only sent to the players seeing the bar
+/// The packets are sent by the [boss_bar_system]
+#[derive(Default)]
+pub struct BossBarManager {
+    bars: AHashMap<Uuid, BossBar>,
+    /// Packets waiting to be sent to their player
+    pending: Vec<(Entity, C0ABossBar)>,
+}
+
+impl BossBarManager {
+    /// Creates a full bar without viewers, returns its id
+    pub fn create(
+        &mut self, title: impl Into<serde_json::Value>,
+        color: C0ABossBarColor, division: C0ABossBarDivision,
+    ) -> Uuid {
+        let uuid = Uuid::new_v4();
+        self.bars.insert(uuid, BossBar {
+            title: title.into(),
+            health: 1.,
+            color,
+            division,
+            flags: 0,
+            viewers: AHashSet::default(),
+        });
+        uuid
+    }
+
+    /// Removes the bar from the screen of its viewers
+    pub fn remove(&mut self, bar: Uuid) -> Option<BossBar> {
+        let removed = self.bars.remove(&bar)?;
+        for viewer in &removed.viewers {
+            self.pending.push((*viewer, C0ABossBar { uuid: bar, action: C0ABossBarAction::Remove }));
+        }
+        Some(removed)
+    }
+
+    pub fn get(&self, bar: Uuid) -> Option<&BossBar> {
+        self.bars.get(&bar)
+    }
+
+    /// Shows the bar to the player, returns false if the bar doesn't exist or is already shown
+    pub fn add_viewer(&mut self, bar: Uuid, player: Entity) -> bool {
+        let boss_bar = match self.bars.get_mut(&bar) {
+            Some(boss_bar) if boss_bar.viewers.insert(player) => boss_bar,
+            _ => return false,
+        };
+        self.pending.push((player, C0ABossBar { uuid: bar, action: boss_bar.add_action() }));
+        true
+    }
+
+    /// Hides the bar from the player, returns false if the player wasn't seeing it
+    pub fn remove_viewer(&mut self, bar: Uuid, player: Entity) -> bool {
+        if !self.bars.get_mut(&bar).map_or(false, |boss_bar| boss_bar.viewers.remove(&player)) {
+            return false;
+        }
+        self.pending.push((player, C0ABossBar { uuid: bar, action: C0ABossBarAction::Remove }));
+        true
+    }
+
+    /// Returns false if the bar doesn't exist
+    pub fn update_health(&mut self, bar: Uuid, health: f32) -> bool {
+        let health = health.clamp(0., 1.);
+        self.update(bar, |boss_bar| {
+            boss_bar.health = health;
+            C0ABossBarAction::UpdateHealth { health }
+        })
+    }
+
+    /// Returns false if the bar doesn't exist
+    pub fn update_title(&mut self, bar: Uuid, title: impl Into<serde_json::Value>) -> bool {
+        let title = title.into();
+        self.update(bar, |boss_bar| {
+            boss_bar.title = title.clone();
+            C0ABossBarAction::UpdateTitle { title }
+        })
+    }
+
+    /// Returns false if the bar doesn't exist
+    pub fn update_style(&mut self, bar: Uuid, color: C0ABossBarColor, division: C0ABossBarDivision) -> bool {
+        self.update(bar, |boss_bar| {
+            boss_bar.color = color;
+            boss_bar.division = division;
+            C0ABossBarAction::UpdateStyle { color, division }
+        })
+    }
+
+    /// Returns false if the bar doesn't exist
+    pub fn update_flags(&mut self, bar: Uuid, flags: C0ABossBarFlags) -> bool {
+        self.update(bar, |boss_bar| {
+            boss_bar.flags = flags;
+            C0ABossBarAction::UpdateFlags { flags }
+        })
+    }
+
+    /// Applies a change to the bar and sends the resulting action to its viewers
+    fn update(&mut self, bar: Uuid, change: impl FnOnce(&mut BossBar) -> C0ABossBarAction) -> bool {
+        let boss_bar = match self.bars.get_mut(&bar) {
+            Some(boss_bar) => boss_bar,
+            None => return false,
+        };
+        let action = change(boss_bar);
+        for viewer in &boss_bar.viewers {
+            self.pending.push((*viewer, C0ABossBar { uuid: bar, action: action.clone() }));
+        }
+        true
+    }
+}
+
+/// Removes the disconnected players from the viewers and sends the pending boss bar packets
+pub fn boss_bar_system(
+    mut manager: ResMut<BossBarManager>,
+    clients: Query<&ClientComponent>,
+) {
+    for boss_bar in manager.bars.values_mut() {
+        boss_bar.viewers.retain(|viewer| clients.contains(*viewer));
+    }
+    for (viewer, packet) in manager.pending.drain(..) {
+        if let Ok(client) = clients.get(viewer) {
+            client.0.send_packet_sync(&packet);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{ connected_client, read_packets };
+    use mc_networking::packets::client_bound::ClientBoundPacket;
+
+    use bevy_ecs::schedule::{ Stage, SystemStage };
+    use bevy_ecs::world::World;
+
+    #[tokio::test]
+    async fn viewer_gets_add_update_and_remove() {
+        let (client, _events, mut peer) = connected_client().await;
+
+        let mut world = World::default();
+        let player = world.spawn().insert(client).id();
+        let mut manager = BossBarManager::default();
+        let bar = manager.create("Boss", C0ABossBarColor::Red, C0ABossBarDivision::NoDivision);
+        // Not seen by anyone yet
+        assert!(manager.update_health(bar, 0.8));
+
+        assert!(manager.add_viewer(bar, player));
+        assert!(!manager.add_viewer(bar, player));
+        assert!(manager.update_health(bar, 0.25));
+        assert!(manager.remove_viewer(bar, player));
+        assert!(!manager.remove_viewer(bar, player));
+        // Not seen by anyone anymore
+        assert!(manager.update_title(bar, "Hidden"));
+        world.insert_resource(manager);
+        SystemStage::single(boss_bar_system).run(&mut world);
+
+        let expected = [
+            C0ABossBarAction::Add {
+                title: "Boss".into(),
+                health: 0.8,
+                color: C0ABossBarColor::Red,
+                division: C0ABossBarDivision::NoDivision,
+                flags: 0,
+            },
+            C0ABossBarAction::UpdateHealth { health: 0.25 },
+            C0ABossBarAction::Remove,
+        ];
+        let packets = read_packets(&mut peer, expected.len()).await;
+        for (packet, action) in packets.iter().zip(expected) {
+            assert_eq!(packet.packet_id, C0ABossBar::PACKET_ID);
+            assert_eq!(packet.data, C0ABossBar { uuid: bar, action }.to_rawpacket().data);
+        }
+    }
+}
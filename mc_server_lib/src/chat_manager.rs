@@ -0,0 +1,782 @@
+use mc_networking::data_types::{
+    command_data::{ ArgumentNode, LiteralNode, Node, RootNode },
+    encoder::PacketEncoder,
+    Position,
+};
+use mc_networking::packets::client_bound::{
+    C0ECommandSuggestionMatch, C0ECommandSuggestionsResponse, C0FCommands,
+};
+use mc_utils::Location;
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::system::Commands;
+use serde_json::json;
+
+/// Function called with the sender and the parsed arguments when a command is run
+pub type CommandHandler = Box<dyn Fn(Entity, &[ArgumentValue], &mut Commands) + Send + Sync>;
+
+/// Type of a command argument, sent to the client in the command graph
+/// See <https://wiki.vg/Command_Data#Parsers>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArgumentKind {
+    /// All the remaining text of the command
+    GreedyString,
+    Integer { min: Option<i32>, max: Option<i32> },
+    Float { min: Option<f32>, max: Option<f32> },
+    EntitySelector { single: bool, players_only: bool },
+    /// Three coordinates, each may be relative to the sender with '~'
+    BlockPos,
+    /// Three coordinates, either all local to the sender's look direction with '^'
+    /// or each absolute or relative to the sender with '~'
+    Vec3,
+}
+
+impl ArgumentKind {
+    pub fn parser(&self) -> &'static str {
+        match self {
+            Self::GreedyString => "brigadier:string",
+            Self::Integer { .. } => "brigadier:integer",
+            Self::Float { .. } => "brigadier:float",
+            Self::EntitySelector { .. } => "minecraft:entity",
+            Self::BlockPos => "minecraft:block_pos",
+            Self::Vec3 => "minecraft:vec3",
+        }
+    }
+
+    pub fn properties(&self) -> Bytes {
+        let mut encoder = PacketEncoder::default();
+        match *self {
+            // GREEDY_PHRASE
+            Self::GreedyString => encoder.write_varint(2),
+            Self::Integer { min, max } => {
+                encoder.write_u8(min.is_some() as u8 | (0x02 * max.is_some() as u8));
+                min.into_iter().chain(max).for_each(|bound| encoder.write_i32(bound));
+            }
+            Self::Float { min, max } => {
+                encoder.write_u8(min.is_some() as u8 | (0x02 * max.is_some() as u8));
+                min.into_iter().chain(max).for_each(|bound| encoder.write_f32(bound));
+            }
+            Self::EntitySelector { single, players_only } => {
+                encoder.write_u8(single as u8 | (0x02 * players_only as u8));
+            }
+            Self::BlockPos | Self::Vec3 => (),
+        }
+        encoder.into_inner().freeze()
+    }
+
+    fn expected(&self) -> &'static str {
+        match self {
+            Self::GreedyString => "Expected string",
+            Self::Integer { .. } => "Expected integer",
+            Self::Float { .. } => "Expected float",
+            Self::EntitySelector { .. } => "Expected entity",
+            Self::BlockPos => "Expected block position",
+            Self::Vec3 => "Expected coordinates",
+        }
+    }
+
+    /// Values suggested to the client when the argument is missing
+    fn suggestions(&self) -> &'static [&'static str] {
+        match self {
+            Self::EntitySelector { players_only: false, .. } => &["@a", "@e", "@p", "@r", "@s"],
+            Self::EntitySelector { players_only: true, .. } => &["@a", "@p", "@r", "@s"],
+            Self::BlockPos => &["~ ~ ~"],
+            Self::Vec3 => &["~ ~ ~", "^ ^ ^"],
+            _ => &[],
+        }
+    }
+
+    fn parse(&self, reader: &mut ArgumentReader) -> Result<ArgumentValue, ParseError> {
+        match *self {
+            Self::GreedyString => {
+                let (_, text) = reader.rest(self)?;
+                Ok(ArgumentValue::String(text.to_string()))
+            }
+            Self::Integer { min, max } => {
+                let (start, word) = reader.word(self)?;
+                let error = |message| ParseError::new(start, start + word.len(), message);
+                let value = word.parse::<i32>()
+                    .map_err(|_| error(format!("Invalid integer '{word}'")))?;
+                if let Some(min) = min.filter(|min| value < *min) {
+                    return Err(error(format!("Integer must not be less than {min}, found {value}")));
+                }
+                if let Some(max) = max.filter(|max| value > *max) {
+                    return Err(error(format!("Integer must not be more than {max}, found {value}")));
+                }
+                Ok(ArgumentValue::Integer(value))
+            }
+            Self::Float { min, max } => {
+                let (start, word) = reader.word(self)?;
+                let error = |message| ParseError::new(start, start + word.len(), message);
+                let value = word.parse::<f32>().ok()
+                    .filter(|value| value.is_finite())
+                    .ok_or_else(|| error(format!("Invalid float '{word}'")))?;
+                if let Some(min) = min.filter(|min| value < *min) {
+                    return Err(error(format!("Float must not be less than {min}, found {value}")));
+                }
+                if let Some(max) = max.filter(|max| value > *max) {
+                    return Err(error(format!("Float must not be more than {max}, found {value}")));
+                }
+                Ok(ArgumentValue::Float(value))
+            }
+            Self::EntitySelector { single, players_only } => {
+                let (start, word) = reader.word(self)?;
+                let error = |message: &str| ParseError::new(start, start + word.len(), message);
+                let selector = EntitySelector::parse(word).map_err(error)?;
+                if single && !selector.is_single() {
+                    return Err(error("Only one entity is allowed, but the provided selector allows more than one"));
+                }
+                if players_only && selector == EntitySelector::AllEntities {
+                    return Err(error("Only players may be affected by this command, but the provided selector includes entities"));
+                }
+                Ok(ArgumentValue::Entity(selector))
+            }
+            Self::BlockPos => {
+                let mut coordinate = || {
+                    let (start, word) = reader.word(self)?;
+                    Coordinate::parse(word)
+                        .map_err(|message| ParseError::new(start, start + word.len(), message))
+                };
+                Ok(ArgumentValue::BlockPos(BlockPosArgument {
+                    x: coordinate()?,
+                    y: coordinate()?,
+                    z: coordinate()?,
+                }))
+            }
+            Self::Vec3 => {
+                let to_error = |start: usize, word: &str| {
+                    let end = start + word.len();
+                    move |message: &str| ParseError::new(start, end, message)
+                };
+                let (start, word) = reader.word(self)?;
+                if word.starts_with('^') {
+                    let local = |(start, word): (usize, &str)| word.strip_prefix('^')
+                        .ok_or("Cannot mix world & local coordinates")
+                        .and_then(parse_offset)
+                        .map_err(to_error(start, word));
+                    return Ok(ArgumentValue::Vec3(CoordinatesArgument::Local {
+                        left: local((start, word))?,
+                        up: local(reader.word(self)?)?,
+                        forward: local(reader.word(self)?)?,
+                    }));
+                }
+                // Like vanilla, integer horizontal coordinates target the center of the block
+                let world = |(start, word): (usize, &str), center: bool| Coordinate::parse_vec3(word, center)
+                    .map_err(to_error(start, word));
+                Ok(ArgumentValue::Vec3(CoordinatesArgument::World {
+                    x: world((start, word), true)?,
+                    y: world(reader.word(self)?, false)?,
+                    z: world(reader.word(self)?, true)?,
+                }))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Coordinate {
+    pub value: f64,
+    /// Whether the value is an offset from the sender's position ('~')
+    pub relative: bool,
+}
+
+impl Coordinate {
+    fn parse(text: &str) -> Result<Self, &'static str> {
+        if text.starts_with('^') {
+            return Err("Local coordinates are not supported");
+        }
+        match text.strip_prefix('~') {
+            Some(offset) => parse_offset(offset).map(|value| Self { value, relative: true }),
+            None => text.parse::<i32>()
+                .map(|value| Self { value: value as f64, relative: false })
+                .map_err(|_| "Expected integer coordinate"),
+        }
+    }
+
+    /// Parses a coordinate of a location, `center` adds half a block to integer absolute values
+    fn parse_vec3(text: &str, center: bool) -> Result<Self, &'static str> {
+        if text.starts_with('^') {
+            return Err("Cannot mix world & local coordinates");
+        }
+        match text.strip_prefix('~') {
+            Some(offset) => parse_offset(offset).map(|value| Self { value, relative: true }),
+            None => text.parse::<f64>().ok()
+                .filter(|value| value.is_finite())
+                .map(|value| Self {
+                    value: if center && !text.contains('.') { value + 0.5 } else { value },
+                    relative: false,
+                })
+                .ok_or("Expected coordinate"),
+        }
+    }
+
+    pub fn resolve(&self, origin: f64) -> f64 {
+        if self.relative {
+            origin + self.value
+        }
+        else {
+            self.value
+        }
+    }
+}
+
+/// Parses the offset following a '~' or '^', which defaults to 0
+fn parse_offset(text: &str) -> Result<f64, &'static str> {
+    if text.is_empty() {
+        return Ok(0.);
+    }
+    text.parse::<f64>().ok()
+        .filter(|offset| offset.is_finite())
+        .ok_or("Invalid relative coordinate")
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CoordinatesArgument {
+    World { x: Coordinate, y: Coordinate, z: Coordinate },
+    /// Offsets along the left, up and forward axes of the sender's look direction
+    Local { left: f64, up: f64, forward: f64 },
+}
+
+impl CoordinatesArgument {
+    /// Target location, keeping the rotation of the origin
+    pub fn resolve(&self, origin: &Location) -> Location {
+        match *self {
+            Self::World { x, y, z } => Location {
+                x: x.resolve(origin.x),
+                y: y.resolve(origin.y),
+                z: z.resolve(origin.z),
+                ..*origin
+            },
+            Self::Local { left, up, forward } => {
+                let forward_axis = origin.direction();
+                let up_axis = Location { pitch: origin.pitch - 90., ..*origin }.direction();
+                let left_axis = (
+                    up_axis.1 * forward_axis.2 - up_axis.2 * forward_axis.1,
+                    up_axis.2 * forward_axis.0 - up_axis.0 * forward_axis.2,
+                    up_axis.0 * forward_axis.1 - up_axis.1 * forward_axis.0,
+                );
+                Location {
+                    x: origin.x + left_axis.0 * left + up_axis.0 * up + forward_axis.0 * forward,
+                    y: origin.y + left_axis.1 * left + up_axis.1 * up + forward_axis.1 * forward,
+                    z: origin.z + left_axis.2 * left + up_axis.2 * up + forward_axis.2 * forward,
+                    ..*origin
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockPosArgument {
+    pub x: Coordinate,
+    pub y: Coordinate,
+    pub z: Coordinate,
+}
+
+impl BlockPosArgument {
+    /// Position of the block, relative coordinates being resolved from the given location
+    pub fn resolve(&self, origin: &Location) -> Position {
+        Position {
+            x: self.x.resolve(origin.x).floor() as i32,
+            y: self.y.resolve(origin.y).floor() as i32,
+            z: self.z.resolve(origin.z).floor() as i32,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EntitySelector {
+    Player(String),
+    /// @p
+    NearestPlayer,
+    /// @r
+    RandomPlayer,
+    /// @a
+    AllPlayers,
+    /// @e
+    AllEntities,
+    /// @s
+    Executor,
+}
+
+impl EntitySelector {
+    fn parse(text: &str) -> Result<Self, &'static str> {
+        match text {
+            "@p" => Ok(Self::NearestPlayer),
+            "@r" => Ok(Self::RandomPlayer),
+            "@a" => Ok(Self::AllPlayers),
+            "@e" => Ok(Self::AllEntities),
+            "@s" => Ok(Self::Executor),
+            _ if text.starts_with('@') && text.contains('[') => Err("Selector arguments are not supported"),
+            _ if text.starts_with('@') => Err("Unknown selector type"),
+            _ if text.len() <= 16 && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+                Ok(Self::Player(text.to_string())),
+            _ => Err("Invalid player name"),
+        }
+    }
+
+    pub fn is_single(&self) -> bool {
+        !matches!(self, Self::AllPlayers | Self::AllEntities)
+    }
+}
+
+/// A parsed command argument
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArgumentValue {
+    String(String),
+    Integer(i32),
+    Float(f32),
+    Entity(EntitySelector),
+    BlockPos(BlockPosArgument),
+    Vec3(CoordinatesArgument),
+}
+
+/// Error of a command parsing, the start and end are byte offsets in the parsed text
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(start: usize, end: usize, message: impl Into<String>) -> Self {
+        Self { start, end, message: message.into() }
+    }
+
+    /// Red chat component to send as feedback to the command sender
+    pub fn to_chat(&self) -> serde_json::Value {
+        json!({ "text": self.message, "color": "red" })
+    }
+}
+
+struct ArgumentReader<'a> {
+    input: &'a str,
+    cursor: usize,
+}
+
+impl<'a> ArgumentReader<'a> {
+    /// Reads the next space separated word
+    fn word(&mut self, kind: &ArgumentKind) -> Result<(usize, &'a str), ParseError> {
+        let start = self.argument_start(kind)?;
+        let end = self.input[start..].find(' ').map(|i| start + i).unwrap_or(self.input.len());
+        self.cursor = end;
+        Ok((start, &self.input[start..end]))
+    }
+
+    fn rest(&mut self, kind: &ArgumentKind) -> Result<(usize, &'a str), ParseError> {
+        let start = self.argument_start(kind)?;
+        self.cursor = self.input.len();
+        Ok((start, &self.input[start..]))
+    }
+
+    /// Skips the space separating arguments
+    fn argument_start(&self, kind: &ArgumentKind) -> Result<usize, ParseError> {
+        let len = self.input.len();
+        match self.input[self.cursor..].strip_prefix(' ') {
+            Some(rest) if !rest.is_empty() && !rest.starts_with(' ') => Ok(self.cursor + 1),
+            _ => Err(ParseError::new(len, len, kind.expected())),
+        }
+    }
+}
+
+/// Permission level of the server operators, the level of most vanilla cheat commands
+pub const OP_PERMISSION_LEVEL: u8 = 2;
+
+pub struct RegisteredCommand {
+    name: String,
+    arguments: Vec<(String, ArgumentKind)>,
+    permission_level: u8,
+    handler: CommandHandler,
+}
+
+impl RegisteredCommand {
+    /// Sets the minimum permission level a player needs to run the command
+    pub fn with_permission_level(&mut self, permission_level: u8) -> &mut Self {
+        self.permission_level = permission_level;
+        self
+    }
+
+    pub fn permission_level(&self) -> u8 {
+        self.permission_level
+    }
+}
+
+/// Resource holding the commands of the server
+/// Commands are parsed following the argument kinds they are registered with,
+/// which are also the ones declared to the client by [ChatManager::commands_packet]
+#[derive(Default)]
+pub struct ChatManager {
+    commands: Vec<RegisteredCommand>,
+}
+
+impl ChatManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a command, the handler is called with the sender and one value per argument
+    /// The command can be run by everyone unless a permission level is set on the returned command
+    pub fn register_command(
+        &mut self, name: &str, arguments: &[(&str, ArgumentKind)],
+        handler: impl Fn(Entity, &[ArgumentValue], &mut Commands) + Send + Sync + 'static,
+    ) -> &mut RegisteredCommand {
+        self.commands.retain(|command| command.name != name);
+        self.commands.push(RegisteredCommand {
+            name: name.to_string(),
+            arguments: arguments.iter()
+                .map(|(name, kind)| (name.to_string(), *kind))
+                .collect(),
+            permission_level: 0,
+            handler: Box::new(handler),
+        });
+        self.commands.last_mut().unwrap()
+    }
+
+    /// Parses the arguments of a command regardless of its permission level,
+    /// the leading '/' is optional
+    pub fn parse(&self, input: &str) -> Result<Vec<ArgumentValue>, ParseError> {
+        self.parse_command(input, u8::MAX).map(|(_, values)| values)
+    }
+
+    fn parse_command(
+        &self, input: &str, permission_level: u8,
+    ) -> Result<(&RegisteredCommand, Vec<ArgumentValue>), ParseError> {
+        let name_start = if input.starts_with('/') { 1 } else { 0 };
+        let name_end = input[name_start..].find(' ').map(|i| name_start + i).unwrap_or(input.len());
+        let name = &input[name_start..name_end];
+        let command = self.commands.iter()
+            .find(|command| command.name == name)
+            .ok_or_else(|| ParseError::new(name_start, name_end, format!("Unknown command '{name}'")))?;
+        if command.permission_level > permission_level {
+            return Err(ParseError::new(
+                name_start, name_end, "You don't have permission to use this command",
+            ));
+        }
+
+        let mut reader = ArgumentReader { input, cursor: name_end };
+        let values = command.arguments.iter()
+            .map(|(_, kind)| kind.parse(&mut reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        if reader.cursor < input.len() {
+            return Err(ParseError::new(
+                reader.cursor, input.len(), "Incorrect argument for command",
+            ));
+        }
+        Ok((command, values))
+    }
+
+    /// Parses and runs a command sent by a player with the given permission level
+    pub fn handle_command(
+        &self, sender: Entity, permission_level: u8, input: &str, commands: &mut Commands,
+    ) -> Result<(), ParseError> {
+        let (command, values) = self.parse_command(input, permission_level)?;
+        (command.handler)(sender, &values, commands);
+        Ok(())
+    }
+
+    /// Answer to a suggestions request, the text must include the leading '/'
+    /// Parse errors are highlighted by suggesting the faulty text itself, with the error as tooltip
+    pub fn suggestions(
+        &self, transaction_id: i32, permission_level: u8, text: &str,
+    ) -> C0ECommandSuggestionsResponse {
+        let error = match self.parse_command(text, permission_level) {
+            Ok(_) => return C0ECommandSuggestionsResponse {
+                id: transaction_id,
+                start: utf16_offset(text, text.len()),
+                length: 0,
+                matches: vec![],
+            },
+            Err(error) => error,
+        };
+
+        let faulty_text = &text[error.start..error.end];
+        let mut matches = vec![];
+        if error.start == 1 && !text.contains(' ') {
+            // The command name is still being typed
+            matches.extend(self.allowed_commands(permission_level)
+                .filter(|command| command.name.starts_with(faulty_text))
+                .map(|command| C0ECommandSuggestionMatch {
+                    value: command.name.clone(),
+                    tooltip: None,
+                }));
+        }
+        else if error.start == error.end {
+            if let Some(kind) = self.missing_argument_kind(text, permission_level) {
+                matches.extend(kind.suggestions().iter().map(|value| C0ECommandSuggestionMatch {
+                    value: value.to_string(),
+                    tooltip: None,
+                }));
+            }
+        }
+        if matches.is_empty() {
+            matches.push(C0ECommandSuggestionMatch {
+                value: faulty_text.to_string(),
+                tooltip: Some(error.to_chat()),
+            });
+        }
+
+        C0ECommandSuggestionsResponse {
+            id: transaction_id,
+            start: utf16_offset(text, error.start),
+            length: utf16_offset(text, error.end) - utf16_offset(text, error.start),
+            matches,
+        }
+    }
+
+    /// Kind of the first argument missing from the text
+    fn missing_argument_kind(&self, text: &str, permission_level: u8) -> Option<ArgumentKind> {
+        let mut words = text.trim_start_matches('/').split(' ');
+        let name = words.next()?;
+        let command = self.allowed_commands(permission_level).find(|command| command.name == name)?;
+        let mut word_count = words.filter(|word| !word.is_empty()).count();
+        command.arguments.iter()
+            .map(|(_, kind)| *kind)
+            .find(|kind| {
+                let words = match kind {
+                    ArgumentKind::BlockPos | ArgumentKind::Vec3 => 3,
+                    _ => 1,
+                };
+                if word_count < words {
+                    return true;
+                }
+                word_count -= words;
+                false
+            })
+    }
+
+    fn allowed_commands(&self, permission_level: u8) -> impl Iterator<Item = &RegisteredCommand> {
+        self.commands.iter().filter(move |command| command.permission_level <= permission_level)
+    }
+
+    /// Command graph of the commands a player with the given permission level can run
+    pub fn commands_packet(&self, permission_level: u8) -> C0FCommands {
+        let children_nodes = self.allowed_commands(permission_level)
+            .map(|command| {
+                let mut next: Option<Arc<dyn Node>> = None;
+                for (name, kind) in command.arguments.iter().rev() {
+                    next = Some(Arc::new(ArgumentNode {
+                        is_executable: next.is_none(),
+                        children_nodes: next.into_iter().collect(),
+                        redirect_node: None,
+                        name: name.clone(),
+                        parser: kind.parser().into(),
+                        properties: kind.properties(),
+                        suggestions_type: None,
+                    }));
+                }
+                Arc::new(LiteralNode {
+                    is_executable: next.is_none(),
+                    children_nodes: next.into_iter().collect(),
+                    redirect_node: None,
+                    name: command.name.clone(),
+                }) as Arc<dyn Node>
+            })
+            .collect();
+
+        C0FCommands {
+            root_node: Arc::new(RootNode {
+                is_executable: false,
+                children_nodes,
+                redirect_node: None,
+            }),
+        }
+    }
+}
+
+/// Offset of the byte in the text as counted by the client, in UTF-16 code units
+fn utf16_offset(text: &str, byte_offset: usize) -> i32 {
+    text[..byte_offset].encode_utf16().count() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs::system::CommandQueue;
+    use bevy_ecs::world::World;
+    use std::sync::Mutex;
+
+    fn tp_manager() -> ChatManager {
+        let mut manager = ChatManager::new();
+        manager.register_command("tp", &[("location", ArgumentKind::BlockPos)], |_, _, _| ());
+        manager.register_command("give", &[
+            ("target", ArgumentKind::EntitySelector { single: false, players_only: true }),
+            ("count", ArgumentKind::Integer { min: Some(1), max: Some(64) }),
+        ], |_, _, _| ());
+        manager.register_command("say", &[("message", ArgumentKind::GreedyString)], |_, _, _| ());
+        manager
+    }
+
+    #[test]
+    fn tp_block_pos_with_relative_coordinates() {
+        let manager = tp_manager();
+        let values = manager.parse("/tp 10 64 ~5").unwrap();
+        let location = match values.as_slice() {
+            [ArgumentValue::BlockPos(location)] => *location,
+            _ => panic!("expected a block position, got {values:?}"),
+        };
+        assert_eq!(location.x, Coordinate { value: 10., relative: false });
+        assert_eq!(location.y, Coordinate { value: 64., relative: false });
+        assert_eq!(location.z, Coordinate { value: 5., relative: true });
+
+        let origin = Location { x: 3.5, y: 20., z: -8.5, yaw: 0., pitch: 0. };
+        assert_eq!(location.resolve(&origin), Position { x: 10, y: 64, z: -4 });
+
+        let values = manager.parse("tp ~ ~-1 ~").unwrap();
+        match values.as_slice() {
+            [ArgumentValue::BlockPos(location)] =>
+                assert_eq!(location.resolve(&origin), Position { x: 3, y: 19, z: -9 }),
+            _ => panic!("expected a block position, got {values:?}"),
+        }
+    }
+
+    fn tp_to(manager: &ChatManager, command: &str, origin: &Location) -> Location {
+        match manager.parse(command).unwrap().as_slice() {
+            [ArgumentValue::Vec3(target)] => target.resolve(origin),
+            values => panic!("expected coordinates, got {values:?}"),
+        }
+    }
+
+    fn assert_close(location: Location, (x, y, z): (f64, f64, f64)) {
+        let distance = ((location.x - x).powi(2) + (location.y - y).powi(2) + (location.z - z).powi(2)).sqrt();
+        assert!(distance < 1e-6, "{location:?} is not at ({x}, {y}, {z})");
+    }
+
+    #[test]
+    fn tp_relative_and_local_coordinates() {
+        let mut manager = ChatManager::new();
+        manager.register_command("tp", &[("location", ArgumentKind::Vec3)], |_, _, _| ());
+        // Looking toward +x
+        let origin = Location { x: 1.5, y: 22., z: 8.5, yaw: -90., pitch: 0. };
+
+        let target = tp_to(&manager, "/tp ~ ~5 ~", &origin);
+        assert_close(target, (1.5, 27., 8.5));
+        assert_eq!((target.yaw, target.pitch), (origin.yaw, origin.pitch));
+
+        assert_close(tp_to(&manager, "/tp ^ ^ ^5", &origin), (6.5, 22., 8.5));
+        // Left of a player looking toward +x is -z
+        assert_close(tp_to(&manager, "/tp ^2 ^1 ^", &origin), (1.5, 23., 6.5));
+
+        let looking_down = Location { pitch: 90., ..origin };
+        assert_close(tp_to(&manager, "/tp ^ ^ ^5", &looking_down), (1.5, 17., 8.5));
+
+        assert_close(tp_to(&manager, "/tp 10 64 -3.25", &origin), (10.5, 64., -3.25));
+
+        let error = manager.parse("/tp ^ ~ ^").unwrap_err();
+        assert_eq!((error.start, error.end), (6, 7));
+    }
+
+    #[test]
+    fn parse_errors_point_to_argument() {
+        let manager = tp_manager();
+        assert_eq!(manager.parse("/tp 10 64").unwrap_err().start, 9);
+        let error = manager.parse("/tp 10 6.5 0").unwrap_err();
+        assert_eq!((error.start, error.end), (7, 10));
+        let error = manager.parse("/tp 10 64 0 1").unwrap_err();
+        assert_eq!((error.start, error.end), (11, 13));
+        let error = manager.parse("/tpa 0 0 0").unwrap_err();
+        assert_eq!((error.start, error.end), (1, 4));
+
+        let error = manager.parse("/give @e 2").unwrap_err();
+        assert_eq!((error.start, error.end), (6, 8));
+        let error = manager.parse("/give Steve 65").unwrap_err();
+        assert_eq!((error.start, error.end), (12, 14));
+        assert_eq!(
+            manager.parse("/give @a 64").unwrap(),
+            vec![
+                ArgumentValue::Entity(EntitySelector::AllPlayers),
+                ArgumentValue::Integer(64),
+            ],
+        );
+        assert_eq!(
+            manager.parse("/say hello  world").unwrap(),
+            vec![ArgumentValue::String("hello  world".to_string())],
+        );
+    }
+
+    #[test]
+    fn suggestions_highlight_errors() {
+        let manager = tp_manager();
+        let response = manager.suggestions(3, 0, "/tp 10 abc 0");
+        assert_eq!(response.id, 3);
+        assert_eq!((response.start, response.length), (7, 3));
+        assert_eq!(response.matches.len(), 1);
+        assert_eq!(response.matches[0].value, "abc");
+        assert!(response.matches[0].tooltip.is_some());
+
+        let response = manager.suggestions(4, 0, "/t");
+        assert_eq!((response.start, response.length), (1, 1));
+        assert_eq!(response.matches[0].value, "tp");
+
+        let response = manager.suggestions(5, 0, "/give ");
+        assert!(response.matches.iter().any(|m| m.value == "@p"));
+        assert!(response.matches.iter().all(|m| m.value != "@e"));
+    }
+
+    #[test]
+    fn suggestions_offsets_count_utf16_units() {
+        let manager = tp_manager();
+        let response = manager.suggestions(6, 0, "/give Stève 65");
+        assert_eq!((response.start, response.length), (6, 5));
+        assert_eq!(response.matches[0].value, "Stève");
+
+        // Characters outside of the basic plane are two units long
+        let response = manager.suggestions(7, 0, "/tp 🙂 0 0");
+        assert_eq!((response.start, response.length), (4, 2));
+        assert_eq!(response.matches[0].value, "🙂");
+
+        let response = manager.suggestions(8, 0, "/say héllo");
+        assert_eq!((response.start, response.length), (10, 0));
+    }
+
+    #[test]
+    fn handler_receives_values() {
+        let received = Arc::new(Mutex::new(vec![]));
+        let mut manager = ChatManager::new();
+        manager.register_command("count", &[("n", ArgumentKind::Float { min: Some(0.), max: None })], {
+            let received = Arc::clone(&received);
+            move |_, values, _| received.lock().unwrap().extend_from_slice(values)
+        });
+
+        let mut world = World::default();
+        let sender = world.spawn().id();
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        manager.handle_command(sender, 0, "count 2.5", &mut commands).unwrap();
+        assert!(manager.handle_command(sender, 0, "count -1", &mut commands).is_err());
+        assert_eq!(*received.lock().unwrap(), vec![ArgumentValue::Float(2.5)]);
+    }
+
+    #[test]
+    fn op_commands_need_permission_level() {
+        let ran = Arc::new(Mutex::new(0));
+        let mut manager = ChatManager::new();
+        manager.register_command("adios", &[], {
+            let ran = Arc::clone(&ran);
+            move |_, _, _| *ran.lock().unwrap() += 1
+        }).with_permission_level(OP_PERMISSION_LEVEL);
+        manager.register_command("hello", &[], |_, _, _| ());
+
+        let mut world = World::default();
+        let sender = world.spawn().id();
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+
+        manager.handle_command(sender, OP_PERMISSION_LEVEL, "adios", &mut commands).unwrap();
+        assert_eq!(*ran.lock().unwrap(), 1);
+
+        let error = manager.handle_command(sender, 0, "adios", &mut commands).unwrap_err();
+        assert!(error.message.contains("permission"));
+        assert_eq!(*ran.lock().unwrap(), 1);
+
+        let names = |permission_level| manager.allowed_commands(permission_level)
+            .map(|command| command.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names(0), ["hello"]);
+        assert_eq!(names(4), ["adios", "hello"]);
+        assert!(manager.suggestions(0, 0, "/adi").matches.iter().all(|m| m.value != "adios"));
+    }
+}
@@ -0,0 +1,337 @@
+use crate::entity::{
+    ClientComponent,
+    chunk::{ ChunkLocationComponent, ChunkObserverComponent },
+};
+use mc_networking::data_types::Position;
+use mc_networking::packets::{
+    client_bound::{ C09BlockChange, C1AUnloadChunk, ClientBoundPacket },
+    RawPacket,
+};
+use mc_utils::{ BlockChangeAccumulator, BlockState, ChunkData };
+
+use ahash::{ AHashMap, AHashSet };
+use bevy_ecs::entity::Entity;
+use bevy_ecs::system::{ Query, ResMut };
+
+/// Default number of ticks a chunk is kept after the last player left its range
+pub const DEFAULT_KEEP_ALIVE_TICKS: u32 = 100;
+
+struct HeldChunk {
+    data: ChunkData,
+    /// Players that were sent this chunk
+    viewers: AHashSet<Entity>,
+    /// Number of ticks since a player was last in range of the chunk
+    ticks_out_of_range: u32,
+}
+
+/// Resource holding the server side data of the chunks loaded by players
+/// Chunks are unloaded by the [chunk_holder_unload] system once no [ChunkObserverComponent]
+/// had them in range for more than [ChunkHolder::keep_alive_ticks]
+pub struct ChunkHolder {
+    chunks: AHashMap<(i32, i32), HeldChunk>,
+    /// Block changes of the current tick, sent by the [chunk_holder_block_changes] system
+    block_changes: BlockChangeAccumulator,
+    pub keep_alive_ticks: u32,
+    /// Y coordinate of the bottom of the chunks, the min_y of the dimension type
+    pub min_y: i32,
+}
+
+impl Default for ChunkHolder {
+    fn default() -> Self {
+        Self::new(DEFAULT_KEEP_ALIVE_TICKS)
+    }
+}
+
+impl ChunkHolder {
+    pub fn new(keep_alive_ticks: u32) -> Self {
+        Self {
+            chunks: AHashMap::default(),
+            block_changes: BlockChangeAccumulator::new(),
+            keep_alive_ticks,
+            min_y: 0,
+        }
+    }
+
+    pub fn with_min_y(mut self, min_y: i32) -> Self {
+        self.min_y = min_y;
+        self
+    }
+
+    /// Adds or replaces a chunk, keeping the viewers of the replaced one
+    pub fn insert(&mut self, chunk_x: i32, chunk_z: i32, data: ChunkData) {
+        match self.chunks.get_mut(&(chunk_x, chunk_z)) {
+            Some(chunk) => chunk.data = data,
+            None => {
+                self.chunks.insert((chunk_x, chunk_z), HeldChunk {
+                    data,
+                    viewers: AHashSet::default(),
+                    ticks_out_of_range: 0,
+                });
+            }
+        }
+    }
+
+    /// Marks the chunk as sent to the player, who will receive the unload packet
+    /// Returns false if the chunk isn't held
+    pub fn add_viewer(&mut self, chunk_x: i32, chunk_z: i32, player: Entity) -> bool {
+        match self.chunks.get_mut(&(chunk_x, chunk_z)) {
+            Some(chunk) => {
+                chunk.viewers.insert(player);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stops sending the block changes of the chunk to a player that was sent its unload packet
+    /// Returns false if the player wasn't viewing the chunk
+    pub fn remove_viewer(&mut self, chunk_x: i32, chunk_z: i32, player: Entity) -> bool {
+        self.chunks.get_mut(&(chunk_x, chunk_z))
+            .map_or(false, |chunk| chunk.viewers.remove(&player))
+    }
+
+    /// Players that were sent the chunk
+    pub fn viewers(&self, chunk_x: i32, chunk_z: i32) -> impl Iterator<Item = Entity> + '_ {
+        self.chunks.get(&(chunk_x, chunk_z))
+            .into_iter()
+            .flat_map(|chunk| chunk.viewers.iter().copied())
+    }
+
+    pub fn get(&self, chunk_x: i32, chunk_z: i32) -> Option<&ChunkData> {
+        self.chunks.get(&(chunk_x, chunk_z)).map(|chunk| &chunk.data)
+    }
+    pub fn get_mut(&mut self, chunk_x: i32, chunk_z: i32) -> Option<&mut ChunkData> {
+        self.chunks.get_mut(&(chunk_x, chunk_z)).map(|chunk| &mut chunk.data)
+    }
+
+    /// Held chunk of a position and the y of the position in that chunk,
+    /// None if the chunk isn't held or the position is under or above it
+    fn chunk_at(&mut self, position: Position) -> Option<(&mut ChunkData, u16)> {
+        let y = position.y.checked_sub(self.min_y)?;
+        let chunk = self.get_mut(position.x.div_euclid(16), position.z.div_euclid(16))?;
+        if y < 0 || y as usize >= chunk.block_height() {
+            return None;
+        }
+        Some((chunk, y as u16))
+    }
+
+    /// Block at a position of a held chunk
+    pub fn get_block(&self, position: Position) -> Option<BlockState> {
+        let y = position.y.checked_sub(self.min_y)?;
+        let chunk = self.get(position.x.div_euclid(16), position.z.div_euclid(16))?;
+        if y < 0 || y as usize >= chunk.block_height() {
+            return None;
+        }
+        Some(chunk.get_block(position.x.rem_euclid(16) as u8, y as u16, position.z.rem_euclid(16) as u8))
+    }
+
+    /// Changes a block of a held chunk, the change is sent to the chunk's viewers at the end of the tick
+    /// in a C09BlockChange or a C3DUpdateSectionBlocks of the section holding the y
+    /// Returns false if the chunk isn't held or the position is outside of the world height
+    pub fn set_block(&mut self, position: Position, block: BlockState) -> bool {
+        let (chunk, y) = match self.chunk_at(position) {
+            Some(chunk) => chunk,
+            None => return false,
+        };
+        chunk.set_block(position.x.rem_euclid(16) as u8, y, position.z.rem_euclid(16) as u8, block);
+        self.block_changes.set_block(position, block);
+        true
+    }
+
+    /// Takes the accumulated block changes as packets with the chunk they must be sent for
+    /// Changes of the same section are sent in one C3DUpdateSectionBlocks,
+    /// a C09BlockChange is used for sections with a single change
+    pub fn take_block_change_packets(&mut self) -> Vec<((i32, i32), RawPacket)> {
+        let packets = self.block_changes.to_packets(None)
+            .map(|section| {
+                let chunk = (section.section_x, section.section_z);
+                let packet = match section.blocks.as_slice() {
+                    [change] => C09BlockChange {
+                        position: Position {
+                            x: section.section_x * 16 + change.x as i32,
+                            y: section.section_y * 16 + change.y as i32,
+                            z: section.section_z * 16 + change.z as i32,
+                        },
+                        block_id: change.block_id,
+                    }.to_rawpacket(),
+                    _ => section.to_rawpacket(),
+                };
+                (chunk, packet)
+            })
+            .collect();
+        self.block_changes = BlockChangeAccumulator::new();
+        packets
+    }
+
+    pub fn contains(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        self.chunks.contains_key(&(chunk_x, chunk_z))
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+}
+
+/// Unloads the chunks of the [ChunkHolder] that no player had in range for too long
+pub fn chunk_holder_unload(
+    mut holder: ResMut<ChunkHolder>,
+    observers: Query<(&ChunkObserverComponent, &ChunkLocationComponent)>,
+    clients: Query<&ClientComponent>,
+) {
+    let keep_alive_ticks = holder.keep_alive_ticks;
+    holder.chunks.retain(|(chunk_x, chunk_z), chunk| {
+        let in_range = observers.iter().any(|(observer, location)| {
+            (chunk_x - location.x).abs() <= observer.radius
+                && (chunk_z - location.z).abs() <= observer.radius
+        });
+        if in_range {
+            chunk.ticks_out_of_range = 0;
+            return true;
+        }
+
+        chunk.ticks_out_of_range += 1;
+        if chunk.ticks_out_of_range <= keep_alive_ticks {
+            return true;
+        }
+
+        let unload_packet = C1AUnloadChunk {
+            chunk_x: *chunk_x,
+            chunk_z: *chunk_z,
+        }.to_rawpacket();
+        for viewer in chunk.viewers.iter().copied() {
+            if let Ok(client) = clients.get(viewer) {
+                client.0.send_raw_packet_sync(unload_packet.clone());
+            }
+        }
+        false
+    });
+}
+
+/// Sends the block changes of the tick to the viewers of the changed chunks
+pub fn chunk_holder_block_changes(
+    mut holder: ResMut<ChunkHolder>,
+    clients: Query<&ClientComponent>,
+) {
+    for (chunk, packet) in holder.take_block_change_packets() {
+        let viewers = match holder.chunks.get(&chunk) {
+            Some(held_chunk) => &held_chunk.viewers,
+            None => continue,
+        };
+        for viewer in viewers.iter().copied() {
+            if let Ok(client) = clients.get(viewer) {
+                client.0.send_raw_packet_sync(packet.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_manager::ChunkProvider;
+    use bevy_ecs::schedule::{ Stage, SystemStage };
+    use bevy_ecs::system::Commands;
+    use bevy_ecs::world::World;
+    use mc_networking::packets::client_bound::C3DUpdateSectionBlocks;
+
+    struct NoopChunkProvider;
+    impl ChunkProvider for NoopChunkProvider {
+        fn load_chunk(&mut self, _: Entity, _: &mut Commands, _: i32, _: i32) {}
+        fn unload_chunk(&mut self, _: Entity, _: &mut Commands, _: i32, _: i32) {}
+    }
+
+    #[test]
+    fn chunk_unloaded_after_player_leaves() {
+        let mut world = World::default();
+        world.insert_resource(ChunkHolder::new(3));
+        let player = world.spawn()
+            .insert(ChunkObserverComponent {
+                radius: 2,
+                loaded_chunks: Default::default(),
+                chunk_provider: Box::new(NoopChunkProvider),
+            })
+            .insert(ChunkLocationComponent::new(0, 0))
+            .id();
+        {
+            let mut holder = world.resource_mut::<ChunkHolder>();
+            holder.insert(1, -1, ChunkData::new(4));
+            assert!(holder.add_viewer(1, -1, player));
+        }
+        let mut stage = SystemStage::single(chunk_holder_unload);
+
+        for _ in 0..10 {
+            stage.run(&mut world);
+        }
+        assert!(world.resource::<ChunkHolder>().contains(1, -1));
+
+        world.entity_mut(player).insert(ChunkLocationComponent::new(10, 0));
+        for _ in 0..3 {
+            stage.run(&mut world);
+            assert!(world.resource::<ChunkHolder>().contains(1, -1));
+        }
+        stage.run(&mut world);
+        assert!(world.resource::<ChunkHolder>().is_empty());
+    }
+
+    #[test]
+    fn block_changes_batched_by_section() {
+        let mut holder = ChunkHolder::default();
+        holder.insert(0, 0, ChunkData::new(4));
+        holder.insert(-1, 0, ChunkData::new(4));
+
+        for x in 0..5 {
+            assert!(holder.set_block(Position { x, y: 20, z: 3 }, 1));
+        }
+        let packets = holder.take_block_change_packets();
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].0, (0, 0));
+        assert_eq!(packets[0].1.packet_id, C3DUpdateSectionBlocks::PACKET_ID);
+        assert_eq!(holder.get(0, 0).unwrap().get_block(4, 20, 3), 1);
+        assert!(holder.take_block_change_packets().is_empty());
+
+        assert!(holder.set_block(Position { x: -3, y: 40, z: 2 }, 2));
+        assert!(!holder.set_block(Position { x: 40, y: 40, z: 2 }, 2));
+        let packets = holder.take_block_change_packets();
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].0, (-1, 0));
+        assert_eq!(packets[0].1.packet_id, C09BlockChange::PACKET_ID);
+    }
+
+    #[test]
+    fn blocks_outside_of_0_255() {
+        // From y=-64 to y=320 like the overworld
+        let mut holder = ChunkHolder::default().with_min_y(-64);
+        holder.insert(0, 0, ChunkData::new(24));
+
+        let low = Position { x: 1, y: -30, z: 2 };
+        let high = Position { x: 1, y: 300, z: 2 };
+        assert!(holder.set_block(low, 1));
+        assert!(holder.set_block(high, 2));
+        assert!(!holder.set_block(Position { y: -65, ..low }, 3));
+        assert!(!holder.set_block(Position { y: 320, ..high }, 3));
+
+        // Sections are counted from the bottom of the chunk
+        let chunk = holder.get(0, 0).unwrap();
+        assert_eq!(chunk.get_section(2).get_block(1, 2, 2), 1);
+        assert_eq!(chunk.get_section(22).get_block(1, 12, 2), 2);
+        assert_eq!(holder.get_block(low), Some(1));
+        assert_eq!(holder.get_block(high), Some(2));
+        assert_eq!(holder.get_block(Position { y: -65, ..low }), None);
+
+        // The packets use the absolute block positions
+        let mut packets = holder.take_block_change_packets()
+            .into_iter()
+            .map(|(_, packet)| packet.data)
+            .collect::<Vec<_>>();
+        packets.sort();
+        let mut expected = [low, high].iter()
+            .zip([1, 2])
+            .map(|(position, block_id)| C09BlockChange { position: *position, block_id }.to_rawpacket().data)
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(packets, expected);
+    }
+}
@@ -3,6 +3,8 @@ use bevy_ecs::entity::Entity;
 use bevy_ecs::system::Commands;
 
 /// A chunk provider is use to generate chunks and send them to players
+/// The sent chunks should be added to the [ChunkHolder](crate::chunk_holder::ChunkHolder)
+/// with the player as a viewer so it receives the block changes
 pub trait ChunkProvider: Send + Sync {
     /// Called when a chunk gets in range of a player
     /// You may send the ChunkData packet at any time after this
@@ -43,6 +45,6 @@ where T: Deref<Target = U> + Send + Sync,
         &mut self, player: Entity, commands: &mut Commands,
         chunk_x: i32, chunk_z: i32
     ){
-        self.const_load_chunk(player, commands, chunk_x, chunk_z);
+        self.const_unload_chunk(player, commands, chunk_x, chunk_z);
     }
 }
@@ -0,0 +1,243 @@
+use crate::entity::ClientComponent;
+use mc_networking::packets::client_bound::{ C1AUnloadChunk, C48SetCenterChunk };
+use mc_utils::ChunkData;
+
+use ahash::AHashMap;
+
+/// Side in chunks of the regions of a [LoadedChunks]
+const REGION_SIDE: i32 = 32;
+const REGION_LONGS: usize = (REGION_SIDE * REGION_SIDE / 64) as usize;
+
+/// Chunks at most `view_distance` chunks away from the center on both axes, from the center
+/// outward, each ring being walked as a spiral
+pub fn chunks_in_view(center: (i32, i32), view_distance: i32) -> Vec<(i32, i32)> {
+    let (center_x, center_z) = center;
+    let side = 2 * view_distance.max(0) as usize + 1;
+    let mut chunks = Vec::with_capacity(side * side);
+    chunks.push(center);
+    for ring in 1..=view_distance {
+        let ring_chunks = (-ring..ring).map(|dx| (dx, -ring))
+            .chain((-ring..ring).map(|dz| (ring, dz)))
+            .chain((-ring + 1..=ring).rev().map(|dx| (dx, ring)))
+            .chain((-ring + 1..=ring).rev().map(|dz| (-ring, dz)));
+        chunks.extend(ring_chunks.map(|(dx, dz)| (center_x + dx, center_z + dz)));
+    }
+    chunks
+}
+
+fn in_view(center: (i32, i32), view_distance: i32, chunk: (i32, i32)) -> bool {
+    (chunk.0 - center.0).abs() <= view_distance && (chunk.1 - center.1).abs() <= view_distance
+}
+
+/// Chunks to load and unload for a player moving between two chunks
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkStream {
+    pub center: (i32, i32),
+    /// Chunks entering the view, closest first
+    pub load: Vec<(i32, i32)>,
+    /// Chunks leaving the view
+    pub unload: Vec<(i32, i32)>,
+}
+
+impl ChunkStream {
+    /// Computes the chunks entering and leaving the view of a player moving from the `old`
+    /// chunk, None if the player just joined, to the `new` one
+    pub fn between(old: Option<(i32, i32)>, new: (i32, i32), view_distance: i32) -> Self {
+        let load = chunks_in_view(new, view_distance).into_iter()
+            .filter(|chunk| old.map_or(true, |old| !in_view(old, view_distance, *chunk)))
+            .collect();
+        let unload = old.into_iter()
+            .flat_map(|old| chunks_in_view(old, view_distance))
+            .filter(|chunk| !in_view(new, view_distance, *chunk))
+            .collect();
+        Self { center: new, load, unload }
+    }
+
+    /// Sends the new center chunk, the unloads and the loaded chunks to a player
+    /// Returns the chunks to load that `chunks` couldn't provide, which must be sent later
+    pub fn send<'a>(
+        &self, client: &ClientComponent,
+        chunks: impl Fn(i32, i32) -> Option<&'a ChunkData>, world_height: i32,
+    ) -> Vec<(i32, i32)> {
+        client.0.send_packet_sync(&C48SetCenterChunk {
+            chunk_x: self.center.0,
+            chunk_z: self.center.1,
+        });
+        for (chunk_x, chunk_z) in &self.unload {
+            client.0.send_packet_sync(&C1AUnloadChunk {
+                chunk_x: *chunk_x,
+                chunk_z: *chunk_z,
+            });
+        }
+        self.load.iter().copied()
+            .filter(|(chunk_x, chunk_z)| match chunks(*chunk_x, *chunk_z) {
+                Some(chunk) => {
+                    client.send_chunk(*chunk_x, *chunk_z, chunk, world_height);
+                    false
+                }
+                None => true,
+            })
+            .collect()
+    }
+}
+
+/// Set of chunks, like the chunks loaded by a player
+/// Chunks are stored as one bit per chunk in 32 by 32 chunks regions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadedChunks {
+    regions: AHashMap<(i32, i32), [u64; REGION_LONGS]>,
+    len: usize,
+}
+
+impl LoadedChunks {
+    /// Every chunk at most `view_distance` chunks away from the center on both axes
+    pub fn in_view(center: (i32, i32), view_distance: i32) -> Self {
+        let mut chunks = Self::default();
+        for (chunk_x, chunk_z) in chunks_in_view(center, view_distance) {
+            chunks.mark_loaded(chunk_x, chunk_z);
+        }
+        chunks
+    }
+
+    /// Region and bit of a chunk
+    fn locate(chunk_x: i32, chunk_z: i32) -> ((i32, i32), usize, u64) {
+        let region = (chunk_x.div_euclid(REGION_SIDE), chunk_z.div_euclid(REGION_SIDE));
+        let index = (chunk_x.rem_euclid(REGION_SIDE) + chunk_z.rem_euclid(REGION_SIDE) * REGION_SIDE) as usize;
+        (region, index / 64, 1 << (index % 64))
+    }
+
+    /// Returns false if the chunk was already loaded
+    pub fn mark_loaded(&mut self, chunk_x: i32, chunk_z: i32) -> bool {
+        let (region, long, bit) = Self::locate(chunk_x, chunk_z);
+        let longs = self.regions.entry(region).or_insert([0; REGION_LONGS]);
+        if longs[long] & bit != 0 {
+            return false;
+        }
+        longs[long] |= bit;
+        self.len += 1;
+        true
+    }
+
+    /// Returns false if the chunk wasn't loaded
+    pub fn mark_unloaded(&mut self, chunk_x: i32, chunk_z: i32) -> bool {
+        let (region, long, bit) = Self::locate(chunk_x, chunk_z);
+        let longs = match self.regions.get_mut(&region) {
+            Some(longs) if longs[long] & bit != 0 => longs,
+            _ => return false,
+        };
+        longs[long] &= !bit;
+        if longs.iter().all(|long| *long == 0) {
+            self.regions.remove(&region);
+        }
+        self.len -= 1;
+        true
+    }
+
+    pub fn is_loaded(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        let (region, long, bit) = Self::locate(chunk_x, chunk_z);
+        self.regions.get(&region).map_or(false, |longs| longs[long] & bit != 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.regions.iter().flat_map(|((region_x, region_z), longs)| {
+            longs.iter().enumerate().flat_map(move |(long_index, long)| {
+                (0..64).filter(move |bit| *long & (1u64 << *bit) != 0).map(move |bit| {
+                    let index = long_index as i32 * 64 + bit;
+                    (
+                        region_x * REGION_SIDE + index % REGION_SIDE,
+                        region_z * REGION_SIDE + index / REGION_SIDE,
+                    )
+                })
+            })
+        })
+    }
+
+    /// Chunks to load and to unload to go from this set to `other`
+    pub fn diff(&self, other: &LoadedChunks) -> (Vec<(i32, i32)>, Vec<(i32, i32)>) {
+        let to_load = other.iter().filter(|(x, z)| !self.is_loaded(*x, *z)).collect();
+        let to_unload = self.iter().filter(|(x, z)| !other.is_loaded(*x, *z)).collect();
+        (to_load, to_unload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn chunks_in_view_spiral_outward() {
+        let chunks = chunks_in_view((3, -2), 2);
+        assert_eq!(chunks.len(), 25);
+        assert_eq!(chunks.iter().collect::<HashSet<_>>().len(), 25);
+        assert_eq!(chunks[0], (3, -2));
+        let ring = |(x, z): (i32, i32)| (x - 3).abs().max((z + 2).abs());
+        assert!(chunks.windows(2).all(|pair| ring(pair[0]) <= ring(pair[1])));
+        // Consecutive chunks of a ring are neighbours
+        assert!(chunks[1..9].windows(2).all(|pair|
+            (pair[0].0 - pair[1].0).abs() + (pair[0].1 - pair[1].1).abs() == 1
+        ));
+    }
+
+    #[test]
+    fn moving_one_chunk_east() {
+        let stream = ChunkStream::between(Some((0, 0)), (1, 0), 2);
+        assert_eq!(stream.center, (1, 0));
+
+        let load = stream.load.iter().copied().collect::<HashSet<_>>();
+        let unload = stream.unload.iter().copied().collect::<HashSet<_>>();
+        assert_eq!(load, (-2..=2).map(|z| (3, z)).collect());
+        assert_eq!(unload, (-2..=2).map(|z| (-2, z)).collect());
+
+        let join = ChunkStream::between(None, (1, 0), 2);
+        assert_eq!(join.load.len(), 25);
+        assert!(join.unload.is_empty());
+        assert_eq!(ChunkStream::between(Some((1, 0)), (1, 0), 2), ChunkStream { center: (1, 0), ..ChunkStream::default() });
+    }
+
+    #[test]
+    fn loaded_chunks_marking() {
+        let mut chunks = LoadedChunks::default();
+        assert!(chunks.mark_loaded(-1, 31));
+        assert!(chunks.mark_loaded(32, 0));
+        assert!(!chunks.mark_loaded(-1, 31));
+        assert!(chunks.is_loaded(-1, 31));
+        assert!(!chunks.is_loaded(31, -1));
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks.iter().collect::<HashSet<_>>(), [(-1, 31), (32, 0)].iter().copied().collect());
+
+        assert!(chunks.mark_unloaded(32, 0));
+        assert!(!chunks.mark_unloaded(32, 0));
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks.mark_unloaded(-1, 31));
+        assert!(chunks.is_empty());
+        assert_eq!(chunks, LoadedChunks::default());
+    }
+
+    #[test]
+    fn loaded_chunks_diff() {
+        let before = LoadedChunks::in_view((0, 0), 2);
+        let after = LoadedChunks::in_view((1, 1), 2);
+        assert_eq!(before.len(), 25);
+
+        let (to_load, to_unload) = before.diff(&after);
+        let to_load = to_load.into_iter().collect::<HashSet<_>>();
+        let to_unload = to_unload.into_iter().collect::<HashSet<_>>();
+        assert_eq!(to_load.len(), 9);
+        assert_eq!(to_unload.len(), 9);
+        assert!(to_load.contains(&(3, 3)) && to_load.contains(&(-1, 3)) && to_load.contains(&(3, -1)));
+        assert!(to_unload.contains(&(-2, -2)) && to_unload.contains(&(2, -2)) && to_unload.contains(&(-2, 2)));
+        assert!(to_load.is_disjoint(&to_unload));
+
+        let (to_load, to_unload) = before.diff(&before);
+        assert!(to_load.is_empty() && to_unload.is_empty());
+    }
+}
@@ -0,0 +1,235 @@
+use crate::entity::{ ClientComponent, NetworkIdComponent };
+use mc_networking::data_types::Position;
+use mc_networking::packets::client_bound::{ C06SetBlockDestroyStage, ClientBoundPacket };
+
+use ahash::AHashMap;
+use bevy_ecs::component::Component;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::system::Query;
+
+/// Part of the break time after which a finished digging is accepted,
+/// like the notchian server to account for the latency of the client
+pub const FINISH_TOLERANCE: f32 = 0.7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolKind {
+    Pickaxe,
+    Axe,
+    Shovel,
+    Hoe,
+    Sword,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ToolTier {
+    Wood,
+    Stone,
+    Iron,
+    Diamond,
+    Netherite,
+    Gold,
+}
+
+impl ToolTier {
+    /// Mining speed multiplier of the tools of this tier on the blocks they are effective on
+    pub fn speed(&self) -> f32 {
+        match self {
+            Self::Wood => 2.,
+            Self::Stone => 4.,
+            Self::Iron => 6.,
+            Self::Diamond => 8.,
+            Self::Netherite => 9.,
+            Self::Gold => 12.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tool {
+    pub kind: ToolKind,
+    pub tier: ToolTier,
+}
+
+/// Mining properties of a block
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockHardness {
+    /// Negative for unbreakable blocks
+    pub hardness: f32,
+    /// Kind of tool mining the block faster
+    pub effective_tool: Option<ToolKind>,
+    /// Whether the block only drops when mined with its effective tool, which also makes it
+    /// slower to mine without it
+    pub requires_tool: bool,
+}
+
+impl BlockHardness {
+    /// Number of ticks needed to break the block with the given tool, None if it is unbreakable
+    /// 0 means the block is broken instantly
+    pub fn break_ticks(&self, tool: Option<Tool>) -> Option<u32> {
+        if self.hardness < 0. {
+            return None;
+        }
+        if self.hardness == 0. {
+            return Some(0);
+        }
+        let effective = tool.filter(|tool| Some(tool.kind) == self.effective_tool);
+        let speed = effective.map(|tool| tool.tier.speed()).unwrap_or(1.);
+        let can_harvest = !self.requires_tool || effective.is_some();
+        let damage_per_tick = speed / self.hardness / if can_harvest { 30. } else { 100. };
+        if damage_per_tick > 1. {
+            return Some(0);
+        }
+        Some((1. / damage_per_tick).ceil() as u32)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Dig {
+    elapsed_ticks: u32,
+    required_ticks: u32,
+    /// Last destroy stage sent to the other players
+    stage: i8,
+}
+
+impl Dig {
+    fn stage(&self) -> i8 {
+        if self.required_ticks == 0 {
+            return 9;
+        }
+        (self.elapsed_ticks * 10 / self.required_ticks).min(9) as i8
+    }
+}
+
+/// Blocks a player is currently digging, indexed by their position
+#[derive(Component, Debug, Clone, Default)]
+pub struct DiggingComponent {
+    digs: AHashMap<Position, Dig>,
+    /// Stopped digs whose destroy stage must be removed for the other players
+    stopped: Vec<Position>,
+}
+
+impl DiggingComponent {
+    /// Starts digging a block that takes `required_ticks` to break, restarting any previous dig
+    /// of the block
+    pub fn start(&mut self, position: Position, required_ticks: u32) {
+        self.digs.insert(position, Dig {
+            elapsed_ticks: 0,
+            required_ticks,
+            stage: -1,
+        });
+    }
+
+    /// Stops digging a block, returns false if the block wasn't being dug
+    pub fn cancel(&mut self, position: Position) -> bool {
+        self.remove(position).is_some()
+    }
+
+    /// Ends the digging of a block, returns whether it was dug for long enough to be broken
+    pub fn finish(&mut self, position: Position) -> bool {
+        match self.remove(position) {
+            Some(dig) => dig.elapsed_ticks as f32 >= dig.required_ticks as f32 * FINISH_TOLERANCE,
+            None => false,
+        }
+    }
+
+    fn remove(&mut self, position: Position) -> Option<Dig> {
+        let dig = self.digs.remove(&position)?;
+        if dig.stage >= 0 {
+            self.stopped.push(position);
+        }
+        Some(dig)
+    }
+
+    pub fn is_digging(&self, position: Position) -> bool {
+        self.digs.contains_key(&position)
+    }
+
+    /// Advances every dig by one tick, returning the digs whose destroy stage changed
+    /// Stopped digs are returned with a stage of -1
+    fn tick(&mut self) -> Vec<(Position, i8)> {
+        let mut changes = self.stopped.drain(..).map(|position| (position, -1)).collect::<Vec<_>>();
+        changes.extend(self.digs.iter_mut()
+            .filter_map(|(position, dig)| {
+                dig.elapsed_ticks += 1;
+                let stage = dig.stage();
+                if stage == dig.stage {
+                    return None;
+                }
+                dig.stage = stage;
+                Some((*position, stage))
+            }));
+        changes
+    }
+}
+
+/// Advances the digs of the players and sends the destroy stages to the other players
+pub fn digging_progress_system(
+    mut diggers: Query<(Entity, &NetworkIdComponent, &mut DiggingComponent)>,
+    clients: Query<(Entity, &ClientComponent)>,
+) {
+    diggers.for_each_mut(|(digger, network_id, mut digging)| {
+        if digging.digs.is_empty() && digging.stopped.is_empty() {
+            return;
+        }
+        for (position, destroy_stage) in digging.tick() {
+            let packet = C06SetBlockDestroyStage {
+                entity_id: network_id.0,
+                position,
+                destroy_stage,
+            }.to_rawpacket();
+            clients.for_each(|(entity, client)| {
+                if entity != digger {
+                    client.0.send_raw_packet_sync(packet.clone());
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STONE: BlockHardness = BlockHardness {
+        hardness: 1.5,
+        effective_tool: Some(ToolKind::Pickaxe),
+        requires_tool: true,
+    };
+
+    #[test]
+    fn stone_break_ticks() {
+        let pickaxe = |tier| Some(Tool { kind: ToolKind::Pickaxe, tier });
+        assert_eq!(STONE.break_ticks(None), Some(150));
+        assert_eq!(STONE.break_ticks(pickaxe(ToolTier::Wood)), Some(23));
+        assert_eq!(STONE.break_ticks(pickaxe(ToolTier::Iron)), Some(8));
+        assert_eq!(STONE.break_ticks(pickaxe(ToolTier::Diamond)), Some(6));
+        // A shovel is no better than a hand
+        let shovel = Some(Tool { kind: ToolKind::Shovel, tier: ToolTier::Diamond });
+        assert_eq!(STONE.break_ticks(shovel), Some(150));
+
+        let dirt = BlockHardness { hardness: 0.5, effective_tool: Some(ToolKind::Shovel), requires_tool: false };
+        assert_eq!(dirt.break_ticks(None), Some(15));
+        let bedrock = BlockHardness { hardness: -1., effective_tool: None, requires_tool: false };
+        assert_eq!(bedrock.break_ticks(pickaxe(ToolTier::Netherite)), None);
+    }
+
+    #[test]
+    fn finish_needs_enough_ticks() {
+        let position = Position { x: 1, y: 64, z: -2 };
+        let mut digging = DiggingComponent::default();
+        digging.start(position, 10);
+        for _ in 0..5 {
+            digging.tick();
+        }
+        assert!(!digging.finish(position));
+        assert!(!digging.is_digging(position));
+        digging.tick();
+
+        digging.start(position, 10);
+        let stages = (0..7).flat_map(|_| digging.tick()).map(|(_, stage)| stage).collect::<Vec<_>>();
+        assert_eq!(stages, [1, 2, 3, 4, 5, 6, 7]);
+        assert!(digging.finish(position));
+        assert_eq!(digging.tick(), [(position, -1)]);
+        assert!(digging.tick().is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+use crate::entity::LocationComponent;
+use mc_utils::Location;
+
+use rand::{ Rng, SeedableRng, rngs::StdRng };
+use bevy_ecs::component::Component;
+use bevy_ecs::system::Query;
+
+/// Behavior of an entity, ticked by the [ai_system]
+pub trait AiController: Send + Sync {
+    /// Called every tick with the current location of the entity, which can be changed
+    /// The movement packets are then sent by the [super::movement::entity_movement_system]
+    fn tick(&mut self, location: &mut Location);
+}
+
+#[derive(Component)]
+pub struct AiComponent(pub Box<dyn AiController>);
+impl AiComponent {
+    pub fn new(controller: impl AiController + 'static) -> Self {
+        Self(Box::new(controller))
+    }
+}
+
+/// Walks toward random points around an origin, idling from time to time
+pub struct WanderAi {
+    pub origin: Location,
+    /// Maximum horizontal distance from the origin of the wander targets
+    pub radius: f64,
+    /// Walking speed in blocks per tick
+    pub speed: f64,
+    target: Option<(f64, f64)>,
+    idle_ticks: u32,
+    rng: StdRng,
+}
+
+impl WanderAi {
+    pub fn new(origin: Location, radius: f64) -> Self {
+        Self::with_rng(origin, radius, StdRng::from_entropy())
+    }
+
+    pub fn with_rng(origin: Location, radius: f64, rng: StdRng) -> Self {
+        Self {
+            origin,
+            radius,
+            speed: 0.1,
+            target: None,
+            idle_ticks: 0,
+            rng,
+        }
+    }
+}
+
+impl AiController for WanderAi {
+    fn tick(&mut self, location: &mut Location) {
+        if self.idle_ticks > 0 {
+            self.idle_ticks -= 1;
+            return;
+        }
+
+        let origin = self.origin;
+        let radius = self.radius;
+        let rng = &mut self.rng;
+        let (target_x, target_z) = *self.target.get_or_insert_with(|| {
+            let angle = rng.gen_range(0. ..std::f64::consts::TAU);
+            let distance = rng.gen_range(0. ..=radius);
+            (origin.x + angle.cos() * distance, origin.z + angle.sin() * distance)
+        });
+
+        let (delta_x, delta_z) = (target_x - location.x, target_z - location.z);
+        let distance = (delta_x.powi(2) + delta_z.powi(2)).sqrt();
+        if distance <= self.speed {
+            location.x = target_x;
+            location.z = target_z;
+            self.target = None;
+            if self.rng.gen_bool(0.5) {
+                self.idle_ticks = self.rng.gen_range(20..80);
+            }
+            return;
+        }
+
+        location.x += delta_x / distance * self.speed;
+        location.z += delta_z / distance * self.speed;
+        // A yaw of 0 looks toward +z and -90 toward +x
+        location.yaw = (-delta_x).atan2(delta_z).to_degrees() as f32;
+        location.pitch = 0.;
+    }
+}
+
+/// Ticks the [AiComponent] of every entity
+pub fn ai_system(mut query: Query<(&mut AiComponent, &mut LocationComponent)>) {
+    query.for_each_mut(|(mut ai, mut location_component)| {
+        let mut location = location_component.0;
+        ai.0.tick(&mut location);
+        // Keeps the change detection of idle entities
+        if location != location_component.0 {
+            location_component.0 = location;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs::schedule::{ Stage, SystemStage };
+    use bevy_ecs::world::World;
+
+    #[test]
+    fn wander_stays_around_origin() {
+        let origin = Location { x: 10.5, y: 64., z: -3.5, ..Location::default() };
+        let mut world = World::default();
+        let entity = world.spawn()
+            .insert(LocationComponent(origin))
+            .insert(AiComponent::new(WanderAi::with_rng(origin, 8., StdRng::seed_from_u64(42))))
+            .id();
+        let mut stage = SystemStage::single(ai_system);
+
+        let mut max_distance: f64 = 0.;
+        let mut previous = origin;
+        for _ in 0..10_000 {
+            stage.run(&mut world);
+            let location = world.get::<LocationComponent>(entity).unwrap().0;
+            max_distance = max_distance.max(location.h_distance(origin));
+            assert!(location.h_distance(previous) <= 0.1 + 1e-9);
+            assert_eq!(location.y, origin.y);
+            previous = location;
+        }
+        assert!(max_distance > 1.);
+        assert!(max_distance <= 8. + 1e-9);
+    }
+
+    #[test]
+    fn wander_faces_movement() {
+        let origin = Location::default();
+        let mut ai = WanderAi::with_rng(origin, 5., StdRng::seed_from_u64(1));
+        ai.target = Some((3., 0.));
+        let mut location = origin;
+        ai.tick(&mut location);
+        assert_eq!(location.yaw, -90.);
+        assert!(location.x > 0.);
+    }
+}
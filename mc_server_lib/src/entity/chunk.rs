@@ -1,5 +1,6 @@
 use crate::{
     chunk_manager::ChunkProvider,
+    chunk_streamer::chunks_in_view,
     entity::{ ClientComponent, LocationComponent },
 };
 use mc_networking::packets::client_bound::*;
@@ -26,6 +27,28 @@ pub struct ChunkObserverComponent {
     pub chunk_provider: Box<dyn ChunkProvider>,
 }
 
+impl ChunkObserverComponent {
+    /// Changes the radius, loading the chunks entering it and unloading the ones leaving it
+    pub fn set_radius(
+        &mut self, entity: Entity, center: (i32, i32), radius: i32, commands: &mut Commands,
+    ) {
+        self.radius = radius;
+        let ChunkObserverComponent { loaded_chunks, chunk_provider, .. } = self;
+        loaded_chunks.retain(|&(chunk_x, chunk_z)| {
+            let keep = (chunk_x - center.0).abs() <= radius && (chunk_z - center.1).abs() <= radius;
+            if !keep {
+                chunk_provider.unload_chunk(entity, commands, chunk_x, chunk_z);
+            }
+            keep
+        });
+        for (chunk_x, chunk_z) in chunks_in_view(center, radius) {
+            if loaded_chunks.insert((chunk_x, chunk_z)) {
+                chunk_provider.load_chunk(entity, commands, chunk_x, chunk_z);
+            }
+        }
+    }
+}
+
 /// Represent the chunk location of an [Entity] with the [ChunkLoaderComponent]
 /// This will be automatically updated based on the [LocationComponent]
 #[readonly::make]
@@ -117,23 +140,14 @@ pub(crate) fn chunk_observer_chunk_loadings(
         }
 
         // Load close enough chunks from the closests to the farthests
-        for square_dist in 0..chunk_observer.radius { // Iterate over chunk distance
-            for chunk_dx in -square_dist..square_dist { // Load chunks of that distance
-                for chunk_dz in -square_dist..square_dist {
-                    for (chunk_dx, chunk_dz) in [(chunk_dx, chunk_dz), (-chunk_dx, -chunk_dz)].to_vec()
-                    {
-                        let chunk_x = chunk_loc.x + chunk_dx;
-                        let chunk_z = chunk_loc.z + chunk_dz;
-                        let should_force_update = concerned_fcucs
-                            .iter().any(|fcuc| fcuc.updates.contains(&(chunk_x, chunk_z)));
-                        if should_force_update || !chunk_observer.loaded_chunks.contains(&(chunk_x, chunk_z)) {
-                            chunk_observer.loaded_chunks.insert((chunk_x, chunk_z));
-                            chunk_observer
-                                .chunk_provider
-                                .load_chunk(entity, &mut commands, chunk_x, chunk_z);
-                        }
-                    }
-                }
+        for (chunk_x, chunk_z) in chunks_in_view((chunk_loc.x, chunk_loc.z), chunk_observer.radius) {
+            let should_force_update = concerned_fcucs
+                .iter().any(|fcuc| fcuc.updates.contains(&(chunk_x, chunk_z)));
+            if should_force_update || !chunk_observer.loaded_chunks.contains(&(chunk_x, chunk_z)) {
+                chunk_observer.loaded_chunks.insert((chunk_x, chunk_z));
+                chunk_observer
+                    .chunk_provider
+                    .load_chunk(entity, &mut commands, chunk_x, chunk_z);
             }
         }
     });
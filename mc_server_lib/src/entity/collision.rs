@@ -0,0 +1,145 @@
+use crate::chunk_holder::ChunkHolder;
+use crate::entity::{ ClientComponent, LocationComponent };
+use crate::entity::teleport::TeleportComponent;
+use mc_networking::data_types::Position;
+use mc_networking::packets::client_bound::RelativeFlags;
+use mc_utils::{ AABB, BlockState, Location };
+
+use bevy_ecs::component::Component;
+
+/// Width of the hitbox of the players, in blocks
+pub const PLAYER_WIDTH: f64 = 0.6;
+/// Height of the hitbox of standing players, in blocks
+pub const PLAYER_HEIGHT: f64 = 1.8;
+/// Number of blocks a player can be pushed up in one correction, players deeper in the
+/// ground are left where they are
+pub const MAX_PUSH_UP: usize = 8;
+
+/// Collision shape of a block state relative to the block, None for blocks that can be walked through
+pub type CollisionShape = fn(BlockState) -> Option<AABB>;
+
+/// Hitbox of a standing player at the given location
+pub fn player_hitbox(location: &Location) -> AABB {
+    let half_width = PLAYER_WIDTH / 2.;
+    AABB::new(
+        (location.x - half_width, location.y, location.z - half_width),
+        (location.x + half_width, location.y + PLAYER_HEIGHT, location.z + half_width),
+    )
+}
+
+/// Pushes the players clipping in the blocks of the [ChunkHolder] back on top of them
+/// Given by the [crate::mc_app::LoginPipeline::with_block_collisions], players without it
+/// aren't checked
+#[derive(Component, Clone, Copy)]
+pub struct BlockCollisionComponent(pub CollisionShape);
+
+impl BlockCollisionComponent {
+    /// Highest top of the block shapes intersecting the hitbox, blocks of unloaded chunks are empty
+    fn highest_collision(&self, hitbox: &AABB, chunk_holder: &ChunkHolder) -> Option<f64> {
+        let mut highest = None;
+        for x in hitbox.min.0.floor() as i32..hitbox.max.0.ceil() as i32 {
+            for y in hitbox.min.1.floor() as i32..hitbox.max.1.ceil() as i32 {
+                for z in hitbox.min.2.floor() as i32..hitbox.max.2.ceil() as i32 {
+                    let shape = chunk_holder.get_block(Position { x, y, z })
+                        .and_then(self.0)
+                        .map(|shape| shape.offset(x as f64, y as f64, z as f64))
+                        .filter(|shape| shape.intersects(hitbox));
+                    if let Some(shape) = shape {
+                        highest = Some(highest.map_or(shape.max.1, |h: f64| h.max(shape.max.1)));
+                    }
+                }
+            }
+        }
+        highest
+    }
+
+    /// Location standing on the blocks the player would be inside of at `location`,
+    /// None if the location doesn't collide or the player can't be pushed out
+    pub fn resolve(&self, location: &Location, chunk_holder: &ChunkHolder) -> Option<Location> {
+        let mut resolved = *location;
+        for _ in 0..MAX_PUSH_UP {
+            match self.highest_collision(&player_hitbox(&resolved), chunk_holder) {
+                Some(top) => resolved.y = top,
+                None if resolved.y == location.y => return None,
+                None => return Some(resolved),
+            }
+        }
+        None
+    }
+
+    /// Moves the player out of the blocks it is inside of, its client is teleported to the
+    /// corrected location
+    /// Returns whether the player was moved
+    pub fn apply(
+        &self, location: &mut LocationComponent,
+        teleport: &mut TeleportComponent,
+        client: &ClientComponent,
+        chunk_holder: &ChunkHolder,
+    ) -> bool {
+        let resolved = match self.resolve(&location.0, chunk_holder) {
+            Some(resolved) => resolved,
+            None => return false,
+        };
+        location.0 = resolved;
+        teleport.teleport(client, resolved, RelativeFlags::new());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{ connected_client, read_packets };
+    use mc_networking::packets::client_bound::{ ClientBoundPacket, C36SynchronizePlayerPosition };
+    use mc_utils::ChunkData;
+
+    fn solid_blocks(block: BlockState) -> Option<AABB> {
+        (block != 0).then(AABB::full_block)
+    }
+
+    fn floor() -> ChunkHolder {
+        let mut chunk_holder = ChunkHolder::default();
+        chunk_holder.insert(0, 0, ChunkData::new(4));
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk_holder.set_block(Position { x, y: 20, z }, 1);
+            }
+        }
+        chunk_holder
+    }
+
+    #[test]
+    fn only_clipping_locations_are_resolved() {
+        let collisions = BlockCollisionComponent(solid_blocks);
+        let chunk_holder = floor();
+        let standing = Location { x: 8., y: 21., z: 8., ..Location::default() };
+        assert_eq!(collisions.resolve(&standing, &chunk_holder), None);
+        assert_eq!(collisions.resolve(&Location { y: 25., ..standing }, &chunk_holder), None);
+        assert_eq!(collisions.resolve(&Location { y: 20.7, ..standing }, &chunk_holder), Some(standing));
+        // Outside of the held chunks
+        assert_eq!(collisions.resolve(&Location { x: -8., y: 20.5, ..standing }, &chunk_holder), None);
+    }
+
+    #[tokio::test]
+    async fn player_inside_block_is_pushed_out() {
+        let (client, _events, mut peer) = connected_client().await;
+
+        let collisions = BlockCollisionComponent(solid_blocks);
+        let chunk_holder = floor();
+        let mut location = LocationComponent(Location { x: 3.5, y: 20.2, z: 3.5, ..Location::default() });
+        let mut teleport = TeleportComponent::default();
+
+        assert!(collisions.apply(&mut location, &mut teleport, &client, &chunk_holder));
+        assert_eq!(location.0.y, 21.);
+        assert!(teleport.is_pending());
+        assert!(!collisions.apply(&mut location, &mut teleport, &client, &chunk_holder));
+
+        let packet = read_packets(&mut peer, 1).await.remove(0);
+        let correction = C36SynchronizePlayerPosition {
+            x: 3.5, y: 21., z: 3.5, yaw: 0., pitch: 0.,
+            flags: 0, teleport_id: 0, dismount_vehicle: false,
+        }.to_rawpacket();
+        assert_eq!(packet.packet_id, C36SynchronizePlayerPosition::PACKET_ID);
+        assert_eq!(packet.data, correction.data);
+    }
+}
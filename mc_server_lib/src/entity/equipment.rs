@@ -0,0 +1,165 @@
+use crate::entity::{ ClientComponent, NetworkIdComponent };
+use crate::entity::player_visibility::PlayerVisibilityTracker;
+use crate::inventory::{ Inventory, InventoryComponent, PLAYER_HOTBAR_START };
+use mc_networking::data_types::{ EquipmentSlot, Slot };
+use mc_networking::packets::client_bound::{ C50EntityEquipment, ClientBoundPacket };
+
+use bevy_ecs::component::Component;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::query::{ Changed, Or };
+use bevy_ecs::system::Query;
+
+/// Number of hotbar slots a player can hold
+pub const HOTBAR_SIZE: u8 = 9;
+
+/// Held hotbar slot of a player and the equipment last shown to the other players
+/// Changes are sent to the players seeing it by the [equipment_sync_system]
+#[derive(Component, Debug, Clone, Default)]
+pub struct EquipmentComponent {
+    held_slot: u8,
+    last_sent: [Slot; 6],
+}
+
+impl EquipmentComponent {
+    /// Selected hotbar slot, from 0 to 8
+    pub fn held_slot(&self) -> u8 {
+        self.held_slot
+    }
+
+    /// Handles a [mc_networking::client::client_event::ClientEvent::SetHeldItem],
+    /// returns false if the slot isn't in the hotbar
+    pub fn set_held_slot(&mut self, slot: i16) -> bool {
+        if !(0..HOTBAR_SIZE as i16).contains(&slot) {
+            return false;
+        }
+        self.held_slot = slot as u8;
+        true
+    }
+
+    /// Items shown in every equipment slot, in the order of [EquipmentSlot::ALL], taken from
+    /// the player inventory window
+    pub fn get_equipment(&self, inventory: &Inventory) -> [(EquipmentSlot, Slot); 6] {
+        EquipmentSlot::ALL.map(|equipment_slot| {
+            let index = equipment_slot.player_inventory_index()
+                .unwrap_or(PLAYER_HOTBAR_START + self.held_slot as usize);
+            (equipment_slot, inventory.get_slot(index).cloned().unwrap_or(Slot::NotPresent))
+        })
+    }
+
+    /// Slots that changed since the last call, the current equipment is then considered sent
+    fn take_changes(&mut self, inventory: &Inventory) -> Vec<(EquipmentSlot, Slot)> {
+        let mut changes = vec![];
+        for (i, (equipment_slot, item)) in self.get_equipment(inventory).iter().enumerate() {
+            if self.last_sent[i] != *item {
+                self.last_sent[i] = item.clone();
+                changes.push((*equipment_slot, item.clone()));
+            }
+        }
+        changes
+    }
+}
+
+/// Sends the changed equipment of the players to the players seeing them, the changed slots
+/// of a player are batched in one [C50EntityEquipment]
+pub fn equipment_sync_system(
+    mut players: Query<
+        (Entity, &NetworkIdComponent, &InventoryComponent, &mut EquipmentComponent),
+        Or<(Changed<InventoryComponent>, Changed<EquipmentComponent>)>
+    >,
+    viewers: Query<(&ClientComponent, &PlayerVisibilityTracker)>,
+) {
+    players.for_each_mut(|(entity, network_id, inventory, mut equipment)| {
+        let changes = equipment.take_changes(&inventory.0);
+        if changes.is_empty() {
+            return;
+        }
+        let packet = C50EntityEquipment {
+            entity_id: network_id.0,
+            equipment: changes,
+        }.to_rawpacket();
+        viewers.for_each(|(client, tracker)| {
+            if tracker.is_visible(entity) {
+                client.0.send_raw_packet_sync(packet.clone());
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::{ LocationComponent, ObjectUuidComponent };
+    use crate::entity::player_visibility::player_visibility_system;
+    use crate::test_utils::{ connected_client, read_packets };
+    use mc_networking::packets::client_bound::{ C02SpawnPlayer, C38RemoveEntities };
+    use mc_utils::Location;
+
+    use bevy_ecs::schedule::{ Stage, SystemStage };
+    use bevy_ecs::world::World;
+    use uuid::Uuid;
+
+    fn item(item_id: i32) -> Slot {
+        Slot::Present { item_id, item_count: 1, nbt: nbt::Blob::new() }
+    }
+
+    #[test]
+    fn held_slot_selects_the_main_hand() {
+        let mut inventory = Inventory::player();
+        inventory.set_slot(PLAYER_HOTBAR_START + 3, item(7));
+        inventory.set_slot(5, item(8));
+        let mut equipment = EquipmentComponent::default();
+        assert!(!equipment.set_held_slot(9));
+        assert!(equipment.set_held_slot(3));
+
+        let slots = equipment.get_equipment(&inventory);
+        assert_eq!(slots[0].0, EquipmentSlot::MainHand);
+        assert_eq!(slots[0].1, item(7));
+        assert_eq!(slots[5].0, EquipmentSlot::Head);
+        assert_eq!(slots[5].1, item(8));
+        assert_eq!(slots[1].1, Slot::NotPresent);
+    }
+
+    #[tokio::test]
+    async fn main_hand_change_is_broadcast() {
+        let (viewer_client, _viewer_events, mut viewer_peer) = connected_client().await;
+        let (player_client, _player_events, _player_peer) = connected_client().await;
+
+        let mut world = World::default();
+        let viewer = world.spawn()
+            .insert(viewer_client)
+            .insert(LocationComponent(Location::default()))
+            .insert(PlayerVisibilityTracker::default())
+            .id();
+        let network_id = NetworkIdComponent::new();
+        let player = world.spawn()
+            .insert(network_id)
+            .insert(ObjectUuidComponent(Uuid::new_v4()))
+            .insert(player_client)
+            .insert(LocationComponent(Location { x: 5., ..Location::default() }))
+            .insert(InventoryComponent::default())
+            .insert(EquipmentComponent::default())
+            .id();
+        SystemStage::single(player_visibility_system).run(&mut world);
+        let mut stage = SystemStage::single(equipment_sync_system);
+        // The empty equipment isn't sent
+        stage.run(&mut world);
+
+        world.get_mut::<InventoryComponent>(player).unwrap().0.set_slot(PLAYER_HOTBAR_START + 2, item(4));
+        world.get_mut::<EquipmentComponent>(player).unwrap().set_held_slot(2);
+        stage.run(&mut world);
+        stage.run(&mut world);
+        // Received right after the equipment if it was only sent once
+        let marker = C38RemoveEntities { entities: vec![] };
+        world.get::<ClientComponent>(viewer).unwrap().0.send_packet_sync(&marker);
+
+        let packets = read_packets(&mut viewer_peer, 3).await;
+        assert_eq!(packets[0].packet_id, C02SpawnPlayer::PACKET_ID);
+        let expected = C50EntityEquipment {
+            entity_id: network_id.0,
+            equipment: vec![(EquipmentSlot::MainHand, item(4))],
+        }.to_rawpacket();
+        assert_eq!(packets[1].packet_id, C50EntityEquipment::PACKET_ID);
+        assert_eq!(packets[1].data, expected.data);
+        assert_eq!(packets[2].data, marker.to_rawpacket().data, "more than one equipment packet was sent");
+    }
+}
@@ -0,0 +1,213 @@
+use crate::entity::{
+    ClientComponent, ExperienceOrbComponent, LocationComponent, NetworkIdComponent,
+    RemoveScheduledComponent,
+};
+use crate::entity_pool::DEFAULT_ENTITY_VIEW_DISTANCE;
+use mc_networking::packets::client_bound::{
+    C01SpawnExperienceOrb, C51SetExperience, C62PickupItem, ClientBoundPacket,
+};
+use mc_utils::Location;
+
+use bevy_ecs::component::Component;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::query::{ Added, Without };
+use bevy_ecs::system::{ Commands, Query };
+
+/// Distance in blocks from which the orbs fly towards the players
+pub const EXPERIENCE_ORB_ATTRACTION_RADIUS: f64 = 8.;
+/// Distance in blocks under which an orb is picked up
+pub const EXPERIENCE_ORB_PICKUP_DISTANCE: f64 = 1.;
+/// Distance in blocks an attracted orb moves every tick
+pub const EXPERIENCE_ORB_SPEED: f64 = 0.3;
+
+/// Experience points of a player, needed to pick up experience orbs
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExperienceComponent {
+    pub total: i32,
+}
+
+impl ExperienceComponent {
+    /// Points needed to go from the level to the next one
+    fn level_points(level: i32) -> i32 {
+        match level {
+            0..=15 => 2 * level + 7,
+            16..=30 => 5 * level - 38,
+            _ => 9 * level - 158,
+        }
+    }
+
+    /// Level and points gained since the start of that level
+    fn level_and_remainder(&self) -> (i32, i32) {
+        let mut level = 0;
+        let mut remainder = self.total.max(0);
+        while remainder >= Self::level_points(level) {
+            remainder -= Self::level_points(level);
+            level += 1;
+        }
+        (level, remainder)
+    }
+
+    pub fn level(&self) -> i32 {
+        self.level_and_remainder().0
+    }
+
+    /// Part of the current level that has been completed, between 0 and 1
+    pub fn progress(&self) -> f32 {
+        let (level, remainder) = self.level_and_remainder();
+        remainder as f32 / Self::level_points(level) as f32
+    }
+
+    pub fn add(&mut self, points: i32) {
+        self.total = self.total.saturating_add(points);
+    }
+
+    pub fn to_packet(&self) -> C51SetExperience {
+        C51SetExperience {
+            experience_bar: self.progress(),
+            level: self.level(),
+            total_experience: self.total,
+        }
+    }
+}
+
+/// Spawns an experience orb giving `count` points, it is shown to the players by the
+/// [experience_orb_spawn_system]
+pub fn spawn_experience_orb(commands: &mut Commands, location: Location, count: i16) -> Entity {
+    commands.spawn()
+        .insert(NetworkIdComponent::new())
+        .insert(LocationComponent(location))
+        .insert(ExperienceOrbComponent { count })
+        .id()
+}
+
+/// Sends the new experience orbs to the players closer than [DEFAULT_ENTITY_VIEW_DISTANCE]
+/// Players getting closer later aren't sent the orb
+pub fn experience_orb_spawn_system(
+    orbs: Query<(&NetworkIdComponent, &LocationComponent, &ExperienceOrbComponent), Added<ExperienceOrbComponent>>,
+    players: Query<(&ClientComponent, &LocationComponent)>,
+) {
+    orbs.for_each(|(network_id, location, orb)| {
+        let packet = C01SpawnExperienceOrb {
+            entity_id: network_id.0,
+            x: location.0.x,
+            y: location.0.y,
+            z: location.0.z,
+            count: orb.count,
+        }.to_rawpacket();
+        players.for_each(|(client, player_location)| {
+            if player_location.0.distance2(location.0) <= DEFAULT_ENTITY_VIEW_DISTANCE.powi(2) {
+                client.0.send_raw_packet_sync(packet.clone());
+            }
+        });
+    });
+}
+
+/// Moves the experience orbs towards the closest player in their attraction radius,
+/// close enough orbs are picked up and removed
+pub fn experience_orb_system(
+    mut orbs: Query<
+        (Entity, &NetworkIdComponent, &ExperienceOrbComponent, &mut LocationComponent),
+        (Without<ClientComponent>, Without<RemoveScheduledComponent>)
+    >,
+    mut players: Query<(Entity, &NetworkIdComponent, &ClientComponent, &LocationComponent, &mut ExperienceComponent)>,
+    mut commands: Commands,
+) {
+    let attraction_radius2 = EXPERIENCE_ORB_ATTRACTION_RADIUS.powi(2);
+    orbs.for_each_mut(|(entity, orb_id, orb, mut location)| {
+        let closest = players.iter()
+            .map(|(player, _, _, player_location, _)| (player, player_location.0.distance2(location.0)))
+            .filter(|(_, distance2)| *distance2 <= attraction_radius2)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+        let (player, distance2) = match closest {
+            Some(closest) => closest,
+            None => return,
+        };
+        let (_, player_id, client, player_location, mut experience) = players.get_mut(player).unwrap();
+
+        let distance = distance2.sqrt();
+        if distance > EXPERIENCE_ORB_PICKUP_DISTANCE {
+            let step = EXPERIENCE_ORB_SPEED.min(distance) / distance;
+            let target = player_location.0;
+            location.0.x += (target.x - location.0.x) * step;
+            location.0.y += (target.y - location.0.y) * step;
+            location.0.z += (target.z - location.0.z) * step;
+            return;
+        }
+
+        experience.add(orb.count as i32);
+        client.0.send_packet_sync(&experience.to_packet());
+        let pickup = C62PickupItem {
+            collected_entity_id: orb_id.0,
+            collector_entity_id: player_id.0,
+            pickup_item_count: 1,
+        }.to_rawpacket();
+        let orb_location = location.0;
+        players.for_each(|(_, _, client, player_location, _)| {
+            if player_location.0.distance2(orb_location) <= DEFAULT_ENTITY_VIEW_DISTANCE.powi(2) {
+                client.0.send_raw_packet_sync(pickup.clone());
+            }
+        });
+        commands.entity(entity).insert(RemoveScheduledComponent);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::connected_client;
+
+    use bevy_ecs::schedule::{ Stage, SystemStage };
+    use bevy_ecs::world::World;
+
+    #[test]
+    fn experience_levels() {
+        let experience = |total| ExperienceComponent { total };
+        assert_eq!(experience(0).level(), 0);
+        assert_eq!(experience(6).level(), 0);
+        assert_eq!(experience(7).level(), 1);
+        assert_eq!(experience(7).progress(), 0.);
+        assert_eq!(experience(352).level(), 16);
+        assert_eq!(experience(1395).level(), 30);
+        assert_eq!(experience(1507).level(), 31);
+        let packet = experience(8).to_packet();
+        assert_eq!((packet.level, packet.total_experience), (1, 8));
+        assert_eq!(packet.experience_bar, 1. / 9.);
+    }
+
+    #[tokio::test]
+    async fn orbs_fly_towards_players() {
+        let (client, _events, _peer) = connected_client().await;
+
+        let mut world = World::default();
+        let player = world.spawn()
+            .insert(NetworkIdComponent::new())
+            .insert(client)
+            .insert(LocationComponent(Location::default()))
+            .insert(ExperienceComponent::default())
+            .id();
+        let orb = world.spawn()
+            .insert(NetworkIdComponent::new())
+            .insert(LocationComponent(Location { x: 5., ..Location::default() }))
+            .insert(ExperienceOrbComponent { count: 3 })
+            .id();
+        let far_orb = world.spawn()
+            .insert(NetworkIdComponent::new())
+            .insert(LocationComponent(Location { x: -20., ..Location::default() }))
+            .insert(ExperienceOrbComponent { count: 3 })
+            .id();
+        let mut stage = SystemStage::single(experience_orb_system);
+
+        let mut previous_x = 5.;
+        while world.get::<RemoveScheduledComponent>(orb).is_none() {
+            stage.run(&mut world);
+            let x = world.get::<LocationComponent>(orb).unwrap().0.x;
+            if world.get::<RemoveScheduledComponent>(orb).is_none() {
+                assert!(x < previous_x, "the orb didn't get closer");
+            }
+            previous_x = x;
+        }
+        assert_eq!(world.get::<ExperienceComponent>(player).unwrap().total, 3);
+        assert_eq!(world.get::<LocationComponent>(far_orb).unwrap().0.x, -20.);
+        assert!(world.get::<RemoveScheduledComponent>(far_orb).is_none());
+    }
+}
@@ -0,0 +1,81 @@
+use crate::entity::ClientComponent;
+
+use std::time::Duration;
+
+use bevy_ecs::component::Component;
+use bevy_ecs::system::Query;
+
+/// Reason shown to the players disconnected by the [idle_timeout_system]
+pub const TIMED_OUT_REASON: &str = "Timed out";
+
+/// Disconnects the player if it doesn't send any packet for the given duration, closing the
+/// half-open connections the keep alives alone can't detect
+/// Given by the [crate::mc_app::LoginPipeline::with_idle_timeout]
+#[derive(Component, Debug, Clone, Copy)]
+pub struct IdleTimeoutComponent(pub Duration);
+
+impl IdleTimeoutComponent {
+    /// Whether the client didn't send anything for longer than the timeout
+    pub fn is_idle(&self, client: &ClientComponent) -> bool {
+        client.0.last_packet_received().elapsed() >= self.0
+    }
+}
+
+/// Disconnects the idle players with the [TIMED_OUT_REASON]
+pub fn idle_timeout_system(query: Query<(&ClientComponent, &IdleTimeoutComponent)>) {
+    query.for_each(|(client, idle_timeout)| {
+        if idle_timeout.is_idle(client) {
+            client.0.disconnect_sync(TIMED_OUT_REASON);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::connected_client;
+    use mc_networking::client::ClientState;
+    use mc_networking::client::client_event::ClientEvent;
+    use mc_networking::data_types::encoder::PacketEncoder;
+    use mc_networking::packets::{ PacketCompression, RawPacket };
+
+    use bevy_ecs::schedule::{ Stage, SystemStage };
+    use bevy_ecs::world::World;
+    use bytes::BytesMut;
+    use tokio::io::AsyncWriteExt;
+    use tokio::time::sleep;
+
+    #[tokio::test]
+    async fn silent_client_times_out() {
+        let (ClientComponent(client), event_receiver, mut peer) = connected_client().await;
+
+        let mut world = World::default();
+        world.spawn()
+            .insert(ClientComponent(client.clone()))
+            .insert(IdleTimeoutComponent(Duration::from_millis(200)));
+        let mut stage = SystemStage::single(idle_timeout_system);
+
+        // A status handshake resets the idle time
+        sleep(Duration::from_millis(120)).await;
+        let mut handshake = PacketEncoder::default();
+        handshake.write_varint(759);
+        handshake.write_string("localhost");
+        handshake.write_u16(25565);
+        handshake.write_varint(1);
+        let mut bytes = BytesMut::new();
+        RawPacket::new(0x00, handshake.into_inner().freeze()).encode(PacketCompression::default(), &mut bytes);
+        peer.write_all(&bytes).await.unwrap();
+        while client.get_state().await != ClientState::Status {
+            sleep(Duration::from_millis(1)).await;
+        }
+
+        sleep(Duration::from_millis(120)).await;
+        stage.run(&mut world);
+        assert_eq!(client.get_state().await, ClientState::Status);
+
+        sleep(Duration::from_millis(120)).await;
+        stage.run(&mut world);
+        assert_eq!(client.get_state().await, ClientState::Disconnected);
+        assert!(matches!(event_receiver.recv_async().await, Ok(ClientEvent::Logout)));
+    }
+}
@@ -0,0 +1,235 @@
+use crate::entity::{
+    ClientComponent, LocationComponent, NetworkIdComponent, ObjectUuidComponent,
+    RemoveScheduledComponent,
+};
+use crate::entity_pool::DEFAULT_ENTITY_VIEW_DISTANCE;
+use crate::inventory::InventoryComponent;
+use mc_networking::data_types::{ MetadataValue, Slot };
+use mc_networking::packets::client_bound::{
+    C00SpawnEntity, C4DSetEntityMetadata, C62PickupItem, ClientBoundPacket,
+};
+use mc_networking::packets::RawPacket;
+use mc_utils::Location;
+
+use std::collections::HashMap;
+
+use bevy_ecs::component::Component;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::query::{ Added, Without };
+use bevy_ecs::system::{ Commands, Query };
+use uuid::Uuid;
+
+/// Entity type id of the dropped items
+pub const ITEM_ENTITY_KIND: i32 = 44;
+/// Index of the item in the metadata of dropped items
+pub const ITEM_SLOT_METADATA_INDEX: u8 = 8;
+/// Distance in blocks under which a player picks up an item
+pub const ITEM_PICKUP_DISTANCE: f64 = 1.;
+/// Ticks before an item dropped by a player can be picked up, like in vanilla
+pub const DEFAULT_PICKUP_DELAY: u32 = 40;
+
+/// Item lying on the ground
+#[derive(Component, Debug, Clone)]
+pub struct ItemEntityComponent {
+    pub slot: Slot,
+    /// Ticks before the item can be picked up
+    pub pickup_delay: u32,
+}
+
+impl ItemEntityComponent {
+    pub fn metadata_packet(&self, network_id: &NetworkIdComponent) -> C4DSetEntityMetadata {
+        let mut metadata = HashMap::new();
+        metadata.insert(ITEM_SLOT_METADATA_INDEX, MetadataValue::Slot(self.slot.clone()));
+        C4DSetEntityMetadata {
+            entity_id: network_id.0,
+            metadata,
+        }
+    }
+
+    /// Spawn packet followed by the metadata holding the item, the client shows a stone block
+    /// until it receives the metadata
+    pub fn spawn_packets(
+        &self, network_id: &NetworkIdComponent, uuid: &ObjectUuidComponent, location: &Location,
+    ) -> [RawPacket; 2] {
+        [
+            C00SpawnEntity {
+                entity_id: network_id.0,
+                object_uuid: uuid.0,
+                kind: ITEM_ENTITY_KIND,
+                x: location.x,
+                y: location.y,
+                z: location.z,
+                pitch: 0,
+                yaw: 0,
+                head_yaw: 0,
+                data: 0,
+                velocity_x: 0,
+                velocity_y: 0,
+                velocity_z: 0,
+            }.to_rawpacket(),
+            self.metadata_packet(network_id).to_rawpacket(),
+        ]
+    }
+}
+
+/// Spawns a dropped item, it is shown to the players by the [item_spawn_system]
+pub fn spawn_item(commands: &mut Commands, location: Location, slot: Slot, pickup_delay: u32) -> Entity {
+    commands.spawn()
+        .insert(NetworkIdComponent::new())
+        .insert(ObjectUuidComponent(Uuid::new_v4()))
+        .insert(LocationComponent(location))
+        .insert(ItemEntityComponent { slot, pickup_delay })
+        .id()
+}
+
+/// Sends the new dropped items to the players closer than [DEFAULT_ENTITY_VIEW_DISTANCE]
+/// Players getting closer later aren't sent the item
+pub fn item_spawn_system(
+    items: Query<
+        (&NetworkIdComponent, &ObjectUuidComponent, &LocationComponent, &ItemEntityComponent),
+        Added<ItemEntityComponent>
+    >,
+    players: Query<(&ClientComponent, &LocationComponent)>,
+) {
+    items.for_each(|(network_id, uuid, location, item)| {
+        let packets = item.spawn_packets(network_id, uuid, &location.0);
+        players.for_each(|(client, player_location)| {
+            if player_location.0.distance2(location.0) > DEFAULT_ENTITY_VIEW_DISTANCE.powi(2) {
+                return;
+            }
+            for packet in &packets {
+                client.0.send_raw_packet_sync(packet.clone());
+            }
+        });
+    });
+}
+
+/// Adds the dropped items to the inventory of the players touching them once their
+/// pickup delay is over, the items are removed once all of it has been picked up
+pub fn item_pickup_system(
+    mut items: Query<
+        (Entity, &NetworkIdComponent, &LocationComponent, &mut ItemEntityComponent),
+        (Without<ClientComponent>, Without<RemoveScheduledComponent>)
+    >,
+    mut players: Query<(&NetworkIdComponent, &ClientComponent, &LocationComponent, &mut InventoryComponent)>,
+    mut commands: Commands,
+) {
+    let pickup_distance2 = ITEM_PICKUP_DISTANCE.powi(2);
+    items.for_each_mut(|(entity, item_id, location, mut item)| {
+        if item.pickup_delay > 0 {
+            item.pickup_delay -= 1;
+            return;
+        }
+
+        let mut collected = vec![];
+        for (player_id, client, player_location, mut inventory) in players.iter_mut() {
+            if !item.slot.is_present() {
+                break;
+            }
+            if player_location.0.distance2(location.0) > pickup_distance2 {
+                continue;
+            }
+            let count_before = item.slot.item_count();
+            let slot_updates = inventory.0.add_item(&mut item.slot);
+            for slot_update in &slot_updates {
+                client.0.send_packet_sync(slot_update);
+            }
+            if !slot_updates.is_empty() {
+                collected.push((player_id.0, count_before - item.slot.item_count()));
+            }
+        }
+        if collected.is_empty() {
+            return;
+        }
+
+        let mut packets = collected.into_iter()
+            .map(|(collector, count)| C62PickupItem {
+                collected_entity_id: item_id.0,
+                collector_entity_id: collector,
+                pickup_item_count: count as i32,
+            }.to_rawpacket())
+            .collect::<Vec<_>>();
+        if item.slot.is_present() {
+            packets.push(item.metadata_packet(item_id).to_rawpacket());
+        } else {
+            commands.entity(entity).insert(RemoveScheduledComponent);
+        }
+        players.for_each(|(_, client, player_location, _)| {
+            if player_location.0.distance2(location.0) > DEFAULT_ENTITY_VIEW_DISTANCE.powi(2) {
+                return;
+            }
+            for packet in &packets {
+                client.0.send_raw_packet_sync(packet.clone());
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::PLAYER_HOTBAR_START;
+    use crate::test_utils::connected_client;
+    use mc_networking::data_types::encoder::PacketEncoder;
+
+    use bevy_ecs::schedule::{ Stage, SystemStage };
+    use bevy_ecs::world::World;
+
+    fn item(item_id: i32, item_count: u8) -> Slot {
+        Slot::Present { item_id, item_count, nbt: nbt::Blob::new() }
+    }
+
+    #[test]
+    fn spawn_metadata_holds_the_slot() {
+        let network_id = NetworkIdComponent::new();
+        let uuid = ObjectUuidComponent(Uuid::new_v4());
+        let item_entity = ItemEntityComponent { slot: item(5, 12), pickup_delay: 0 };
+
+        let [spawn, metadata] = item_entity.spawn_packets(&network_id, &uuid, &Location::default());
+        assert_eq!(spawn.packet_id, C00SpawnEntity::PACKET_ID);
+        assert_eq!(metadata.packet_id, C4DSetEntityMetadata::PACKET_ID);
+
+        let mut encoder = PacketEncoder::default();
+        encoder.write_varint(network_id.0);
+        encoder.write_u8(ITEM_SLOT_METADATA_INDEX);
+        encoder.write_bytes(&MetadataValue::Slot(item(5, 12)).encode());
+        encoder.write_u8(0xFF);
+        assert_eq!(metadata.data, encoder.into_inner().freeze());
+    }
+
+    #[tokio::test]
+    async fn items_are_picked_up() {
+        let (client, _events, _peer) = connected_client().await;
+
+        let mut world = World::default();
+        let player = world.spawn()
+            .insert(NetworkIdComponent::new())
+            .insert(client)
+            .insert(LocationComponent(Location::default()))
+            .insert(InventoryComponent::default())
+            .id();
+        let close = world.spawn()
+            .insert(NetworkIdComponent::new())
+            .insert(LocationComponent(Location { x: 0.5, ..Location::default() }))
+            .insert(ItemEntityComponent { slot: item(5, 12), pickup_delay: 1 })
+            .id();
+        let far = world.spawn()
+            .insert(NetworkIdComponent::new())
+            .insert(LocationComponent(Location { x: 10., ..Location::default() }))
+            .insert(ItemEntityComponent { slot: item(6, 1), pickup_delay: 0 })
+            .id();
+        let mut stage = SystemStage::single(item_pickup_system);
+
+        // Still waiting for the pickup delay
+        stage.run(&mut world);
+        assert!(world.get::<RemoveScheduledComponent>(close).is_none());
+
+        stage.run(&mut world);
+        assert!(world.get::<RemoveScheduledComponent>(close).is_some());
+        assert_eq!(world.get::<ItemEntityComponent>(close).unwrap().slot, Slot::NotPresent);
+        let inventory = &world.get::<InventoryComponent>(player).unwrap().0;
+        assert_eq!(inventory.get_slot(PLAYER_HOTBAR_START), Some(&item(5, 12)));
+        assert!(world.get::<RemoveScheduledComponent>(far).is_none());
+        assert_eq!(world.get::<ItemEntityComponent>(far).unwrap().slot, item(6, 1));
+    }
+}
@@ -0,0 +1,181 @@
+use crate::entity::{
+    ClientComponent, LivingEntityComponent, NetworkIdComponent, RemoveScheduledComponent,
+    metadata::MetadataComponent,
+};
+use crate::entity_pool::ViewDistanceComponent;
+use mc_networking::data_types::MetadataValue;
+use mc_networking::packets::client_bound::{
+    C03EntityAnimation, C52SetHealth, ClientBoundPacket, EntityAnimation,
+};
+
+use bevy_ecs::component::Component;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::query::Changed;
+use bevy_ecs::system::{ Commands, Query };
+
+/// Metadata index of the health of living entities
+pub const HEALTH_METADATA_INDEX: u8 = 9;
+
+/// Height in blocks a player can fall from without taking damage
+pub const SAFE_FALL_DISTANCE: f64 = 3.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageSource {
+    Entity(Entity),
+    Fall,
+    Void,
+    Generic,
+}
+
+/// Damage taken when landing after falling the given distance, one health point per block
+/// fallen above [SAFE_FALL_DISTANCE]
+pub fn fall_damage(fall_distance: f64) -> f32 {
+    (fall_distance - SAFE_FALL_DISTANCE).ceil().max(0.) as f32
+}
+
+/// Distance fallen by a player, tracked from the positions sent by its client
+#[derive(Component, Debug, Clone, Default)]
+pub struct FallComponent {
+    pub fall_distance: f64,
+    last_y: Option<f64>,
+}
+impl FallComponent {
+    /// Accumulates the fall from a new position of the player
+    /// `cancels_fall` is whether the player is in a block that stops falls like water or ladders
+    /// Returns the damage taken if the player just landed
+    pub fn update(&mut self, y: f64, on_ground: bool, cancels_fall: bool) -> Option<f32> {
+        if let Some(last_y) = self.last_y.filter(|last_y| *last_y > y) {
+            self.fall_distance += last_y - y;
+        }
+        self.last_y = Some(y);
+
+        if cancels_fall {
+            self.fall_distance = 0.;
+            return None;
+        }
+        if !on_ground {
+            return None;
+        }
+        let damage = fall_damage(self.fall_distance);
+        self.fall_distance = 0.;
+        Some(damage).filter(|damage| *damage > 0.)
+    }
+}
+
+/// Sends the damages taken by the living entities during the tick
+/// The hurt animation is played for the viewers of the entity and players are sent their health
+/// Dead entities that aren't players are scheduled for removal
+pub fn living_entity_damage_system(
+    mut query: Query<
+        (
+            Entity, &NetworkIdComponent, &mut LivingEntityComponent,
+            Option<&mut MetadataComponent>, Option<&ViewDistanceComponent>, Option<&ClientComponent>,
+        ),
+        Changed<LivingEntityComponent>
+    >,
+    clients: Query<&ClientComponent>,
+    mut commands: Commands,
+) {
+    query.for_each_mut(|(entity, network_id, mut living, metadata, visibility, own_client)| {
+        if living.take_last_damage().is_none() {
+            return;
+        }
+
+        let animation = C03EntityAnimation::new(network_id.0, EntityAnimation::TakeDamage).to_rawpacket();
+        for viewer in visibility.into_iter().flat_map(|visibility| visibility.viewers()) {
+            if let Ok(client) = clients.get(viewer) {
+                client.0.send_raw_packet_sync(animation.clone());
+            }
+        }
+        if let Some(mut metadata) = metadata {
+            metadata.0.insert(HEALTH_METADATA_INDEX, MetadataValue::Float(living.health));
+        }
+
+        match own_client {
+            Some(client) => client.0.send_packet_sync(&C52SetHealth {
+                health: living.health,
+                food: 20,
+                food_saturation: 5.,
+            }),
+            None if living.is_dead() => {
+                commands.entity(entity).insert(RemoveScheduledComponent);
+            }
+            None => (),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs::schedule::{ Stage, SystemStage };
+    use bevy_ecs::world::World;
+
+    #[test]
+    fn damage_reduces_health() {
+        let mut living = LivingEntityComponent::new(20.);
+        living.damage(4.5, DamageSource::Generic);
+        assert_eq!(living.health, 15.5);
+        assert_eq!(living.last_damage(), Some(&DamageSource::Generic));
+        assert!(!living.is_dead());
+
+        living.damage(100., DamageSource::Void);
+        assert_eq!(living.health, 0.);
+        assert!(living.is_dead());
+        living.damage(1., DamageSource::Fall);
+        assert_eq!(living.last_damage(), Some(&DamageSource::Void));
+    }
+
+    #[test]
+    fn lethal_hit_schedules_removal() {
+        let mut world = World::default();
+        let attacker = world.spawn().id();
+        let entity = world.spawn()
+            .insert(NetworkIdComponent::new())
+            .insert(LivingEntityComponent::new(10.))
+            .insert(MetadataComponent::default())
+            .id();
+        let mut stage = SystemStage::single(living_entity_damage_system);
+
+        world.get_mut::<LivingEntityComponent>(entity).unwrap()
+            .damage(4., DamageSource::Entity(attacker));
+        stage.run(&mut world);
+        assert!(world.get::<RemoveScheduledComponent>(entity).is_none());
+        assert!(matches!(
+            world.get::<MetadataComponent>(entity).unwrap().0.get(&HEALTH_METADATA_INDEX),
+            Some(MetadataValue::Float(health)) if *health == 6.
+        ));
+
+        world.get_mut::<LivingEntityComponent>(entity).unwrap()
+            .damage(6., DamageSource::Entity(attacker));
+        stage.run(&mut world);
+        assert!(world.get::<RemoveScheduledComponent>(entity).is_some());
+        assert!(matches!(
+            world.get::<MetadataComponent>(entity).unwrap().0.get(&HEALTH_METADATA_INDEX),
+            Some(MetadataValue::Float(health)) if *health == 0.
+        ));
+    }
+
+    #[test]
+    fn ten_blocks_fall_damage() {
+        let mut fall = FallComponent::default();
+        let mut living = LivingEntityComponent::new(20.);
+        fall.update(74., true, false);
+        for step in 1..10 {
+            assert_eq!(fall.update(74. - step as f64, false, false), None);
+        }
+        let damage = fall.update(64., true, false).unwrap();
+        assert_eq!(damage, 7.);
+        living.damage(damage, DamageSource::Fall);
+        assert_eq!(living.health, 13.);
+
+        // Short falls are harmless
+        fall.update(67., false, false);
+        assert_eq!(fall.update(64., true, false), None);
+
+        // Landing in water cancels the fall
+        fall.update(84., false, false);
+        assert_eq!(fall.update(70., false, true), None);
+        assert_eq!(fall.update(69.5, true, false), None);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::entity::{ ClientComponent, LocationComponent, NetworkIdComponent };
+use crate::spatial_index::SpatialIndex;
+use mc_networking::data_types::{ MetadataValue, ENTITY_FLAGS_METADATA_INDEX, GLOWING_FLAG };
+use mc_networking::packets::{ client_bound::{ C4DSetEntityMetadata, ClientBoundPacket }, RawPacket };
+
+use std::collections::HashMap;
+
+use bevy_ecs::component::Component;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::query::Changed;
+use bevy_ecs::system::{ Query, Res };
+
+/// Distance in blocks up to which players receive metadata updates
+pub const METADATA_VIEW_DISTANCE: f64 = 128.;
+
+/// Metadata of an entity, indexed by the metadata field index
+/// Every change to it is sent to the nearby players by the [metadata_sync_system]
+#[derive(Component, Debug, Clone, Default)]
+pub struct MetadataComponent(pub HashMap<u8, MetadataValue>);
+impl MetadataComponent {
+    /// Sets or clears the given bits of the entity flags, keeping the other flags
+    pub fn set_flag(&mut self, mask: u8, value: bool) {
+        let flags = match self.0.get(&ENTITY_FLAGS_METADATA_INDEX) {
+            Some(MetadataValue::Byte(flags)) => *flags,
+            _ => 0,
+        };
+        let flags = if value { flags | mask } else { flags & !mask };
+        self.0.insert(ENTITY_FLAGS_METADATA_INDEX, MetadataValue::Byte(flags));
+    }
+
+    pub fn has_flag(&self, mask: u8) -> bool {
+        matches!(self.0.get(&ENTITY_FLAGS_METADATA_INDEX), Some(MetadataValue::Byte(flags)) if flags & mask == mask)
+    }
+
+    /// Outlines the entity, the change is sent by the [metadata_sync_system]
+    /// The outline is white unless the entity is in a team with a color, the client colors it
+    /// with the color sent for the team in the Update Teams packet
+    pub fn set_glowing(&mut self, glowing: bool) {
+        self.set_flag(GLOWING_FLAG, glowing);
+    }
+
+    pub fn is_glowing(&self) -> bool {
+        self.has_flag(GLOWING_FLAG)
+    }
+
+    pub fn to_packet(&self, network_id: &NetworkIdComponent) -> C4DSetEntityMetadata {
+        C4DSetEntityMetadata {
+            entity_id: network_id.0,
+            metadata: self.0.clone(),
+        }
+    }
+}
+
+/// Sends the changed metadata of every entity to the players close to it
+pub fn metadata_sync_system(
+    query: Query<
+        (Entity, &NetworkIdComponent, &LocationComponent, &MetadataComponent),
+        Changed<MetadataComponent>
+    >,
+    clients: Query<&ClientComponent>,
+    index: Res<SpatialIndex>,
+) {
+    query.for_each(|(entity, network_id, location, metadata)| {
+        if metadata.0.is_empty() {
+            return;
+        }
+        let packet: RawPacket = metadata.to_packet(network_id).to_rawpacket();
+        for nearby in index.query_around(&location.0, METADATA_VIEW_DISTANCE) {
+            if nearby == entity {
+                continue;
+            }
+            if let Ok(client) = clients.get(nearby) {
+                client.0.send_raw_packet_sync(packet.clone());
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mc_app::McApp;
+    use crate::test_utils::{ connected_client, read_packets_until };
+    use mc_networking::packets::client_bound::C38RemoveEntities;
+    use mc_utils::Location;
+
+    #[tokio::test]
+    async fn changed_metadata_is_sent_to_close_players() {
+        let (close_client, _close_events, mut close_peer) = connected_client().await;
+        let (far_client, _far_events, mut far_peer) = connected_client().await;
+        let mut app = McApp::new();
+        let close = app.world.spawn()
+            .insert(close_client)
+            .insert(LocationComponent(Location { x: 10., ..Location::default() }))
+            .id();
+        let far = app.world.spawn()
+            .insert(far_client)
+            .insert(LocationComponent(Location { x: METADATA_VIEW_DISTANCE + 10., ..Location::default() }))
+            .id();
+        let entity = app.world.spawn()
+            .insert(NetworkIdComponent::new())
+            .insert(LocationComponent(Location::default()))
+            .insert(MetadataComponent::default())
+            .id();
+
+        // Empty and unchanged metadata isn't sent
+        app.tick();
+        app.tick();
+        app.world.get_mut::<MetadataComponent>(entity).unwrap().0
+            .insert(0, MetadataValue::Byte(0x20));
+        app.tick();
+        app.tick();
+        app.world.get_mut::<MetadataComponent>(entity).unwrap().set_glowing(true);
+        app.tick();
+
+        let marker = C38RemoveEntities { entities: vec![] };
+        for player in [close, far] {
+            app.world.get::<ClientComponent>(player).unwrap().0.send_packet_sync(&marker);
+        }
+        let metadata_packets = |packets: Vec<RawPacket>| packets.into_iter()
+            .filter(|packet| packet.packet_id == C4DSetEntityMetadata::PACKET_ID)
+            .map(|packet| packet.data)
+            .collect::<Vec<_>>();
+        let close_packets = metadata_packets(read_packets_until(&mut close_peer, C38RemoveEntities::PACKET_ID).await);
+        assert_eq!(close_packets.len(), 2);
+        // Index, type, value then the 0xFF terminator
+        assert!(close_packets[0].ends_with(&[0, 0, 0x20, 0xFF]));
+        // The invisible flag is kept
+        assert!(close_packets[1].ends_with(&[0, 0, 0x60, 0xFF]));
+        assert!(metadata_packets(read_packets_until(&mut far_peer, C38RemoveEntities::PACKET_ID).await).is_empty());
+    }
+
+    #[test]
+    fn glowing_flag() {
+        let mut metadata = MetadataComponent::default();
+        assert!(!metadata.is_glowing());
+        metadata.set_glowing(true);
+        assert!(metadata.is_glowing());
+        assert!(matches!(metadata.0.get(&ENTITY_FLAGS_METADATA_INDEX), Some(MetadataValue::Byte(0x40))));
+
+        metadata.set_flag(0x02, true);
+        metadata.set_glowing(false);
+        assert!(!metadata.is_glowing());
+        assert!(matches!(metadata.0.get(&ENTITY_FLAGS_METADATA_INDEX), Some(MetadataValue::Byte(0x02))));
+    }
+}
@@ -1,14 +1,29 @@
+pub mod ai;
 pub mod chunk;
+pub mod collision;
+pub mod equipment;
+pub mod experience;
+pub mod idle_timeout;
+pub mod item;
+pub mod living;
+pub mod metadata;
+pub mod movement;
+pub mod movement_validation;
+pub mod physics;
+pub mod ping;
+pub mod player_visibility;
+pub mod teleport;
 
+use living::DamageSource;
 use mc_networking::client::Client;
-use mc_utils::Location;
+use mc_utils::{ ChunkData, Location };
 
 use std::sync::atomic::{AtomicI32, Ordering};
 
 use uuid::Uuid;
 use bevy_ecs::component::Component;
 
-const NETWORK_ID_COUNTER: AtomicI32 = AtomicI32::new(0);
+static NETWORK_ID_COUNTER: AtomicI32 = AtomicI32::new(0);
 
 #[derive(Component, Clone, Copy, Debug)]
 #[readonly::make]
@@ -28,9 +43,52 @@ pub struct MobKindComponent(pub i32);
 #[derive(Component)]
 pub struct ObjectUuidComponent(pub Uuid);
 
-#[derive(Component)]
-pub struct LivingEntityComponent;
+/// Health of a living entity, damages are sent to the clients by the
+/// [living::living_entity_damage_system]
+#[derive(Component, Debug, Clone)]
+pub struct LivingEntityComponent {
+    pub health: f32,
+    pub max_health: f32,
+    /// Source of the last damage not yet sent to the clients
+    last_damage: Option<DamageSource>,
+}
+impl LivingEntityComponent {
+    pub fn new(max_health: f32) -> Self {
+        Self {
+            health: max_health,
+            max_health,
+            last_damage: None,
+        }
+    }
+
+    /// Removes health from the entity, dead entities can't be damaged
+    pub fn damage(&mut self, amount: f32, source: DamageSource) {
+        if self.is_dead() {
+            return;
+        }
+        self.health = (self.health - amount).max(0.);
+        self.last_damage = Some(source);
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health <= 0.
+    }
+
+    pub fn last_damage(&self) -> Option<&DamageSource> {
+        self.last_damage.as_ref()
+    }
+
+    fn take_last_damage(&mut self) -> Option<DamageSource> {
+        self.last_damage.take()
+    }
+}
+
+/// Marks an entity to be despawned by the [crate::entity_pool::remove_scheduled_system]
+#[derive(Component, Debug, Clone, Copy)]
+pub struct RemoveScheduledComponent;
 
+/// Experience orb giving `count` points, attracted and picked up by the
+/// [experience::experience_orb_system]
 #[derive(Component)]
 pub struct ExperienceOrbComponent {
     pub count: i16,
@@ -38,9 +96,39 @@ pub struct ExperienceOrbComponent {
 
 #[derive(Component)]
 pub struct ClientComponent(pub Client);
+impl ClientComponent {
+    /// Sends a chunk with its heightmaps and light, see [ChunkData::encode_for_world]
+    /// The center chunk of the player is updated by the [chunk::ChunkObserverComponent] systems
+    pub fn send_chunk(&self, chunk_x: i32, chunk_z: i32, chunk: &ChunkData, world_height: i32) {
+        self.0.send_packet_sync(&chunk.encode_for_world(chunk_x, chunk_z, world_height));
+    }
+}
 
 #[derive(Component)]
 pub struct UsernameComponent(pub String);
 
 #[derive(Component)]
 pub struct CustomNameComponent(pub serde_json::Value);
+
+/// Permission level of a player, from 0 (everyone) to 4 (server owner)
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct PermissionLevelComponent(pub u8);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn network_ids_are_unique() {
+        let threads = (0..4)
+            .map(|_| std::thread::spawn(|| {
+                (0..250).map(|_| NetworkIdComponent::new().0).collect::<Vec<_>>()
+            }))
+            .collect::<Vec<_>>();
+        let ids = threads.into_iter()
+            .flat_map(|thread| thread.join().unwrap())
+            .collect::<HashSet<_>>();
+        assert_eq!(ids.len(), 1000);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::entity::{ ClientComponent, LocationComponent, NetworkIdComponent };
+use mc_networking::packets::{ client_bound::*, RawPacket };
+use mc_utils::Location;
+
+use smallvec::{ smallvec, SmallVec };
+use bevy_ecs::component::Component;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::query::Changed;
+use bevy_ecs::system::{ Query, Commands };
+
+/// Last location of an entity sent to the clients by the [entity_movement_system]
+/// Automatically added to entities with a [NetworkIdComponent] and a [LocationComponent]
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PreviousLocationComponent(pub Location);
+
+/// Position as sent in movement packets deltas
+fn fixed_point(v: f64) -> i64 {
+    (v * 4096.).round() as i64
+}
+
+/// Packets updating the clients' view of an entity that moved from `previous` to `current`
+/// The entity is teleported if it moved 8 or more blocks on any axis
+pub fn movement_packets(
+    entity_id: i32, previous: &Location, current: &Location, on_ground: bool,
+) -> SmallVec<[RawPacket; 2]> {
+    let deltas = [
+        fixed_point(current.x) - fixed_point(previous.x),
+        fixed_point(current.y) - fixed_point(previous.y),
+        fixed_point(current.z) - fixed_point(previous.z),
+    ];
+    let moved = deltas != [0; 3];
+    let rotated = previous.yaw_angle() != current.yaw_angle()
+        || previous.pitch_angle() != current.pitch_angle();
+    let too_far = deltas.iter().any(|d| *d < i16::MIN as i64 || *d > i16::MAX as i64);
+
+    let mut packets: SmallVec<[RawPacket; 2]> = if too_far {
+        smallvec![C63TeleportEntity {
+            entity_id,
+            x: current.x,
+            y: current.y,
+            z: current.z,
+            yaw: current.yaw_angle(),
+            pitch: current.pitch_angle(),
+            on_ground,
+        }.to_rawpacket()]
+    }
+    else if moved && rotated {
+        smallvec![C27UpdateEntityPositionAndRotation {
+            entity_id,
+            delta_x: deltas[0] as i16,
+            delta_y: deltas[1] as i16,
+            delta_z: deltas[2] as i16,
+            yaw: current.yaw_angle(),
+            pitch: current.pitch_angle(),
+            on_ground,
+        }.to_rawpacket()]
+    }
+    else if moved {
+        smallvec![C26UpdateEntityPosition {
+            entity_id,
+            delta_x: deltas[0] as i16,
+            delta_y: deltas[1] as i16,
+            delta_z: deltas[2] as i16,
+            on_ground,
+        }.to_rawpacket()]
+    }
+    else if rotated {
+        smallvec![C28UpdateEntityRotation {
+            entity_id,
+            yaw: current.yaw_angle(),
+            pitch: current.pitch_angle(),
+            on_ground,
+        }.to_rawpacket()]
+    }
+    else {
+        smallvec![]
+    };
+
+    if rotated {
+        packets.push(C3CSetHeadRotation {
+            entity_id,
+            head_yaw: current.yaw_angle(),
+        }.to_rawpacket());
+    }
+    packets
+}
+
+/// Sends the movements of every entity to all clients except the moving entity's own
+pub fn entity_movement_system(
+    mut query: Query<
+        (Entity, &NetworkIdComponent, &LocationComponent, Option<&mut PreviousLocationComponent>),
+        Changed<LocationComponent>
+    >,
+    clients: Query<(Entity, &ClientComponent)>,
+    mut commands: Commands,
+) {
+    query.for_each_mut(|(entity, network_id, location, previous)| {
+        let mut previous = match previous {
+            Some(previous) => previous,
+            // The spawn packet already has the entity's location
+            None => {
+                commands.entity(entity).insert(PreviousLocationComponent(location.0));
+                return;
+            }
+        };
+
+        let packets = movement_packets(network_id.0, &previous.0, &location.0, false);
+        previous.0 = location.0;
+        if packets.is_empty() {
+            return;
+        }
+        clients.for_each(|(client_entity, client)| {
+            if client_entity == entity {
+                return;
+            }
+            for packet in &packets {
+                client.0.send_raw_packet_sync(packet.clone());
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn movement_packets_kind() {
+        let previous = Location { x: 0.5, y: 64., z: 0.5, ..Location::default() };
+
+        let packets = movement_packets(1, &previous, &Location { x: 2.5, ..previous }, true);
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].packet_id, C26UpdateEntityPosition::PACKET_ID);
+        // 2 blocks as (currentX * 32 - prevX * 32) * 128
+        assert_eq!(&packets[0].data[1..3], &(2i16 * 32 * 128).to_be_bytes());
+
+        let packets = movement_packets(1, &previous, &Location { z: 20.5, ..previous }, true);
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].packet_id, C63TeleportEntity::PACKET_ID);
+
+        let packets = movement_packets(1, &previous, &Location { yaw: 90., ..previous }, true);
+        let ids = packets.iter().map(|p| p.packet_id).collect::<Vec<_>>();
+        assert_eq!(ids, [C28UpdateEntityRotation::PACKET_ID, C3CSetHeadRotation::PACKET_ID]);
+
+        assert!(movement_packets(1, &previous, &previous, true).is_empty());
+    }
+}
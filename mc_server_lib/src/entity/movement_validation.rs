@@ -0,0 +1,96 @@
+use crate::entity::{ ClientComponent, LocationComponent };
+use crate::entity::teleport::TeleportComponent;
+use mc_networking::packets::client_bound::RelativeFlags;
+use mc_utils::Location;
+
+use bevy_ecs::component::Component;
+
+/// Distance in blocks a player can move in a tick with the [MaxSpeedValidator::default]
+/// Falling players can go close to 4 blocks per tick
+pub const DEFAULT_MAX_DISTANCE_PER_TICK: f64 = 10.;
+
+/// Decides whether the moves reported by the clients are possible
+pub trait MovementValidator: Send + Sync {
+    /// Called for every position update of a player, from its last accepted location
+    fn is_valid_move(&self, from: &Location, to: &Location) -> bool;
+}
+
+/// Rejects moves longer than a distance in one tick
+#[derive(Clone, Copy, Debug)]
+pub struct MaxSpeedValidator {
+    pub max_distance_per_tick: f64,
+}
+impl MaxSpeedValidator {
+    pub fn new(max_distance_per_tick: f64) -> Self {
+        Self { max_distance_per_tick }
+    }
+}
+impl Default for MaxSpeedValidator {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_DISTANCE_PER_TICK)
+    }
+}
+impl MovementValidator for MaxSpeedValidator {
+    fn is_valid_move(&self, from: &Location, to: &Location) -> bool {
+        from.distance2(*to) <= self.max_distance_per_tick.powi(2)
+    }
+}
+
+/// Validates the moves of a player, players without it move freely
+/// Given by the [crate::mc_app::LoginPipeline::with_movement_validator]
+#[derive(Component)]
+pub struct MovementValidatorComponent(pub Box<dyn MovementValidator>);
+
+impl MovementValidatorComponent {
+    /// Applies the move reported by the client to its location if it is valid,
+    /// otherwise the player is teleported back to its current location
+    /// Returns whether the move was applied
+    pub fn apply_move(
+        &self, to: Location,
+        location: &mut LocationComponent,
+        teleport: &mut TeleportComponent,
+        client: &ClientComponent,
+    ) -> bool {
+        if self.0.is_valid_move(&location.0, &to) {
+            location.0 = to;
+            return true;
+        }
+        teleport.teleport(client, location.0, RelativeFlags::new());
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{ connected_client, read_packets };
+    use mc_networking::packets::client_bound::{ ClientBoundPacket, C36SynchronizePlayerPosition };
+
+    #[tokio::test]
+    async fn teleport_hack_is_corrected() {
+        let (client, _events, mut peer) = connected_client().await;
+
+        let validator = MovementValidatorComponent(Box::new(MaxSpeedValidator::default()));
+        let start = Location { x: 0., y: 64., z: 0., yaw: 0., pitch: 0. };
+        let mut location = LocationComponent(start);
+        let mut teleport = TeleportComponent::default();
+
+        let walked = Location { x: 0.2, ..start };
+        assert!(validator.apply_move(walked, &mut location, &mut teleport, &client));
+        assert_eq!(location.0, walked);
+        assert!(!teleport.is_pending());
+
+        let jumped = Location { x: 1000.2, ..start };
+        assert!(!validator.apply_move(jumped, &mut location, &mut teleport, &client));
+        assert_eq!(location.0, walked);
+        assert!(teleport.is_pending());
+
+        let packet = read_packets(&mut peer, 1).await.remove(0);
+        let correction = C36SynchronizePlayerPosition {
+            x: walked.x, y: walked.y, z: walked.z, yaw: 0., pitch: 0.,
+            flags: 0, teleport_id: 0, dismount_vehicle: false,
+        }.to_rawpacket();
+        assert_eq!(packet.packet_id, C36SynchronizePlayerPosition::PACKET_ID);
+        assert_eq!(packet.data, correction.data);
+    }
+}
@@ -0,0 +1,210 @@
+use crate::chunk_holder::ChunkHolder;
+use crate::entity::{ ClientComponent, LocationComponent, NetworkIdComponent };
+use crate::entity::collision::BlockCollisionComponent;
+use mc_networking::packets::client_bound::C4FSetEntityVelocity;
+use mc_utils::Location;
+
+use bevy_ecs::component::Component;
+use bevy_ecs::query::Without;
+use bevy_ecs::system::{ Query, Res };
+
+/// Acceleration towards the ground, in blocks per tick squared
+pub const GRAVITY: f64 = 0.08;
+/// Part of the velocity kept after each tick
+pub const DRAG: f64 = 0.98;
+/// Part of the horizontal velocity kept after each tick spent on the ground, on top of the drag
+pub const GROUND_FRICTION: f64 = 0.6;
+/// Velocities slower than this, in blocks per tick, are stopped like the notchian server does
+pub const MIN_VELOCITY: f64 = 0.003;
+/// Distance below the feet of an entity checked for the ground it stands on
+const GROUND_CHECK: f64 = 0.001;
+
+/// Velocity of an entity, in blocks per tick
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct VelocityComponent {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+impl VelocityComponent {
+    /// The velocity the entity stops accelerating at because of the drag
+    pub fn terminal_velocity() -> f64 {
+        -GRAVITY * DRAG / (1. - DRAG)
+    }
+
+    /// Pushes the entity toward the given horizontal direction like a notchian hit
+    /// Half of the current velocity is kept, the vertical velocity is capped at 0.4
+    pub fn knockback(&mut self, direction_x: f64, direction_z: f64, strength: f64) {
+        let length = (direction_x.powi(2) + direction_z.powi(2)).sqrt();
+        if strength <= 0. || length == 0. {
+            return;
+        }
+        self.x = self.x / 2. + direction_x / length * strength;
+        self.y = (self.y / 2. + strength).min(0.4);
+        self.z = self.z / 2. + direction_z / length * strength;
+    }
+
+    /// Whether the velocity changed enough since `sent` to be sent again to the clients
+    pub fn differs_from(&self, sent: &VelocityComponent, threshold: f64) -> bool {
+        (self.x - sent.x).abs() > threshold
+            || (self.y - sent.y).abs() > threshold
+            || (self.z - sent.z).abs() > threshold
+            // Stopping is always sent so the clients don't keep moving the entity
+            || (*self == VelocityComponent::default() && *sent != VelocityComponent::default())
+    }
+
+    pub fn to_packet(&self, network_id: &NetworkIdComponent) -> C4FSetEntityVelocity {
+        // Velocity is sent in 1/8000 of block per tick, the notchian client clamps it at 3.9
+        let encode = |v: f64| (v.clamp(-3.9, 3.9) * 8000.).round() as i16;
+        C4FSetEntityVelocity {
+            entity_id: network_id.0,
+            vel_x: encode(self.x),
+            vel_y: encode(self.y),
+            vel_z: encode(self.z),
+        }
+    }
+}
+
+/// Moves entities with a [VelocityComponent] and applies gravity and drag to them
+/// Entities with a [BlockCollisionComponent] land on the blocks of the [ChunkHolder] and
+/// stop falling while standing on them
+/// Players are moved by their client so they are ignored
+pub fn gravity_system(
+    mut query: Query<
+        (&mut VelocityComponent, &mut LocationComponent, Option<&BlockCollisionComponent>),
+        Without<ClientComponent>
+    >,
+    chunk_holder: Res<ChunkHolder>,
+) {
+    query.for_each_mut(|(mut velocity, mut location, collisions)| {
+        let mut next = *velocity;
+        if next != VelocityComponent::default() {
+            let moved = Location {
+                x: location.0.x + next.x,
+                y: location.0.y + next.y,
+                z: location.0.z + next.z,
+                ..location.0
+            };
+            // Only falling entities land, rising ones aren't pushed through the ceilings
+            let landed = collisions
+                .filter(|_| next.y <= 0.)
+                .and_then(|collisions| collisions.resolve(&moved, &chunk_holder));
+            location.0 = match landed {
+                Some(landed) => {
+                    next.y = 0.;
+                    landed
+                }
+                None => moved,
+            };
+        }
+
+        let below = Location { y: location.0.y - GROUND_CHECK, ..location.0 };
+        let on_ground = next.y <= 0. && collisions
+            .map_or(false, |collisions| collisions.resolve(&below, &chunk_holder).is_some());
+        if on_ground {
+            next.x *= GROUND_FRICTION;
+            next.y = 0.;
+            next.z *= GROUND_FRICTION;
+        } else {
+            next.y -= GRAVITY;
+        }
+        next.x *= DRAG;
+        next.y *= DRAG;
+        next.z *= DRAG;
+        for v in [&mut next.x, &mut next.y, &mut next.z] {
+            if v.abs() < MIN_VELOCITY {
+                *v = 0.;
+            }
+        }
+
+        // Resting entities are left unchanged so their velocity isn't sent again
+        if next != *velocity {
+            *velocity = next;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_networking::data_types::Position;
+    use mc_utils::{ AABB, BlockState, ChunkData };
+
+    use bevy_ecs::schedule::{ Stage, SystemStage };
+    use bevy_ecs::world::World;
+
+    fn solid_blocks(block: BlockState) -> Option<AABB> {
+        (block != 0).then(AABB::full_block)
+    }
+
+    #[test]
+    fn gravity_reaches_terminal_velocity() {
+        let mut world = World::default();
+        world.init_resource::<ChunkHolder>();
+        let entity = world.spawn()
+            .insert(VelocityComponent::default())
+            .insert(LocationComponent(Location { y: 100., ..Location::default() }))
+            .id();
+        let mut stage = SystemStage::single(gravity_system);
+
+        let mut previous = world.get::<VelocityComponent>(entity).unwrap().y;
+        for _ in 0..10 {
+            stage.run(&mut world);
+            let velocity = world.get::<VelocityComponent>(entity).unwrap().y;
+            assert!(velocity < previous);
+            previous = velocity;
+        }
+        assert!(world.get::<LocationComponent>(entity).unwrap().0.y < 100.);
+
+        for _ in 0..1000 {
+            stage.run(&mut world);
+        }
+        let velocity = world.get::<VelocityComponent>(entity).unwrap().y;
+        assert!((velocity - VelocityComponent::terminal_velocity()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn falling_entity_lands_on_blocks() {
+        let mut chunk_holder = ChunkHolder::default();
+        chunk_holder.insert(0, 0, ChunkData::new(4));
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk_holder.set_block(Position { x, y: 20, z }, 1);
+            }
+        }
+        let mut world = World::default();
+        world.insert_resource(chunk_holder);
+        let entity = world.spawn()
+            .insert(VelocityComponent { x: 0.1, ..VelocityComponent::default() })
+            .insert(LocationComponent(Location { x: 8.5, y: 30., z: 8.5, ..Location::default() }))
+            .insert(BlockCollisionComponent(solid_blocks))
+            .id();
+        let mut stage = SystemStage::single(gravity_system);
+
+        for _ in 0..100 {
+            stage.run(&mut world);
+        }
+        let location = world.get::<LocationComponent>(entity).unwrap().0;
+        assert_eq!(location.y, 21.);
+        assert!(location.x > 9. && location.x < 11.);
+        // The friction stops the entity
+        assert_eq!(*world.get::<VelocityComponent>(entity).unwrap(), VelocityComponent::default());
+    }
+
+    #[test]
+    fn knockback_velocity() {
+        let network_id = NetworkIdComponent::new();
+        let mut velocity = VelocityComponent::default();
+        velocity.knockback(3., -4., 0.5);
+        let packet = velocity.to_packet(&network_id);
+        assert_eq!((packet.vel_x, packet.vel_y, packet.vel_z), (2400, 3200, -3200));
+
+        // Half of the previous velocity is kept
+        velocity.knockback(1., 0., 0.2);
+        let packet = velocity.to_packet(&network_id);
+        assert_eq!((packet.vel_x, packet.vel_y, packet.vel_z), (2800, 3200, -1600));
+
+        velocity.knockback(0., 0., 1.);
+        assert_eq!(velocity.to_packet(&network_id).vel_x, 2800);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::entity::ObjectUuidComponent;
+use crate::tab_list::TabList;
+
+use bevy_ecs::component::Component;
+use bevy_ecs::query::Changed;
+use bevy_ecs::system::{ Query, ResMut };
+
+/// Minimum change in milliseconds of the ping of a player for it to be sent to the other players
+pub const PING_BROADCAST_THRESHOLD: u32 = 20;
+
+/// Latency of a player, measured by the round-trip of the keep alives
+#[derive(Component, Debug, Clone, Default)]
+pub struct PingComponent {
+    ping: u32,
+    /// Ping last shown in the players list, None before the first measure
+    broadcast_ping: Option<u32>,
+}
+
+impl PingComponent {
+    /// Ping in milliseconds
+    pub fn ping(&self) -> u32 {
+        self.ping
+    }
+
+    /// Records a keep alive round-trip delay, from a [mc_networking::client::client_event::ClientEvent::Ping]
+    pub fn update(&mut self, delay: u128) {
+        self.ping = delay.min(u32::MAX as u128) as u32;
+    }
+
+    /// Returns the ping if it changed enough since the last time it was sent to the players
+    fn take_broadcast(&mut self) -> Option<u32> {
+        let changed_enough = self.broadcast_ping
+            .map_or(true, |broadcast_ping| self.ping.abs_diff(broadcast_ping) > PING_BROADCAST_THRESHOLD);
+        if !changed_enough {
+            return None;
+        }
+        self.broadcast_ping = Some(self.ping);
+        Some(self.ping)
+    }
+}
+
+/// Updates the latencies shown in the [TabList] when the ping of players change enough
+pub fn ping_broadcast_system(
+    mut query: Query<(&ObjectUuidComponent, &mut PingComponent), Changed<PingComponent>>,
+    mut tab_list: ResMut<TabList>,
+) {
+    query.for_each_mut(|(uuid, mut ping)| {
+        if let Some(ping) = ping.take_broadcast() {
+            tab_list.set_ping(uuid.0, ping as i32);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_ping_changes_not_broadcast() {
+        let mut ping = PingComponent::default();
+        ping.update(5);
+        assert_eq!(ping.take_broadcast(), Some(5));
+
+        ping.update(6);
+        assert_eq!(ping.ping(), 6);
+        assert_eq!(ping.take_broadcast(), None);
+
+        ping.update(100);
+        assert_eq!(ping.take_broadcast(), Some(100));
+        assert_eq!(ping.take_broadcast(), None);
+
+        // Changes are compared to the last broadcast ping, not the last measure
+        ping.update(110);
+        assert_eq!(ping.take_broadcast(), None);
+        ping.update(121);
+        assert_eq!(ping.take_broadcast(), Some(121));
+    }
+}
@@ -0,0 +1,201 @@
+use crate::entity::{ ClientComponent, LocationComponent, NetworkIdComponent, ObjectUuidComponent };
+use crate::entity::equipment::EquipmentComponent;
+use crate::entity::metadata::MetadataComponent;
+use crate::entity_pool::DEFAULT_ENTITY_VIEW_DISTANCE;
+use crate::inventory::InventoryComponent;
+use mc_networking::packets::client_bound::{
+    C02SpawnPlayer, C38RemoveEntities, C50EntityEquipment, ClientBoundPacket,
+};
+use mc_networking::packets::RawPacket;
+
+use ahash::AHashMap;
+use bevy_ecs::component::Component;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::query::With;
+use bevy_ecs::system::Query;
+
+/// Other players a player has been sent, kept up to date by the [player_visibility_system]
+/// Entities that aren't players are spawned by the
+/// [crate::entity_pool::entity_visibility_system] instead
+#[derive(Component, Debug, Clone)]
+pub struct PlayerVisibilityTracker {
+    pub view_distance: f64,
+    /// Network ids of the spawned players, kept to remove players that disconnected
+    visible: AHashMap<Entity, i32>,
+}
+
+impl Default for PlayerVisibilityTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_ENTITY_VIEW_DISTANCE)
+    }
+}
+
+impl PlayerVisibilityTracker {
+    pub fn new(view_distance: f64) -> Self {
+        Self {
+            view_distance,
+            visible: AHashMap::default(),
+        }
+    }
+
+    /// Players spawned for this player
+    pub fn visible(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.visible.keys().copied()
+    }
+
+    pub fn is_visible(&self, player: Entity) -> bool {
+        self.visible.contains_key(&player)
+    }
+}
+
+/// Packets spawning a player with its equipment and metadata
+/// Players without an [EquipmentComponent] hold their first hotbar slot
+fn spawn_packets(
+    network_id: &NetworkIdComponent, uuid: &ObjectUuidComponent, location: &LocationComponent,
+    inventory: Option<&InventoryComponent>, equipment: Option<&EquipmentComponent>,
+    metadata: Option<&MetadataComponent>,
+) -> Vec<RawPacket> {
+    let mut packets = vec![C02SpawnPlayer {
+        entity_id: network_id.0,
+        uuid: uuid.0,
+        x: location.0.x,
+        y: location.0.y,
+        z: location.0.z,
+        yaw: location.0.yaw_angle(),
+        pitch: location.0.pitch_angle(),
+    }.to_rawpacket()];
+    let equipment = inventory
+        .map(|inventory| {
+            equipment.cloned().unwrap_or_default()
+                .get_equipment(&inventory.0)
+                .iter()
+                .filter(|(_, item)| item.is_present())
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if !equipment.is_empty() {
+        packets.push(C50EntityEquipment {
+            entity_id: network_id.0,
+            equipment,
+        }.to_rawpacket());
+    }
+    if let Some(metadata) = metadata.filter(|metadata| !metadata.0.is_empty()) {
+        packets.push(metadata.to_packet(network_id).to_rawpacket());
+    }
+    packets
+}
+
+/// Spawns the players entering the view distance of the players with a
+/// [PlayerVisibilityTracker] and removes the ones leaving it or disconnecting
+pub fn player_visibility_system(
+    mut trackers: Query<(Entity, &ClientComponent, &LocationComponent, &mut PlayerVisibilityTracker)>,
+    players: Query<
+        (
+            Entity, &NetworkIdComponent, &ObjectUuidComponent, &LocationComponent,
+            Option<&InventoryComponent>, Option<&EquipmentComponent>, Option<&MetadataComponent>,
+        ),
+        With<ClientComponent>
+    >,
+) {
+    trackers.for_each_mut(|(viewer, client, viewer_location, mut tracker)| {
+        let view_distance2 = tracker.view_distance.powi(2);
+
+        // Players that disconnected or moved away
+        let removed = tracker.visible.iter()
+            .filter(|(player, _)| match players.get(**player) {
+                Ok((_, _, _, location, ..)) => location.0.distance2(viewer_location.0) > view_distance2,
+                Err(_) => true,
+            })
+            .map(|(player, network_id)| (*player, *network_id))
+            .collect::<Vec<_>>();
+        if !removed.is_empty() {
+            for (player, _) in &removed {
+                tracker.visible.remove(player);
+            }
+            client.0.send_packet_sync(&C38RemoveEntities {
+                entities: removed.into_iter().map(|(_, network_id)| network_id).collect(),
+            });
+        }
+
+        players.for_each(|(player, network_id, uuid, location, inventory, equipment, metadata)| {
+            if player == viewer
+                || tracker.is_visible(player)
+                || location.0.distance2(viewer_location.0) > view_distance2 {
+                return;
+            }
+            for packet in spawn_packets(network_id, uuid, location, inventory, equipment, metadata) {
+                client.0.send_raw_packet_sync(packet);
+            }
+            tracker.visible.insert(player, network_id.0);
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::PLAYER_HOTBAR_START;
+    use crate::test_utils::{ connected_client, read_packets };
+    use mc_networking::data_types::Slot;
+    use mc_utils::Location;
+
+    use bevy_ecs::schedule::{ Stage, SystemStage };
+    use bevy_ecs::world::World;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn players_walking_in_and_out_of_range() {
+        let (client_a, _events_a, mut peer_a) = connected_client().await;
+        let (client_b, _events_b, mut peer_b) = connected_client().await;
+        let mut inventory_b = InventoryComponent::default();
+        inventory_b.0.set_slot(PLAYER_HOTBAR_START, Slot::Present {
+            item_id: 1, item_count: 1, nbt: nbt::Blob::new(),
+        });
+
+        let mut world = World::default();
+        let id_a = NetworkIdComponent::new();
+        let id_b = NetworkIdComponent::new();
+        let a = world.spawn()
+            .insert(id_a)
+            .insert(ObjectUuidComponent(Uuid::new_v4()))
+            .insert(client_a)
+            .insert(LocationComponent(Location::default()))
+            .insert(InventoryComponent::default())
+            .insert(PlayerVisibilityTracker::new(32.))
+            .id();
+        let b = world.spawn()
+            .insert(id_b)
+            .insert(ObjectUuidComponent(Uuid::new_v4()))
+            .insert(client_b)
+            .insert(LocationComponent(Location { x: 100., ..Location::default() }))
+            .insert(inventory_b)
+            .insert(PlayerVisibilityTracker::new(32.))
+            .id();
+        let mut stage = SystemStage::single(player_visibility_system);
+
+        stage.run(&mut world);
+        assert!(!world.get::<PlayerVisibilityTracker>(a).unwrap().is_visible(b));
+
+        world.get_mut::<LocationComponent>(b).unwrap().0.x = 10.;
+        stage.run(&mut world);
+        stage.run(&mut world);
+        assert!(world.get::<PlayerVisibilityTracker>(a).unwrap().is_visible(b));
+        assert!(world.get::<PlayerVisibilityTracker>(b).unwrap().is_visible(a));
+        assert!(!world.get::<PlayerVisibilityTracker>(a).unwrap().is_visible(a));
+
+        world.get_mut::<LocationComponent>(b).unwrap().0.x = 100.;
+        stage.run(&mut world);
+        assert_eq!(world.get::<PlayerVisibilityTracker>(a).unwrap().visible().count(), 0);
+        assert_eq!(world.get::<PlayerVisibilityTracker>(b).unwrap().visible().count(), 0);
+
+        // B holds an item, its equipment is sent along with it
+        let packets = read_packets(&mut peer_a, 3).await;
+        assert_eq!(packets[0].packet_id, C02SpawnPlayer::PACKET_ID);
+        assert_eq!(packets[1].packet_id, C50EntityEquipment::PACKET_ID);
+        assert_eq!(packets[2].data, C38RemoveEntities { entities: vec![id_b.0] }.to_rawpacket().data);
+        let packets = read_packets(&mut peer_b, 2).await;
+        assert_eq!(packets[0].packet_id, C02SpawnPlayer::PACKET_ID);
+        assert_eq!(packets[1].data, C38RemoveEntities { entities: vec![id_a.0] }.to_rawpacket().data);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::entity::ClientComponent;
+use mc_networking::packets::client_bound::{ C36SynchronizePlayerPosition, RelativeFlags };
+use mc_utils::Location;
+
+use bevy_ecs::component::Component;
+
+/// Tracks the teleportations of a player that haven't been confirmed yet
+/// The position updates sent by the client before it confirmed the last teleportation
+/// were computed from its old position and must be ignored
+#[derive(Component, Debug, Clone, Default)]
+pub struct TeleportComponent {
+    next_id: i32,
+    /// Id of the last teleportation sent, until it is confirmed
+    pending: Option<i32>,
+}
+
+impl TeleportComponent {
+    /// Sends a [C36SynchronizePlayerPosition] with a new teleport id and returns that id
+    /// Fields of the location set in `flags` are relative to the current position of the player
+    pub fn teleport(&mut self, client: &ClientComponent, location: Location, flags: RelativeFlags) -> i32 {
+        let teleport_id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.pending = Some(teleport_id);
+
+        client.0.send_packet_sync(&C36SynchronizePlayerPosition {
+            x: location.x, y: location.y, z: location.z,
+            yaw: location.yaw, pitch: location.pitch,
+            flags: flags.into(), teleport_id, dismount_vehicle: false,
+        });
+        teleport_id
+    }
+
+    /// Handles a [mc_networking::client::client_event::ClientEvent::ConfirmTeleportation],
+    /// returns false if the id isn't the one of the last teleportation
+    pub fn confirm(&mut self, teleport_id: i32) -> bool {
+        if self.pending != Some(teleport_id) {
+            return false;
+        }
+        self.pending = None;
+        true
+    }
+
+    /// True while the last teleportation hasn't been confirmed
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Whether position updates from the client should be applied
+    pub fn accepts_positions(&self) -> bool {
+        !self.is_pending()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{ connected_client, read_packets };
+    use mc_networking::packets::client_bound::ClientBoundPacket;
+
+    #[tokio::test]
+    async fn positions_ignored_until_confirmed() {
+        let (client, _events, mut peer) = connected_client().await;
+
+        let mut teleport = TeleportComponent::default();
+        assert!(teleport.accepts_positions());
+
+        let first = teleport.teleport(&client, Location::default(), RelativeFlags::new());
+        let second = teleport.teleport(&client, Location::default(), RelativeFlags::new().yaw().pitch());
+        assert_eq!(second, first + 1);
+        assert!(!teleport.accepts_positions());
+
+        // Confirming an older teleportation isn't enough
+        assert!(!teleport.confirm(first));
+        assert!(!teleport.accepts_positions());
+        assert!(teleport.confirm(second));
+        assert!(teleport.accepts_positions());
+        assert!(!teleport.confirm(second));
+
+        let packet = read_packets(&mut peer, 1).await.remove(0);
+        assert_eq!(packet.packet_id, C36SynchronizePlayerPosition::PACKET_ID);
+    }
+
+    #[test]
+    fn relative_flags() {
+        assert_eq!(RelativeFlags::new().bits(), 0);
+        assert_eq!(u8::from(RelativeFlags::new().x().z().pitch()), 0b10101);
+        assert_eq!(RelativeFlags::new().yaw().pitch().bits(), 0b11000);
+    }
+}
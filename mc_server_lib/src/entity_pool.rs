@@ -0,0 +1,550 @@
+use crate::entity::{
+    ClientComponent, LocationComponent, MobKindComponent, NetworkIdComponent, ObjectUuidComponent,
+    RemoveScheduledComponent,
+    movement::PreviousLocationComponent,
+    physics::VelocityComponent,
+    teleport::TeleportComponent,
+};
+use mc_networking::packets::client_bound::{
+    C00SpawnEntity, C38RemoveEntities, C3CSetHeadRotation, C63TeleportEntity,
+    ClientBoundPacket, RelativeFlags,
+};
+use mc_utils::Location;
+
+use ahash::AHashSet;
+use bevy_ecs::component::Component;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::query::{ Changed, With };
+use bevy_ecs::system::{ Commands, Query, SystemParam, SystemState };
+use bevy_ecs::world::World;
+
+/// Default distance in blocks up to which players see the entities
+pub const DEFAULT_ENTITY_VIEW_DISTANCE: f64 = 64.;
+/// Smallest change of velocity, in blocks per tick, sent to the viewers of an entity
+pub const VELOCITY_UPDATE_THRESHOLD: f64 = 0.02;
+
+/// Makes an entity visible only by the players closer than its view distance
+/// Players entering and leaving the range are sent the spawn and remove packets
+/// by the [entity_visibility_system]
+#[derive(Component, Debug, Clone)]
+pub struct ViewDistanceComponent {
+    pub view_distance: f64,
+    viewers: AHashSet<Entity>,
+    /// Last velocity sent to the viewers
+    sent_velocity: VelocityComponent,
+}
+
+impl Default for ViewDistanceComponent {
+    fn default() -> Self {
+        Self::new(DEFAULT_ENTITY_VIEW_DISTANCE)
+    }
+}
+
+impl ViewDistanceComponent {
+    pub fn new(view_distance: f64) -> Self {
+        Self {
+            view_distance,
+            viewers: AHashSet::default(),
+            sent_velocity: VelocityComponent::default(),
+        }
+    }
+
+    /// Players the entity was spawned for
+    pub fn viewers(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.viewers.iter().copied()
+    }
+
+    pub fn is_viewed_by(&self, player: Entity) -> bool {
+        self.viewers.contains(&player)
+    }
+}
+
+/// System parameter giving access to the connected players and their location
+#[derive(SystemParam)]
+pub struct Players<'w, 's> {
+    query: Query<'w, 's, (Entity, &'static ClientComponent, &'static LocationComponent)>,
+}
+
+impl<'w, 's> Players<'w, 's> {
+    /// Players at most `radius` blocks away from `center`, skipping the `except` player
+    pub fn within<'a>(
+        &'a self, center: &'a Location, radius: f64, except: Option<Entity>,
+    ) -> impl Iterator<Item = (Entity, &'a ClientComponent)> + 'a {
+        let radius2 = radius * radius;
+        self.query.iter()
+            .filter(move |(player, _, location)| {
+                Some(*player) != except && location.0.distance2(*center) <= radius2
+            })
+            .map(|(player, client, _)| (player, client))
+    }
+
+    /// Sends a packet to every player, the packet is only encoded once and its buffer is
+    /// shared by the clients
+    pub fn broadcast(&self, packet: &impl ClientBoundPacket) {
+        let packet = packet.to_rawpacket();
+        self.query.for_each(|(_, client, _)| client.0.send_raw_packet_sync(packet.clone()));
+    }
+
+    /// Sends a packet to the players at most `radius` blocks away from `center`,
+    /// except to the `except` player
+    pub fn broadcast_within(
+        &self, center: &Location, radius: f64, packet: &impl ClientBoundPacket, except: Option<Entity>,
+    ) {
+        let packet = packet.to_rawpacket();
+        for (_, client) in self.within(center, radius, except) {
+            client.0.send_raw_packet_sync(packet.clone());
+        }
+    }
+}
+
+/// Spawns and removes the entities with a [ViewDistanceComponent] for the players entering
+/// or leaving their view distance
+pub fn entity_visibility_system(
+    mut entities: Query<(
+        Entity, &NetworkIdComponent, &ObjectUuidComponent, &MobKindComponent,
+        &LocationComponent, &mut ViewDistanceComponent, Option<&VelocityComponent>,
+    )>,
+    players: Query<(Entity, &ClientComponent, &LocationComponent)>,
+) {
+    entities.for_each_mut(|(entity, network_id, uuid, kind, location, mut visibility, velocity)| {
+        let view_distance2 = visibility.view_distance.powi(2);
+
+        // Players that disconnected or moved away
+        let removed = visibility.viewers()
+            .filter(|viewer| match players.get(*viewer) {
+                Ok((_, _, player_location)) => player_location.0.distance2(location.0) > view_distance2,
+                Err(_) => true,
+            })
+            .collect::<Vec<_>>();
+        if !removed.is_empty() {
+            let packet = C38RemoveEntities { entities: vec![network_id.0] }.to_rawpacket();
+            for viewer in removed {
+                visibility.viewers.remove(&viewer);
+                if let Ok((_, client, _)) = players.get(viewer) {
+                    client.0.send_raw_packet_sync(packet.clone());
+                }
+            }
+        }
+
+        let mut spawn_packet = None;
+        players.for_each(|(player, client, player_location)| {
+            if player == entity
+                || visibility.is_viewed_by(player)
+                || player_location.0.distance2(location.0) > view_distance2 {
+                return;
+            }
+            let packet = spawn_packet.get_or_insert_with(|| {
+                // New viewers are spawned with the current velocity
+                let velocity = velocity.copied().unwrap_or_default().to_packet(network_id);
+                C00SpawnEntity {
+                    entity_id: network_id.0,
+                    object_uuid: uuid.0,
+                    kind: kind.0,
+                    x: location.0.x,
+                    y: location.0.y,
+                    z: location.0.z,
+                    pitch: location.0.pitch_angle(),
+                    yaw: location.0.yaw_angle(),
+                    head_yaw: location.0.yaw_angle(),
+                    data: 0,
+                    velocity_x: velocity.vel_x,
+                    velocity_y: velocity.vel_y,
+                    velocity_z: velocity.vel_z,
+                }.to_rawpacket()
+            });
+            client.0.send_raw_packet_sync(packet.clone());
+            visibility.viewers.insert(player);
+        });
+    });
+}
+
+/// Sends the changed velocities to the client of the entity itself for players which are
+/// moved by their client, and to the viewers of the entities when it changed by more than
+/// [VELOCITY_UPDATE_THRESHOLD] since the last velocity they were sent
+/// Velocities of entities without a [ViewDistanceComponent] are only sent to their own client
+pub fn entity_velocity_system(
+    mut entities: Query<
+        (&NetworkIdComponent, &VelocityComponent, Option<&mut ViewDistanceComponent>, Option<&ClientComponent>),
+        Changed<VelocityComponent>
+    >,
+    clients: Query<&ClientComponent>,
+) {
+    entities.for_each_mut(|(network_id, velocity, visibility, own_client)| {
+        let packet = velocity.to_packet(network_id).to_rawpacket();
+        if let Some(client) = own_client {
+            client.0.send_raw_packet_sync(packet.clone());
+        }
+        let mut visibility = match visibility {
+            Some(visibility) if velocity.differs_from(&visibility.sent_velocity, VELOCITY_UPDATE_THRESHOLD) =>
+                visibility,
+            _ => return,
+        };
+        visibility.sent_velocity = *velocity;
+        for viewer in visibility.viewers() {
+            if let Ok(client) = clients.get(viewer) {
+                client.0.send_raw_packet_sync(packet.clone());
+            }
+        }
+    });
+}
+
+/// Despawns the entities with a [RemoveScheduledComponent], removing them for their viewers
+/// Entities without a [ViewDistanceComponent] are removed for the players closer than
+/// [DEFAULT_ENTITY_VIEW_DISTANCE]
+pub fn remove_scheduled_system(
+    query: Query<
+        (Entity, &NetworkIdComponent, Option<&ViewDistanceComponent>, Option<&LocationComponent>),
+        With<RemoveScheduledComponent>
+    >,
+    players: Players,
+    mut commands: Commands,
+) {
+    query.for_each(|(entity, network_id, visibility, location)| {
+        let packet = C38RemoveEntities { entities: vec![network_id.0] }.to_rawpacket();
+        match (visibility, location) {
+            (Some(visibility), _) => for viewer in visibility.viewers() {
+                if let Ok((_, client, _)) = players.query.get(viewer) {
+                    client.0.send_raw_packet_sync(packet.clone());
+                }
+            },
+            (None, Some(location)) =>
+                for (_, client) in players.within(&location.0, DEFAULT_ENTITY_VIEW_DISTANCE, Some(entity)) {
+                    client.0.send_raw_packet_sync(packet.clone());
+                },
+            (None, None) => (),
+        }
+        commands.entity(entity).despawn();
+    });
+}
+
+/// Moves an entity to a location, usually run with [Commands::add]
+/// Players are sent a [mc_networking::packets::client_bound::C36SynchronizePlayerPosition]
+/// with a new teleport id, and the viewers of the entity a [C63TeleportEntity] and a
+/// [C3CSetHeadRotation]
+/// Entities without a [ViewDistanceComponent] are seen by the players closer than
+/// [DEFAULT_ENTITY_VIEW_DISTANCE]
+pub fn teleport_entity(world: &mut World, entity: Entity, location: Location) {
+    let mut entity_mut = match world.get_entity_mut(entity) {
+        Some(entity_mut) => entity_mut,
+        None => return,
+    };
+    entity_mut.insert(LocationComponent(location));
+    // The viewers are sent the teleportation instead of a movement by the entity_movement_system
+    if let Some(mut previous) = entity_mut.get_mut::<PreviousLocationComponent>() {
+        previous.0 = location;
+    }
+    if let Some(client) = entity_mut.get::<ClientComponent>().map(|client| ClientComponent(client.0.clone())) {
+        match entity_mut.get_mut::<TeleportComponent>() {
+            Some(mut teleport) => {
+                teleport.teleport(&client, location, RelativeFlags::new());
+            }
+            None => {
+                let mut teleport = TeleportComponent::default();
+                teleport.teleport(&client, location, RelativeFlags::new());
+                entity_mut.insert(teleport);
+            }
+        }
+    }
+
+    let network_id = match world.get::<NetworkIdComponent>(entity) {
+        Some(network_id) => network_id.0,
+        None => return,
+    };
+    let packets = [
+        C63TeleportEntity {
+            entity_id: network_id,
+            x: location.x,
+            y: location.y,
+            z: location.z,
+            yaw: location.yaw_angle(),
+            pitch: location.pitch_angle(),
+            on_ground: false,
+        }.to_rawpacket(),
+        C3CSetHeadRotation {
+            entity_id: network_id,
+            head_yaw: location.yaw_angle(),
+        }.to_rawpacket(),
+    ];
+    let send = |client: &ClientComponent| for packet in &packets {
+        client.0.send_raw_packet_sync(packet.clone());
+    };
+
+    let mut state = SystemState::<(Players, Query<&ViewDistanceComponent>)>::new(world);
+    let (players, visibilities) = state.get_mut(world);
+    match visibilities.get(entity) {
+        Ok(visibility) => for viewer in visibility.viewers() {
+            if let Ok((_, client, _)) = players.query.get(viewer) {
+                send(client);
+            }
+        },
+        Err(_) => for (_, client) in players.within(&location, DEFAULT_ENTITY_VIEW_DISTANCE, Some(entity)) {
+            send(client);
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_holder::ChunkHolder;
+    use crate::entity::collision::BlockCollisionComponent;
+    use crate::entity::physics::gravity_system;
+    use crate::test_utils::{ connected_client, read_packets, read_packets_until };
+    use mc_networking::data_types::Position;
+    use mc_networking::packets::RawPacket;
+    use mc_networking::packets::client_bound::{ C36SynchronizePlayerPosition, C4FSetEntityVelocity };
+    use mc_utils::{ AABB, ChunkData };
+
+    use bevy_ecs::schedule::{ Stage, SystemStage };
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn mob_removed_when_player_leaves_range() {
+        let (client, _events, mut peer) = connected_client().await;
+
+        let mut world = World::default();
+        let player = world.spawn()
+            .insert(client)
+            .insert(LocationComponent(Location::default()))
+            .id();
+        let network_id = NetworkIdComponent::new();
+        let mob = world.spawn()
+            .insert(network_id)
+            .insert(ObjectUuidComponent(Uuid::new_v4()))
+            .insert(MobKindComponent(1))
+            .insert(LocationComponent(Location { x: 10., ..Location::default() }))
+            .insert(ViewDistanceComponent::new(32.))
+            .id();
+        let mut stage = SystemStage::single(entity_visibility_system);
+
+        stage.run(&mut world);
+        stage.run(&mut world);
+        assert!(world.get::<ViewDistanceComponent>(mob).unwrap().is_viewed_by(player));
+
+        world.get_mut::<LocationComponent>(player).unwrap().0.x = 100.;
+        stage.run(&mut world);
+        assert!(!world.get::<ViewDistanceComponent>(mob).unwrap().is_viewed_by(player));
+
+        let packets = read_packets(&mut peer, 2).await;
+        assert_eq!(packets[0].packet_id, C00SpawnEntity::PACKET_ID);
+        assert_eq!(packets[1].packet_id, C38RemoveEntities::PACKET_ID);
+        assert_eq!(packets[1].data, C38RemoveEntities { entities: vec![network_id.0] }.to_rawpacket().data);
+    }
+
+    #[tokio::test]
+    async fn velocity_sent_until_the_mob_rests() {
+        let (client, _events, mut peer) = connected_client().await;
+
+        let mut chunk_holder = ChunkHolder::default();
+        chunk_holder.insert(0, 0, ChunkData::new(4));
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk_holder.set_block(Position { x, y: 20, z }, 1);
+            }
+        }
+        let mut world = World::default();
+        world.insert_resource(chunk_holder);
+        let player = world.spawn()
+            .insert(client)
+            .insert(LocationComponent(Location { x: 4., y: 21., z: 4., ..Location::default() }))
+            .id();
+        let network_id = NetworkIdComponent::new();
+        world.spawn()
+            .insert(network_id)
+            .insert(ObjectUuidComponent(Uuid::new_v4()))
+            .insert(MobKindComponent(1))
+            .insert(LocationComponent(Location { x: 8.5, y: 30., z: 8.5, ..Location::default() }))
+            .insert(ViewDistanceComponent::new(32.))
+            .insert(VelocityComponent::default())
+            .insert(BlockCollisionComponent(|block| (block != 0).then(AABB::full_block)));
+        let mut stages = [
+            SystemStage::single(entity_visibility_system),
+            SystemStage::single(gravity_system),
+            SystemStage::single(entity_velocity_system),
+        ];
+        let marker = C38RemoveEntities { entities: vec![] };
+        let mut run_ticks = |world: &mut World, ticks: usize| {
+            for _ in 0..ticks {
+                for stage in &mut stages {
+                    stage.run(world);
+                }
+            }
+            world.get::<ClientComponent>(player).unwrap().0.send_packet_sync(&marker);
+        };
+
+        run_ticks(&mut world, 100);
+        let packets = read_packets_until(&mut peer, C38RemoveEntities::PACKET_ID).await;
+        assert_eq!(packets[0].packet_id, C00SpawnEntity::PACKET_ID);
+        let velocities = &packets[1..packets.len() - 1];
+        assert!(velocities.iter().all(|packet| packet.packet_id == C4FSetEntityVelocity::PACKET_ID));
+        // One velocity per tick of the fall, the last one stops the mob
+        assert!(velocities.len() > 5 && velocities.len() < 30, "{} velocities sent", velocities.len());
+        let stopped = VelocityComponent::default().to_packet(&network_id).to_rawpacket();
+        assert_eq!(velocities.last().unwrap().data, stopped.data);
+
+        // The mob rests on the ground
+        run_ticks(&mut world, 100);
+        let packets = read_packets_until(&mut peer, C38RemoveEntities::PACKET_ID).await;
+        assert_eq!(packets.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn scheduled_entities_are_removed() {
+        let (client, _events, mut peer) = connected_client().await;
+
+        let mut world = World::default();
+        world.spawn()
+            .insert(client)
+            .insert(LocationComponent(Location::default()));
+        let (mob_id, item_id, far_id) =
+            (NetworkIdComponent::new(), NetworkIdComponent::new(), NetworkIdComponent::new());
+        let mob = world.spawn()
+            .insert(mob_id)
+            .insert(ObjectUuidComponent(Uuid::new_v4()))
+            .insert(MobKindComponent(1))
+            .insert(LocationComponent(Location { x: 10., ..Location::default() }))
+            .insert(ViewDistanceComponent::new(32.))
+            .id();
+        // Entities without a view distance are removed for the close enough players
+        let item = world.spawn()
+            .insert(item_id)
+            .insert(LocationComponent(Location { z: 5., ..Location::default() }))
+            .id();
+        let far = world.spawn()
+            .insert(far_id)
+            .insert(LocationComponent(Location { z: 500., ..Location::default() }))
+            .id();
+        SystemStage::single(entity_visibility_system).run(&mut world);
+
+        for entity in [mob, item, far] {
+            world.entity_mut(entity).insert(RemoveScheduledComponent);
+        }
+        SystemStage::single(remove_scheduled_system).run(&mut world);
+        for entity in [mob, item, far] {
+            assert!(world.get_entity(entity).is_none());
+        }
+
+        let packets = read_packets(&mut peer, 3).await;
+        assert_eq!(packets[0].packet_id, C00SpawnEntity::PACKET_ID);
+        let mut removed = packets[1..].iter()
+            .map(|packet| {
+                assert_eq!(packet.packet_id, C38RemoveEntities::PACKET_ID);
+                packet.data.clone()
+            })
+            .collect::<Vec<_>>();
+        removed.sort();
+        let mut expected = [mob_id, item_id]
+            .map(|id| C38RemoveEntities { entities: vec![id.0] }.to_rawpacket().data)
+            .to_vec();
+        expected.sort();
+        assert_eq!(removed, expected);
+    }
+
+    #[tokio::test]
+    async fn broadcast_reaches_players_in_radius() {
+        let mut world = World::default();
+        let mut peers = vec![];
+        let mut players = vec![];
+        for x in [0., 8., 30.] {
+            let (client, _events, peer) = connected_client().await;
+            peers.push(peer);
+            players.push(world.spawn()
+                .insert(client)
+                .insert(LocationComponent(Location { x, ..Location::default() }))
+                .id());
+        }
+
+        let broadcast = C38RemoveEntities { entities: vec![1] };
+        let marker = C38RemoveEntities { entities: vec![2] };
+        let broadcast_data = broadcast.to_rawpacket().data;
+        let sender = players[0];
+        let mut stage = SystemStage::single(move |players: Players| {
+            players.broadcast_within(&Location::default(), 10., &broadcast, Some(sender));
+            for (_, client, _) in players.query.iter() {
+                client.0.send_packet_sync(&marker);
+            }
+        });
+        stage.run(&mut world);
+
+        let expected = [vec![2], vec![1, 2], vec![2]];
+        for (peer, expected) in peers.iter_mut().zip(expected) {
+            let packets = read_packets(peer, expected.len()).await;
+            let entities = packets.iter()
+                .map(|packet| if packet.data == broadcast_data { 1 } else { 2 })
+                .collect::<Vec<_>>();
+            assert_eq!(entities, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn broadcast_sends_identical_data() {
+        let mut world = World::default();
+        let mut peers = vec![];
+        for x in [0., 1000., -1000.] {
+            let (client, _events, peer) = connected_client().await;
+            peers.push(peer);
+            world.spawn()
+                .insert(client)
+                .insert(LocationComponent(Location { x, ..Location::default() }));
+        }
+
+        let mut stage = SystemStage::single(|players: Players| {
+            players.broadcast(&C38RemoveEntities { entities: vec![3, 300, 30000] });
+        });
+        stage.run(&mut world);
+
+        let mut received = vec![];
+        for peer in &mut peers {
+            let packet = read_packets(peer, 1).await.remove(0);
+            received.push((packet.packet_id, packet.data));
+        }
+        assert_eq!(received[0].0, C38RemoveEntities::PACKET_ID);
+        assert!(received.iter().all(|packet| *packet == received[0]));
+    }
+
+    #[tokio::test]
+    async fn teleported_players_are_synchronized() {
+        let mut world = World::default();
+        let mut peers = vec![];
+        let mut players = vec![];
+        for x in [0., 5.] {
+            let (client, _events, peer) = connected_client().await;
+            peers.push(peer);
+            players.push(world.spawn()
+                .insert(client)
+                .insert(NetworkIdComponent::new())
+                .insert(LocationComponent(Location { x, ..Location::default() }))
+                .insert(TeleportComponent::default())
+                .id());
+        }
+        let mob = world.spawn()
+            .insert(NetworkIdComponent::new())
+            .insert(LocationComponent(Location { x: 10., ..Location::default() }))
+            .id();
+
+        let player_target = Location { x: 20., yaw: 90., ..Location::default() };
+        teleport_entity(&mut world, players[1], player_target);
+        let mob_target = Location { x: 3., ..Location::default() };
+        teleport_entity(&mut world, mob, mob_target);
+        assert_eq!(world.get::<LocationComponent>(players[1]).unwrap().0, player_target);
+        assert!(world.get::<TeleportComponent>(players[1]).unwrap().is_pending());
+        assert_eq!(world.get::<LocationComponent>(mob).unwrap().0, mob_target);
+
+        let ids = |packets: Vec<RawPacket>| packets.iter().map(|p| p.packet_id).collect::<Vec<_>>();
+        // The teleported player is only sent its new position, the mob isn't a player
+        assert_eq!(ids(read_packets(&mut peers[1], 3).await), [
+            C36SynchronizePlayerPosition::PACKET_ID,
+            C63TeleportEntity::PACKET_ID, C3CSetHeadRotation::PACKET_ID,
+        ]);
+        let observed = read_packets(&mut peers[0], 4).await;
+        let player_id = world.get::<NetworkIdComponent>(players[1]).unwrap().0;
+        assert_eq!(observed[1].data, C3CSetHeadRotation {
+            entity_id: player_id,
+            head_yaw: player_target.yaw_angle(),
+        }.to_rawpacket().data);
+        assert_eq!(ids(observed), [
+            C63TeleportEntity::PACKET_ID, C3CSetHeadRotation::PACKET_ID,
+            C63TeleportEntity::PACKET_ID, C3CSetHeadRotation::PACKET_ID,
+        ]);
+    }
+}
@@ -0,0 +1,598 @@
+use mc_networking::data_types::Slot;
+use mc_networking::packets::{
+    client_bound::{ C11SetContainerContent, C13SetContainerSlot, ClientBoundPacket },
+    server_bound::S0AClickContainer,
+    RawPacket,
+};
+
+use std::ops::Range;
+
+use bevy_ecs::component::Component;
+
+/// Number of slots of the player inventory window
+pub const PLAYER_INVENTORY_SIZE: usize = 46;
+/// First slot of the main inventory in the player inventory window
+pub const PLAYER_MAIN_START: usize = 9;
+/// First slot of the hotbar in the player inventory window
+pub const PLAYER_HOTBAR_START: usize = 36;
+/// Offhand slot in the player inventory window
+pub const PLAYER_OFFHAND_SLOT: usize = 45;
+pub const MAX_STACK_SIZE: u8 = 64;
+/// Slot id used by the client for clicks outside of the window
+pub const OUTSIDE_SLOT: i16 = -999;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DragKind {
+    Left,
+    Right,
+}
+
+impl DragKind {
+    /// Buttons of the clicks adding a slot to the drag and ending it
+    fn buttons(self) -> (i8, i8) {
+        match self {
+            DragKind::Left => (1, 2),
+            DragKind::Right => (5, 6),
+        }
+    }
+}
+
+/// Result of a click in an [Inventory]
+#[derive(Clone, Debug, Default)]
+pub struct ClickResult {
+    /// Slots modified by the click
+    pub slot_updates: Vec<C13SetContainerSlot>,
+    /// Whole content of the window, only set when the click couldn't be applied
+    /// so the client goes back to the server's state
+    pub content_update: Option<C11SetContainerContent>,
+    /// Item carried by the cursor after the click
+    pub carried_item: Slot,
+    /// Items thrown out of the window by the click
+    pub dropped_items: Vec<Slot>,
+}
+impl ClickResult {
+    pub fn to_rawpackets(&self) -> Vec<RawPacket> {
+        self.content_update.iter().map(|packet| packet.to_rawpacket())
+            .chain(self.slot_updates.iter().map(|packet| packet.to_rawpacket()))
+            .collect()
+    }
+}
+
+/// Server side content of a window with the item carried by the player's cursor
+/// The last 36 slots of container windows are the player's main inventory followed by the hotbar
+#[derive(Clone, Debug)]
+pub struct Inventory {
+    window_id: u8,
+    state_id: i32,
+    slots: Vec<Slot>,
+    carried_item: Slot,
+    drag: Option<(DragKind, Vec<usize>)>,
+}
+
+impl Inventory {
+    /// Creates an empty window of the given size
+    pub fn new(window_id: u8, size: usize) -> Self {
+        assert!(size >= 36, "windows must include the player inventory");
+        Self {
+            window_id,
+            state_id: 0,
+            slots: vec![Slot::NotPresent; size],
+            carried_item: Slot::NotPresent,
+            drag: None,
+        }
+    }
+
+    /// Creates an empty player inventory window
+    pub fn player() -> Self {
+        Self::new(0, PLAYER_INVENTORY_SIZE)
+    }
+
+    pub fn window_id(&self) -> u8 {
+        self.window_id
+    }
+    pub fn state_id(&self) -> i32 {
+        self.state_id
+    }
+    pub fn slots(&self) -> &[Slot] {
+        &self.slots
+    }
+    pub fn get_slot(&self, slot: usize) -> Option<&Slot> {
+        self.slots.get(slot)
+    }
+    pub fn set_slot(&mut self, slot: usize, item: Slot) {
+        self.slots[slot] = item;
+    }
+    pub fn carried_item(&self) -> &Slot {
+        &self.carried_item
+    }
+
+    /// Packet sending the whole content of the window
+    pub fn content_packet(&self) -> C11SetContainerContent {
+        C11SetContainerContent {
+            window_id: self.window_id,
+            state_id: self.state_id,
+            slots: self.slots.clone(),
+            carried_item: self.carried_item.clone(),
+        }
+    }
+
+    fn hotbar_start(&self) -> usize {
+        if self.window_id == 0 { PLAYER_HOTBAR_START } else { self.slots.len() - 9 }
+    }
+
+    /// Slot ranges, by priority, an item is moved to when shift-clicked
+    fn quick_move_targets(&self, slot: usize) -> Vec<Range<usize>> {
+        let player_start = self.slots.len() - 36;
+        if self.window_id != 0 {
+            return if slot < player_start {
+                vec![player_start..self.slots.len()]
+            } else {
+                vec![0..player_start]
+            };
+        }
+        if slot >= PLAYER_HOTBAR_START && slot < PLAYER_OFFHAND_SLOT {
+            vec![PLAYER_MAIN_START..PLAYER_HOTBAR_START]
+        } else if slot >= PLAYER_MAIN_START && slot < PLAYER_HOTBAR_START {
+            vec![PLAYER_HOTBAR_START..PLAYER_OFFHAND_SLOT]
+        } else {
+            vec![PLAYER_MAIN_START..PLAYER_HOTBAR_START, PLAYER_HOTBAR_START..PLAYER_OFFHAND_SLOT]
+        }
+    }
+
+    /// Applies a click sent by the client
+    pub fn click(&mut self, click: &S0AClickContainer) -> ClickResult {
+        if click.window_id != self.window_id {
+            return self.rejected_click();
+        }
+        let slot = match click.slot_id {
+            OUTSIDE_SLOT => None,
+            slot if slot >= 0 && (slot as usize) < self.slots.len() => Some(slot as usize),
+            _ => return self.rejected_click(),
+        };
+
+        let mut changed = vec![];
+        let mut dropped_items = vec![];
+        let applied = match (click.mode, click.button, slot) {
+            // Pickup
+            (0, 0, None) => {
+                dropped_items.push(std::mem::take(&mut self.carried_item));
+                true
+            }
+            (0, 1, None) => {
+                dropped_items.push(self.carried_item.split(1));
+                true
+            }
+            (0, button @ (0 | 1), Some(slot)) => {
+                self.pickup(slot, button == 1);
+                changed.push(slot);
+                true
+            }
+            // Quick move
+            (1, 0 | 1, Some(slot)) => {
+                changed.extend(self.quick_move(slot));
+                true
+            }
+            // Swap with the hotbar or the offhand
+            (2, button, Some(slot)) => {
+                let target = match button {
+                    0..=8 => Some(self.hotbar_start() + button as usize),
+                    40 if self.window_id == 0 => Some(PLAYER_OFFHAND_SLOT),
+                    _ => None,
+                };
+                match target {
+                    Some(target) => {
+                        self.slots.swap(slot, target);
+                        changed.extend([slot, target]);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            // Drop
+            (4, _, None) => true,
+            (4, button @ (0 | 1), Some(slot)) => {
+                let dropped = if button == 0 { self.slots[slot].split(1) }
+                    else { std::mem::take(&mut self.slots[slot]) };
+                if dropped.is_present() {
+                    dropped_items.push(dropped);
+                    changed.push(slot);
+                }
+                true
+            }
+            // Drag
+            (5, 0, None) => self.start_drag(DragKind::Left),
+            (5, 4, None) => self.start_drag(DragKind::Right),
+            // The buttons must match the kind of drag started, other clicks cancel it
+            (5, button @ (1 | 5), Some(slot)) => match &mut self.drag {
+                Some((kind, slots)) if kind.buttons().0 == button => {
+                    if !slots.contains(&slot) {
+                        slots.push(slot);
+                    }
+                    true
+                }
+                _ => false,
+            },
+            (5, button @ (2 | 6), None) => match self.drag.take() {
+                Some((kind, slots)) if kind.buttons().1 == button => {
+                    changed.extend(self.distribute(kind, &slots));
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+        if !applied {
+            self.drag = None;
+            return self.rejected_click();
+        }
+
+        self.state_id = self.state_id.wrapping_add(1);
+        ClickResult {
+            slot_updates: changed.into_iter().map(|slot| self.slot_update(slot)).collect(),
+            content_update: None,
+            carried_item: self.carried_item.clone(),
+            dropped_items: dropped_items.into_iter().filter(Slot::is_present).collect(),
+        }
+    }
+
+    fn slot_update(&self, slot: usize) -> C13SetContainerSlot {
+        C13SetContainerSlot {
+            window_id: self.window_id,
+            state_id: self.state_id,
+            slot: slot as i16,
+            slot_data: self.slots[slot].clone(),
+        }
+    }
+
+    /// Adds an item to the hotbar then to the main inventory, like an item picked up from the ground
+    /// Items that didn't fit are left in `item`
+    /// Returns the packets updating the modified slots
+    pub fn add_item(&mut self, item: &mut Slot) -> Vec<C13SetContainerSlot> {
+        let hotbar_start = self.hotbar_start();
+        let targets = (hotbar_start..hotbar_start + 9)
+            .chain(hotbar_start - 27..hotbar_start)
+            .collect::<Vec<_>>();
+        let changed = self.insert(item, &targets);
+        if changed.is_empty() {
+            return vec![];
+        }
+        self.state_id = self.state_id.wrapping_add(1);
+        changed.into_iter().map(|slot| self.slot_update(slot)).collect()
+    }
+
+    fn rejected_click(&mut self) -> ClickResult {
+        self.state_id = self.state_id.wrapping_add(1);
+        ClickResult {
+            content_update: Some(self.content_packet()),
+            carried_item: self.carried_item.clone(),
+            ..ClickResult::default()
+        }
+    }
+
+    fn pickup(&mut self, slot: usize, right_click: bool) {
+        let slot_item = &mut self.slots[slot];
+
+        if !self.carried_item.is_present() {
+            let slot_count = slot_item.item_count();
+            self.carried_item = slot_item.split(if right_click { (slot_count + 1) / 2 } else { slot_count });
+        }
+        else if right_click && (!slot_item.is_present() || slot_item.is_stackable_with(&self.carried_item)) {
+            let mut placed = self.carried_item.split(1);
+            slot_item.try_merge(&mut placed, MAX_STACK_SIZE);
+            // The slot was full
+            self.carried_item.try_merge(&mut placed, u8::MAX);
+        }
+        else if slot_item.try_merge(&mut self.carried_item, MAX_STACK_SIZE) == 0
+            && !slot_item.is_stackable_with(&self.carried_item)
+        {
+            std::mem::swap(slot_item, &mut self.carried_item);
+        }
+    }
+
+    /// Moves the item to the targets, first to the stacks of the same item then to empty slots
+    /// Returns the modified slots
+    fn insert(&mut self, item: &mut Slot, targets: &[usize]) -> Vec<usize> {
+        let mut changed = vec![];
+        for fill_empty in [false, true] {
+            for target in targets.iter().copied() {
+                if !item.is_present() {
+                    break;
+                }
+                let target_item = &mut self.slots[target];
+                if target_item.is_present() == fill_empty {
+                    continue;
+                }
+                if target_item.try_merge(item, MAX_STACK_SIZE) > 0 {
+                    changed.push(target);
+                }
+            }
+        }
+        changed
+    }
+
+    /// Moves the item of the slot to the other part of the window
+    /// Returns the modified slots
+    fn quick_move(&mut self, slot: usize) -> Vec<usize> {
+        let mut item = std::mem::take(&mut self.slots[slot]);
+        if !item.is_present() {
+            return vec![];
+        }
+        let targets = self.quick_move_targets(slot).into_iter()
+            .flatten()
+            .filter(|target| *target != slot)
+            .collect::<Vec<_>>();
+
+        let mut changed = self.insert(&mut item, &targets);
+        self.slots[slot] = item;
+        if !changed.is_empty() {
+            changed.push(slot);
+        }
+        changed
+    }
+
+    fn start_drag(&mut self, kind: DragKind) -> bool {
+        if !self.carried_item.is_present() {
+            return false;
+        }
+        self.drag = Some((kind, vec![]));
+        true
+    }
+
+    /// Splits the carried item between the dragged over slots
+    /// Returns the modified slots
+    fn distribute(&mut self, kind: DragKind, slots: &[usize]) -> Vec<usize> {
+        let slots = slots.iter().copied()
+            .filter(|slot| {
+                let item = &self.slots[*slot];
+                !item.is_present()
+                    || (item.is_stackable_with(&self.carried_item) && item.item_count() < MAX_STACK_SIZE)
+            })
+            .collect::<Vec<_>>();
+        let carried_count = self.carried_item.item_count() as usize;
+        if slots.is_empty() || (kind == DragKind::Left && carried_count < slots.len()) {
+            return vec![];
+        }
+        let per_slot = match kind {
+            DragKind::Left => (carried_count / slots.len()) as u8,
+            DragKind::Right => 1,
+        };
+
+        let mut changed = vec![];
+        for slot in slots {
+            if !self.carried_item.is_present() {
+                break;
+            }
+            let mut placed = self.carried_item.split(per_slot);
+            self.slots[slot].try_merge(&mut placed, MAX_STACK_SIZE);
+            self.carried_item.try_merge(&mut placed, u8::MAX);
+            changed.push(slot);
+        }
+        changed
+    }
+}
+
+/// Player inventory window of a player, picked up items are added to it
+#[derive(Component, Clone, Debug)]
+pub struct InventoryComponent(pub Inventory);
+impl Default for InventoryComponent {
+    fn default() -> Self {
+        Self(Inventory::player())
+    }
+}
+
+/// Content of a player's inventory, named after the vanilla player inventory window
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlayerInventory {
+    pub crafting_output: Slot,
+    /// Crafting grid from the top left to the bottom right
+    pub crafting_input: [Slot; 4],
+    /// Armor from the head to the feet
+    pub armor: [Slot; 4],
+    pub main_inventory: [Slot; 27],
+    pub hotbar: [Slot; 9],
+    pub offhand: Slot,
+}
+
+impl PlayerInventory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn armor_head(&mut self) -> &mut Slot {
+        &mut self.armor[0]
+    }
+    pub fn armor_chest(&mut self) -> &mut Slot {
+        &mut self.armor[1]
+    }
+    pub fn armor_legs(&mut self) -> &mut Slot {
+        &mut self.armor[2]
+    }
+    pub fn armor_feet(&mut self) -> &mut Slot {
+        &mut self.armor[3]
+    }
+
+    /// Slot at the given index of the player inventory window
+    /// See <https://wiki.vg/Inventory#Player_Inventory>
+    pub fn slot_at(&mut self, index: usize) -> Option<&mut Slot> {
+        match index {
+            0 => Some(&mut self.crafting_output),
+            1..=4 => Some(&mut self.crafting_input[index - 1]),
+            5..=8 => Some(&mut self.armor[index - 5]),
+            PLAYER_MAIN_START..=35 => Some(&mut self.main_inventory[index - PLAYER_MAIN_START]),
+            PLAYER_HOTBAR_START..=44 => Some(&mut self.hotbar[index - PLAYER_HOTBAR_START]),
+            PLAYER_OFFHAND_SLOT => Some(&mut self.offhand),
+            _ => None,
+        }
+    }
+
+    /// Every slot in the order of the player inventory window
+    pub fn to_slots(&self) -> Vec<Slot> {
+        std::iter::once(&self.crafting_output)
+            .chain(&self.crafting_input)
+            .chain(&self.armor)
+            .chain(&self.main_inventory)
+            .chain(&self.hotbar)
+            .chain(std::iter::once(&self.offhand))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(item_id: i32, item_count: u8) -> Slot {
+        Slot::Present { item_id, item_count, nbt: nbt::Blob::new() }
+    }
+
+    fn click(slot_id: i16, button: i8, mode: i32) -> S0AClickContainer {
+        S0AClickContainer {
+            window_id: 0,
+            state_id: 0,
+            slot_id,
+            button,
+            mode,
+            slots: vec![],
+            carried_item: Slot::NotPresent,
+        }
+    }
+
+    #[test]
+    fn left_click_pickup() {
+        let mut inventory = Inventory::player();
+        inventory.set_slot(10, item(1, 32));
+        inventory.set_slot(11, item(1, 40));
+
+        let result = inventory.click(&click(10, 0, 0));
+        assert_eq!(result.carried_item, item(1, 32));
+        assert_eq!(result.slot_updates.len(), 1);
+        assert_eq!(result.slot_updates[0].slot, 10);
+        assert_eq!(result.slot_updates[0].slot_data, Slot::NotPresent);
+
+        // Stacking on a slot of the same item only fills it up to a full stack
+        let result = inventory.click(&click(11, 0, 0));
+        assert_eq!(inventory.get_slot(11), Some(&item(1, 64)));
+        assert_eq!(result.carried_item, item(1, 8));
+
+        // Different items are swapped
+        inventory.set_slot(12, item(2, 1));
+        inventory.click(&click(12, 0, 0));
+        assert_eq!(inventory.get_slot(12), Some(&item(1, 8)));
+        assert_eq!(inventory.carried_item(), &item(2, 1));
+    }
+
+    #[test]
+    fn shift_click_moves_to_hotbar() {
+        let mut inventory = Inventory::player();
+        inventory.set_slot(37, item(3, 60));
+        inventory.set_slot(20, item(3, 10));
+
+        let result = inventory.click(&click(20, 0, 1));
+        // The existing stack is filled before the first empty slot
+        assert_eq!(inventory.get_slot(37), Some(&item(3, 64)));
+        assert_eq!(inventory.get_slot(36), Some(&item(3, 6)));
+        assert_eq!(inventory.get_slot(20), Some(&Slot::NotPresent));
+        let mut updated = result.slot_updates.iter().map(|u| u.slot).collect::<Vec<_>>();
+        updated.sort();
+        assert_eq!(updated, [20, 36, 37]);
+        assert_eq!(result.carried_item, Slot::NotPresent);
+    }
+
+    #[test]
+    fn added_items_fill_the_hotbar_first() {
+        let mut inventory = Inventory::player();
+        inventory.set_slot(20, item(3, 60));
+        inventory.set_slot(PLAYER_HOTBAR_START, item(4, 1));
+
+        let mut added = item(3, 10);
+        let updates = inventory.add_item(&mut added);
+        assert_eq!(added, Slot::NotPresent);
+        assert_eq!(inventory.get_slot(20), Some(&item(3, 64)));
+        assert_eq!(inventory.get_slot(PLAYER_HOTBAR_START + 1), Some(&item(3, 6)));
+        let mut updated = updates.iter().map(|u| u.slot).collect::<Vec<_>>();
+        updated.sort();
+        assert_eq!(updated, [20, PLAYER_HOTBAR_START as i16 + 1]);
+        assert!(updates.iter().all(|u| u.state_id == inventory.state_id()));
+
+        // Items that don't fit are left
+        for slot in PLAYER_MAIN_START..PLAYER_OFFHAND_SLOT {
+            inventory.set_slot(slot, item(5, 64));
+        }
+        let mut added = item(6, 3);
+        assert!(inventory.add_item(&mut added).is_empty());
+        assert_eq!(added, item(6, 3));
+    }
+
+    #[test]
+    fn right_click_splits_stack() {
+        let mut inventory = Inventory::player();
+        inventory.set_slot(15, item(4, 7));
+
+        let result = inventory.click(&click(15, 1, 0));
+        assert_eq!(result.carried_item, item(4, 4));
+        assert_eq!(inventory.get_slot(15), Some(&item(4, 3)));
+
+        // Right clicking an empty slot places a single item
+        inventory.click(&click(16, 1, 0));
+        assert_eq!(inventory.get_slot(16), Some(&item(4, 1)));
+        assert_eq!(inventory.carried_item(), &item(4, 3));
+    }
+
+    #[test]
+    fn drag_buttons_match_the_drag_kind() {
+        let mut inventory = Inventory::player();
+        inventory.set_slot(15, item(4, 8));
+        inventory.click(&click(15, 0, 0));
+
+        // Adding a slot with the right button to a left drag cancels it
+        assert!(inventory.click(&click(OUTSIDE_SLOT, 0, 5)).content_update.is_none());
+        assert!(inventory.click(&click(20, 5, 5)).content_update.is_some());
+        assert!(inventory.click(&click(OUTSIDE_SLOT, 2, 5)).content_update.is_some());
+        assert_eq!(inventory.get_slot(20), Some(&Slot::NotPresent));
+
+        inventory.click(&click(OUTSIDE_SLOT, 0, 5));
+        inventory.click(&click(20, 1, 5));
+        inventory.click(&click(21, 1, 5));
+        // Ending a left drag with the right button cancels it
+        assert!(inventory.click(&click(OUTSIDE_SLOT, 6, 5)).content_update.is_some());
+        assert_eq!(inventory.carried_item(), &item(4, 8));
+
+        inventory.click(&click(OUTSIDE_SLOT, 0, 5));
+        inventory.click(&click(20, 1, 5));
+        inventory.click(&click(21, 1, 5));
+        let result = inventory.click(&click(OUTSIDE_SLOT, 2, 5));
+        assert_eq!(result.slot_updates.len(), 2);
+        assert_eq!(inventory.get_slot(20), Some(&item(4, 4)));
+        assert_eq!(inventory.get_slot(21), Some(&item(4, 4)));
+    }
+
+    #[test]
+    fn invalid_click_resyncs() {
+        let mut inventory = Inventory::player();
+        let result = inventory.click(&click(100, 0, 0));
+        assert!(result.slot_updates.is_empty());
+        assert_eq!(result.content_update.unwrap().slots.len(), PLAYER_INVENTORY_SIZE);
+    }
+
+    #[test]
+    fn player_inventory_slot_mapping() {
+        let mut inventory = PlayerInventory::new();
+        *inventory.slot_at(5).unwrap() = item(10, 1);
+        *inventory.slot_at(36).unwrap() = item(11, 2);
+        *inventory.slot_at(45).unwrap() = item(12, 3);
+        *inventory.slot_at(9).unwrap() = item(13, 4);
+
+        assert_eq!(inventory.armor_head(), &item(10, 1));
+        assert_eq!(inventory.hotbar[0], item(11, 2));
+        assert_eq!(inventory.offhand, item(12, 3));
+        assert_eq!(inventory.main_inventory[0], item(13, 4));
+        assert!(inventory.slot_at(46).is_none());
+
+        let slots = inventory.to_slots();
+        assert_eq!(slots.len(), PLAYER_INVENTORY_SIZE);
+        assert_eq!(slots[5], item(10, 1));
+        assert_eq!(slots[36], item(11, 2));
+        assert_eq!(slots[45], item(12, 3));
+    }
+}
@@ -1,3 +1,18 @@
+pub mod async_chunk_provider;
+pub mod boss_bar;
+pub mod chat_manager;
+pub mod chunk_holder;
 pub mod chunk_manager;
+pub mod chunk_streamer;
+pub mod digging;
 pub mod entity;
+pub mod entity_pool;
+pub mod inventory;
 pub mod mc_app;
+pub mod scoreboard;
+pub mod spatial_index;
+pub mod tab_list;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+pub mod title;
+pub mod world_border;
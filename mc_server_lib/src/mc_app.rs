@@ -1,13 +1,44 @@
-use crate::entity::chunk::*;
+use crate::boss_bar::{ BossBarManager, boss_bar_system };
+use crate::chunk_holder::{ ChunkHolder, chunk_holder_unload, chunk_holder_block_changes };
+use crate::chunk_manager::ChunkProvider;
+use crate::digging::digging_progress_system;
+use crate::scoreboard::{ Scoreboard, scoreboard_system };
+use crate::spatial_index::{ SpatialIndex, spatial_index_update };
+use crate::tab_list::{ TabList, tab_list_system };
+use crate::entity::{
+    chunk::*,
+    ClientComponent, LocationComponent, NetworkIdComponent, ObjectUuidComponent, UsernameComponent,
+};
+use crate::entity::collision::{ BlockCollisionComponent, CollisionShape };
+use crate::entity::equipment::{ EquipmentComponent, equipment_sync_system };
+use crate::entity::experience::{ ExperienceComponent, experience_orb_spawn_system, experience_orb_system };
+use crate::entity::idle_timeout::{ IdleTimeoutComponent, idle_timeout_system };
+use crate::entity::item::{ item_pickup_system, item_spawn_system };
+use crate::entity::living::living_entity_damage_system;
+use crate::entity::metadata::metadata_sync_system;
+use crate::entity::ping::{ PingComponent, ping_broadcast_system };
+use crate::entity::player_visibility::{ PlayerVisibilityTracker, player_visibility_system };
+use crate::entity::movement_validation::{ MovementValidator, MovementValidatorComponent };
+use crate::entity::teleport::TeleportComponent;
+use crate::inventory::InventoryComponent;
+use crate::entity_pool::{ entity_visibility_system, entity_velocity_system, remove_scheduled_system };
+use mc_networking::client::client_event::LoginStartResult;
+use mc_networking::data_types::{ Gamemode, Identifier };
+use mc_networking::packets::client_bound::*;
+use mc_utils::Location;
 
 use std::any::TypeId;
+use std::time::Duration;
 
 use bevy_ecs::schedule::{
-    Schedule, SystemStage, SystemSet,
-    StageLabel, StageLabelId, IntoSystemDescriptor,
-    ParallelSystemDescriptorCoercion,
+    Schedule, Stage, SystemStage, SystemSet,
+    StageLabel, StageLabelId, IntoSystemDescriptor, SystemDescriptor,
+    ParallelSystemDescriptorCoercion, ExclusiveSystemDescriptorCoercion,
 };
+use bevy_ecs::entity::Entity;
+use bevy_ecs::system::{ Commands, Query, SystemState };
 use bevy_ecs::world::World;
+use uuid::Uuid;
 
 fn chunks_systems() -> SystemSet {
     SystemSet::new()
@@ -16,13 +47,24 @@ fn chunks_systems() -> SystemSet {
         .with_system(chunk_observer_chunk_loadings
             .label("chunk_observer_chunk_loadings")
             .after("chunk_locations_update"))
+        .with_system(chunk_holder_block_changes
+            .label("chunk_holder_block_changes"))
+        .with_system(chunk_holder_unload
+            .after("chunk_locations_update")
+            .after("chunk_holder_block_changes"))
 }
 
+/// Stages of a [McApp], executed in declaration order every tick
+/// Systems of parallel stages have no order between them,
+/// use labels with `.before` and `.after` when one must run before another
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum McAppStage {
     BeforeTick,
     Tick,
     AfterTick,
+    /// Always the last stage, meant for flushing outgoing packets after every gameplay system ran
+    /// This stage is single threaded and its systems run in insertion order
+    PostTick,
 }
 
 impl StageLabel for McAppStage {
@@ -31,6 +73,7 @@ impl StageLabel for McAppStage {
             Self::BeforeTick => "before_tick",
             Self::Tick => "tick",
             Self::AfterTick => "after_tick",
+            Self::PostTick => "post_tick",
         }
     }
 
@@ -43,10 +86,18 @@ impl StageLabel for McAppStage {
     }
 }
 
+/// Label of the n-th system added to [McAppStage::PostTick]
+/// Labels are static strings, they are only created when building the app so leaking them is fine
+fn post_tick_label(index: usize) -> &'static str {
+    Box::leak(format!("post_tick_{index}").into_boxed_str())
+}
+
 /// Wrapper arroun the bevy_ecs's schedule that adds required systems from the lib
 /// To add custom systems use [McSchedule::set_custom_schedule]
 pub struct McApp {
     schedule: Schedule,
+    on_shutdown: SystemStage,
+    post_tick_systems: usize,
     pub world: World,
 }
 
@@ -54,31 +105,575 @@ impl McApp {
     /// Creates a new [McSchedule]
     pub fn new() -> Self {
         let mut schedule = Schedule::default();
-        let world = World::default();
+        let mut world = World::default();
+        world.init_resource::<ChunkHolder>();
+        world.init_resource::<TabList>();
+        world.init_resource::<BossBarManager>();
+        world.init_resource::<Scoreboard>();
+        world.init_resource::<SpatialIndex>();
 
         schedule.add_stage(McAppStage::BeforeTick, SystemStage::parallel());
         schedule.add_stage(McAppStage::Tick, SystemStage::parallel());
         schedule.add_stage(McAppStage::AfterTick, SystemStage::parallel());
+        schedule.add_stage(McAppStage::PostTick, SystemStage::single_threaded());
 
         schedule.add_system_set_to_stage(McAppStage::Tick, chunks_systems());
+        schedule.add_system_to_stage(McAppStage::Tick, experience_orb_system);
+        schedule.add_system_to_stage(McAppStage::Tick, item_pickup_system);
+        // Systems reading the index run after it in the same stage or in a later stage
+        schedule.add_system_to_stage(McAppStage::AfterTick, spatial_index_update
+            .label("spatial_index_update"));
+        schedule.add_system_to_stage(McAppStage::AfterTick, metadata_sync_system
+            .after("spatial_index_update"));
+        schedule.add_system_to_stage(McAppStage::AfterTick, experience_orb_spawn_system);
+        schedule.add_system_to_stage(McAppStage::AfterTick, item_spawn_system);
+        schedule.add_system_to_stage(McAppStage::AfterTick, entity_visibility_system);
+        schedule.add_system_to_stage(McAppStage::AfterTick, player_visibility_system);
+        schedule.add_system_to_stage(McAppStage::AfterTick, equipment_sync_system);
+        schedule.add_system_to_stage(McAppStage::AfterTick, entity_velocity_system);
+        schedule.add_system_to_stage(McAppStage::AfterTick, living_entity_damage_system);
+        schedule.add_system_to_stage(McAppStage::AfterTick, remove_scheduled_system);
+        schedule.add_system_to_stage(McAppStage::AfterTick, digging_progress_system);
+        schedule.add_system_to_stage(McAppStage::AfterTick, idle_timeout_system);
+        schedule.add_system_to_stage(McAppStage::AfterTick, boss_bar_system);
+        schedule.add_system_to_stage(McAppStage::AfterTick, scoreboard_system);
+        schedule.add_system_to_stage(McAppStage::AfterTick, ping_broadcast_system
+            .label("ping_broadcast_system"));
+        schedule.add_system_to_stage(McAppStage::AfterTick, tab_list_system
+            .after("ping_broadcast_system"));
 
         Self {
             schedule,
+            on_shutdown: SystemStage::single_threaded(),
+            post_tick_systems: 0,
             world,
         }
     }
 
+    /// Adds a system to the given stage
+    /// Systems added to [McAppStage::PostTick] are ordered after the previously added ones
     pub fn add_system<Params>(
         &mut self, stage: McAppStage, system: impl IntoSystemDescriptor<Params>
     ) {
-        self.schedule.add_system_to_stage(stage, system);
+        if stage != McAppStage::PostTick {
+            self.schedule.add_system_to_stage(stage, system);
+            return;
+        }
+
+        let label = post_tick_label(self.post_tick_systems);
+        let previous = self.post_tick_systems.checked_sub(1).map(post_tick_label);
+        self.post_tick_systems += 1;
+        let descriptor = match system.into_descriptor() {
+            SystemDescriptor::Parallel(descriptor) => {
+                let descriptor = descriptor.label(label);
+                SystemDescriptor::Parallel(match previous {
+                    Some(previous) => descriptor.after(previous),
+                    None => descriptor,
+                })
+            }
+            SystemDescriptor::Exclusive(descriptor) => {
+                let descriptor = descriptor.label(label);
+                SystemDescriptor::Exclusive(match previous {
+                    Some(previous) => descriptor.after(previous),
+                    None => descriptor,
+                })
+            }
+        };
+        self.schedule.add_system_to_stage(stage, descriptor);
     }
     pub fn add_system_set(&mut self, stage: McAppStage, system: SystemSet) {
         self.schedule.add_system_set_to_stage(stage, system);
     }
 
+    /// Adds a system only executed by [McApp::shutdown]
+    pub fn add_shutdown_system<Params>(&mut self, system: impl IntoSystemDescriptor<Params>) {
+        self.on_shutdown.add_system(system);
+    }
+    pub fn add_shutdown_system_set(&mut self, system: SystemSet) {
+        self.on_shutdown.add_system_set(system);
+    }
+
     /// Execute "execute" on the created schedule
     pub fn tick(&mut self) {
         self.schedule.run_once(&mut self.world)
     }
+
+    /// Runs the shutdown systems, should be called once after the last tick
+    pub fn shutdown(&mut self) {
+        self.on_shutdown.run(&mut self.world)
+    }
+
+    /// Changes the view distance of the connected players, loading and unloading the chunks
+    /// entering and leaving their view, and of the players joining through the [LoginPipeline]
+    /// resource if there is one
+    pub fn set_view_distance(&mut self, view_distance: i32) {
+        if let Some(mut pipeline) = self.world.get_resource_mut::<LoginPipeline>() {
+            pipeline.view_distance = view_distance;
+        }
+
+        let mut state = SystemState::<(
+            Query<(Entity, &mut ChunkObserverComponent, &ChunkLocationComponent, Option<&ClientComponent>)>,
+            Commands,
+        )>::new(&mut self.world);
+        let (mut observers, mut commands) = state.get_mut(&mut self.world);
+        observers.for_each_mut(|(entity, mut observer, chunk_loc, client)| {
+            if let Some(client) = client {
+                client.0.send_packet_sync(&C49SetRenderDistance { view_distance });
+            }
+            observer.set_radius(entity, (chunk_loc.x, chunk_loc.z), view_distance, &mut commands);
+        });
+        state.apply(&mut self.world);
+    }
+}
+
+/// Dimension the players spawn into when joining
+#[derive(Clone, Debug)]
+pub struct DimensionConfig {
+    pub dimension_type: Identifier,
+    pub dimension_name: Identifier,
+    /// Every dimension of the server, should include [DimensionConfig::dimension_name]
+    pub dimension_names: Vec<Identifier>,
+    pub hashed_seed: u64,
+    pub is_flat: bool,
+    pub is_debug: bool,
+}
+impl DimensionConfig {
+    /// A dimension using the same identifier for its type and its name
+    pub fn new(name: impl Into<Identifier>) -> Self {
+        let name = name.into();
+        Self {
+            dimension_type: name.clone(),
+            dimension_name: name.clone(),
+            dimension_names: vec![name],
+            hashed_seed: 0,
+            is_flat: true,
+            is_debug: false,
+        }
+    }
+}
+
+type RegistryCodecHook = Box<dyn Fn(&DimensionConfig) -> C23RegistryCodec + Send + Sync>;
+type ChunkProviderHook = Box<dyn Fn() -> Box<dyn ChunkProvider> + Send + Sync>;
+type MovementValidatorHook = Box<dyn Fn() -> Box<dyn MovementValidator> + Send + Sync>;
+
+/// Standard sequence bringing a client from the login to the play state
+/// Can be used as a resource by the systems handling the client events
+pub struct LoginPipeline {
+    pub dimension: DimensionConfig,
+    pub spawn_location: Location,
+    pub gamemode: Gamemode,
+    pub max_players: i32,
+    /// View distance in chunks, also used as the radius of the [ChunkObserverComponent]
+    pub view_distance: i32,
+    /// Sent in the minecraft:brand plugin message
+    pub brand: String,
+    registry_codec: RegistryCodecHook,
+    chunk_provider: Option<ChunkProviderHook>,
+    movement_validator: Option<MovementValidatorHook>,
+    block_collisions: Option<CollisionShape>,
+    idle_timeout: Option<Duration>,
+}
+
+impl LoginPipeline {
+    /// Creates a new [LoginPipeline], `registry_codec` builds the codec sent in the [C23Login] packet
+    pub fn new(
+        dimension: DimensionConfig,
+        registry_codec: impl Fn(&DimensionConfig) -> C23RegistryCodec + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            dimension,
+            spawn_location: Location::default(),
+            gamemode: Gamemode::Survival,
+            max_players: 20,
+            view_distance: 12,
+            brand: "mc_server_rs".to_string(),
+            registry_codec: Box::new(registry_codec),
+            chunk_provider: None,
+            movement_validator: None,
+            block_collisions: None,
+            idle_timeout: None,
+        }
+    }
+
+    /// Replaces the hook building the codec sent in the [C23Login] packet
+    pub fn with_registry_codec(
+        mut self,
+        registry_codec: impl Fn(&DimensionConfig) -> C23RegistryCodec + Send + Sync + 'static,
+    ) -> Self {
+        self.registry_codec = Box::new(registry_codec);
+        self
+    }
+
+    /// Gives a [ChunkObserverComponent] using the created provider to every joining player
+    pub fn with_chunk_provider(
+        mut self,
+        chunk_provider: impl Fn() -> Box<dyn ChunkProvider> + Send + Sync + 'static,
+    ) -> Self {
+        self.chunk_provider = Some(Box::new(chunk_provider));
+        self
+    }
+
+    /// Gives a [MovementValidatorComponent] using the created validator to every joining player
+    pub fn with_movement_validator(
+        mut self,
+        movement_validator: impl Fn() -> Box<dyn MovementValidator> + Send + Sync + 'static,
+    ) -> Self {
+        self.movement_validator = Some(Box::new(movement_validator));
+        self
+    }
+
+    /// Gives a [BlockCollisionComponent] to every joining player, pushing them out of the blocks
+    /// having a collision shape
+    pub fn with_block_collisions(mut self, collision_shape: CollisionShape) -> Self {
+        self.block_collisions = Some(collision_shape);
+        self
+    }
+
+    /// Gives an [IdleTimeoutComponent] to every joining player, disconnecting the ones that
+    /// don't send any packet for the given duration
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_spawn_location(mut self, spawn_location: Location) -> Self {
+        self.spawn_location = spawn_location;
+        self
+    }
+
+    pub fn with_gamemode(mut self, gamemode: Gamemode) -> Self {
+        self.gamemode = gamemode;
+        self
+    }
+
+    /// Accepts the player in offline mode, to be sent as the response of the LoginStart event
+    /// Adds the [ObjectUuidComponent] and [UsernameComponent] to the entity
+    pub fn accept_login(
+        &self, entity: Entity, username: String, commands: &mut Commands,
+    ) -> LoginStartResult {
+        let uuid = Uuid::new_v3(
+            &Uuid::new_v4(),
+            format!("OfflinePlayer:{}", username).as_bytes(),
+        );
+        commands.entity(entity)
+            .insert(ObjectUuidComponent(uuid))
+            .insert(UsernameComponent(username.clone()));
+
+        LoginStartResult::Accept {
+            compress: false,
+            encrypt: false,
+            username, uuid,
+        }
+    }
+
+    /// Sends the join sequence to a client that just logged in
+    /// and adds the components of a player to its entity
+    pub fn join(
+        &self, entity: Entity, client: &ClientComponent,
+        username: &str, uuid: Uuid, commands: &mut Commands,
+    ) -> NetworkIdComponent {
+        let network_id = NetworkIdComponent::new();
+        let spawn_location = self.spawn_location;
+
+        let mut entity_commands = commands.entity(entity);
+        entity_commands
+            .insert(network_id)
+            .insert(ChunkLocationComponent::new(spawn_location.chunk_x(), spawn_location.chunk_z()))
+            .insert(LocationComponent(spawn_location))
+            .insert(PingComponent::default())
+            .insert(ExperienceComponent::default())
+            .insert(InventoryComponent::default())
+            .insert(EquipmentComponent::default())
+            .insert(PlayerVisibilityTracker::default());
+        if let Some(chunk_provider) = &self.chunk_provider {
+            entity_commands.insert(ChunkObserverComponent {
+                radius: self.view_distance,
+                loaded_chunks: Default::default(),
+                chunk_provider: chunk_provider(),
+            });
+        }
+        if let Some(movement_validator) = &self.movement_validator {
+            entity_commands.insert(MovementValidatorComponent(movement_validator()));
+        }
+        if let Some(collision_shape) = self.block_collisions {
+            entity_commands.insert(BlockCollisionComponent(collision_shape));
+        }
+        if let Some(idle_timeout) = self.idle_timeout {
+            entity_commands.insert(IdleTimeoutComponent(idle_timeout));
+        }
+
+        client.0.send_packet_sync(&C23Login {
+            entity_id: network_id.0,
+            is_hardcore: false,
+            gamemode: self.gamemode,
+            previous_gamemode: None,
+            dimension_type: self.dimension.dimension_type.clone(),
+            dimension_name: self.dimension.dimension_name.clone(),
+            dimension_names: self.dimension.dimension_names.clone(),
+            registry_codec: (self.registry_codec)(&self.dimension),
+            hashed_seed: self.dimension.hashed_seed,
+            max_players: self.max_players,
+            view_distance: self.view_distance,
+            simulation_distance: self.view_distance,
+            reduced_debug_info: false,
+            enable_respawn_screen: true,
+            is_debug: self.dimension.is_debug,
+            is_flat: self.dimension.is_flat,
+            death_location: None,
+        });
+
+        client.0.send_brand(&self.brand);
+
+        client.0.send_packet_sync(&C2FPlayerAbilities::new(
+            true, false, false, self.gamemode == Gamemode::Creative, 1., 0.1
+        ));
+        client.0.send_packet_sync(&C47SetHeldItem {
+            slot: 0,
+        });
+
+        // The player is sent the whole list once its entry is added
+        let entry = C34AddPlayer {
+            uuid,
+            name: username.to_string(),
+            properties: vec![],
+            gamemode: self.gamemode,
+            ping: 0,
+            display_name: None,
+            sig_data: (),
+        };
+        commands.add(move |world: &mut World| {
+            world.resource_mut::<TabList>().insert_player(entity, entry);
+        });
+
+        client.0.send_packet_sync(&C4ASetDefaultSpawnPosition {
+            location: spawn_location.block_position(),
+            angle: spawn_location.pitch,
+        });
+        let mut teleport = TeleportComponent::default();
+        teleport.teleport(client, spawn_location, RelativeFlags::new());
+        commands.entity(entity).insert(teleport);
+
+        network_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::async_chunk_provider::{ AsyncChunkProvider, async_chunk_provider_system };
+    use crate::test_utils::{ connected_client, read_packets_until };
+    use mc_networking::client::{ ClientState, client_event::ClientEvent };
+    use mc_networking::data_types::{ Position, encoder::PacketEncoder };
+    use mc_networking::packets::{ PacketCompression, RawPacket };
+    use mc_utils::{ ChunkData, ChunkGenerator };
+
+    use bevy_ecs::system::{ CommandQueue, ResMut };
+    use bytes::BytesMut;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpStream;
+
+    async fn write_packet(peer: &mut TcpStream, packet_id: i32, encoder: PacketEncoder) {
+        let mut bytes = BytesMut::new();
+        RawPacket::new(packet_id, encoder.into_inner().freeze())
+            .encode(PacketCompression::default(), &mut bytes);
+        peer.write_all(&bytes).await.unwrap();
+    }
+
+    #[derive(Default)]
+    struct ExecutionOrder(Vec<&'static str>);
+
+    fn record(name: &'static str) -> impl FnMut(ResMut<ExecutionOrder>) {
+        move |mut order: ResMut<ExecutionOrder>| order.0.push(name)
+    }
+
+    #[test]
+    fn stages_run_in_order() {
+        let mut app = McApp::new();
+        app.world.init_resource::<ExecutionOrder>();
+
+        app.add_system(McAppStage::PostTick, record("post_tick_1"));
+        app.add_system(McAppStage::Tick, record("tick"));
+        app.add_system(McAppStage::PostTick, record("post_tick_2"));
+        app.add_system(McAppStage::BeforeTick, record("before_tick"));
+        app.add_system(McAppStage::PostTick, record("post_tick_3"));
+        app.add_system(McAppStage::AfterTick, record("after_tick"));
+
+        app.tick();
+        app.tick();
+
+        let tick = ["before_tick", "tick", "after_tick", "post_tick_1", "post_tick_2", "post_tick_3"];
+        assert_eq!(app.world.resource::<ExecutionOrder>().0, [tick, tick].concat());
+    }
+
+    #[test]
+    fn shutdown_systems_run_on_shutdown() {
+        let mut app = McApp::new();
+        app.world.init_resource::<ExecutionOrder>();
+        app.add_system(McAppStage::Tick, record("tick"));
+        app.add_shutdown_system(record("shutdown"));
+
+        app.tick();
+        assert_eq!(app.world.resource::<ExecutionOrder>().0, ["tick"]);
+        app.shutdown();
+        assert_eq!(app.world.resource::<ExecutionOrder>().0, ["tick", "shutdown"]);
+    }
+
+    #[test]
+    fn spatial_index_is_maintained() {
+        let mut app = McApp::new();
+        let entity = app.world.spawn()
+            .insert(LocationComponent(Location { x: 40., ..Location::default() }))
+            .id();
+        app.tick();
+        let index = app.world.resource::<SpatialIndex>();
+        assert_eq!(index.query_around(&Location { x: 38., ..Location::default() }, 4.), vec![entity]);
+
+        app.world.despawn(entity);
+        app.tick();
+        assert!(app.world.resource::<SpatialIndex>().is_empty());
+    }
+
+    #[tokio::test]
+    async fn login_pipeline_reaches_play() {
+        let (ClientComponent(client), event_receiver, mut peer) = connected_client().await;
+
+        let mut handshake = PacketEncoder::default();
+        handshake.write_varint(759);
+        handshake.write_string("localhost");
+        handshake.write_u16(25565);
+        handshake.write_varint(2);
+        write_packet(&mut peer, 0x00, handshake).await;
+        let mut login_start = PacketEncoder::default();
+        login_start.write_string("Steve");
+        login_start.write_bool(false);
+        write_packet(&mut peer, 0x00, login_start).await;
+
+        let pipeline = LoginPipeline::new(DimensionConfig::new("test:flat"), |_| C23RegistryCodec {
+            dimension_types: vec![],
+            biomes: vec![],
+            chat_types: (),
+        }).with_spawn_location(Location { x: 20.5, y: 10., z: -3.5, ..Location::default() });
+
+        let mut world = World::default();
+        let entity = world.spawn().insert(ClientComponent(client.clone())).id();
+        let mut queue = CommandQueue::default();
+
+        let username = match event_receiver.recv_async().await.unwrap() {
+            ClientEvent::LoginStart { username, response } => {
+                let result = pipeline.accept_login(entity, username.clone(), &mut Commands::new(&mut queue, &world));
+                response.send(result).unwrap();
+                username
+            }
+            event => panic!("expected a login start, got {event:?}"),
+        };
+        assert!(matches!(event_receiver.recv_async().await.unwrap(), ClientEvent::LoggedIn));
+        assert_eq!(client.get_state().await, ClientState::Play);
+        queue.apply(&mut world);
+
+        let uuid = world.get::<ObjectUuidComponent>(entity).unwrap().0;
+        let network_id = pipeline.join(
+            entity, world.get::<ClientComponent>(entity).unwrap(),
+            &username, uuid, &mut Commands::new(&mut queue, &world),
+        );
+        queue.apply(&mut world);
+
+        assert_eq!(world.get::<UsernameComponent>(entity).unwrap().0, "Steve");
+        assert_eq!(world.get::<NetworkIdComponent>(entity).unwrap().0, network_id.0);
+        assert_eq!(world.get::<LocationComponent>(entity).unwrap().0.x, 20.5);
+        let chunk_location = world.get::<ChunkLocationComponent>(entity).unwrap();
+        assert_eq!((chunk_location.x, chunk_location.z), (1, -1));
+        assert!(world.get::<ChunkObserverComponent>(entity).is_none());
+
+        let ids = read_packets_until(&mut peer, C36SynchronizePlayerPosition::PACKET_ID).await
+            .iter()
+            .map(|packet| packet.packet_id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids.first(), Some(&C02LoginSuccess::PACKET_ID));
+        assert_eq!(ids.get(1), Some(&C23Login::PACKET_ID));
+    }
+
+    /// Records the loaded and unloaded chunks
+    #[derive(Clone, Default)]
+    struct RecordingChunkProvider(std::sync::Arc<std::sync::Mutex<(Vec<(i32, i32)>, Vec<(i32, i32)>)>>);
+    impl ChunkProvider for RecordingChunkProvider {
+        fn load_chunk(&mut self, _: Entity, _: &mut Commands, chunk_x: i32, chunk_z: i32) {
+            self.0.lock().unwrap().0.push((chunk_x, chunk_z));
+        }
+        fn unload_chunk(&mut self, _: Entity, _: &mut Commands, chunk_x: i32, chunk_z: i32) {
+            self.0.lock().unwrap().1.push((chunk_x, chunk_z));
+        }
+    }
+
+    #[test]
+    fn view_distance_change_loads_chunks() {
+        let mut app = McApp::new();
+        let provider = RecordingChunkProvider::default();
+        let player = app.world.spawn()
+            .insert(ChunkObserverComponent {
+                radius: 1,
+                loaded_chunks: crate::chunk_streamer::chunks_in_view((5, 5), 1).into_iter().collect(),
+                chunk_provider: Box::new(provider.clone()),
+            })
+            .insert(ChunkLocationComponent::new(5, 5))
+            .id();
+
+        app.set_view_distance(2);
+        {
+            let (loaded, unloaded) = &*provider.0.lock().unwrap();
+            // Only the ring around the already loaded chunks is loaded
+            assert_eq!(loaded.len(), 16);
+            assert!(loaded.iter().all(|(x, z)| (x - 5).abs() == 2 || (z - 5).abs() == 2));
+            assert!(unloaded.is_empty());
+        }
+        let observer = app.world.get::<ChunkObserverComponent>(player).unwrap();
+        assert_eq!(observer.radius, 2);
+        assert_eq!(observer.loaded_chunks.len(), 25);
+
+        app.set_view_distance(0);
+        assert_eq!(provider.0.lock().unwrap().1.len(), 24);
+        assert_eq!(app.world.get::<ChunkObserverComponent>(player).unwrap().loaded_chunks.len(), 1);
+    }
+
+    struct EmptyGenerator;
+    impl ChunkGenerator for EmptyGenerator {
+        fn generate(&self, _chunk_x: i32, _chunk_z: i32, sections: usize) -> ChunkData {
+            ChunkData::new(sections)
+        }
+    }
+
+    #[tokio::test]
+    async fn block_changes_reach_the_players_sent_the_chunk() {
+        let (client, _events, mut peer) = connected_client().await;
+        let mut app = McApp::new();
+        let provider = std::sync::Arc::new(AsyncChunkProvider::new(EmptyGenerator, 4));
+        app.world.insert_resource(std::sync::Arc::clone(&provider));
+        app.add_system(McAppStage::Tick, async_chunk_provider_system::<EmptyGenerator>);
+        let player = app.world.spawn()
+            .insert(client)
+            .insert(LocationComponent(Location::default()))
+            .insert(ChunkLocationComponent::new(0, 0).with_force_change(1))
+            .insert(ChunkObserverComponent {
+                radius: 0,
+                loaded_chunks: Default::default(),
+                chunk_provider: Box::new(provider),
+            })
+            .id();
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while app.world.resource::<ChunkHolder>().viewers(0, 0).next().is_none() {
+            assert!(std::time::Instant::now() < deadline, "the chunk was never sent");
+            app.tick();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(app.world.resource::<ChunkHolder>().viewers(0, 0).collect::<Vec<_>>(), [player]);
+
+        let position = Position { x: 3, y: 10, z: 12 };
+        assert!(app.world.resource_mut::<ChunkHolder>().set_block(position, 5));
+        app.tick();
+
+        let packets = read_packets_until(&mut peer, C09BlockChange::PACKET_ID).await;
+        assert!(packets.iter().any(|packet| packet.packet_id == C1FChunkDataAndUpdateLight::PACKET_ID));
+        let block_change = packets.last().unwrap();
+        assert_eq!(block_change.data, C09BlockChange { position, block_id: 5 }.to_rawpacket().data);
+    }
 }
@@ -0,0 +1,307 @@
+use crate::entity::{ ClientComponent, NetworkIdComponent };
+use mc_networking::packets::RawPacket;
+use mc_networking::packets::client_bound::{
+    C4CDisplayObjective, C4CDisplaySlot, C53ObjectiveType, C53UpdateObjectives,
+    C53UpdateObjectivesMode, C56UpdateScore, C56UpdateScoreAction, ClientBoundPacket,
+};
+
+use std::collections::BTreeMap;
+
+use ahash::{ AHashMap, AHashSet };
+use bevy_ecs::entity::Entity;
+use bevy_ecs::query::With;
+use bevy_ecs::system::{ Query, ResMut };
+
+/// Number of lines shown by the client in the sidebar
+pub const SIDEBAR_LINES: usize = 15;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Objective {
+    pub display_name: serde_json::Value,
+    pub kind: C53ObjectiveType,
+    scores: AHashMap<String, i32>,
+    /// Text of the lines set with [Scoreboard::set_line], by line
+    lines: BTreeMap<usize, String>,
+}
+
+impl Objective {
+    pub fn score(&self, entity_name: &str) -> Option<i32> {
+        self.scores.get(entity_name).copied()
+    }
+}
+
+/// Resource holding the objectives and scores shown to every player
+/// Changes are sent by the [scoreboard_system] with the fewest packets
+#[derive(Default)]
+pub struct Scoreboard {
+    objectives: AHashMap<String, Objective>,
+    displayed: AHashMap<C4CDisplaySlot, String>,
+    /// Objectives and displayed slots as known by the players
+    sent_objectives: AHashMap<String, Objective>,
+    sent_displayed: AHashMap<C4CDisplaySlot, String>,
+    /// Players that were sent the scoreboard
+    viewers: AHashSet<Entity>,
+}
+
+impl Scoreboard {
+    /// Adds or replaces an objective, replaced objectives keep their scores
+    pub fn set_objective(
+        &mut self, name: &str, display_name: impl Into<serde_json::Value>, kind: C53ObjectiveType,
+    ) {
+        let display_name = display_name.into();
+        match self.objectives.get_mut(name) {
+            Some(objective) => {
+                objective.display_name = display_name;
+                objective.kind = kind;
+            }
+            None => {
+                self.objectives.insert(name.to_string(), Objective {
+                    display_name,
+                    kind,
+                    scores: AHashMap::default(),
+                    lines: BTreeMap::default(),
+                });
+            }
+        }
+    }
+
+    /// Removes the objective and clears the slots displaying it
+    pub fn remove_objective(&mut self, name: &str) -> Option<Objective> {
+        self.displayed.retain(|_, displayed| displayed != name);
+        self.objectives.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Objective> {
+        self.objectives.get(name)
+    }
+
+    /// Displays the objective in the slot, None clears it
+    /// Returns false if there is no objective with this name
+    pub fn display(&mut self, slot: C4CDisplaySlot, objective: Option<&str>) -> bool {
+        match objective {
+            Some(name) if !self.objectives.contains_key(name) => false,
+            Some(name) => {
+                self.displayed.insert(slot, name.to_string());
+                true
+            }
+            None => {
+                self.displayed.remove(&slot);
+                true
+            }
+        }
+    }
+
+    /// Returns false if there is no objective with this name
+    pub fn set_score(&mut self, objective: &str, entity_name: &str, value: i32) -> bool {
+        self.objectives.get_mut(objective)
+            .map(|objective| objective.scores.insert(entity_name.to_string(), value))
+            .is_some()
+    }
+
+    /// Returns the removed score, None if there was none
+    pub fn remove_score(&mut self, objective: &str, entity_name: &str) -> Option<i32> {
+        self.objectives.get_mut(objective)?.scores.remove(entity_name)
+    }
+
+    /// Shows a line of text in the sidebar, 0 being the top line, by giving it a score
+    /// ordering it among the other lines
+    /// Texts already used by another line are made unique with invisible reset codes
+    /// Returns false if there is no objective with this name or the line isn't shown by the client
+    pub fn set_line(&mut self, objective: &str, line: usize, text: &str) -> bool {
+        if line >= SIDEBAR_LINES || !self.objectives.contains_key(objective) {
+            return false;
+        }
+        self.clear_line(objective, line);
+        let objective = self.objectives.get_mut(objective).unwrap();
+        let mut text = text.to_string();
+        while objective.lines.values().any(|other| *other == text) {
+            text.push_str("§r");
+        }
+        objective.scores.insert(text.clone(), (SIDEBAR_LINES - line) as i32);
+        objective.lines.insert(line, text);
+        true
+    }
+
+    /// Removes a line set with [Scoreboard::set_line], returns false if it wasn't set
+    pub fn clear_line(&mut self, objective: &str, line: usize) -> bool {
+        let objective = match self.objectives.get_mut(objective) {
+            Some(objective) => objective,
+            None => return false,
+        };
+        match objective.lines.remove(&line) {
+            Some(text) => {
+                objective.scores.remove(&text);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Packets creating every objective, score and displayed slot known by the players,
+    /// for players that just joined
+    pub fn full_packets(&self) -> Vec<RawPacket> {
+        let mut packets = vec![];
+        for (name, objective) in &self.sent_objectives {
+            packets.push(C53UpdateObjectives {
+                objective_name: name.clone(),
+                mode: C53UpdateObjectivesMode::Create {
+                    display_name: objective.display_name.clone(),
+                    kind: objective.kind,
+                },
+            }.to_rawpacket());
+            for (entity_name, value) in &objective.scores {
+                packets.push(update_score(entity_name, name, Some(*value)));
+            }
+        }
+        for (slot, name) in &self.sent_displayed {
+            packets.push(C4CDisplayObjective {
+                position: *slot,
+                objective_name: name.clone(),
+            }.to_rawpacket());
+        }
+        packets
+    }
+
+    /// Packets sending the changes since the last call
+    pub fn take_packets(&mut self) -> Vec<RawPacket> {
+        let mut packets = vec![];
+
+        for name in self.sent_objectives.keys() {
+            if !self.objectives.contains_key(name) {
+                packets.push(C53UpdateObjectives {
+                    objective_name: name.clone(),
+                    mode: C53UpdateObjectivesMode::Remove,
+                }.to_rawpacket());
+            }
+        }
+        for (name, objective) in &self.objectives {
+            let sent = self.sent_objectives.get(name);
+            let mode = match sent {
+                None => Some(C53UpdateObjectivesMode::Create {
+                    display_name: objective.display_name.clone(),
+                    kind: objective.kind,
+                }),
+                Some(sent) if sent.display_name != objective.display_name || sent.kind != objective.kind => {
+                    Some(C53UpdateObjectivesMode::Update {
+                        display_name: objective.display_name.clone(),
+                        kind: objective.kind,
+                    })
+                }
+                Some(_) => None,
+            };
+            if let Some(mode) = mode {
+                packets.push(C53UpdateObjectives { objective_name: name.clone(), mode }.to_rawpacket());
+            }
+
+            if let Some(sent) = sent {
+                for entity_name in sent.scores.keys() {
+                    if !objective.scores.contains_key(entity_name) {
+                        packets.push(update_score(entity_name, name, None));
+                    }
+                }
+            }
+            for (entity_name, value) in &objective.scores {
+                if sent.and_then(|sent| sent.score(entity_name)) != Some(*value) {
+                    packets.push(update_score(entity_name, name, Some(*value)));
+                }
+            }
+        }
+
+        for (slot, name) in &self.displayed {
+            if self.sent_displayed.get(slot) != Some(name) {
+                packets.push(C4CDisplayObjective { position: *slot, objective_name: name.clone() }.to_rawpacket());
+            }
+        }
+        for (slot, name) in &self.sent_displayed {
+            // Removing an objective already clears its slots
+            if !self.displayed.contains_key(slot) && self.objectives.contains_key(name) {
+                packets.push(C4CDisplayObjective { position: *slot, objective_name: String::new() }.to_rawpacket());
+            }
+        }
+
+        self.sent_objectives = self.objectives.clone();
+        self.sent_displayed = self.displayed.clone();
+        packets
+    }
+}
+
+fn update_score(entity_name: &str, objective_name: &str, value: Option<i32>) -> RawPacket {
+    C56UpdateScore {
+        entity_name: entity_name.to_string(),
+        objective_name: objective_name.to_string(),
+        action: match value {
+            Some(value) => C56UpdateScoreAction::CreateOrUpdate { value },
+            None => C56UpdateScoreAction::Remove,
+        },
+    }.to_rawpacket()
+}
+
+/// Sends the changes of the [Scoreboard] to the players and the whole scoreboard to the
+/// players that joined
+pub fn scoreboard_system(
+    mut scoreboard: ResMut<Scoreboard>,
+    // Clients get a network id when joining, the others are still logging in
+    clients: Query<(Entity, &ClientComponent), With<NetworkIdComponent>>,
+) {
+    scoreboard.viewers.retain(|viewer| clients.contains(*viewer));
+
+    let packets = scoreboard.take_packets();
+    let mut full_packets = None;
+    clients.for_each(|(entity, client)| {
+        let packets = if scoreboard.viewers.contains(&entity) {
+            &packets
+        } else {
+            &*full_packets.get_or_insert_with(|| scoreboard.full_packets())
+        };
+        for packet in packets {
+            client.0.send_raw_packet_sync(packet.clone());
+        }
+    });
+    let new_viewers = clients.iter()
+        .map(|(entity, _)| entity)
+        .filter(|entity| !scoreboard.viewers.contains(entity))
+        .collect::<Vec<_>>();
+    scoreboard.viewers.extend(new_viewers);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score_packet(entity_name: &str, value: Option<i32>) -> RawPacket {
+        update_score(entity_name, "sidebar", value)
+    }
+
+    fn assert_packets(packets: &[RawPacket], expected: &[RawPacket]) {
+        assert_eq!(packets.len(), expected.len());
+        for (packet, expected) in packets.iter().zip(expected) {
+            assert_eq!(packet.packet_id, expected.packet_id);
+            assert_eq!(packet.data, expected.data);
+        }
+    }
+
+    #[test]
+    fn sidebar_lines_are_scores() {
+        let mut scoreboard = Scoreboard::default();
+        assert!(!scoreboard.set_line("sidebar", 0, "Missing objective"));
+        scoreboard.set_objective("sidebar", "My server", C53ObjectiveType::Integer);
+        assert!(scoreboard.display(C4CDisplaySlot::Sidebar, Some("sidebar")));
+        assert_eq!(scoreboard.take_packets().len(), 2);
+
+        assert!(scoreboard.set_line("sidebar", 0, "Welcome"));
+        assert!(!scoreboard.set_line("sidebar", SIDEBAR_LINES, "Too low"));
+        assert_packets(&scoreboard.take_packets(), &[score_packet("Welcome", Some(15))]);
+        assert!(scoreboard.take_packets().is_empty());
+
+        // Replacing a line removes the previous text
+        assert!(scoreboard.set_line("sidebar", 0, "Hello"));
+        let packets = scoreboard.take_packets();
+        assert_packets(&packets, &[score_packet("Welcome", None), score_packet("Hello", Some(15))]);
+
+        // Duplicated texts are made unique
+        assert!(scoreboard.set_line("sidebar", 2, "Hello"));
+        assert_eq!(scoreboard.get("sidebar").unwrap().score("Hello§r"), Some(13));
+        assert!(scoreboard.clear_line("sidebar", 0));
+        assert_eq!(scoreboard.get("sidebar").unwrap().score("Hello"), None);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::entity::LocationComponent;
+use mc_utils::Location;
+
+use ahash::AHashMap;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::query::{ Changed, With };
+use bevy_ecs::system::{ Query, ResMut };
+
+/// Resource indexing entities by chunk to quickly find the entities close to a location
+#[derive(Default, Debug)]
+pub struct SpatialIndex {
+    chunks: AHashMap<(i32, i32), Vec<Entity>>,
+    locations: AHashMap<Entity, Location>,
+}
+
+impl SpatialIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn chunk_of(location: &Location) -> (i32, i32) {
+        (location.chunk_x(), location.chunk_z())
+    }
+
+    /// Adds an entity to the index, or moves it if it was already in it
+    pub fn insert(&mut self, entity: Entity, location: Location) {
+        if self.locations.contains_key(&entity) {
+            self.update(entity, location);
+            return;
+        }
+        self.chunks.entry(Self::chunk_of(&location)).or_default().push(entity);
+        self.locations.insert(entity, location);
+    }
+
+    /// Moves an entity, inserting it if it wasn't in the index
+    pub fn update(&mut self, entity: Entity, location: Location) {
+        let previous = match self.locations.get_mut(&entity) {
+            Some(previous) => previous,
+            None => return self.insert(entity, location),
+        };
+        let previous_chunk = Self::chunk_of(previous);
+        *previous = location;
+
+        let new_chunk = Self::chunk_of(&location);
+        if previous_chunk != new_chunk {
+            self.remove_from_chunk(previous_chunk, entity);
+            self.chunks.entry(new_chunk).or_default().push(entity);
+        }
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<Location> {
+        let location = self.locations.remove(&entity)?;
+        self.remove_from_chunk(Self::chunk_of(&location), entity);
+        Some(location)
+    }
+
+    fn remove_from_chunk(&mut self, chunk: (i32, i32), entity: Entity) {
+        if let Some(entities) = self.chunks.get_mut(&chunk) {
+            entities.retain(|e| *e != entity);
+            if entities.is_empty() {
+                self.chunks.remove(&chunk);
+            }
+        }
+    }
+
+    /// Removes the entities for which `keep` returns false
+    pub fn retain(&mut self, mut keep: impl FnMut(Entity) -> bool) {
+        self.locations.retain(|entity, _| keep(*entity));
+        let locations = &self.locations;
+        self.chunks.retain(|_, entities| {
+            entities.retain(|entity| locations.contains_key(entity));
+            !entities.is_empty()
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.locations.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    pub fn get_location(&self, entity: Entity) -> Option<&Location> {
+        self.locations.get(&entity)
+    }
+
+    /// Returns every entity at most `radius` blocks away from the given location
+    pub fn query_around(&self, location: &Location, radius: f64) -> Vec<Entity> {
+        let (center_x, center_z) = Self::chunk_of(location);
+        let chunk_radius = (radius / 16.).ceil() as i32;
+        let radius2 = radius * radius;
+
+        let mut entities = vec![];
+        for chunk_x in center_x - chunk_radius..=center_x + chunk_radius {
+            for chunk_z in center_z - chunk_radius..=center_z + chunk_radius {
+                let chunk_entities = match self.chunks.get(&(chunk_x, chunk_z)) {
+                    Some(chunk_entities) => chunk_entities,
+                    None => continue,
+                };
+                entities.extend(chunk_entities.iter().copied().filter(|entity| {
+                    self.locations[entity].distance2(*location) <= radius2
+                }));
+            }
+        }
+        entities
+    }
+}
+
+/// Keeps the [SpatialIndex] resource in sync with the [LocationComponent]s
+pub fn spatial_index_update(
+    mut index: ResMut<SpatialIndex>,
+    query: Query<(Entity, &LocationComponent), Changed<LocationComponent>>,
+    located: Query<(), With<LocationComponent>>,
+) {
+    // Despawned entities and the ones that lost their location
+    index.retain(|entity| located.contains(entity));
+    query.for_each(|(entity, location)| index.update(entity, location.0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs::schedule::{ Stage, SystemStage };
+    use bevy_ecs::world::World;
+
+    fn at(x: f64, z: f64) -> Location {
+        Location { x, y: 64., z, ..Location::default() }
+    }
+
+    #[test]
+    fn query_around_radius() {
+        let mut world = World::default();
+        let (near, far, other_chunk) = (world.spawn().id(), world.spawn().id(), world.spawn().id());
+
+        let mut index = SpatialIndex::new();
+        index.insert(near, at(3., 3.));
+        index.insert(far, at(60., 3.));
+        index.insert(other_chunk, at(-10., -10.));
+
+        let mut found = index.query_around(&at(0., 0.), 20.);
+        found.sort();
+        let mut expected = vec![near, other_chunk];
+        expected.sort();
+        assert_eq!(found, expected);
+        assert!(index.query_around(&at(0., 0.), 1.).is_empty());
+    }
+
+    #[test]
+    fn update_moves_between_buckets() {
+        let mut world = World::default();
+        let entity = world.spawn().id();
+
+        let mut index = SpatialIndex::new();
+        index.insert(entity, at(1., 1.));
+        index.update(entity, at(100., 100.));
+
+        assert!(index.query_around(&at(1., 1.), 10.).is_empty());
+        assert_eq!(index.query_around(&at(100., 100.), 10.), vec![entity]);
+        assert!(!index.chunks.contains_key(&(0, 0)));
+
+        assert_eq!(index.remove(entity).map(|l| l.x), Some(100.));
+        assert!(index.query_around(&at(100., 100.), 10.).is_empty());
+    }
+
+    #[test]
+    fn system_follows_the_locations() {
+        let mut world = World::default();
+        world.init_resource::<SpatialIndex>();
+        let entity = world.spawn().insert(LocationComponent(at(1., 1.))).id();
+        let despawned = world.spawn().insert(LocationComponent(at(2., 2.))).id();
+        let mut stage = SystemStage::single(spatial_index_update);
+
+        stage.run(&mut world);
+        assert_eq!(world.resource::<SpatialIndex>().len(), 2);
+
+        world.get_mut::<LocationComponent>(entity).unwrap().0 = at(100., 100.);
+        world.despawn(despawned);
+        stage.run(&mut world);
+        let index = world.resource::<SpatialIndex>();
+        assert_eq!(index.query_around(&at(100., 100.), 10.), vec![entity]);
+        assert!(index.query_around(&at(1., 1.), 10.).is_empty());
+        assert_eq!(index.len(), 1);
+
+        world.entity_mut(entity).remove::<LocationComponent>();
+        stage.run(&mut world);
+        assert!(world.resource::<SpatialIndex>().is_empty());
+    }
+}
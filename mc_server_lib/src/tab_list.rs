@@ -0,0 +1,236 @@
+use crate::entity::ClientComponent;
+use mc_networking::data_types::Gamemode;
+use mc_networking::packets::client_bound::{
+    C34AddPlayer, C34PlayerInfo, C34RemovePlayer, C34UpdateDisplayName, C34UpdateGamemode,
+    C34UpdateLatency, ClientBoundPacket,
+};
+
+use ahash::{ AHashMap, AHashSet };
+use bevy_ecs::entity::Entity;
+use bevy_ecs::system::{ Query, ResMut };
+use uuid::Uuid;
+
+/// Resource holding the entries of the players list shown to every player
+/// Changes are sent by the [tab_list_system] with the fewest [C34PlayerInfo] actions
+#[derive(Default)]
+pub struct TabList {
+    entries: AHashMap<Uuid, C34AddPlayer>,
+    /// Entries as known by the players
+    sent: AHashMap<Uuid, C34AddPlayer>,
+    /// Entries removed when their player disconnects
+    owners: AHashMap<Entity, Uuid>,
+    /// Players that were sent the entries
+    viewers: AHashSet<Entity>,
+}
+
+impl TabList {
+    /// Adds or replaces an entry, replaced entries only have their gamemode,
+    /// ping and display name updated
+    pub fn insert(&mut self, entry: C34AddPlayer) {
+        self.entries.insert(entry.uuid, entry);
+    }
+
+    /// Adds the entry of a player, removed when the player disconnects
+    pub fn insert_player(&mut self, player: Entity, entry: C34AddPlayer) {
+        self.owners.insert(player, entry.uuid);
+        self.insert(entry);
+    }
+
+    pub fn remove(&mut self, uuid: Uuid) -> Option<C34AddPlayer> {
+        self.owners.retain(|_, owned| *owned != uuid);
+        self.entries.remove(&uuid)
+    }
+
+    pub fn get(&self, uuid: Uuid) -> Option<&C34AddPlayer> {
+        self.entries.get(&uuid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns false if there is no entry with this uuid
+    pub fn set_gamemode(&mut self, uuid: Uuid, gamemode: Gamemode) -> bool {
+        self.entries.get_mut(&uuid).map(|entry| entry.gamemode = gamemode).is_some()
+    }
+
+    /// Returns false if there is no entry with this uuid
+    pub fn set_ping(&mut self, uuid: Uuid, ping: i32) -> bool {
+        self.entries.get_mut(&uuid).map(|entry| entry.ping = ping).is_some()
+    }
+
+    /// Returns false if there is no entry with this uuid
+    pub fn set_display_name(&mut self, uuid: Uuid, display_name: Option<String>) -> bool {
+        self.entries.get_mut(&uuid).map(|entry| entry.display_name = display_name).is_some()
+    }
+
+    /// Packet adding every entry known by the players, for players that just joined
+    pub fn full_packet(&self) -> C34PlayerInfo {
+        C34PlayerInfo::AddPlayers {
+            players: self.sent.values().cloned().collect(),
+        }
+    }
+
+    /// Packets sending the changes since the last call, at most one per action
+    pub fn take_packets(&mut self) -> Vec<C34PlayerInfo> {
+        let removed = self.sent.keys()
+            .filter(|uuid| !self.entries.contains_key(uuid))
+            .map(|uuid| C34RemovePlayer { uuid: *uuid })
+            .collect::<Vec<_>>();
+        let mut added = vec![];
+        let mut gamemodes = vec![];
+        let mut latencies = vec![];
+        let mut display_names = vec![];
+        for (uuid, entry) in &self.entries {
+            let sent = match self.sent.get(uuid) {
+                Some(sent) => sent,
+                None => {
+                    added.push(entry.clone());
+                    continue;
+                }
+            };
+            if sent.gamemode != entry.gamemode {
+                gamemodes.push(C34UpdateGamemode { uuid: *uuid, gamemode: entry.gamemode });
+            }
+            if sent.ping != entry.ping {
+                latencies.push(C34UpdateLatency { uuid: *uuid, ping: entry.ping });
+            }
+            if sent.display_name != entry.display_name {
+                display_names.push(C34UpdateDisplayName {
+                    uuid: *uuid,
+                    display_name: entry.display_name.clone(),
+                });
+            }
+        }
+        self.sent = self.entries.clone();
+
+        let mut packets = vec![];
+        if !removed.is_empty() {
+            packets.push(C34PlayerInfo::RemovePlayers { players: removed });
+        }
+        if !added.is_empty() {
+            packets.push(C34PlayerInfo::AddPlayers { players: added });
+        }
+        if !gamemodes.is_empty() {
+            packets.push(C34PlayerInfo::UpdateGamemodes { players: gamemodes });
+        }
+        if !latencies.is_empty() {
+            packets.push(C34PlayerInfo::UpdateLatencies { players: latencies });
+        }
+        if !display_names.is_empty() {
+            packets.push(C34PlayerInfo::UpdateDisplayNames { players: display_names });
+        }
+        packets
+    }
+}
+
+/// Removes the entries of disconnected players, sends the changes of the [TabList]
+/// to the players and the whole list to the new players
+pub fn tab_list_system(
+    mut tab_list: ResMut<TabList>,
+    clients: Query<(Entity, &ClientComponent)>,
+) {
+    let disconnected = tab_list.owners.iter()
+        .filter(|(owner, _)| !clients.contains(**owner))
+        .map(|(_, uuid)| *uuid)
+        .collect::<Vec<_>>();
+    for uuid in disconnected {
+        tab_list.remove(uuid);
+    }
+    tab_list.viewers.retain(|viewer| clients.contains(*viewer));
+
+    let packets = tab_list.take_packets().iter()
+        .map(|packet| packet.to_rawpacket())
+        .collect::<Vec<_>>();
+    let mut full_packet = None;
+    clients.for_each(|(entity, client)| {
+        if tab_list.viewers.contains(&entity) {
+            for packet in &packets {
+                client.0.send_raw_packet_sync(packet.clone());
+            }
+            return;
+        }
+        // Only players with an entry see the list, the others are still logging in
+        if !tab_list.owners.contains_key(&entity) {
+            return;
+        }
+        let packet = full_packet.get_or_insert_with(|| tab_list.full_packet().to_rawpacket());
+        client.0.send_raw_packet_sync(packet.clone());
+    });
+    let new_viewers = tab_list.owners.keys()
+        .filter(|owner| !tab_list.viewers.contains(*owner))
+        .copied()
+        .collect::<Vec<_>>();
+    tab_list.viewers.extend(new_viewers);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str) -> C34AddPlayer {
+        C34AddPlayer {
+            uuid: Uuid::new_v4(),
+            name: name.to_string(),
+            properties: vec![],
+            gamemode: Gamemode::Survival,
+            ping: 0,
+            display_name: None,
+            sig_data: (),
+        }
+    }
+
+    #[test]
+    fn gamemode_change_only_updates_gamemodes() {
+        let mut tab_list = TabList::default();
+        let alice = entry("Alice");
+        let bob = entry("Bob");
+        let alice_uuid = alice.uuid;
+        tab_list.insert(alice);
+        tab_list.insert(bob);
+
+        let packets = tab_list.take_packets();
+        assert_eq!(packets.len(), 1);
+        assert!(matches!(&packets[0], C34PlayerInfo::AddPlayers { players } if players.len() == 2));
+        assert!(tab_list.take_packets().is_empty());
+
+        assert!(tab_list.set_gamemode(alice_uuid, Gamemode::Creative));
+        let packets = tab_list.take_packets();
+        assert_eq!(packets.len(), 1);
+        assert!(matches!(
+            &packets[0],
+            C34PlayerInfo::UpdateGamemodes { players }
+                if players.len() == 1 && players[0].uuid == alice_uuid && players[0].gamemode == Gamemode::Creative
+        ));
+
+        // Setting the same gamemode changes nothing
+        tab_list.set_gamemode(alice_uuid, Gamemode::Creative);
+        assert!(tab_list.take_packets().is_empty());
+        assert!(!tab_list.set_gamemode(Uuid::new_v4(), Gamemode::Creative));
+    }
+
+    #[test]
+    fn removal_and_updates_grouped_by_action() {
+        let mut tab_list = TabList::default();
+        let alice = entry("Alice");
+        let bob = entry("Bob");
+        let (alice_uuid, bob_uuid) = (alice.uuid, bob.uuid);
+        tab_list.insert(alice);
+        tab_list.insert(bob);
+        tab_list.take_packets();
+
+        tab_list.remove(bob_uuid);
+        tab_list.set_ping(alice_uuid, 50);
+        tab_list.set_display_name(alice_uuid, Some("Al".into()));
+        let packets = tab_list.take_packets();
+        assert_eq!(packets.len(), 3);
+        assert!(matches!(&packets[0], C34PlayerInfo::RemovePlayers { players } if players[0].uuid == bob_uuid));
+        assert!(matches!(&packets[1], C34PlayerInfo::UpdateLatencies { players } if players[0].ping == 50));
+        assert!(matches!(&packets[2], C34PlayerInfo::UpdateDisplayNames { players } if players.len() == 1));
+        assert!(matches!(tab_list.full_packet(), C34PlayerInfo::AddPlayers { players } if players.len() == 1));
+    }
+}
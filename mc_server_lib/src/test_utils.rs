@@ -0,0 +1,62 @@
+use crate::entity::ClientComponent;
+use mc_networking::client::Client;
+use mc_networking::client::client_event::ClientEvent;
+use mc_networking::packets::{ PacketCompression, RawPacket };
+
+use bytes::BytesMut;
+use tokio::io::AsyncReadExt;
+use tokio::net::{ TcpListener, TcpStream };
+
+/// Client connected through the loopback, the returned stream is the peer receiving its packets
+/// Sending blocks when the packet buffer is full, it is large enough for the tests to never
+/// fill it as that would block the runtime the client uses
+pub async fn connected_client() -> (ClientComponent, flume::Receiver<ClientEvent>, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let peer = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+    let (socket, _) = listener.accept().await.unwrap();
+    let (client, event_receiver) = Client::new(socket, 100, 100);
+    (ClientComponent(client), event_receiver, peer)
+}
+
+/// Reads the next `count` packets sent to the peer
+pub async fn read_packets(peer: &mut TcpStream, count: usize) -> Vec<RawPacket> {
+    let mut packets = vec![];
+    let mut bytes = BytesMut::new();
+    while packets.len() < count {
+        match decode_next(&mut bytes) {
+            Some(packet) => packets.push(packet),
+            None => assert!(peer.read_buf(&mut bytes).await.unwrap() > 0, "connection closed"),
+        }
+    }
+    packets
+}
+
+/// Reads the packets sent to the peer until one with the `last` id is received
+pub async fn read_packets_until(peer: &mut TcpStream, last: i32) -> Vec<RawPacket> {
+    let mut packets = vec![];
+    let mut bytes = BytesMut::new();
+    loop {
+        match decode_next(&mut bytes) {
+            Some(packet) => {
+                let is_last = packet.packet_id == last;
+                packets.push(packet);
+                if is_last {
+                    return packets;
+                }
+            }
+            None => {
+                let read = peer.read_buf(&mut bytes).await.unwrap();
+                assert!(read > 0, "connection closed before receiving {last:#x}");
+            }
+        }
+    }
+}
+
+/// Decodes the first packet of the bytes, None if it isn't complete yet
+fn decode_next(bytes: &mut BytesMut) -> Option<RawPacket> {
+    // Decoding consumes the length of incomplete packets so it is done on a copy
+    let mut remaining = bytes.clone();
+    let packet = RawPacket::decode(&mut remaining, PacketCompression::default()).ok()?;
+    *bytes = remaining;
+    Some(packet)
+}
@@ -0,0 +1,144 @@
+use crate::entity::{ ClientComponent, NetworkIdComponent };
+use mc_networking::packets::RawPacket;
+use mc_networking::packets::client_bound::{
+    C0DClearTitles, C58SetSubtitleText, C5ASetTitleText, C5BSetTitleAnimationTimes, ClientBoundPacket,
+};
+
+use bevy_ecs::entity::Entity;
+use bevy_ecs::query::With;
+use bevy_ecs::system::{ Query, SystemParam };
+
+/// Vanilla durations of the title animations, in ticks
+pub const DEFAULT_FADE_IN: i32 = 10;
+pub const DEFAULT_STAY: i32 = 70;
+pub const DEFAULT_FADE_OUT: i32 = 20;
+
+/// A title shown in the middle of the screen, with an optional subtitle under it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Title {
+    pub title: serde_json::Value,
+    pub subtitle: Option<serde_json::Value>,
+    /// Durations in ticks
+    pub fade_in: i32,
+    pub stay: i32,
+    pub fade_out: i32,
+}
+
+impl Title {
+    pub fn new(title: impl Into<serde_json::Value>) -> Self {
+        Self {
+            title: title.into(),
+            subtitle: None,
+            fade_in: DEFAULT_FADE_IN,
+            stay: DEFAULT_STAY,
+            fade_out: DEFAULT_FADE_OUT,
+        }
+    }
+
+    pub fn with_subtitle(mut self, subtitle: impl Into<serde_json::Value>) -> Self {
+        self.subtitle = Some(subtitle.into());
+        self
+    }
+
+    pub fn with_times(mut self, fade_in: i32, stay: i32, fade_out: i32) -> Self {
+        self.fade_in = fade_in;
+        self.stay = stay;
+        self.fade_out = fade_out;
+        self
+    }
+
+    /// Packets showing the title, the title text comes last as it starts the animation
+    /// with the subtitle received before it
+    /// Titles without subtitle send an empty one to hide the previous subtitle
+    pub fn packets(&self) -> Vec<RawPacket> {
+        vec![
+            C5BSetTitleAnimationTimes {
+                fade_in: self.fade_in,
+                stay: self.stay,
+                fade_out: self.fade_out,
+            }.to_rawpacket(),
+            C58SetSubtitleText {
+                subtitle: self.subtitle.clone().unwrap_or_else(|| "".into()),
+            }.to_rawpacket(),
+            C5ASetTitleText { title: self.title.clone() }.to_rawpacket(),
+        ]
+    }
+}
+
+/// Players a title is shown to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TitleTarget {
+    Player(Entity),
+    /// Every player that joined
+    All,
+}
+
+/// System parameter showing and clearing the titles of the players
+#[derive(SystemParam)]
+pub struct Titles<'w, 's> {
+    // Clients get a network id when joining, the others are still logging in
+    clients: Query<'w, 's, (Entity, &'static ClientComponent), With<NetworkIdComponent>>,
+}
+
+impl<'w, 's> Titles<'w, 's> {
+    pub fn show(&self, target: TitleTarget, title: &Title) {
+        self.send(target, &title.packets());
+    }
+
+    /// Hides the current title, `reset` also resets the subtitle and the animation times
+    pub fn clear(&self, target: TitleTarget, reset: bool) {
+        self.send(target, &[C0DClearTitles { reset }.to_rawpacket()]);
+    }
+
+    fn send(&self, target: TitleTarget, packets: &[RawPacket]) {
+        let send = |client: &ClientComponent| {
+            for packet in packets {
+                client.0.send_raw_packet_sync(packet.clone());
+            }
+        };
+        match target {
+            TitleTarget::Player(player) => {
+                if let Ok((_, client)) = self.clients.get(player) {
+                    send(client);
+                }
+            }
+            TitleTarget::All => self.clients.for_each(|(_, client)| send(client)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{ connected_client, read_packets };
+
+    use bevy_ecs::system::SystemState;
+    use bevy_ecs::world::World;
+
+    #[tokio::test]
+    async fn show_then_reset() {
+        let (client, _events, mut peer) = connected_client().await;
+
+        let mut world = World::default();
+        let player = world.spawn()
+            .insert(client)
+            .insert(NetworkIdComponent::new())
+            .id();
+        let title = Title::new("Welcome").with_subtitle("to the server").with_times(5, 40, 5);
+        let mut system_state = SystemState::<Titles>::new(&mut world);
+        let titles = system_state.get_mut(&mut world);
+        titles.show(TitleTarget::Player(player), &title);
+        titles.clear(TitleTarget::All, true);
+
+        let packets = read_packets(&mut peer, 4).await;
+        let ids = packets.iter().map(|packet| packet.packet_id).collect::<Vec<_>>();
+        assert_eq!(ids, [
+            C5BSetTitleAnimationTimes::PACKET_ID,
+            C58SetSubtitleText::PACKET_ID,
+            C5ASetTitleText::PACKET_ID,
+            C0DClearTitles::PACKET_ID,
+        ]);
+        assert_eq!(packets[0].data, C5BSetTitleAnimationTimes { fade_in: 5, stay: 40, fade_out: 5 }.to_rawpacket().data);
+        assert_eq!(&*packets[3].data, &[1]);
+    }
+}
@@ -0,0 +1,123 @@
+use mc_networking::packets::client_bound::{
+    C1DInitializeWorldBorder, C41SetBorderCenter, C42SetBorderLerpSize, C43SetBorderSize,
+    C44SetBorderWarningDelay, C45SetBorderWarningDistance,
+};
+
+use std::time::Duration;
+
+/// Diameter of the vanilla world border
+pub const DEFAULT_BORDER_DIAMETER: f64 = 59_999_968.;
+/// Coordinates of portal teleportations are limited to ± this value
+pub const DEFAULT_PORTAL_TELEPORT_BOUNDARY: i32 = 29_999_984;
+pub const DEFAULT_WARNING_BLOCKS: i32 = 5;
+pub const DEFAULT_WARNING_TIME: i32 = 15;
+
+/// State of a world border, every operation returns the packet to send to the players
+/// The players are expected to be sent the [WorldBorder::init_packet] when joining
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldBorder {
+    pub center_x: f64,
+    pub center_z: f64,
+    /// Length of a side of the border, the one reached at the end of a resize
+    pub diameter: f64,
+    pub portal_teleport_boundary: i32,
+    /// Distance in blocks from the border at which players see the warning
+    pub warning_blocks: i32,
+    /// Seconds before a moving border reaches players at which they see the warning
+    pub warning_time: i32,
+}
+
+impl Default for WorldBorder {
+    fn default() -> Self {
+        Self::new(0., 0., DEFAULT_BORDER_DIAMETER)
+    }
+}
+
+impl WorldBorder {
+    pub fn new(center_x: f64, center_z: f64, diameter: f64) -> Self {
+        Self {
+            center_x,
+            center_z,
+            diameter,
+            portal_teleport_boundary: DEFAULT_PORTAL_TELEPORT_BOUNDARY,
+            warning_blocks: DEFAULT_WARNING_BLOCKS,
+            warning_time: DEFAULT_WARNING_TIME,
+        }
+    }
+
+    /// Sends the whole border, a resize in progress is sent as already finished
+    pub fn init_packet(&self) -> C1DInitializeWorldBorder {
+        C1DInitializeWorldBorder {
+            x: self.center_x,
+            z: self.center_z,
+            old_diameter: self.diameter,
+            new_diameter: self.diameter,
+            speed: 0,
+            portal_teleport_boundary: self.portal_teleport_boundary,
+            warning_blocks: self.warning_blocks,
+            warning_time: self.warning_time,
+        }
+    }
+
+    pub fn set_center(&mut self, x: f64, z: f64) -> C41SetBorderCenter {
+        self.center_x = x;
+        self.center_z = z;
+        C41SetBorderCenter { x, z }
+    }
+
+    /// Resizes the border instantly
+    pub fn set_size(&mut self, diameter: f64) -> C43SetBorderSize {
+        self.diameter = diameter.max(0.);
+        C43SetBorderSize { diameter: self.diameter }
+    }
+
+    /// Resizes the border progressively over the given duration
+    pub fn lerp_size(&mut self, diameter: f64, duration: Duration) -> C42SetBorderLerpSize {
+        let old_diameter = self.diameter;
+        self.diameter = diameter.max(0.);
+        C42SetBorderLerpSize {
+            old_diameter,
+            new_diameter: self.diameter,
+            speed: duration.as_millis() as i64,
+        }
+    }
+
+    /// Reduces the diameter by `amount` blocks over the given duration
+    pub fn shrink(&mut self, amount: f64, duration: Duration) -> C42SetBorderLerpSize {
+        self.lerp_size(self.diameter - amount, duration)
+    }
+
+    /// Increases the diameter by `amount` blocks over the given duration
+    pub fn grow(&mut self, amount: f64, duration: Duration) -> C42SetBorderLerpSize {
+        self.lerp_size(self.diameter + amount, duration)
+    }
+
+    pub fn set_warning_time(&mut self, seconds: i32) -> C44SetBorderWarningDelay {
+        self.warning_time = seconds;
+        C44SetBorderWarningDelay { warning_time: seconds }
+    }
+
+    pub fn set_warning_blocks(&mut self, blocks: i32) -> C45SetBorderWarningDistance {
+        self.warning_blocks = blocks;
+        C45SetBorderWarningDistance { warning_blocks: blocks }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shrink_and_grow() {
+        let mut border = WorldBorder::new(0., 0., 100.);
+        let packet = border.shrink(40., Duration::from_secs(30));
+        assert_eq!((packet.old_diameter, packet.new_diameter, packet.speed), (100., 60., 30_000));
+        let packet = border.grow(20., Duration::from_millis(500));
+        assert_eq!((packet.old_diameter, packet.new_diameter, packet.speed), (60., 80., 500));
+        assert_eq!(border.shrink(1000., Duration::from_secs(1)).new_diameter, 0.);
+
+        assert_eq!(border.set_size(16.).diameter, 16.);
+        let init = border.init_packet();
+        assert_eq!((init.old_diameter, init.new_diameter, init.speed), (16., 16., 0));
+    }
+}
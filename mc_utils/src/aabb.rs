@@ -0,0 +1,64 @@
+use crate::Location;
+
+/// Axis aligned bounding box, used for block collision shapes and entity hitboxes
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AABB {
+    pub min: (f64, f64, f64),
+    pub max: (f64, f64, f64),
+}
+
+impl AABB {
+    pub fn new(min: (f64, f64, f64), max: (f64, f64, f64)) -> Self {
+        Self { min, max }
+    }
+
+    /// Shape of a full block at 0, 0, 0
+    pub fn full_block() -> Self {
+        Self::new((0., 0., 0.), (1., 1., 1.))
+    }
+
+    pub fn offset(&self, x: f64, y: f64, z: f64) -> Self {
+        Self {
+            min: (self.min.0 + x, self.min.1 + y, self.min.2 + z),
+            max: (self.max.0 + x, self.max.1 + y, self.max.2 + z),
+        }
+    }
+
+    /// Whether the two boxes overlap, boxes only touching each other don't intersect
+    pub fn intersects(&self, other: &AABB) -> bool {
+        self.min.0 < other.max.0
+            && self.max.0 > other.min.0
+            && self.min.1 < other.max.1
+            && self.max.1 > other.min.1
+            && self.min.2 < other.max.2
+            && self.max.2 > other.min.2
+    }
+
+    pub fn contains(&self, location: &Location) -> bool {
+        (self.min.0..self.max.0).contains(&location.x)
+            && (self.min.1..self.max.1).contains(&location.y)
+            && (self.min.2..self.max.2).contains(&location.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersections() {
+        let block = AABB::full_block();
+        assert!(block.intersects(&block.offset(0.5, 0.5, 0.5)));
+        assert!(!block.intersects(&block.offset(1., 0., 0.)));
+        assert!(block.contains(&Location {
+            x: 0.5,
+            y: 0.,
+            z: 0.99,
+            ..Location::default()
+        }));
+        assert!(!block.contains(&Location {
+            x: 1.,
+            ..Location::default()
+        }));
+    }
+}
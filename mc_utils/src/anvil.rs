@@ -0,0 +1,423 @@
+//! Reading and writing [ChunkData] in vanilla region files (.mca)
+//!
+//! <https://minecraft.fandom.com/wiki/Region_file_format>
+
+use crate::{BlockState, ChunkArray, ChunkData, ChunkDataSection};
+use mc_networking::data_types::bitbuffer::BitBuffer;
+
+use nbt::{Blob, Value};
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Cursor},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SECTOR_SIZE: usize = 4096;
+const CHUNKS_PER_REGION: usize = 32 * 32;
+/// Data version of 1.19
+const DATA_VERSION: i32 = 3105;
+
+const COMPRESSION_GZIP: u8 = 1;
+const COMPRESSION_ZLIB: u8 = 2;
+const COMPRESSION_NONE: u8 = 3;
+
+/// Name and properties of a block state, as stored in the palettes of region files
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockStateName {
+    pub name: String,
+    pub properties: BTreeMap<String, String>,
+}
+
+/// Converts between the block state ids of [ChunkData] and the names stored in region files
+pub trait BlockStateRegistry {
+    fn get_name(&self, state: BlockState) -> Option<BlockStateName>;
+    fn get_state(&self, name: &BlockStateName) -> Option<BlockState>;
+}
+
+/// Compression type and compressed nbt of a chunk, with the time it was last saved
+#[derive(Clone)]
+struct RawChunk {
+    timestamp: u32,
+    compression: u8,
+    data: Vec<u8>,
+}
+
+fn invalid_data(message: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn chunk_index(chunk_x: i32, chunk_z: i32) -> usize {
+    (chunk_x.rem_euclid(32) + chunk_z.rem_euclid(32) * 32) as usize
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> io::Result<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| invalid_data("region file is truncated"))
+}
+
+/// Reads every chunk of a region file, a missing file is an empty region
+fn read_raw_chunks(path: &Path) -> io::Result<Vec<Option<RawChunk>>> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+        Err(e) => return Err(e),
+    };
+    let mut chunks = vec![None; CHUNKS_PER_REGION];
+    if bytes.is_empty() {
+        return Ok(chunks);
+    }
+
+    for (i, chunk) in chunks.iter_mut().enumerate() {
+        let location = read_u32(&bytes, i * 4)?;
+        if location == 0 {
+            continue;
+        }
+        let offset = (location >> 8) as usize * SECTOR_SIZE;
+        let timestamp = read_u32(&bytes, SECTOR_SIZE + i * 4)?;
+        // The length includes the compression byte
+        let length = read_u32(&bytes, offset)? as usize;
+        let payload = bytes
+            .get(offset + 4..offset + 4 + length)
+            .filter(|payload| !payload.is_empty())
+            .ok_or_else(|| invalid_data(format!("chunk {} is out of the region file", i)))?;
+        *chunk = Some(RawChunk {
+            timestamp,
+            compression: payload[0],
+            data: payload[1..].to_vec(),
+        });
+    }
+    Ok(chunks)
+}
+
+/// Rewrites the whole region file, chunks are stored one after the other after the header
+fn write_raw_chunks(path: &Path, chunks: &[Option<RawChunk>]) -> io::Result<()> {
+    let mut header = vec![0u8; SECTOR_SIZE * 2];
+    let mut body = vec![];
+    for (i, chunk) in chunks.iter().enumerate() {
+        let chunk = match chunk {
+            Some(chunk) => chunk,
+            None => continue,
+        };
+        let sector_offset = 2 + body.len() / SECTOR_SIZE;
+        let length = chunk.data.len() + 1;
+        let sector_count = (4 + length + SECTOR_SIZE - 1) / SECTOR_SIZE;
+        if sector_count > 255 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "chunks bigger than 1MiB are not supported",
+            ));
+        }
+
+        let location = ((sector_offset as u32) << 8) | sector_count as u32;
+        header[i * 4..i * 4 + 4].copy_from_slice(&location.to_be_bytes());
+        header[SECTOR_SIZE + i * 4..SECTOR_SIZE + i * 4 + 4]
+            .copy_from_slice(&chunk.timestamp.to_be_bytes());
+
+        body.extend_from_slice(&(length as u32).to_be_bytes());
+        body.push(chunk.compression);
+        body.extend_from_slice(&chunk.data);
+        body.resize(body.len() + (SECTOR_SIZE - body.len() % SECTOR_SIZE) % SECTOR_SIZE, 0);
+    }
+
+    header.extend_from_slice(&body);
+    fs::write(path, header)
+}
+
+fn encode_palette_entry(name: BlockStateName) -> Value {
+    let mut entry = vec![("Name".to_string(), Value::String(name.name))];
+    if !name.properties.is_empty() {
+        entry.push((
+            "Properties".to_string(),
+            Value::Compound(
+                name.properties
+                    .into_iter()
+                    .map(|(key, value)| (key, Value::String(value)))
+                    .collect(),
+            ),
+        ));
+    }
+    Value::Compound(entry.into_iter().collect())
+}
+
+fn decode_palette_entry(entry: &Value) -> io::Result<BlockStateName> {
+    let entry = match entry {
+        Value::Compound(entry) => entry,
+        _ => return Err(invalid_data("palette entries must be compounds")),
+    };
+    let name = match entry.get("Name") {
+        Some(Value::String(name)) => name.clone(),
+        _ => return Err(invalid_data("palette entry without a name")),
+    };
+    let mut properties = BTreeMap::new();
+    if let Some(Value::Compound(props)) = entry.get("Properties") {
+        for (key, value) in props {
+            match value {
+                Value::String(value) => properties.insert(key.clone(), value.clone()),
+                _ => return Err(invalid_data("block state properties must be strings")),
+            };
+        }
+    }
+    Ok(BlockStateName { name, properties })
+}
+
+fn encode_section(
+    y: i8,
+    section: &ChunkDataSection,
+    registry: &impl BlockStateRegistry,
+) -> io::Result<Value> {
+    let (palette, data) = match section {
+        ChunkDataSection::Filled(block) => (vec![*block as i32], None),
+        ChunkDataSection::Paletted { blocks, palette } => {
+            let bits_per_entry = ((palette.len() as f64).log2().ceil() as u8).max(4);
+            let mut data = BitBuffer::create(bits_per_entry, 4096);
+            for (i, b) in blocks.iter().enumerate() {
+                data.set_entry(i, *b as u32);
+            }
+            (palette.clone(), Some(data.into_buffer()))
+        }
+    };
+
+    let palette = palette
+        .into_iter()
+        .map(|state| {
+            registry
+                .get_name(state as BlockState)
+                .map(encode_palette_entry)
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("unknown block state {}", state),
+                    )
+                })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut block_states = vec![("palette".to_string(), Value::List(palette))];
+    if let Some(data) = data {
+        block_states.push(("data".to_string(), Value::LongArray(data)));
+    }
+
+    let biomes = vec![(
+        "palette".to_string(),
+        Value::List(vec![Value::String("minecraft:plains".to_string())]),
+    )];
+    Ok(Value::Compound(
+        vec![
+            ("Y".to_string(), Value::Byte(y)),
+            ("block_states".to_string(), Value::Compound(block_states.into_iter().collect())),
+            ("biomes".to_string(), Value::Compound(biomes.into_iter().collect())),
+        ]
+        .into_iter()
+        .collect(),
+    ))
+}
+
+fn decode_section(
+    block_states: &Value,
+    registry: &impl BlockStateRegistry,
+) -> io::Result<ChunkDataSection> {
+    let block_states = match block_states {
+        Value::Compound(block_states) => block_states,
+        _ => return Err(invalid_data("block_states must be a compound")),
+    };
+    let palette = match block_states.get("palette") {
+        Some(Value::List(palette)) if !palette.is_empty() => palette
+            .iter()
+            .map(|entry| {
+                let name = decode_palette_entry(entry)?;
+                registry
+                    .get_state(&name)
+                    .ok_or_else(|| invalid_data(format!("unknown block {:?}", name)))
+            })
+            .collect::<io::Result<Vec<_>>>()?,
+        _ => return Err(invalid_data("missing block states palette")),
+    };
+    if palette.len() == 1 {
+        return Ok(ChunkDataSection::Filled(palette[0]));
+    }
+
+    let bits_per_entry = ((palette.len() as f64).log2().ceil() as u8).max(4);
+    let entries_per_long = 64 / bits_per_entry as usize;
+    let data = match block_states.get("data") {
+        Some(Value::LongArray(data))
+            if data.len() == (4096 + entries_per_long - 1) / entries_per_long =>
+        {
+            BitBuffer::load(bits_per_entry, data.clone())
+        }
+        _ => return Err(invalid_data("invalid block states data")),
+    };
+    let mut indices = [0; 4096];
+    for (i, index) in indices.iter_mut().enumerate() {
+        *index = data.get_entry(i) as BlockState;
+        if *index as usize >= palette.len() {
+            return Err(invalid_data("block states data is out of the palette"));
+        }
+    }
+
+    // Paletted sections always start their palette with air
+    if palette[0] == 0 {
+        Ok(ChunkDataSection::Paletted {
+            blocks: Box::new(ChunkArray::from(indices)),
+            palette: palette.into_iter().map(|state| state as i32).collect(),
+        })
+    } else {
+        let mut section = ChunkDataSection::new();
+        for (i, index) in indices.iter().enumerate() {
+            section.set_block(
+                (i % 16) as u8,
+                (i / 256) as u8,
+                ((i / 16) % 16) as u8,
+                palette[*index as usize],
+            );
+        }
+        Ok(section)
+    }
+}
+
+/// Reads a chunk from the given region file, None if the file or the chunk doesn't exist
+pub fn read_region(
+    path: impl AsRef<Path>,
+    chunk_x: i32,
+    chunk_z: i32,
+    registry: &impl BlockStateRegistry,
+) -> io::Result<Option<ChunkData>> {
+    let chunk = match read_raw_chunks(path.as_ref())?.swap_remove(chunk_index(chunk_x, chunk_z)) {
+        Some(chunk) => chunk,
+        None => return Ok(None),
+    };
+    let mut reader = Cursor::new(chunk.data);
+    let blob = match chunk.compression {
+        COMPRESSION_GZIP => Blob::from_gzip_reader(&mut reader),
+        COMPRESSION_ZLIB => Blob::from_zlib_reader(&mut reader),
+        COMPRESSION_NONE => Blob::from_reader(&mut reader),
+        c => return Err(invalid_data(format!("unknown compression type {}", c))),
+    }
+    .map_err(invalid_data)?;
+
+    let min_section = match blob.get("yPos") {
+        Some(Value::Int(y)) => *y,
+        _ => 0,
+    };
+    let sections = match blob.get("sections") {
+        Some(Value::List(sections)) => sections,
+        _ => return Err(invalid_data("missing chunk sections")),
+    };
+    let mut decoded = BTreeMap::new();
+    for section in sections {
+        let section = match section {
+            Value::Compound(section) => section,
+            _ => return Err(invalid_data("sections must be compounds")),
+        };
+        let y = match section.get("Y") {
+            Some(Value::Byte(y)) => *y as i32 - min_section,
+            _ => return Err(invalid_data("section without Y")),
+        };
+        // Sections only holding light data, below and above the world
+        let block_states = match section.get("block_states") {
+            Some(block_states) if y >= 0 => block_states,
+            _ => continue,
+        };
+        decoded.insert(y as u16, decode_section(block_states, registry)?);
+    }
+
+    let height = decoded.keys().next_back().map(|y| *y as usize + 1).unwrap_or(0);
+    let mut chunk_data = ChunkData::new(height);
+    for (y, section) in decoded {
+        *chunk_data.get_section_mut(y) = section;
+    }
+    Ok(Some(chunk_data))
+}
+
+/// Writes a chunk in the given region file, creating it if needed and keeping its other chunks
+pub fn write_region(
+    path: impl AsRef<Path>,
+    chunk_x: i32,
+    chunk_z: i32,
+    chunk_data: &ChunkData,
+    registry: &impl BlockStateRegistry,
+) -> io::Result<()> {
+    let sections = (0..chunk_data.sections_height())
+        .map(|y| encode_section(y as i8, chunk_data.get_section(y as u16), registry))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut blob = Blob::new();
+    blob.insert("DataVersion", DATA_VERSION).map_err(invalid_data)?;
+    blob.insert("xPos", chunk_x).map_err(invalid_data)?;
+    blob.insert("zPos", chunk_z).map_err(invalid_data)?;
+    blob.insert("yPos", 0).map_err(invalid_data)?;
+    blob.insert("Status", "full").map_err(invalid_data)?;
+    blob.insert("sections", Value::List(sections)).map_err(invalid_data)?;
+
+    let mut data = vec![];
+    blob.to_zlib_writer(&mut data).map_err(invalid_data)?;
+
+    let path = path.as_ref();
+    let mut chunks = read_raw_chunks(path)?;
+    chunks[chunk_index(chunk_x, chunk_z)] = Some(RawChunk {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or(0),
+        compression: COMPRESSION_ZLIB,
+        data,
+    });
+    write_raw_chunks(path, &chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestRegistry;
+    impl BlockStateRegistry for TestRegistry {
+        fn get_name(&self, state: BlockState) -> Option<BlockStateName> {
+            let (name, properties) = match state {
+                0 => ("minecraft:air", vec![]),
+                1 => ("minecraft:stone", vec![]),
+                9 => ("minecraft:grass_block", vec![("snowy", "false")]),
+                _ => return None,
+            };
+            Some(BlockStateName {
+                name: name.to_string(),
+                properties: properties
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            })
+        }
+
+        fn get_state(&self, name: &BlockStateName) -> Option<BlockState> {
+            (0..10).find(|state| self.get_name(*state).as_ref() == Some(name))
+        }
+    }
+
+    #[test]
+    fn region_round_trip() {
+        let path = std::env::temp_dir().join(format!("mc_utils_anvil_{}.mca", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut chunk = ChunkData::new(4);
+        chunk.get_section_mut(0).fill_with(1);
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.set_block(x, 16, z, 1);
+                chunk.set_block(x, 17, z, 9);
+            }
+        }
+        chunk.set_block(3, 40, 7, 9);
+
+        assert!(read_region(&path, -3, 5, &TestRegistry).unwrap().is_none());
+        write_region(&path, -3, 5, &chunk, &TestRegistry).unwrap();
+        write_region(&path, 0, 0, &ChunkData::new(4), &TestRegistry).unwrap();
+
+        assert_eq!(read_region(&path, -3, 5, &TestRegistry).unwrap(), Some(chunk));
+        assert_eq!(read_region(&path, 0, 0, &TestRegistry).unwrap(), Some(ChunkData::new(4)));
+        assert!(read_region(&path, 1, 0, &TestRegistry).unwrap().is_none());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -7,6 +7,8 @@ use mc_networking::packets::client_bound::{
     C1FSection, C1FPalettedContainer
 };
 
+use crate::{ build_heightmap, ChunkLight, LightProperties };
+
 use std::ops::Deref;
 use std::ops::DerefMut;
 
@@ -209,35 +211,53 @@ impl ChunkData {
         }
     }
 
-    pub fn encode_full(
-        &self, chunk_x: i32, chunk_z: i32
-    ) -> C1FChunkDataAndUpdateLight {
-        let motion_blocking_heightmap = {
-            let mut motion_blocking_heightmap = BitBuffer::create(9, 256);
-            for x in 0..16 {
+    /// Blocks of `other` that differ from this chunk as (x, y, z, block of `other`),
+    /// ordered by y, z then x
+    /// Sections missing in one of the chunks are considered empty
+    pub fn diff(&self, other: &ChunkData) -> Vec<(u8, u16, u8, BlockState)> {
+        let empty = ChunkDataSection::default();
+        let mut changes = vec![];
+        for section_y in 0..self.sections.len().max(other.sections.len()) {
+            let old = self.sections.get(section_y).unwrap_or(&empty);
+            let new = other.sections.get(section_y).unwrap_or(&empty);
+            if let (ChunkDataSection::Filled(old), ChunkDataSection::Filled(new)) = (old, new) {
+                if old == new {
+                    continue;
+                }
+            }
+            for y in 0..16 {
                 for z in 0..16 {
-                    'height_loop: for y in (0..self.block_height()).rev() {
-                        if self.get_block(x, y as u16, z) != 0 {
-                            motion_blocking_heightmap.set_entry(((x * 16) + z) as usize, y as u32);
-                            break 'height_loop;
+                    for x in 0..16 {
+                        let block = new.get_block(x, y, z);
+                        if old.get_block(x, y, z) != block {
+                            changes.push((x, section_y as u16 * 16 + y as u16, z, block));
                         }
                     }
                 }
             }
-            motion_blocking_heightmap
-        };
+        }
+        changes
+    }
+
+    /// Encodes the chunk for a world as high as the chunk, see [ChunkData::encode_for_world]
+    pub fn encode_full(
+        &self, chunk_x: i32, chunk_z: i32
+    ) -> C1FChunkDataAndUpdateLight {
+        self.encode_for_world(chunk_x, chunk_z, self.block_height() as i32)
+    }
+
+    /// Encodes the chunk with its heightmaps sized for `world_height` and the light computed by
+    /// [ChunkLight] with [LightProperties::solid_blocks],
+    /// use [ChunkLight::apply_to] on the packet to send more accurate light
+    pub fn encode_for_world(
+        &self, chunk_x: i32, chunk_z: i32, world_height: i32
+    ) -> C1FChunkDataAndUpdateLight {
         let chunk_sections = self.sections.iter().map(|s| s.encode()).collect();
 
-        C1FChunkDataAndUpdateLight {
+        let mut packet = C1FChunkDataAndUpdateLight {
             chunk_x,
             chunk_z,
-            heightmaps: {
-                let mut heightmaps = nbt::Blob::new();
-                heightmaps
-                    .insert("MOTION_BLOCKING", motion_blocking_heightmap.into_buffer())
-                    .unwrap();
-                heightmaps
-            },
+            heightmaps: build_heightmap(self, world_height),
             chunk_sections,
             block_entities: vec![],
             trust_edges: true,
@@ -247,6 +267,62 @@ impl ChunkData {
             empty_block_light_mask: BitSet::new(),
             sky_light_array: vec![],
             block_light_array: vec![],
-        }
+        };
+        ChunkLight::compute(self, LightProperties::solid_blocks).apply_to(&mut packet);
+        packet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_chunk_for_tall_world() {
+        let mut chunk = ChunkData::new(24);
+        chunk.set_block(1, 200, 2, 1);
+        let packet = chunk.encode_for_world(-3, 7, 384);
+
+        assert_eq!((packet.chunk_x, packet.chunk_z), (-3, 7));
+        assert_eq!(packet.chunk_sections.len(), 24);
+        assert_eq!(packet.chunk_sections[12].block_count, 1);
+        assert!(matches!(
+            packet.heightmaps.get("MOTION_BLOCKING"),
+            Some(nbt::Value::LongArray(longs)) if longs.len() == 37
+        ));
+        // Light is sent for the sections of the chunk plus one below and one above
+        let light_sections = (0..64)
+            .filter(|i| packet.sky_light_mask.get_bit(*i) || packet.empty_sky_light_mask.get_bit(*i))
+            .count();
+        assert_eq!(light_sections, 26);
+        assert_eq!(packet.sky_light_array.len(), 26);
+    }
+
+    #[test]
+    fn chunk_diff_lists_changed_blocks() {
+        let mut old = ChunkData::new(4);
+        old.get_section_mut(0).fill_with(1);
+        old.set_block(5, 40, 5, 7);
+        let mut new = old.clone();
+        new.set_block(3, 2, 1, 0);
+        new.set_block(15, 63, 0, 9);
+        new.set_block(5, 40, 5, 0);
+        new.set_block(0, 40, 8, 2);
+        // Changed then restored
+        new.set_block(4, 20, 4, 3);
+        new.set_block(4, 20, 4, 0);
+
+        assert_eq!(old.diff(&new), vec![
+            (3, 2, 1, 0),
+            (5, 40, 5, 0),
+            (0, 40, 8, 2),
+            (15, 63, 0, 9),
+        ]);
+        assert!(new.diff(&new).is_empty());
+
+        // Extra sections are compared to air
+        let mut taller = ChunkData::new(5);
+        taller.set_block(1, 70, 1, 4);
+        assert_eq!(ChunkData::new(4).diff(&taller), vec![(1, 70, 1, 4)]);
     }
 }
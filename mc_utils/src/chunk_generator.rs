@@ -0,0 +1,206 @@
+use crate::{BlockState, ChunkData};
+
+/// Generates the content of new chunks
+pub trait ChunkGenerator: Send + Sync {
+    /// Generates the chunk at the given chunk coordinates, with the given height in sections
+    fn generate(&self, chunk_x: i32, chunk_z: i32, sections: usize) -> ChunkData;
+}
+
+/// Generates empty chunks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VoidGenerator;
+
+impl ChunkGenerator for VoidGenerator {
+    fn generate(&self, _chunk_x: i32, _chunk_z: i32, sections: usize) -> ChunkData {
+        ChunkData::new(sections)
+    }
+}
+
+/// Generates a flat world made of the same layers everywhere
+#[derive(Debug, Clone)]
+pub struct FlatGenerator {
+    /// Block state of each layer from the bottom of the world
+    layers: Vec<BlockState>,
+}
+
+impl FlatGenerator {
+    /// Creates the generator from a list of block state and thickness in blocks,
+    /// starting from the bottom of the world
+    /// e.g. `FlatGenerator::new(vec![(bedrock, 1), (dirt, 3), (grass, 1)])`
+    pub fn new(layers: Vec<(BlockState, usize)>) -> Self {
+        Self {
+            layers: layers
+                .into_iter()
+                .flat_map(|(block, thickness)| std::iter::repeat(block).take(thickness))
+                .collect(),
+        }
+    }
+
+    /// Block state at the given height, air above the layers
+    pub fn block_at(&self, y: usize) -> BlockState {
+        self.layers.get(y).copied().unwrap_or(0)
+    }
+}
+
+impl ChunkGenerator for FlatGenerator {
+    fn generate(&self, _chunk_x: i32, _chunk_z: i32, sections: usize) -> ChunkData {
+        let mut chunk_data = ChunkData::new(sections);
+        let height = self.layers.len().min(chunk_data.block_height());
+        for (y, block) in self.layers[0..height].iter().enumerate() {
+            for x in 0..16 {
+                for z in 0..16 {
+                    chunk_data.set_block(x, y as u16, z, *block);
+                }
+            }
+        }
+        chunk_data
+    }
+}
+
+/// Generates hills using a seeded perlin noise heightmap,
+/// each column only depends on the seed and its coordinates so chunks line up
+#[derive(Debug, Clone)]
+pub struct NoiseGenerator {
+    pub seed: u64,
+    /// Average height of the terrain
+    pub base_height: f64,
+    /// Maximum distance from the base height
+    pub amplitude: f64,
+    /// Noise cycles per block, lower values give wider hills
+    pub frequency: f64,
+
+    pub stone: BlockState,
+    pub dirt: BlockState,
+    pub grass: BlockState,
+}
+
+impl NoiseGenerator {
+    pub fn new(seed: u64, stone: BlockState, dirt: BlockState, grass: BlockState) -> Self {
+        Self {
+            seed,
+            base_height: 64.,
+            amplitude: 16.,
+            frequency: 1. / 64.,
+            stone,
+            dirt,
+            grass,
+        }
+    }
+
+    /// Random gradient of a noise grid corner
+    fn gradient(&self, x: i64, z: i64) -> (f64, f64) {
+        // splitmix64 of the seed and corner coordinates
+        let mut h = self.seed
+            ^ (x as u64).wrapping_mul(0x9E3779B97F4A7C15)
+            ^ (z as u64).wrapping_mul(0xC2B2AE3D27D4EB4F);
+        h = (h ^ (h >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94D049BB133111EB);
+        h ^= h >> 31;
+        let angle = (h as f64 / u64::MAX as f64) * std::f64::consts::TAU;
+        (angle.cos(), angle.sin())
+    }
+
+    /// Perlin noise in about -1..1
+    fn noise(&self, x: f64, z: f64) -> f64 {
+        let (x0, z0) = (x.floor(), z.floor());
+        let (fx, fz) = (x - x0, z - z0);
+        let corner = |dx: f64, dz: f64| {
+            let (gx, gz) = self.gradient(x0 as i64 + dx as i64, z0 as i64 + dz as i64);
+            gx * (fx - dx) + gz * (fz - dz)
+        };
+        let fade = |t: f64| t * t * t * (t * (t * 6. - 15.) + 10.);
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+
+        let (u, v) = (fade(fx), fade(fz));
+        let noise = lerp(
+            lerp(corner(0., 0.), corner(1., 0.), u),
+            lerp(corner(0., 1.), corner(1., 1.), u),
+            v,
+        );
+        // The maximum of 2d perlin noise is sqrt(2)/2
+        noise * std::f64::consts::SQRT_2
+    }
+
+    /// Height of the terrain at the given world column, the grass block is at height - 1
+    pub fn height_at(&self, x: i32, z: i32) -> usize {
+        let noise = self.noise(x as f64 * self.frequency, z as f64 * self.frequency);
+        (self.base_height + noise * self.amplitude).round().max(1.) as usize
+    }
+}
+
+impl ChunkGenerator for NoiseGenerator {
+    fn generate(&self, chunk_x: i32, chunk_z: i32, sections: usize) -> ChunkData {
+        let mut chunk_data = ChunkData::new(sections);
+        let block_height = chunk_data.block_height();
+        for x in 0..16u8 {
+            for z in 0..16u8 {
+                let height = self
+                    .height_at(chunk_x * 16 + x as i32, chunk_z * 16 + z as i32)
+                    .min(block_height);
+                for y in 0..height {
+                    let block = match height - y {
+                        1 => self.grass,
+                        2..=4 => self.dirt,
+                        _ => self.stone,
+                    };
+                    chunk_data.set_block(x, y as u16, z, block);
+                }
+            }
+        }
+        chunk_data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_generator_layers() {
+        let (bedrock, dirt, grass) = (33, 10, 9);
+        let generator = FlatGenerator::new(vec![(bedrock, 1), (dirt, 3), (grass, 1)]);
+        let chunk = generator.generate(4, -7, 2);
+
+        assert_eq!(chunk.sections_height(), 2);
+        for (x, z) in [(0, 0), (15, 15), (3, 12)] {
+            assert_eq!(chunk.get_block(x, 0, z), bedrock);
+            for y in 1..4 {
+                assert_eq!(chunk.get_block(x, y, z), dirt);
+            }
+            assert_eq!(chunk.get_block(x, 4, z), grass);
+            for y in 5..32 {
+                assert_eq!(chunk.get_block(x, y, z), 0);
+            }
+        }
+
+        assert_eq!(VoidGenerator.generate(0, 0, 2), ChunkData::new(2));
+    }
+
+    /// Height of the highest non air block + 1
+    fn column_height(chunk: &ChunkData, x: u8, z: u8) -> usize {
+        (0..chunk.block_height())
+            .rev()
+            .find(|y| chunk.get_block(x, *y as u16, z) != 0)
+            .map(|y| y + 1)
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn noise_generator_is_continuous_and_deterministic() {
+        let generator = NoiseGenerator::new(42, 1, 10, 9);
+        let chunk = generator.generate(0, 0, 16);
+        let east_chunk = generator.generate(1, 0, 16);
+
+        for z in 0..16 {
+            let border = column_height(&chunk, 15, z);
+            let east_border = column_height(&east_chunk, 0, z);
+            assert_eq!(border, generator.height_at(15, z as i32));
+            assert_eq!(east_border, generator.height_at(16, z as i32));
+            assert!((border as i64 - east_border as i64).abs() <= 2);
+            assert_eq!(east_chunk.get_block(0, east_border as u16 - 1, z), 9);
+        }
+
+        assert_eq!(NoiseGenerator::new(42, 1, 10, 9).generate(0, 0, 16), chunk);
+        assert_ne!(NoiseGenerator::new(43, 1, 10, 9).generate(0, 0, 16), chunk);
+    }
+}
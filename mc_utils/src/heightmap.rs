@@ -0,0 +1,76 @@
+use crate::ChunkData;
+use mc_networking::data_types::bitbuffer::BitBuffer;
+
+/// Number of bits of the heightmap entries of a world, enough to store every height from 0
+/// (empty column) to `world_height` (block at the top of the world)
+pub fn heightmap_bits(world_height: i32) -> u8 {
+    (((world_height + 1) as f64).log2().ceil() as u8).max(1)
+}
+
+/// Builds the heightmaps of a chunk sent with its data
+/// Entries are indexed by `x + z * 16` and are the height above the bottom of the world of the
+/// highest block of the column plus one, 0 for empty columns
+///
+/// The blocks have no collision data so every non-air block is considered as motion blocking,
+/// `WORLD_SURFACE` is then identical to `MOTION_BLOCKING`
+pub fn build_heightmap(chunk: &ChunkData, world_height: i32) -> nbt::Blob {
+    let height = (chunk.block_height() as i32).min(world_height).max(0) as u16;
+    let mut heightmap = BitBuffer::create(heightmap_bits(world_height), 256);
+    for z in 0..16 {
+        for x in 0..16 {
+            let top = (0..height).rev()
+                .find(|y| chunk.get_block(x, *y, z) != 0)
+                .map_or(0, |y| y as u32 + 1);
+            heightmap.set_entry(x as usize + z as usize * 16, top);
+        }
+    }
+    let longs = heightmap.into_buffer();
+
+    let mut heightmaps = nbt::Blob::new();
+    heightmaps.insert("MOTION_BLOCKING", longs.clone()).unwrap();
+    heightmaps.insert("WORLD_SURFACE", longs).unwrap();
+    heightmaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn motion_blocking(heightmaps: &nbt::Blob, bits: u8) -> BitBuffer {
+        match heightmaps.get("MOTION_BLOCKING") {
+            Some(nbt::Value::LongArray(longs)) => BitBuffer::load(bits, longs.clone()),
+            value => panic!("Unexpected heightmap {value:?}"),
+        }
+    }
+
+    #[test]
+    fn heightmap_bits_fit_world_height() {
+        assert_eq!(heightmap_bits(16), 5);
+        assert_eq!(heightmap_bits(256), 9);
+        assert_eq!(heightmap_bits(384), 9);
+        assert_eq!(heightmap_bits(511), 9);
+        assert_eq!(heightmap_bits(512), 10);
+    }
+
+    #[test]
+    fn tall_world_heightmap() {
+        let mut chunk = ChunkData::new(24);
+        chunk.set_block(3, 300, 5, 1);
+        chunk.set_block(3, 10, 5, 1);
+        chunk.set_block(15, 383, 15, 1);
+        chunk.set_block(0, 0, 0, 1);
+
+        let heightmaps = build_heightmap(&chunk, 384);
+        // 7 entries of 9 bits per long
+        let heightmap = motion_blocking(&heightmaps, 9);
+        assert_eq!(heightmap.clone().into_buffer().len(), 37);
+        assert_eq!(heightmap.get_entry(3 + 5 * 16), 301);
+        assert_eq!(heightmap.get_entry(255), 384);
+        assert_eq!(heightmap.get_entry(0), 1);
+        assert_eq!(heightmap.get_entry(1), 0);
+        assert_eq!(
+            heightmaps.get("WORLD_SURFACE"),
+            Some(&nbt::Value::LongArray(heightmap.into_buffer())),
+        );
+    }
+}
@@ -1,12 +1,22 @@
 pub mod abort_contract;
+mod aabb;
+pub mod anvil;
 mod chunk_data;
+mod chunk_generator;
+mod heightmap;
+mod light;
 mod world_section;
 mod block_change_accumulator;
 mod location;
 mod position_ext;
+pub mod pathfinding;
 pub mod tick_scheduler;
 
+pub use aabb::*;
 pub use chunk_data::*;
+pub use chunk_generator::*;
+pub use heightmap::*;
+pub use light::*;
 pub use location::*;
 pub use world_section::*;
 pub use block_change_accumulator::*;
@@ -0,0 +1,239 @@
+use crate::{ BlockState, ChunkData };
+use mc_networking::data_types::bitset::BitSet;
+use mc_networking::packets::client_bound::C1FChunkDataAndUpdateLight;
+
+use std::collections::VecDeque;
+
+pub const MAX_LIGHT_LEVEL: u8 = 15;
+
+/// How a block interacts with light
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LightProperties {
+    /// Block light level emitted by the block
+    pub emission: u8,
+    /// Opaque blocks stop both sky and block light
+    pub opaque: bool,
+}
+
+impl LightProperties {
+    /// Properties used when nothing is known about the blocks: air is transparent
+    /// and every other block is opaque, no block emits light
+    pub fn solid_blocks(block: BlockState) -> Self {
+        Self {
+            emission: 0,
+            opaque: block != 0,
+        }
+    }
+}
+
+/// Light levels of a chunk, with one more section below and above the chunk
+/// like the light sent to the clients
+pub struct ChunkLight {
+    sections: usize,
+    sky: Vec<[u8; 4096]>,
+    block: Vec<[u8; 4096]>,
+}
+
+impl ChunkLight {
+    /// Computes the light of a chunk alone, light from the neighbouring chunks is ignored
+    /// Sky light is full above the highest opaque block of every column and block light
+    /// spreads from the emitting blocks, both losing one level per block travelled
+    pub fn compute(chunk: &ChunkData, properties: impl Fn(BlockState) -> LightProperties) -> Self {
+        let sections = chunk.sections_height() + 2;
+        let mut light = Self {
+            sections,
+            sky: vec![[0; 4096]; sections],
+            block: vec![[0; 4096]; sections],
+        };
+        let height = sections * 16;
+        // Indexed by the light y, 16 blocks above the chunk y
+        let block_properties = |x: usize, y: usize, z: usize| {
+            if y < 16 || y >= height - 16 {
+                return LightProperties::default();
+            }
+            properties(chunk.get_block(x as u8, (y - 16) as u16, z as u8))
+        };
+
+        let mut sky_sources = vec![];
+        let mut block_sources = vec![];
+        for z in 0..16 {
+            for x in 0..16 {
+                let mut sky_visible = true;
+                for y in (0..height).rev() {
+                    let block = block_properties(x, y, z);
+                    sky_visible &= !block.opaque;
+                    if sky_visible {
+                        sky_sources.push(((x, y, z), MAX_LIGHT_LEVEL));
+                    }
+                    if block.emission > 0 {
+                        block_sources.push(((x, y, z), block.emission.min(MAX_LIGHT_LEVEL)));
+                    }
+                }
+            }
+        }
+        flood_fill(&mut light.sky, height, sky_sources, &block_properties);
+        flood_fill(&mut light.block, height, block_sources, &block_properties);
+        light
+    }
+
+    fn level(levels: &[[u8; 4096]], x: u8, y: i32, z: u8) -> u8 {
+        let y = (y + 16) as usize;
+        levels[y / 16][index(x as usize, y % 16, z as usize)]
+    }
+
+    /// Sky light level of a block of the chunk, `y` going from -16 to 16 blocks above the chunk
+    pub fn sky_light(&self, x: u8, y: i32, z: u8) -> u8 {
+        Self::level(&self.sky, x, y, z)
+    }
+
+    /// Block light level of a block of the chunk, `y` going from -16 to 16 blocks above the chunk
+    pub fn block_light(&self, x: u8, y: i32, z: u8) -> u8 {
+        Self::level(&self.block, x, y, z)
+    }
+
+    /// Sets the light masks and arrays of a chunk data packet
+    pub fn apply_to(&self, packet: &mut C1FChunkDataAndUpdateLight) {
+        let (sky_light_mask, empty_sky_light_mask, sky_light_array) = pack(&self.sky);
+        let (block_light_mask, empty_block_light_mask, block_light_array) = pack(&self.block);
+        packet.sky_light_mask = sky_light_mask;
+        packet.empty_sky_light_mask = empty_sky_light_mask;
+        packet.sky_light_array = sky_light_array;
+        packet.block_light_mask = block_light_mask;
+        packet.empty_block_light_mask = empty_block_light_mask;
+        packet.block_light_array = block_light_array;
+    }
+
+    /// Number of light sections, two more than the sections of the chunk
+    pub fn sections(&self) -> usize {
+        self.sections
+    }
+}
+
+fn index(x: usize, y: usize, z: usize) -> usize {
+    (y << 8) | (z << 4) | x
+}
+
+/// Spreads the light of the sources to the non-opaque blocks, decreasing by one per block
+fn flood_fill(
+    levels: &mut [[u8; 4096]], height: usize,
+    sources: Vec<((usize, usize, usize), u8)>,
+    properties: &impl Fn(usize, usize, usize) -> LightProperties,
+) {
+    let mut queue = VecDeque::with_capacity(sources.len());
+    for ((x, y, z), level) in sources {
+        levels[y / 16][index(x, y % 16, z)] = level;
+        queue.push_back((x, y, z));
+    }
+
+    while let Some((x, y, z)) = queue.pop_front() {
+        let level = levels[y / 16][index(x, y % 16, z)];
+        if level <= 1 {
+            continue;
+        }
+        let neighbours = [
+            (x.wrapping_sub(1), y, z), (x + 1, y, z),
+            (x, y.wrapping_sub(1), z), (x, y + 1, z),
+            (x, y, z.wrapping_sub(1)), (x, y, z + 1),
+        ];
+        for (nx, ny, nz) in neighbours {
+            if nx >= 16 || nz >= 16 || ny >= height || properties(nx, ny, nz).opaque {
+                continue;
+            }
+            let neighbour = &mut levels[ny / 16][index(nx, ny % 16, nz)];
+            if *neighbour < level - 1 {
+                *neighbour = level - 1;
+                queue.push_back((nx, ny, nz));
+            }
+        }
+    }
+}
+
+/// Packs light levels into the light mask, empty light mask and nibble arrays of the
+/// chunk data packet
+fn pack(levels: &[[u8; 4096]]) -> (BitSet, BitSet, Vec<Box<[u8; 2048]>>) {
+    let mut mask = BitSet::new();
+    let mut empty_mask = BitSet::new();
+    let mut arrays = vec![];
+    for (i, section) in levels.iter().enumerate() {
+        if section.iter().all(|level| *level == 0) {
+            empty_mask.set_bit(i, true);
+            continue;
+        }
+        mask.set_bit(i, true);
+        let mut array = Box::new([0; 2048]);
+        for (i, pair) in section.chunks(2).enumerate() {
+            array[i] = pair[0] | (pair[1] << 4);
+        }
+        arrays.push(array);
+    }
+    (mask, empty_mask, arrays)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STONE: BlockState = 1;
+    const TORCH: BlockState = 2;
+
+    fn properties(block: BlockState) -> LightProperties {
+        match block {
+            TORCH => LightProperties { emission: 14, opaque: false },
+            block => LightProperties::solid_blocks(block),
+        }
+    }
+
+    #[test]
+    fn open_column_has_full_sky_light() {
+        let mut chunk = ChunkData::new(4);
+        chunk.set_block(0, 20, 0, STONE);
+        let light = ChunkLight::compute(&chunk, properties);
+
+        for y in -16..80 {
+            assert_eq!(light.sky_light(5, y, 5), 15);
+            assert_eq!(light.block_light(5, y, 5), 0);
+        }
+        assert_eq!(light.sky_light(0, 21, 0), 15);
+        // Under the block the light comes from the sides
+        assert_eq!(light.sky_light(0, 19, 0), 14);
+
+        let mut packet = ChunkData::new(4).encode_full(0, 0);
+        light.apply_to(&mut packet);
+        assert!((0..6).all(|i| packet.sky_light_mask.get_bit(i)));
+        assert!((0..6).all(|i| packet.empty_block_light_mask.get_bit(i)));
+        assert_eq!(packet.sky_light_array.len(), 6);
+        assert!(packet.block_light_array.is_empty());
+        assert_eq!(packet.sky_light_array[1][0], 0xFF);
+    }
+
+    #[test]
+    fn torch_light_gradient() {
+        let mut chunk = ChunkData::new(4);
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.set_block(x, 10, z, STONE);
+                chunk.set_block(x, 20, z, STONE);
+            }
+        }
+        chunk.set_block(8, 11, 8, TORCH);
+        let light = ChunkLight::compute(&chunk, properties);
+
+        for distance in 0..8 {
+            assert_eq!(light.block_light(8 + distance, 11, 8), 14 - distance);
+        }
+        assert_eq!(light.block_light(8, 14, 8), 11);
+        assert_eq!(light.block_light(3, 13, 5), 14 - 5 - 2 - 3);
+        assert_eq!(light.block_light(8, 10, 8), 0);
+        assert_eq!(light.block_light(8, 9, 8), 0);
+        // The ceiling shades the room
+        assert_eq!(light.sky_light(8, 15, 8), 0);
+        assert_eq!(light.sky_light(8, 21, 8), 15);
+
+        let mut packet = chunk.encode_full(0, 0);
+        light.apply_to(&mut packet);
+        assert!(packet.block_light_mask.get_bit(1));
+        assert!(packet.empty_block_light_mask.get_bit(0));
+        // Torch at x = 8, even index so in the low nibble
+        assert_eq!(packet.block_light_array[0][((11 << 8) | (8 << 4) | 8) / 2] & 0x0F, 14);
+    }
+}
@@ -35,6 +35,13 @@ impl Location {
         self.h_distance2(other).sqrt()
     }
 
+    /// Unit vector (x, y, z) of the direction the location is looking at
+    pub fn direction(&self) -> (f64, f64, f64) {
+        let yaw = (self.yaw as f64).to_radians();
+        let pitch = (self.pitch as f64).to_radians();
+        (-pitch.cos() * yaw.sin(), -pitch.sin(), pitch.cos() * yaw.cos())
+    }
+
     pub fn yaw_angle(&self) -> Angle {
         (self.yaw * 256f32 / 360f32).rem_euclid(256f32) as Angle
     }
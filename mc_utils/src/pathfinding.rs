@@ -0,0 +1,206 @@
+use mc_networking::data_types::Position;
+
+use std::cmp::Reverse;
+use std::collections::{ BinaryHeap, HashMap };
+
+/// Cost of moving one block along an axis
+const STRAIGHT_COST: u32 = 10;
+/// Cost of a diagonal move, approximately `STRAIGHT_COST * sqrt(2)`
+const DIAGONAL_COST: u32 = 14;
+/// Additional cost of climbing a block
+const STEP_UP_COST: u32 = 10;
+/// Additional cost of each block fallen
+const DROP_COST: u32 = 5;
+/// Maximum height an entity will willingly drop
+pub const MAX_DROP: i32 = 3;
+
+const DIRECTIONS: [(i32, i32); 8] = [
+    (1, 0), (-1, 0), (0, 1), (0, -1),
+    (1, 1), (1, -1), (-1, 1), (-1, -1),
+];
+
+/// Lower bound of the cost from `from` to `to`, ignoring the vertical moves
+fn heuristic(from: Position, to: Position) -> u32 {
+    let dx = (from.x - to.x).unsigned_abs();
+    let dz = (from.z - to.z).unsigned_abs();
+    let (min, max) = if dx < dz { (dx, dz) } else { (dz, dx) };
+    DIAGONAL_COST * min + STRAIGHT_COST * (max - min)
+}
+
+struct World<F: Fn(Position) -> bool> {
+    is_solid: F,
+}
+
+impl<F: Fn(Position) -> bool> World<F> {
+    fn offset(position: Position, x: i32, y: i32, z: i32) -> Position {
+        Position { x: position.x + x, y: position.y + y, z: position.z + z }
+    }
+
+    /// Whether a two blocks tall entity fits with its feet at the position
+    fn is_free(&self, position: Position) -> bool {
+        !(self.is_solid)(position) && !(self.is_solid)(Self::offset(position, 0, 1, 0))
+    }
+
+    fn can_stand(&self, position: Position) -> bool {
+        self.is_free(position) && (self.is_solid)(Self::offset(position, 0, -1, 0))
+    }
+
+    /// Positions reachable in one move from `position` with the cost of the move
+    fn neighbours(&self, position: Position) -> impl Iterator<Item = (Position, u32)> + '_ {
+        DIRECTIONS.iter().filter_map(move |&(dx, dz)| {
+            let diagonal = dx != 0 && dz != 0;
+            // Diagonal moves can't cut the corners of blocks
+            if diagonal && !(
+                self.is_free(Self::offset(position, dx, 0, 0))
+                && self.is_free(Self::offset(position, 0, 0, dz))
+            ) {
+                return None;
+            }
+            let move_cost = if diagonal { DIAGONAL_COST } else { STRAIGHT_COST };
+
+            let next = Self::offset(position, dx, 0, dz);
+            if self.can_stand(next) {
+                return Some((next, move_cost));
+            }
+
+            let step_up = Self::offset(next, 0, 1, 0);
+            if !diagonal && self.can_stand(step_up)
+                && !(self.is_solid)(Self::offset(position, 0, 2, 0)) {
+                return Some((step_up, move_cost + STEP_UP_COST));
+            }
+
+            if !self.is_free(next) {
+                return None;
+            }
+            (1..=MAX_DROP)
+                .map(|drop| (drop, Self::offset(next, 0, -drop, 0)))
+                .take_while(|(_, below)| !(self.is_solid)(*below))
+                .find(|(_, below)| self.can_stand(*below))
+                .map(|(drop, below)| (below, move_cost + DROP_COST * drop as u32))
+        })
+    }
+}
+
+/// Finds a walking path for a two blocks tall entity from `start` to `goal`, both being
+/// the position of the entity's feet, with A*
+/// The path can climb one block, drop up to [MAX_DROP] blocks and move diagonally
+///
+/// Returns the positions of the path including the start and the goal, or None if
+/// the goal can't be reached or more than `max_nodes` positions had to be explored
+pub fn find_path(
+    start: Position, goal: Position,
+    is_solid: impl Fn(Position) -> bool, max_nodes: usize,
+) -> Option<Vec<Position>> {
+    let world = World { is_solid };
+    // Best known cost and previous position of every visited position
+    let mut visited: HashMap<Position, (u32, Option<Position>)> = HashMap::new();
+    let mut open = BinaryHeap::new();
+    visited.insert(start, (0, None));
+    open.push(Reverse((heuristic(start, goal), 0, start.x, start.y, start.z)));
+
+    let mut expanded = 0;
+    while let Some(Reverse((_, cost, x, y, z))) = open.pop() {
+        let position = Position { x, y, z };
+        // Outdated entry of a position reached with a lower cost since
+        if visited[&position].0 < cost {
+            continue;
+        }
+        if position == goal {
+            let mut path = vec![position];
+            while let Some(previous) = visited[path.last().unwrap()].1 {
+                path.push(previous);
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        expanded += 1;
+        if expanded > max_nodes {
+            return None;
+        }
+        for (next, move_cost) in world.neighbours(position) {
+            let next_cost = cost + move_cost;
+            if visited.get(&next).map_or(false, |(known, _)| *known <= next_cost) {
+                continue;
+            }
+            visited.insert(next, (next_cost, Some(position)));
+            open.push(Reverse((next_cost + heuristic(next, goal), next_cost, next.x, next.y, next.z)));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn pos(x: i32, y: i32, z: i32) -> Position {
+        Position { x, y, z }
+    }
+
+    /// A 10x10 floor at y = 0
+    fn floor() -> HashSet<Position> {
+        (0..10).flat_map(|x| (0..10).map(move |z| pos(x, 0, z))).collect()
+    }
+
+    fn assert_walkable(path: &[Position], blocks: &HashSet<Position>) {
+        for step in path.windows(2) {
+            assert!((step[0].x - step[1].x).abs() <= 1 && (step[0].z - step[1].z).abs() <= 1);
+        }
+        for position in path {
+            assert!(!blocks.contains(position) && !blocks.contains(&pos(position.x, position.y + 1, position.z)));
+            assert!(blocks.contains(&pos(position.x, position.y - 1, position.z)));
+        }
+    }
+
+    #[test]
+    fn path_routes_around_wall() {
+        let mut blocks = floor();
+        // Two blocks high wall at x = 5 with a gap at z = 9
+        for z in 0..9 {
+            blocks.insert(pos(5, 1, z));
+            blocks.insert(pos(5, 2, z));
+        }
+
+        let path = find_path(pos(1, 1, 1), pos(8, 1, 1), |p| blocks.contains(&p), 1000).unwrap();
+        assert_eq!(path.first(), Some(&pos(1, 1, 1)));
+        assert_eq!(path.last(), Some(&pos(8, 1, 1)));
+        assert!(path.contains(&pos(5, 1, 9)));
+        assert_walkable(&path, &blocks);
+
+        // Closing the gap makes the goal unreachable
+        blocks.insert(pos(5, 1, 9));
+        blocks.insert(pos(5, 2, 9));
+        assert_eq!(find_path(pos(1, 1, 1), pos(8, 1, 1), |p| blocks.contains(&p), 1000), None);
+    }
+
+    #[test]
+    fn path_steps_up_and_drops() {
+        let mut blocks = floor();
+        // One block high wall with a three blocks deep pit behind it
+        for z in 0..10 {
+            blocks.insert(pos(4, 1, z));
+            blocks.remove(&pos(6, 0, z));
+            blocks.insert(pos(6, -3, z));
+        }
+
+        let path = find_path(pos(2, 1, 5), pos(6, -2, 5), |p| blocks.contains(&p), 1000).unwrap();
+        assert!(path.contains(&pos(4, 2, 5)));
+        assert_eq!(path.last(), Some(&pos(6, -2, 5)));
+        assert_walkable(&path, &blocks);
+
+        // The pit can't be climbed out of
+        assert_eq!(find_path(pos(6, -2, 5), pos(2, 1, 5), |p| blocks.contains(&p), 1000), None);
+    }
+
+    #[test]
+    fn path_search_is_capped() {
+        let blocks = floor();
+        let is_solid = |p: Position| blocks.contains(&p);
+        assert!(find_path(pos(0, 1, 0), pos(9, 1, 9), is_solid, 1000).is_some());
+        assert_eq!(find_path(pos(0, 1, 0), pos(9, 1, 9), is_solid, 3), None);
+        // Diagonal moves are used on open ground
+        assert_eq!(find_path(pos(0, 1, 0), pos(9, 1, 9), is_solid, 1000).unwrap().len(), 10);
+    }
+}
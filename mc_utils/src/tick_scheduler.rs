@@ -1,13 +1,16 @@
+use crate::abort_contract::AbortContract;
+
 use std::{
     sync::{Arc, RwLock},
     thread::{sleep, spawn},
     time::{Duration, Instant},
 };
 
-fn interval(delay: Duration, mut callback: impl FnMut() -> ()) {
+/// Calls the callback every `delay` until the contract is aborted
+fn interval(delay: Duration, abort_contract: &AbortContract, mut callback: impl FnMut() -> ()) {
     let start = Instant::now();
     let mut i = 0;
-    loop {
+    while !abort_contract.is_aborted() {
         i += 1;
         callback();
         let sleep_to = start + delay.checked_mul(i).unwrap();
@@ -47,6 +50,7 @@ impl TickProfiler {
 /// Create it with the [TickSchedulerBuilder]
 pub struct TickScheduler {
     profiler: Arc<RwLock<TickProfiler>>,
+    abort_contract: Arc<AbortContract>,
 }
 
 impl TickScheduler {
@@ -57,6 +61,17 @@ impl TickScheduler {
 
     /// Creates a [TickScheduler], but you probably want to use [TickScheduler::builder] instead
     pub fn new(minimum_duration_per_ticks: Duration, profiling_interval: Duration) -> Self {
+        Self::with_abort_contract(
+            minimum_duration_per_ticks, profiling_interval, Arc::new(AbortContract::new())
+        )
+    }
+
+    /// Creates a [TickScheduler] stopped by the given contract
+    pub fn with_abort_contract(
+        minimum_duration_per_ticks: Duration,
+        profiling_interval: Duration,
+        abort_contract: Arc<AbortContract>,
+    ) -> Self {
         Self {
             profiler: Arc::new(RwLock::new(TickProfiler {
                 minimum_duration_per_ticks: minimum_duration_per_ticks.clone(),
@@ -64,11 +79,19 @@ impl TickScheduler {
                 tick_duration_sum: Duration::from_nanos(0),
                 profiling_interval,
             })),
+            abort_contract,
         }
     }
 
+    /// Contract that stops the [TickScheduler] when aborted
+    /// The tick running when it gets aborted is completed and no other tick is started
+    pub fn abort_contract(&self) -> Arc<AbortContract> {
+        Arc::clone(&self.abort_contract)
+    }
+
     /// Starts the [TickScheduler] from the provided callbacks
     /// This will create a new thread if a profiler_callback is given
+    /// Returns once the [TickScheduler::abort_contract] is aborted
     pub fn start(
         self,
         mut tick_callback: impl FnMut() -> (),
@@ -77,8 +100,9 @@ impl TickScheduler {
         if let Some(mut profiler_callback) = profiler_callback {
             let profiling_interval = self.profiler.read().unwrap().profiling_interval.clone();
             let profiler = self.profiler.clone();
+            let abort_contract = self.abort_contract();
             spawn(move || {
-                interval(profiling_interval, move || {
+                interval(profiling_interval, &abort_contract, move || {
                     let mut profiler = profiler.write().unwrap();
                     profiler_callback(&*profiler);
                     profiler.reset();
@@ -92,7 +116,8 @@ impl TickScheduler {
             .unwrap()
             .minimum_duration_per_ticks
             .clone();
-        interval(delay, move || {
+        let abort_contract = self.abort_contract();
+        interval(delay, &abort_contract, move || {
             let start = Instant::now();
             tick_callback();
             let duration = start.elapsed();
@@ -107,6 +132,7 @@ impl TickScheduler {
 pub struct TickSchedulerBuilder {
     minimum_duration_per_ticks: Duration,
     profiling_interval: Duration,
+    abort_contract: Option<Arc<AbortContract>>,
 }
 impl TickSchedulerBuilder {
     /// Creates a new [TickSchedulerBuilder] with default config
@@ -114,6 +140,7 @@ impl TickSchedulerBuilder {
         Self {
             minimum_duration_per_ticks: Duration::from_millis(50),
             profiling_interval: Duration::from_secs(2),
+            abort_contract: None,
         }
     }
 
@@ -128,8 +155,72 @@ impl TickSchedulerBuilder {
         self
     }
 
+    /// Sets the contract stopping the scheduler, a new one is created by default
+    pub fn abort_contract(mut self, abort_contract: Arc<AbortContract>) -> Self {
+        self.abort_contract = Some(abort_contract);
+        self
+    }
+
     /// Consumes the builder and create a [TickScheduler] based on the config
     pub fn build(self) -> TickScheduler {
-        TickScheduler::new(self.minimum_duration_per_ticks, self.profiling_interval)
+        TickScheduler::with_abort_contract(
+            self.minimum_duration_per_ticks,
+            self.profiling_interval,
+            self.abort_contract.unwrap_or_else(|| Arc::new(AbortContract::new())),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abort_stops_after_current_tick() {
+        let scheduler = TickScheduler::builder()
+            .minimum_duration_per_ticks(Duration::from_millis(1))
+            .build();
+        let abort_contract = scheduler.abort_contract();
+
+        let mut ticks = 0;
+        scheduler.start(
+            || {
+                ticks += 1;
+                if ticks == 3 {
+                    abort_contract.abort();
+                }
+            },
+            None::<fn(&TickProfiler)>,
+        );
+        assert_eq!(ticks, 3);
+    }
+
+    #[test]
+    fn abort_from_other_thread() {
+        let abort_contract = Arc::new(AbortContract::new());
+        let scheduler = TickScheduler::builder()
+            .minimum_duration_per_ticks(Duration::from_millis(5))
+            .profiling_interval(Duration::from_millis(5))
+            .abort_contract(Arc::clone(&abort_contract))
+            .build();
+
+        let handle = spawn({
+            let abort_contract = Arc::clone(&abort_contract);
+            move || {
+                let mut ticks_after_abort = 0;
+                scheduler.start(
+                    || if abort_contract.is_aborted() { ticks_after_abort += 1 },
+                    Some(|_: &TickProfiler| ()),
+                );
+                ticks_after_abort
+            }
+        });
+        sleep(Duration::from_millis(20));
+        let aborted_at = Instant::now();
+        abort_contract.abort();
+
+        // The tick running while aborting may still see the abort
+        assert!(handle.join().unwrap() <= 1);
+        assert!(aborted_at.elapsed() < Duration::from_millis(50));
     }
 }